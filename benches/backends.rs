@@ -0,0 +1,101 @@
+//! Compares how long the tree-walking interpreter and the bytecode VM take on a few workloads.
+//!
+//! Run with `cargo bench`.
+use std::time::{Duration, Instant};
+use wally_lang::vm::Backend;
+use wally_lang::Engine;
+
+const PROGRAM: &str = "
+fn fib(n: int): int {
+    if (n < 2) { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+
+fn loops(n: int): int {
+    var total: int = 0;
+    var i: int = 0;
+    while (i < n) {
+        var j: int = 0;
+        while (j < 100) {
+            total = total + i % 7 * j;
+            j = j + 1;
+        }
+        i = i + 1;
+    }
+    return total;
+}
+
+fn sieve(n: int): int {
+    var composite: array<bool> = [];
+    var i: int = 0;
+    while (i <= n) { composite.push(false); i = i + 1; }
+    var count: int = 0;
+    var p: int = 2;
+    while (p <= n) {
+        if (!composite[p]) {
+            count = count + 1;
+            var multiple: int = p * p;
+            while (multiple <= n) { composite[multiple] = true; multiple = multiple + p; }
+        }
+        p = p + 1;
+    }
+    return count;
+}
+
+class Vector {
+    var x: float;
+    var y: float;
+    constructor(x: float, y: float) { this.x = x; this.y = y; }
+    fn plus(other: Vector): Vector { return new Vector(this.x + other.x, this.y + other.y); }
+}
+
+fn objects(n: int): float {
+    var sum: Vector = new Vector(0.0, 0.0);
+    var step: Vector = new Vector(0.5, 0.25);
+    for (i in [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]) {
+        var j: int = 0;
+        while (j < n) { sum = sum.plus(step); j = j + 1; }
+    }
+    return sum.x + sum.y;
+}
+
+fn closures(n: int): int {
+    var total: int = 0;
+    var add: fn(int): int = fn(x: int): int { total = total + x; return total; };
+    var i: int = 0;
+    while (i < n) { add(i); i = i + 1; }
+    return total;
+}
+";
+
+const WORKLOADS: &[(&str, i64)] = &[("fib", 24), ("loops", 2_000), ("sieve", 100_000), ("objects", 5_000), ("closures", 100_000)];
+
+/// The fastest of a few runs, which is the least disturbed by the rest of the machine.
+fn time(backend: Backend, function: &str, argument: i64) -> Duration {
+    let mut engine = Engine::new();
+    engine.set_backend(backend);
+    engine.eval(PROGRAM).unwrap();
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            engine.call::<wally_lang::Value>(function, (argument,)).unwrap();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    println!("{:<10} {:>12} {:>12} {:>8}", "workload", "tree", "bytecode", "speedup");
+    for (function, argument) in WORKLOADS {
+        let tree = time(Backend::Tree, function, *argument);
+        let bytecode = time(Backend::Bytecode, function, *argument);
+        println!(
+            "{:<10} {:>10.2}ms {:>10.2}ms {:>7.2}x",
+            function,
+            tree.as_secs_f64() * 1000.0,
+            bytecode.as_secs_f64() * 1000.0,
+            tree.as_secs_f64() / bytecode.as_secs_f64()
+        );
+    }
+}
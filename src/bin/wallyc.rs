@@ -0,0 +1,46 @@
+use wally_lang::Engine;
+use std::path::PathBuf;
+use std::process;
+
+/// Compiles a program and the files it imports to a `.wlyc` bytecode module, which `wally`
+/// runs without the source.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let (mut root, mut path, mut output) = (None, None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--root" => root = Some(args.next().map(PathBuf::from).unwrap_or_else(|| usage())),
+            "-o" => output = Some(args.next().map(PathBuf::from).unwrap_or_else(|| usage())),
+            _ if path.is_none() => path = Some(PathBuf::from(arg)),
+            _ => usage(),
+        }
+    }
+    let (Some(path), Some(output)) = (path, output) else {
+        usage();
+    };
+
+    let mut engine = Engine::new();
+    if let Some(root) = root {
+        engine.set_root(root);
+    }
+    let result = engine.compile_file(&path);
+    for warning in engine.warnings() {
+        eprint!("{}", warning);
+    }
+    let bytes = match result {
+        Ok(bytes) => bytes,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    if let Err(error) = std::fs::write(&output, bytes) {
+        eprintln!("error: Cannot write `{}`: {}", output.display(), error);
+        process::exit(1);
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: wallyc [--root <dir>] <file> -o <output>");
+    process::exit(2);
+}
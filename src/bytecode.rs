@@ -0,0 +1,1056 @@
+//! The `.wlyc` file format, which holds a program compiled ahead of time so that it can be
+//! run without its source.
+//!
+//! A file starts with the magic number `WLYC` and the version of the format, followed by the
+//! names of the natives the program was resolved against, its function table and then each of
+//! its modules in the order they run. Every chunk of code carries its constant pool, the
+//! classes and enums it declares, and a line table mapping its instructions back to the source.
+//!
+//! Integers are little-endian, strings are UTF-8 prefixed with their length in bytes, and lists
+//! are prefixed with their length. The layout changes with the version, so files of any other
+//! version are rejected before anything else is read.
+use crate::compiler::{bound, compile, compile_module, Access, CatchBlock, Chunk, ClassPrototype, Instruction, MatchArm, Operator, Prototype, SuperCall, TryBlock};
+use crate::module::Module;
+use crate::native::Natives;
+use crate::parser::*;
+use crate::resolver::global_name;
+use crate::value::{Enum, Field, Initializer, Value};
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::iter;
+use std::rc::Rc;
+
+pub const MAGIC: [u8; 4] = *b"WLYC";
+
+/// The version of the format this build reads and writes, increased whenever the layout or
+/// the instruction set changes.
+pub const VERSION: u32 = 1;
+
+/// How deeply types, patterns, field initializers and `try` blocks may nest, so that neither
+/// reading a file nor running it can exhaust the stack. Anything the parser accepts fits, with
+/// a level to spare for the chunk around the outermost `try` block.
+const MAX_NESTING: usize = crate::parser::MAX_NESTING + 1;
+
+/// The most slots a frame of a loaded chunk may have.
+const MAX_LOCALS: u32 = 1 << 16;
+
+/// A program compiled ahead of time.
+#[derive(Debug)]
+pub struct Program {
+    pub modules: Vec<CompiledModule>,
+}
+
+/// A module compiled ahead of time, whose top level is code run in the global environment.
+#[derive(Debug)]
+pub struct CompiledModule {
+    pub path: String,
+    /// Names of the globals the module declares, in the order of their slots.
+    pub globals: Vec<String>,
+    /// Declared types of the variables among its globals.
+    pub variables: Vec<(String, Type)>,
+    pub code: Chunk,
+}
+
+/// Why a file could not be read as a compiled program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormatError {
+    /// The file does not start with the magic number.
+    NotBytecode,
+    /// The file was written in another version of the format.
+    Version(u32),
+    /// The program uses a native that is not registered.
+    MissingNative(String),
+    /// The file ends early or holds something the compiler never writes.
+    Corrupt(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::NotBytecode => write!(f, "Not a Wally bytecode file"),
+            FormatError::Version(version) => write!(
+                f,
+                "Compiled for bytecode version {}, but this version of Wally runs version {}; compile the program again",
+                version, VERSION
+            ),
+            FormatError::MissingNative(name) => write!(f, "The program uses `{}`, which is not a registered native", name),
+            FormatError::Corrupt(message) => write!(f, "Corrupt bytecode file: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl Program {
+    /// Compiles a resolved and checked program, given its modules in the order they run.
+    pub fn compile(modules: &[Module]) -> Program {
+        let mut base = 0;
+        let modules = modules
+            .iter()
+            .map(|module| {
+                let globals: Vec<String> = module.statements.iter().filter_map(global_name).map(str::to_string).collect();
+                let variables = module
+                    .statements
+                    .iter()
+                    .filter_map(|statement| match &statement.kind {
+                        StatementKind::VarDeclaration(variable) => Some((variable.name.clone(), variable.type_.clone())),
+                        _ => None,
+                    })
+                    .collect();
+                let code = compile_module(&module.statements, base);
+                base += globals.len() as u32;
+                CompiledModule {
+                    path: module.path.display().to_string(),
+                    globals,
+                    variables,
+                    code,
+                }
+            })
+            .collect();
+        Program { modules }
+    }
+
+    /// Encodes the program, compiling every function in it that has not been called yet.
+    /// Natives are recorded by name, which is how they are found again when it is read.
+    pub fn to_bytes(&self, natives: &Natives) -> Vec<u8> {
+        let mut functions = Functions::default();
+        for module in &self.modules {
+            functions.collect(&module.code);
+        }
+        let mut writer = Writer { bytes: MAGIC.to_vec(), functions: &functions.indices };
+        writer.u32(VERSION);
+        writer.list(&natives.iter().map(|function| function.name.clone()).collect::<Vec<_>>(), Writer::string);
+        writer.list(&natives.classes().map(|class| class.name.clone()).collect::<Vec<_>>(), Writer::string);
+        writer.list(&natives.constants().map(|constant| constant.name.clone()).collect::<Vec<_>>(), Writer::string);
+        // Every signature comes before any code, which refers to functions by their index.
+        writer.list(&functions.prototypes, |writer, prototype| {
+            let declaration = &prototype.declaration;
+            writer.string(&declaration.name);
+            writer.list(&declaration.parameters, |writer, parameter| {
+                writer.string(&parameter.name);
+                writer.type_(&parameter.type_);
+            });
+            writer.type_(&declaration.return_type);
+        });
+        for prototype in &functions.prototypes {
+            writer.chunk(prototype.code.get().expect("collected functions are compiled"));
+        }
+        writer.list(&self.modules, |writer, module| {
+            writer.string(&module.path);
+            writer.list(&module.globals, Writer::string);
+            writer.list(&module.variables, |writer, (name, type_)| {
+                writer.string(name);
+                writer.type_(type_);
+            });
+            writer.chunk(&module.code);
+        });
+        writer.bytes
+    }
+
+    /// Decodes a program written by `to_bytes`, finding the natives it uses in `natives`.
+    pub fn from_bytes(bytes: &[u8], natives: &Natives) -> Result<Program, FormatError> {
+        if !bytes.starts_with(&MAGIC) {
+            return Err(FormatError::NotBytecode);
+        }
+        let mut reader = Reader { bytes, position: MAGIC.len(), natives: [Vec::new(), Vec::new(), Vec::new()], functions: Vec::new(), depth: 0 };
+        let version = reader.u32()?;
+        if version != VERSION {
+            return Err(FormatError::Version(version));
+        }
+        let mut names = || reader.list(Reader::string);
+        let (functions, classes, constants) = (names()?, names()?, names()?);
+        reader.natives = [
+            functions.into_iter().map(|name| (natives.index_of(&name), name)).collect(),
+            classes.into_iter().map(|name| (natives.class_index(&name), name)).collect(),
+            constants.into_iter().map(|name| (natives.constant_index(&name), name)).collect(),
+        ];
+        reader.functions = reader.list(|reader| {
+            let name = reader.string()?;
+            let parameters = reader.list(|reader| Ok(Parameter::new(reader.string()?, reader.type_()?)))?;
+            let return_type = reader.type_()?;
+            Ok(Prototype {
+                declaration: Rc::new(FunctionDeclaration::new(name, Vec::new(), parameters, return_type, Vec::new())),
+                code: Rc::new(OnceCell::new()),
+            })
+        })?;
+        for index in 0..reader.functions.len() {
+            let chunk = reader.chunk()?;
+            let _ = reader.functions[index].code.set(chunk);
+        }
+        let modules = reader.list(|reader| {
+            Ok(CompiledModule {
+                path: reader.string()?,
+                globals: reader.list(Reader::string)?,
+                variables: reader.list(|reader| Ok((reader.string()?, reader.type_()?)))?,
+                code: reader.chunk()?,
+            })
+        })?;
+        if reader.position != bytes.len() {
+            return Err(corrupt("data after the last module"));
+        }
+        let globals = modules.iter().map(|module| module.globals.len()).sum();
+        for prototype in &reader.functions {
+            check(prototype.code.get().expect("every function was read"), globals)?;
+        }
+        for module in &modules {
+            check(&module.code, globals)?;
+        }
+        Ok(Program { modules })
+    }
+}
+
+/// The function table of a program, gathered from its code.
+#[derive(Default)]
+struct Functions {
+    prototypes: Vec<Prototype>,
+    /// Index of each function in the table by the address of its code, which the functions
+    /// created from the same declaration share.
+    indices: HashMap<*const OnceCell<Chunk>, u32>,
+}
+
+impl Functions {
+    /// Adds the functions `chunk` creates, and those they create in turn, compiling them.
+    fn collect(&mut self, chunk: &Chunk) {
+        let classes = chunk.classes.iter().flat_map(|class| class.methods.iter().chain(&class.static_methods).chain(&class.constructor));
+        for prototype in chunk.functions.iter().chain(classes) {
+            if self.indices.contains_key(&Rc::as_ptr(&prototype.code)) {
+                continue;
+            }
+            self.indices.insert(Rc::as_ptr(&prototype.code), self.prototypes.len() as u32);
+            self.prototypes.push(prototype.clone());
+            self.collect(prototype.code.get_or_init(|| compile(&prototype.declaration)));
+        }
+        for class in &chunk.classes {
+            for field in class.fields.iter().chain(&class.static_fields) {
+                if let Some(Initializer::Code(code)) = &field.initializer {
+                    self.collect(code);
+                }
+            }
+        }
+    }
+}
+
+const OPERATORS: [Operator; 13] = [
+    Operator::Add,
+    Operator::Subtract,
+    Operator::Multiply,
+    Operator::Divide,
+    Operator::Remainder,
+    Operator::Equal,
+    Operator::NotEqual,
+    Operator::Less,
+    Operator::LessEqual,
+    Operator::Greater,
+    Operator::GreaterEqual,
+    Operator::Negate,
+    Operator::Not,
+];
+
+struct Writer<'a> {
+    bytes: Vec<u8>,
+    functions: &'a HashMap<*const OnceCell<Chunk>, u32>,
+}
+
+impl Writer<'_> {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn string(&mut self, value: &String) {
+        self.u32(value.len() as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn list<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.u32(items.len() as u32);
+        for item in items {
+            write(self, item);
+        }
+    }
+
+    fn option<T>(&mut self, item: &Option<T>, write: impl FnOnce(&mut Self, &T)) {
+        self.bool(item.is_some());
+        if let Some(item) = item {
+            write(self, item);
+        }
+    }
+
+    fn range(&mut self, (start, end): &(u32, u32)) {
+        self.u32(*start);
+        self.u32(*end);
+    }
+
+    fn function(&mut self, prototype: &Prototype) {
+        self.u32(self.functions[&Rc::as_ptr(&prototype.code)]);
+    }
+
+    fn chunk(&mut self, chunk: &Chunk) {
+        self.list(&chunk.code, |writer, instruction| writer.instruction(*instruction));
+        // The line table only has an entry where the position changes.
+        let mut lines = Vec::new();
+        for (pc, position) in chunk.positions.iter().enumerate() {
+            if pc == 0 || chunk.positions[pc - 1] != *position {
+                lines.push((pc as u32, *position));
+            }
+        }
+        self.list(&lines, |writer, (pc, position)| {
+            writer.u32(*pc);
+            writer.range(position);
+        });
+        self.list(&chunk.constants, Writer::constant);
+        self.list(&chunk.names, Writer::string);
+        self.list(&chunk.types, Writer::type_);
+        self.list(&chunk.functions, Writer::function);
+        self.list(&chunk.supers, |writer, call| {
+            writer.option(&call.method, Writer::string);
+            writer.access(call.superclass);
+            writer.access(call.object);
+            writer.u32(call.arguments);
+        });
+        self.list(&chunk.tries, |writer, block| {
+            writer.range(&block.body);
+            writer.list(&block.catches, |writer, catch| {
+                writer.option(&catch.class, |writer, access| writer.access(*access));
+                writer.string(&catch.name);
+                writer.range(&catch.position);
+                writer.range(&catch.code);
+            });
+            writer.option(&block.finally, Writer::range);
+            writer.u32(block.after);
+            writer.u32(block.on_break);
+            writer.u32(block.on_continue);
+        });
+        self.list(&chunk.arms, |writer, arm| {
+            writer.pattern(&arm.pattern);
+            writer.list(&arm.enums, |writer, access| writer.option(access, |writer, access| writer.access(*access)));
+            writer.u32(arm.subject);
+            writer.u32(arm.bindings);
+            writer.u32(arm.next);
+        });
+        self.list(&chunk.classes, |writer, class| {
+            writer.string(&class.name);
+            writer.bool(class.superclass);
+            writer.list(&class.fields, Writer::field);
+            writer.list(&class.static_fields, Writer::field);
+            writer.list(&class.methods, Writer::function);
+            writer.list(&class.static_methods, Writer::function);
+            writer.option(&class.constructor, Writer::function);
+            writer.bool(class.calls_super);
+        });
+        self.list(&chunk.enums, |writer, enum_| {
+            writer.string(&enum_.name);
+            writer.list(&enum_.variants, |writer, (name, arity)| {
+                writer.string(name);
+                writer.u32(*arity as u32);
+            });
+        });
+        self.u32(chunk.locals);
+        self.bool(chunk.environments);
+    }
+
+    fn field(&mut self, field: &Field) {
+        self.string(&field.name);
+        self.type_(&field.type_);
+        self.option(&field.initializer, |writer, initializer| match initializer {
+            Initializer::Code(code) => writer.chunk(code),
+            Initializer::Expression(_) => unreachable!("compiled classes initialize their fields with code"),
+        });
+    }
+
+    fn instruction(&mut self, instruction: Instruction) {
+        let (opcode, operands): (u8, &[u32]) = match instruction {
+            Instruction::Constant(index) => (0, &[index]),
+            Instruction::Pop => (1, &[]),
+            Instruction::Load(access) => {
+                self.u8(2);
+                return self.access(access);
+            }
+            Instruction::Store(access) => {
+                self.u8(3);
+                return self.access(access);
+            }
+            Instruction::SetLocal(slot) => (4, &[slot]),
+            Instruction::Define => (5, &[]),
+            Instruction::PushScope => (6, &[]),
+            Instruction::PopScope => (7, &[]),
+            Instruction::Unary(operator) => (8, &[operator_code(operator)]),
+            Instruction::Binary(operator) => (9, &[operator_code(operator)]),
+            Instruction::Jump(target) => (10, &[target]),
+            Instruction::JumpIfFalse(target) => (11, &[target]),
+            Instruction::And(target) => (12, &[target]),
+            Instruction::Or(target) => (13, &[target]),
+            Instruction::Condition => (14, &[]),
+            Instruction::Call(count) => (15, &[count]),
+            Instruction::CallMethod(name, count) => (16, &[name, count]),
+            Instruction::SuperCall(index) => (17, &[index]),
+            Instruction::New(count) => (18, &[count]),
+            Instruction::Index => (19, &[]),
+            Instruction::SetIndex => (20, &[]),
+            Instruction::Slice => (21, &[]),
+            Instruction::GetMember(name) => (22, &[name]),
+            Instruction::SetMember(name) => (23, &[name]),
+            Instruction::Array(count) => (24, &[count]),
+            Instruction::MapKey => (25, &[]),
+            Instruction::Map(count) => (26, &[count]),
+            Instruction::Cast(index) => (27, &[index]),
+            Instruction::Closure(index) => (28, &[index]),
+            Instruction::Iterate(slot) => (29, &[slot]),
+            Instruction::Next(slot, exit) => (30, &[slot, exit]),
+            Instruction::Throw => (31, &[]),
+            Instruction::Return => (32, &[]),
+            Instruction::Break => (33, &[]),
+            Instruction::Continue => (34, &[]),
+            Instruction::Try(index) => (35, &[index]),
+            Instruction::MatchArm(index) => (36, &[index]),
+            Instruction::NoMatch(slot) => (37, &[slot]),
+            Instruction::Class(index) => (38, &[index]),
+            Instruction::Enum(index) => (39, &[index]),
+            Instruction::Statics => (40, &[]),
+            Instruction::Fail(message) => (41, &[message]),
+        };
+        self.u8(opcode);
+        for operand in operands {
+            self.u32(*operand);
+        }
+    }
+
+    fn access(&mut self, access: Access) {
+        let (tag, operands): (u8, &[u32]) = match access {
+            Access::Local(slot) => (0, &[slot]),
+            Access::Environment(depth, slot) => (1, &[depth, slot]),
+            Access::Global(slot) => (2, &[slot]),
+            Access::Native(index) => (3, &[index]),
+            Access::NativeClass(index) => (4, &[index]),
+            Access::NativeConstant(index) => (5, &[index]),
+        };
+        self.u8(tag);
+        for operand in operands {
+            self.u32(*operand);
+        }
+    }
+
+    fn constant(&mut self, value: &Value) {
+        match value {
+            Value::Integer(integer) => {
+                self.u8(0);
+                self.bytes.extend_from_slice(&integer.to_le_bytes());
+            }
+            Value::Float(float) => {
+                self.u8(1);
+                self.bytes.extend_from_slice(&float.to_le_bytes());
+            }
+            Value::Bool(boolean) => {
+                self.u8(2);
+                self.bool(*boolean);
+            }
+            Value::Char(character) => {
+                self.u8(3);
+                self.u32(*character as u32);
+            }
+            Value::String(string) => {
+                self.u8(4);
+                self.string(&string.to_string());
+            }
+            Value::Null => self.u8(5),
+            value => unreachable!("constants are literals, not {}", value.type_name()),
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        self.constant(&crate::interpreter::literal_value(literal));
+        self.u32(literal.line as u32);
+        self.u32(literal.column as u32);
+    }
+
+    fn type_(&mut self, type_: &Type) {
+        match &type_.kind {
+            TypeKind::Array(element) => {
+                self.u8(0);
+                self.type_(element);
+            }
+            TypeKind::Map(key, value) => {
+                self.u8(1);
+                self.type_(key);
+                self.type_(value);
+            }
+            TypeKind::String => self.u8(2),
+            TypeKind::Char => self.u8(3),
+            TypeKind::Integer => self.u8(4),
+            TypeKind::Float => self.u8(5),
+            TypeKind::Bool => self.u8(6),
+            TypeKind::Void => self.u8(7),
+            TypeKind::Function(parameters, result) => {
+                self.u8(8);
+                self.list(parameters, Writer::type_);
+                self.type_(result);
+            }
+            TypeKind::Named(name, arguments) => {
+                self.u8(9);
+                self.string(name);
+                self.list(arguments, Writer::type_);
+            }
+        }
+        self.u32(type_.line as u32);
+        self.u32(type_.column as u32);
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Wildcard => self.u8(0),
+            PatternKind::Literal(literal) => {
+                self.u8(1);
+                self.literal(literal);
+            }
+            PatternKind::Range(range) => {
+                self.u8(2);
+                self.literal(&range.start);
+                self.literal(&range.end);
+                self.bool(range.inclusive);
+            }
+            PatternKind::Binding(name) => {
+                self.u8(3);
+                self.string(name);
+            }
+            PatternKind::Variant(variant) => {
+                self.u8(4);
+                self.string(&variant.enum_.name);
+                self.u32(variant.enum_.line as u32);
+                self.u32(variant.enum_.column as u32);
+                self.string(&variant.variant);
+                self.list(&variant.fields, Writer::pattern);
+            }
+        }
+        self.u32(pattern.line as u32);
+        self.u32(pattern.column as u32);
+    }
+}
+
+fn operator_code(operator: Operator) -> u32 {
+    OPERATORS.iter().position(|other| *other == operator).expect("every operator is listed") as u32
+}
+
+fn corrupt(message: &str) -> FormatError {
+    FormatError::Corrupt(message.to_string())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// The index of each native the file names among the functions, classes and constants
+    /// registered now, with its name.
+    natives: [Vec<(Option<usize>, String)>; 3],
+    functions: Vec<Prototype>,
+    /// How many types, patterns and chunks enclose the one being read.
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], FormatError> {
+        let end = self.position.checked_add(count).filter(|end| *end <= self.bytes.len()).ok_or_else(|| corrupt("the file ends early"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, FormatError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().expect("four bytes were taken")))
+    }
+
+    fn u64(&mut self) -> Result<u64, FormatError> {
+        let bytes = self.take(8)?;
+        Ok(u64::from_le_bytes(bytes.try_into().expect("eight bytes were taken")))
+    }
+
+    fn usize(&mut self) -> Result<usize, FormatError> {
+        Ok(self.u32()? as usize)
+    }
+
+    fn bool(&mut self) -> Result<bool, FormatError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(corrupt("invalid bool")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, FormatError> {
+        let length = self.usize()?;
+        let bytes = self.take(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| corrupt("invalid UTF-8 in a string"))
+    }
+
+    fn list<T>(&mut self, mut read: impl FnMut(&mut Self) -> Result<T, FormatError>) -> Result<Vec<T>, FormatError> {
+        let count = self.usize()?;
+        let mut items = Vec::new();
+        for _ in 0..count {
+            items.push(read(self)?);
+        }
+        Ok(items)
+    }
+
+    fn option<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, FormatError>) -> Result<Option<T>, FormatError> {
+        match self.bool()? {
+            true => Ok(Some(read(self)?)),
+            false => Ok(None),
+        }
+    }
+
+    /// Reads something nested in what is being read, failing if it nests too deeply.
+    fn nested<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, FormatError>) -> Result<T, FormatError> {
+        if self.depth == MAX_NESTING {
+            return Err(corrupt("nesting is too deep"));
+        }
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn range(&mut self) -> Result<(u32, u32), FormatError> {
+        Ok((self.u32()?, self.u32()?))
+    }
+
+    fn function(&mut self) -> Result<Prototype, FormatError> {
+        let index = self.usize()?;
+        self.functions.get(index).cloned().ok_or_else(|| corrupt("function index out of range"))
+    }
+
+    fn chunk(&mut self) -> Result<Chunk, FormatError> {
+        let code = self.list(Reader::instruction)?;
+        let lines = self.list(|reader| Ok((reader.usize()?, reader.range()?)))?;
+        let mut positions = Vec::with_capacity(code.len());
+        let mut entries = lines.iter().peekable();
+        let mut position = (0, 0);
+        for pc in 0..code.len() {
+            while let Some((_, next)) = entries.next_if(|(start, _)| *start <= pc) {
+                position = *next;
+            }
+            positions.push(position);
+        }
+        Ok(Chunk {
+            code,
+            positions,
+            constants: self.list(Reader::constant)?,
+            names: self.list(Reader::string)?,
+            types: self.list(Reader::type_)?,
+            functions: self.list(Reader::function)?,
+            supers: self.list(|reader| {
+                Ok(SuperCall {
+                    method: reader.option(Reader::string)?,
+                    superclass: reader.access()?,
+                    object: reader.access()?,
+                    arguments: reader.u32()?,
+                })
+            })?,
+            tries: self.list(|reader| {
+                Ok(TryBlock {
+                    body: reader.range()?,
+                    catches: reader.list(|reader| {
+                        Ok(CatchBlock {
+                            class: reader.option(Reader::access)?,
+                            name: reader.string()?,
+                            position: reader.range()?,
+                            code: reader.range()?,
+                        })
+                    })?,
+                    finally: reader.option(Reader::range)?,
+                    after: reader.u32()?,
+                    on_break: reader.u32()?,
+                    on_continue: reader.u32()?,
+                })
+            })?,
+            arms: self.list(|reader| {
+                Ok(MatchArm {
+                    pattern: reader.pattern()?,
+                    enums: reader.list(|reader| reader.option(Reader::access))?,
+                    subject: reader.u32()?,
+                    bindings: reader.u32()?,
+                    next: reader.u32()?,
+                })
+            })?,
+            classes: self.list(|reader| {
+                Ok(ClassPrototype {
+                    name: reader.string()?,
+                    superclass: reader.bool()?,
+                    fields: reader.list(Reader::field)?,
+                    static_fields: reader.list(Reader::field)?,
+                    methods: reader.list(Reader::function)?,
+                    static_methods: reader.list(Reader::function)?,
+                    constructor: reader.option(Reader::function)?,
+                    calls_super: reader.bool()?,
+                })
+            })?,
+            enums: self.list(|reader| {
+                Ok(Enum {
+                    name: reader.string()?,
+                    variants: reader.list(|reader| Ok((reader.string()?, reader.usize()?)))?,
+                })
+            })?,
+            locals: self.u32()?,
+            environments: self.bool()?,
+        })
+    }
+
+    fn field(&mut self) -> Result<Field, FormatError> {
+        Ok(Field {
+            name: self.string()?,
+            type_: self.type_()?,
+            initializer: self.option(|reader| Ok(Initializer::Code(Rc::new(reader.nested(Reader::chunk)?))))?,
+        })
+    }
+
+    fn instruction(&mut self) -> Result<Instruction, FormatError> {
+        let opcode = self.u8()?;
+        Ok(match opcode {
+            0 => Instruction::Constant(self.u32()?),
+            1 => Instruction::Pop,
+            2 => Instruction::Load(self.access()?),
+            3 => Instruction::Store(self.access()?),
+            4 => Instruction::SetLocal(self.u32()?),
+            5 => Instruction::Define,
+            6 => Instruction::PushScope,
+            7 => Instruction::PopScope,
+            8 => Instruction::Unary(self.operator()?),
+            9 => Instruction::Binary(self.operator()?),
+            10 => Instruction::Jump(self.u32()?),
+            11 => Instruction::JumpIfFalse(self.u32()?),
+            12 => Instruction::And(self.u32()?),
+            13 => Instruction::Or(self.u32()?),
+            14 => Instruction::Condition,
+            15 => Instruction::Call(self.u32()?),
+            16 => Instruction::CallMethod(self.u32()?, self.u32()?),
+            17 => Instruction::SuperCall(self.u32()?),
+            18 => Instruction::New(self.u32()?),
+            19 => Instruction::Index,
+            20 => Instruction::SetIndex,
+            21 => Instruction::Slice,
+            22 => Instruction::GetMember(self.u32()?),
+            23 => Instruction::SetMember(self.u32()?),
+            24 => Instruction::Array(self.u32()?),
+            25 => Instruction::MapKey,
+            26 => Instruction::Map(self.u32()?),
+            27 => Instruction::Cast(self.u32()?),
+            28 => Instruction::Closure(self.u32()?),
+            29 => Instruction::Iterate(self.u32()?),
+            30 => Instruction::Next(self.u32()?, self.u32()?),
+            31 => Instruction::Throw,
+            32 => Instruction::Return,
+            33 => Instruction::Break,
+            34 => Instruction::Continue,
+            35 => Instruction::Try(self.u32()?),
+            36 => Instruction::MatchArm(self.u32()?),
+            37 => Instruction::NoMatch(self.u32()?),
+            38 => Instruction::Class(self.u32()?),
+            39 => Instruction::Enum(self.u32()?),
+            40 => Instruction::Statics,
+            41 => Instruction::Fail(self.u32()?),
+            _ => return Err(FormatError::Corrupt(format!("unknown opcode {}", opcode))),
+        })
+    }
+
+    fn operator(&mut self) -> Result<Operator, FormatError> {
+        let code = self.usize()?;
+        OPERATORS.get(code).copied().ok_or_else(|| corrupt("unknown operator"))
+    }
+
+    fn access(&mut self) -> Result<Access, FormatError> {
+        Ok(match self.u8()? {
+            0 => Access::Local(self.u32()?),
+            1 => Access::Environment(self.u32()?, self.u32()?),
+            2 => Access::Global(self.u32()?),
+            3 => Access::Native(self.native(0)?),
+            4 => Access::NativeClass(self.native(1)?),
+            5 => Access::NativeConstant(self.native(2)?),
+            _ => return Err(corrupt("unknown kind of variable")),
+        })
+    }
+
+    /// The index a native the file refers to by its index at compile time has now.
+    fn native(&mut self, kind: usize) -> Result<u32, FormatError> {
+        let index = self.usize()?;
+        match self.natives[kind].get(index) {
+            Some((Some(index), _)) => Ok(*index as u32),
+            Some((None, name)) => Err(FormatError::MissingNative(name.clone())),
+            None => Err(corrupt("native index out of range")),
+        }
+    }
+
+    fn constant(&mut self) -> Result<Value, FormatError> {
+        Ok(match self.u8()? {
+            0 => Value::Integer(self.u64()? as i64),
+            1 => Value::Float(f64::from_bits(self.u64()?)),
+            2 => Value::Bool(self.bool()?),
+            3 => Value::Char(char::from_u32(self.u32()?).ok_or_else(|| corrupt("invalid char"))?),
+            4 => Value::string(&self.string()?),
+            5 => Value::Null,
+            _ => return Err(corrupt("unknown kind of constant")),
+        })
+    }
+
+    fn literal(&mut self) -> Result<Literal, FormatError> {
+        let kind = match self.constant()? {
+            Value::Integer(integer) => LiteralKind::Integer(integer),
+            Value::Float(float) => LiteralKind::Float(float),
+            Value::Bool(boolean) => LiteralKind::Bool(boolean),
+            Value::Char(character) => LiteralKind::Char(character),
+            Value::String(string) => LiteralKind::String(string.to_string()),
+            _ => LiteralKind::Null,
+        };
+        Ok(Literal::new(kind, self.usize()?, self.usize()?))
+    }
+
+    fn type_(&mut self) -> Result<Type, FormatError> {
+        let kind = match self.u8()? {
+            0 => TypeKind::Array(Box::new(self.nested(Reader::type_)?)),
+            1 => TypeKind::Map(Box::new(self.nested(Reader::type_)?), Box::new(self.nested(Reader::type_)?)),
+            2 => TypeKind::String,
+            3 => TypeKind::Char,
+            4 => TypeKind::Integer,
+            5 => TypeKind::Float,
+            6 => TypeKind::Bool,
+            7 => TypeKind::Void,
+            8 => TypeKind::Function(self.nested(|reader| reader.list(Reader::type_))?, Box::new(self.nested(Reader::type_)?)),
+            9 => TypeKind::Named(self.string()?, self.nested(|reader| reader.list(Reader::type_))?),
+            _ => return Err(corrupt("unknown kind of type")),
+        };
+        Ok(Type::new(kind, self.usize()?, self.usize()?))
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, FormatError> {
+        let kind = match self.u8()? {
+            0 => PatternKind::Wildcard,
+            1 => PatternKind::Literal(self.literal()?),
+            2 => PatternKind::Range(RangePattern::new(self.literal()?, self.literal()?, self.bool()?)),
+            3 => PatternKind::Binding(self.string()?),
+            4 => {
+                let enum_ = Variable::new(self.string()?, self.usize()?, self.usize()?);
+                PatternKind::Variant(VariantPattern::new(enum_, self.string()?, self.nested(|reader| reader.list(Reader::pattern))?))
+            }
+            _ => return Err(corrupt("unknown kind of pattern")),
+        };
+        Ok(Pattern::new(kind, self.usize()?, self.usize()?))
+    }
+}
+
+/// Checks that the operands of the code of a chunk are within its tables and frame, and that
+/// the code keeps its stack balanced and its jumps within their blocks, so that a damaged file
+/// fails to load rather than while it runs.
+fn check(chunk: &Chunk, globals: usize) -> Result<(), FormatError> {
+    if chunk.locals > MAX_LOCALS {
+        return Err(corrupt("a frame has too many slots"));
+    }
+    let within = |index: u32, length: usize| (index as usize) < length;
+    let locals = chunk.locals as usize;
+    let access = |access: &Access| match access {
+        Access::Local(slot) => within(*slot, locals),
+        Access::Global(slot) => within(*slot, globals),
+        _ => true,
+    };
+    let target = |target: u32| target as usize <= chunk.code.len();
+    let valid = chunk.code.iter().all(|instruction| match *instruction {
+        Instruction::Constant(index) => within(index, chunk.constants.len()),
+        Instruction::Load(variable) => access(&variable),
+        Instruction::Store(variable) => access(&variable) && matches!(variable, Access::Local(_) | Access::Environment(..) | Access::Global(_)),
+        Instruction::SetLocal(slot) | Instruction::NoMatch(slot) => within(slot, locals),
+        Instruction::Iterate(slot) => slot as usize + 1 < locals,
+        Instruction::Next(slot, exit) => slot as usize + 1 < locals && target(exit),
+        Instruction::Jump(to) | Instruction::JumpIfFalse(to) | Instruction::And(to) | Instruction::Or(to) => target(to),
+        Instruction::CallMethod(name, _) | Instruction::GetMember(name) | Instruction::SetMember(name) | Instruction::Fail(name) => {
+            within(name, chunk.names.len())
+        }
+        Instruction::SuperCall(index) => within(index, chunk.supers.len()),
+        Instruction::Cast(index) => within(index, chunk.types.len()),
+        Instruction::Closure(index) => within(index, chunk.functions.len()),
+        Instruction::Try(index) => within(index, chunk.tries.len()),
+        Instruction::MatchArm(index) => within(index, chunk.arms.len()),
+        Instruction::Class(index) => within(index, chunk.classes.len()),
+        Instruction::Enum(index) => within(index, chunk.enums.len()),
+        _ => true,
+    });
+    let range = |(start, end): (u32, u32)| start <= end && target(end);
+    let valid = valid
+        && chunk.supers.iter().all(|call| access(&call.superclass) && access(&call.object))
+        && chunk.tries.iter().all(|block| {
+            range(block.body)
+                && block.finally.is_none_or(range)
+                && block.catches.iter().all(|catch| range(catch.code) && catch.class.as_ref().is_none_or(access))
+                && [block.after, block.on_break, block.on_continue].into_iter().all(target)
+        })
+        && chunk.arms.iter().all(|arm| {
+            within(arm.subject, locals)
+                && (chunk.environments || arm.bindings as usize + bound(&arm.pattern) <= locals)
+                && target(arm.next)
+                && arm.enums.iter().flatten().all(access)
+        });
+    if !valid {
+        return Err(corrupt("an instruction refers to something that does not exist"));
+    }
+    check_stack(chunk, &blocks(chunk)?)?;
+    for class in &chunk.classes {
+        for field in class.fields.iter().chain(&class.static_fields) {
+            if let Some(Initializer::Code(code)) = &field.initializer {
+                check(code, globals)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The blocks of the `try` statements of a chunk, which the VM runs apart from the code around
+/// them.
+struct Blocks {
+    /// The innermost block each instruction is in, where the whole chunk counts as a block.
+    innermost: Vec<(u32, u32)>,
+    /// The block each block is directly nested in.
+    parents: HashMap<(u32, u32), (u32, u32)>,
+}
+
+/// The ranges of code a `try` statement runs as blocks of their own.
+fn block_ranges(block: &TryBlock) -> impl Iterator<Item = (u32, u32)> + '_ {
+    iter::once(block.body).chain(block.catches.iter().map(|catch| catch.code)).chain(block.finally)
+}
+
+/// Finds the block each instruction is in, failing unless the blocks are properly nested.
+fn blocks(chunk: &Chunk) -> Result<Blocks, FormatError> {
+    let length = chunk.code.len() as u32;
+    let mut ranges: Vec<(u32, u32)> = chunk.tries.iter().flat_map(block_ranges).filter(|(start, end)| start < end).collect();
+    ranges.sort_by_key(|&(start, end)| (start, Reverse(end)));
+    ranges.dedup();
+    let mut ranges = ranges.into_iter().peekable();
+    let mut open = vec![(0, length)];
+    let mut blocks = Blocks { innermost: Vec::with_capacity(chunk.code.len()), parents: HashMap::new() };
+    for pc in 0..length {
+        while open.len() > 1 && open[open.len() - 1].1 <= pc {
+            open.pop();
+        }
+        while let Some(range) = ranges.next_if(|(start, _)| *start == pc) {
+            let parent = open[open.len() - 1];
+            if range.1 > parent.1 {
+                return Err(corrupt("try blocks overlap"));
+            }
+            blocks.parents.insert(range, parent);
+            open.push(range);
+        }
+        if open.len() > MAX_NESTING {
+            return Err(corrupt("try blocks nest too deeply"));
+        }
+        blocks.innermost.push(open[open.len() - 1]);
+    }
+    Ok(blocks)
+}
+
+/// Follows every path through the code of a chunk, checking that each instruction finds its
+/// operands on the stack, that the stack is as high however an instruction is reached, and
+/// that control only leaves a block through its end.
+fn check_stack(chunk: &Chunk, blocks: &Blocks) -> Result<(), FormatError> {
+    let mut heights: Vec<Option<usize>> = vec![None; chunk.code.len()];
+    let mut pending = Vec::new();
+    if !chunk.code.is_empty() {
+        pending.push((0, 0));
+    }
+    // Blocks start with an empty stack, except that a `catch` block starts with what it caught.
+    for block in &chunk.tries {
+        let catches = block.catches.iter().map(|catch| (catch.code, 1));
+        for (range, height) in iter::once((block.body, 0)).chain(catches).chain(block.finally.map(|finally| (finally, 0))) {
+            if range.0 == range.1 {
+                continue;
+            }
+            if blocks.innermost[range.0 as usize] != range {
+                return Err(corrupt("try blocks overlap"));
+            }
+            pending.push((range.0 as usize, height));
+        }
+    }
+    while let Some((pc, height)) = pending.pop() {
+        match heights[pc] {
+            Some(known) if known == height => continue,
+            Some(_) => return Err(corrupt("the stack is unbalanced")),
+            None => heights[pc] = Some(height),
+        }
+        let instruction = chunk.code[pc];
+        let (taken, pushed) = operands(chunk, instruction);
+        let Some(after) = height.checked_sub(taken).map(|rest| rest + pushed) else {
+            return Err(corrupt("an instruction takes more values than the stack holds"));
+        };
+        let block = blocks.innermost[pc];
+        let next = pc as u32 + 1;
+        let successors = match instruction {
+            Instruction::Jump(target) => vec![(target, after)],
+            Instruction::JumpIfFalse(target) => vec![(next, after), (target, after)],
+            Instruction::And(target) | Instruction::Or(target) => vec![(next, after), (target, height)],
+            Instruction::Next(_, exit) => vec![(next, after), (exit, height)],
+            Instruction::Try(index) => {
+                let statement = &chunk.tries[index as usize];
+                // Blocks run only from the block around them, so running them cannot recurse
+                // deeper than they nest.
+                if block_ranges(statement).any(|range| range.0 < range.1 && blocks.parents.get(&range) != Some(&block)) {
+                    return Err(corrupt("a try statement is outside the block around its blocks"));
+                }
+                vec![(statement.after, height), (statement.on_break, height), (statement.on_continue, height)]
+            }
+            Instruction::MatchArm(index) => vec![(next, height), (chunk.arms[index as usize].next, height)],
+            Instruction::Throw | Instruction::Return | Instruction::Break | Instruction::Continue | Instruction::NoMatch(_) | Instruction::Fail(_) => Vec::new(),
+            _ => vec![(next, after)],
+        };
+        for (target, height) in successors {
+            if target == block.1 {
+                continue;
+            }
+            if !(block.0..block.1).contains(&target) || blocks.innermost[target as usize] != block {
+                return Err(corrupt("a jump leaves the block it is in"));
+            }
+            pending.push((target as usize, height));
+        }
+    }
+    Ok(())
+}
+
+/// How many values an instruction takes off the stack, and how many it leaves there when
+/// control goes on to the next one.
+fn operands(chunk: &Chunk, instruction: Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::Constant(_) | Instruction::Load(_) | Instruction::Closure(_) | Instruction::Enum(_) | Instruction::Next(..) => (0, 1),
+        Instruction::Pop
+        | Instruction::SetLocal(_)
+        | Instruction::Define
+        | Instruction::JumpIfFalse(_)
+        | Instruction::And(_)
+        | Instruction::Or(_)
+        | Instruction::Iterate(_)
+        | Instruction::Throw
+        | Instruction::Return
+        | Instruction::Statics => (1, 0),
+        Instruction::Store(_) | Instruction::Unary(_) | Instruction::Condition | Instruction::GetMember(_) | Instruction::MapKey | Instruction::Cast(_) => (1, 1),
+        Instruction::Binary(_) | Instruction::Index | Instruction::SetMember(_) => (2, 1),
+        Instruction::SetIndex | Instruction::Slice => (3, 1),
+        Instruction::Call(count) | Instruction::CallMethod(_, count) | Instruction::New(count) => (count as usize + 1, 1),
+        Instruction::SuperCall(index) => (chunk.supers[index as usize].arguments as usize, 1),
+        Instruction::Array(count) => (count as usize, 1),
+        Instruction::Map(count) => (2 * count as usize, 1),
+        Instruction::Class(index) => (chunk.classes[index as usize].superclass as usize, 1),
+        Instruction::PushScope
+        | Instruction::PopScope
+        | Instruction::Jump(_)
+        | Instruction::Break
+        | Instruction::Continue
+        | Instruction::Try(_)
+        | Instruction::MatchArm(_)
+        | Instruction::NoMatch(_)
+        | Instruction::Fail(_) => (0, 0),
+    }
+}
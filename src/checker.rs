@@ -0,0 +1,2297 @@
+use crate::diagnostics::Diagnostic;
+use crate::module::Module;
+use crate::native::{primitive, NativeClass, Natives, STRING_METHODS};
+use crate::parser::*;
+use crate::resolver::global_name;
+use crate::token::TokenKind;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The static type of an expression.
+///
+/// `Unknown` is produced wherever an error has already been reported, and is
+/// compatible with everything so that one mistake does not cascade.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Float,
+    Bool,
+    Char,
+    String,
+    Void,
+    Null,
+    Array(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Function(Vec<Ty>, Box<Ty>),
+    /// A generic function, whose type arguments are inferred at each call.
+    Generic(Vec<String>, Vec<Ty>, Box<Ty>),
+    /// A type parameter of the generic class or function being checked.
+    Param(String),
+    /// An instance of the named class, with its type arguments.
+    Object(String, Vec<Ty>),
+    /// The class itself, as referred to by its name.
+    Class(String),
+    /// A value of any class implementing the named interface.
+    Interface(String),
+    /// A value of the named enum.
+    Enum(String),
+    /// The enum itself, through which its variants are constructed.
+    EnumType(String),
+    Unknown,
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Int => write!(f, "int"),
+            Ty::Float => write!(f, "float"),
+            Ty::Bool => write!(f, "bool"),
+            Ty::Char => write!(f, "char"),
+            Ty::String => write!(f, "string"),
+            Ty::Void => write!(f, "void"),
+            Ty::Null => write!(f, "null"),
+            Ty::Array(element) => write!(f, "array<{}>", element),
+            Ty::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Ty::Function(parameters, return_type) => {
+                let parameters: Vec<String> = parameters.iter().map(|parameter| parameter.to_string()).collect();
+                write!(f, "fn({}): {}", parameters.join(", "), return_type)
+            }
+            Ty::Generic(type_parameters, parameters, return_type) => {
+                let parameters: Vec<String> = parameters.iter().map(|parameter| parameter.to_string()).collect();
+                write!(f, "fn<{}>({}): {}", type_parameters.join(", "), parameters.join(", "), return_type)
+            }
+            Ty::Param(name) => write!(f, "{}", name),
+            Ty::Object(name, arguments) if arguments.is_empty() => write!(f, "{}", name),
+            Ty::Object(name, arguments) => {
+                let arguments: Vec<String> = arguments.iter().map(|argument| argument.to_string()).collect();
+                write!(f, "{}<{}>", name, arguments.join(", "))
+            }
+            Ty::Class(name) => write!(f, "class {}", name),
+            Ty::Interface(name) => write!(f, "{}", name),
+            Ty::Enum(name) => write!(f, "{}", name),
+            Ty::EnumType(name) => write!(f, "enum {}", name),
+            Ty::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A pattern reduced to what exhaustiveness and reachability checking look at.
+#[derive(Debug, Clone)]
+enum Space {
+    /// Matches every value, like `_` or a binding.
+    Any,
+    Constructor(Constructor, Vec<Space>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Constructor {
+    Bool(bool),
+    /// The variant with this tag, whose payload patterns are the space's fields.
+    Variant(usize),
+    /// An inclusive range of ints or chars; a single value is a range of one.
+    Range(i128, i128),
+    /// Any other literal, which only covers values equal to it.
+    Literal(String),
+}
+
+impl Constructor {
+    /// Whether every value this constructor matches is also matched by `self`.
+    fn covers(&self, other: &Constructor) -> bool {
+        match (self, other) {
+            (Constructor::Range(start, end), Constructor::Range(other_start, other_end)) => start <= other_start && other_end <= end,
+            _ => self == other,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Signature {
+    type_parameters: Vec<String>,
+    parameters: Vec<Ty>,
+    return_type: Ty,
+    line: usize,
+    column: usize,
+}
+
+impl Signature {
+    fn ty(&self) -> Ty {
+        if self.type_parameters.is_empty() {
+            Ty::Function(self.parameters.clone(), Box::new(self.return_type.clone()))
+        } else {
+            Ty::Generic(self.type_parameters.clone(), self.parameters.clone(), Box::new(self.return_type.clone()))
+        }
+    }
+
+    fn substitute(&self, bindings: &HashMap<String, Ty>) -> Signature {
+        // The signature's own type parameters shadow those of its class.
+        let mut bindings = bindings.clone();
+        for type_parameter in &self.type_parameters {
+            bindings.remove(type_parameter);
+        }
+        Signature {
+            type_parameters: self.type_parameters.clone(),
+            parameters: self.parameters.iter().map(|parameter| substitute(parameter, &bindings)).collect(),
+            return_type: substitute(&self.return_type, &bindings),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FieldInfo {
+    ty: Ty,
+    constant: bool,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ClassInfo {
+    type_parameters: Vec<String>,
+    /// The superclass with its type arguments, in terms of this class's type parameters.
+    superclass: Option<(String, Vec<Ty>)>,
+    interfaces: Vec<String>,
+    fields: HashMap<String, FieldInfo>,
+    methods: HashMap<String, Signature>,
+    constructor: Option<Signature>,
+    static_fields: HashMap<String, FieldInfo>,
+    static_methods: HashMap<String, Signature>,
+    /// Visibility of each field and method, and of the constructor under `constructor`.
+    visibility: HashMap<String, Visibility>,
+    /// Index of the module declaring the class.
+    module: usize,
+    /// Whether the class is implemented by the host, which makes it impossible to extend.
+    host: bool,
+}
+
+#[derive(Debug, Clone)]
+struct VariantInfo {
+    name: String,
+    payload: Vec<Ty>,
+}
+
+#[derive(Debug, Clone)]
+struct EnumInfo {
+    /// Variants in declaration order.
+    variants: Vec<VariantInfo>,
+}
+
+#[derive(Debug, Clone)]
+struct InterfaceInfo {
+    methods: HashMap<String, Signature>,
+}
+
+/// Type checks a resolved program.
+///
+/// Runs after the resolver, so every name is known to be declared; the checker
+/// only tracks what type each of them has.
+#[derive(Debug, Default)]
+pub struct Checker {
+    classes: HashMap<String, ClassInfo>,
+    interfaces: HashMap<String, InterfaceInfo>,
+    enums: HashMap<String, EnumInfo>,
+    scopes: Vec<HashMap<String, Ty>>,
+    /// The return type of the function being checked, if any.
+    return_type: Option<Ty>,
+    /// The class whose methods are being checked, if any.
+    class: Option<String>,
+    /// The class whose body is being checked, if any. Unlike `class`, it is kept in
+    /// field initializers and nested functions, which can see private members too.
+    enclosing: Option<String>,
+    /// Type parameters in scope, innermost last.
+    type_parameters: Vec<String>,
+    /// Type names of the module being checked that stand for a qualified name,
+    /// like `Circle` or `shapes.Circle` for `geometry.shapes.Circle`.
+    types: HashMap<String, String>,
+    /// Types that imported modules do not export, by the name the module being checked would
+    /// refer to them with, and where they are declared.
+    private_types: HashMap<String, (String, usize, usize, usize)>,
+    /// The globals of each module checked so far, which modules importing it can refer to.
+    module_globals: Vec<HashMap<String, Ty>>,
+    /// Index of the module being checked.
+    module: usize,
+    /// Types of the native functions, constants and host classes by their name in the registry.
+    natives: HashMap<String, Ty>,
+    diagnostics: Vec<(usize, Diagnostic)>,
+}
+
+/// Type checks the modules of a resolved program, which must be in dependency order.
+///
+/// Diagnostics are paired with the index of the module they were found in.
+pub fn check(modules: &[Module], natives: &Natives) -> Vec<(usize, Diagnostic)> {
+    let mut checker = Checker::default();
+    // Host classes may refer to each other in their signatures, so they are all declared first.
+    for class in natives.classes() {
+        checker.classes.insert(class.name.clone(), host_class_skeleton());
+        checker.natives.insert(class.name.clone(), Ty::Class(class.name.clone()));
+    }
+    for class in natives.classes() {
+        let info = checker.host_class_info(class);
+        checker.classes.insert(class.name.clone(), info);
+    }
+    for native in natives.iter() {
+        let parameters = native.parameters.iter().map(|parameter| checker.ty(parameter)).collect();
+        let ty = Ty::Function(parameters, Box::new(checker.ty(&native.return_type)));
+        checker.natives.insert(native.name.clone(), ty);
+    }
+    for constant in natives.constants() {
+        let ty = checker.ty(&constant.type_);
+        checker.natives.insert(constant.name.clone(), ty);
+    }
+    for index in 0..modules.len() {
+        checker.check_module(modules, index);
+    }
+    checker.diagnostics
+}
+
+impl Checker {
+    fn check_module(&mut self, modules: &[Module], index: usize) {
+        let module = &modules[index];
+        self.module = index;
+        // Types of other modules are qualified with the module name so that modules can reuse names.
+        // Those of the prelude and the entry module are not, which keeps messages about single-file programs short.
+        self.types.clear();
+        self.private_types.clear();
+        if index > 0 && index + 1 < modules.len() {
+            for statement in &module.statements {
+                if let Some(name) = type_name(statement) {
+                    self.types.insert(name.to_string(), format!("{}.{}", module.name, name));
+                }
+            }
+        }
+        // Functions of native modules are only visible through the namespace they are imported as.
+        let mut globals: HashMap<String, Ty> = self.natives.iter().filter(|(name, _)| !name.contains('.')).map(|(name, ty)| (name.clone(), ty.clone())).collect();
+        for (namespace, native) in &module.native_imports {
+            for (name, ty) in &self.natives {
+                if let Some(name) = name.strip_prefix(native.as_str()).and_then(|name| name.strip_prefix('.')) {
+                    globals.insert(format!("{}.{}", namespace, name), ty.clone());
+                }
+            }
+        }
+        if index > 0 {
+            for export in &modules[0].exports {
+                if let Some(ty) = self.module_globals[0].get(export) {
+                    globals.insert(export.clone(), ty.clone());
+                }
+            }
+        }
+        for (namespace, imported) in &module.imports {
+            for statement in &modules[*imported].statements {
+                match type_name(statement) {
+                    Some(name) if !modules[*imported].exports.iter().any(|export| export == name) => {
+                        let name = format!("{}.{}", namespace, name);
+                        let declaration = (modules[*imported].name.clone(), *imported, statement.line, statement.column);
+                        self.private_types.insert(name, declaration);
+                    }
+                    _ => {}
+                }
+            }
+            for export in &modules[*imported].exports {
+                let name = format!("{}.{}", namespace, export);
+                self.types.insert(name.clone(), format!("{}.{}", modules[*imported].name, export));
+                if let Some(ty) = self.module_globals[*imported].get(export) {
+                    globals.insert(name, ty.clone());
+                }
+            }
+        }
+        self.declare_types(&module.statements);
+
+        for statement in &module.statements {
+            if let Some(name) = global_name(statement) {
+                globals.insert(name.to_string(), self.declared_type(statement));
+            }
+        }
+        self.module_globals.push(globals.clone());
+        self.scopes = vec![globals];
+
+        for statement in &module.statements {
+            self.check_statement(statement);
+        }
+        if index + 1 < modules.len() {
+            return;
+        }
+        if let Some(main) = module.statements.iter().find_map(|statement| match &statement.kind {
+            StatementKind::FunctionDeclaration(function) if function.name == "main" => Some((function, statement)),
+            _ => None,
+        }) {
+            self.check_main(main.0, main.1);
+        }
+    }
+
+    fn error(&mut self, message: String, line: usize, column: usize) {
+        // Types are resolved again wherever they are used, which would repeat errors in them.
+        let diagnostic = (self.module, Diagnostic::error(message, line, column));
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push((self.module, diagnostic));
+    }
+
+    /// The name the type written as `name` is registered under.
+    fn type_key(&self, name: &str) -> String {
+        self.types.get(name).cloned().unwrap_or_else(|| name.to_string())
+    }
+
+    /// Registers the classes, interfaces and enums declared in one scope, which may refer to each other.
+    fn declare_types(&mut self, statements: &[Statement]) {
+        let mut classes = Vec::new();
+        let mut enums = Vec::new();
+        let mut interfaces: Vec<&InterfaceDeclaration> = Vec::new();
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::ClassDeclaration(class) => classes.push(class),
+                StatementKind::EnumDeclaration(enum_) => enums.push(enum_),
+                StatementKind::InterfaceDeclaration(interface) => {
+                    // Classes are values and already checked for redeclaration by the resolver, interfaces are not.
+                    let declared = interfaces.iter().any(|other| other.name == interface.name)
+                        || statements.iter().any(|other| global_name(other) == Some(&interface.name));
+                    if declared {
+                        self.error(format!("`{}` is already declared", interface.name), statement.line, statement.column);
+                    }
+                    interfaces.push(interface);
+                }
+                _ => {}
+            }
+        }
+        for interface in &interfaces {
+            self.interfaces.insert(self.type_key(&interface.name), InterfaceInfo { methods: HashMap::new() });
+        }
+        for class in &classes {
+            self.classes.insert(
+                self.type_key(&class.name),
+                ClassInfo {
+                    type_parameters: class.type_parameters.clone(),
+                    superclass: None,
+                    interfaces: Vec::new(),
+                    fields: HashMap::new(),
+                    methods: HashMap::new(),
+                    constructor: None,
+                    static_fields: HashMap::new(),
+                    static_methods: HashMap::new(),
+                    visibility: HashMap::new(),
+                    module: self.module,
+                    host: false,
+                },
+            );
+        }
+        for enum_ in &enums {
+            self.enums.insert(self.type_key(&enum_.name), EnumInfo { variants: Vec::new() });
+        }
+        for enum_ in &enums {
+            let info = self.enum_info(enum_);
+            self.enums.insert(self.type_key(&enum_.name), info);
+        }
+        for interface in &interfaces {
+            let info = self.interface_info(interface);
+            self.interfaces.insert(self.type_key(&interface.name), info);
+        }
+        for class in &classes {
+            let info = self.class_info(class);
+            self.classes.insert(self.type_key(&class.name), info);
+        }
+        for class in &classes {
+            self.check_superclass(class);
+        }
+    }
+
+    fn enum_info(&mut self, enum_: &EnumDeclaration) -> EnumInfo {
+        let mut variants: Vec<VariantInfo> = Vec::new();
+        for variant in &enum_.variants {
+            if variants.iter().any(|other| other.name == variant.name) {
+                self.error(format!("Variant `{}` is already declared in enum `{}`", variant.name, enum_.name), variant.line, variant.column);
+            }
+            let payload = variant.payload.iter().map(|type_| self.ty(type_)).collect();
+            variants.push(VariantInfo { name: variant.name.clone(), payload });
+        }
+        EnumInfo { variants }
+    }
+
+    fn interface_info(&mut self, interface: &InterfaceDeclaration) -> InterfaceInfo {
+        let mut methods = HashMap::new();
+        for method in &interface.methods {
+            if methods.contains_key(&method.name) {
+                self.error(
+                    format!("Method `{}` is already declared in interface `{}`", method.name, interface.name),
+                    method.line,
+                    method.column,
+                );
+            }
+            let signature = self.signature(&[], &method.parameters, Some(&method.return_type), method.line, method.column);
+            methods.insert(method.name.clone(), signature);
+        }
+        InterfaceInfo { methods }
+    }
+
+    /// Describes a host class, whose properties are fields that are constant unless they have a setter.
+    fn host_class_info(&mut self, class: &NativeClass) -> ClassInfo {
+        let mut info = host_class_skeleton();
+        let signature = |checker: &mut Checker, parameters: &[Type], return_type: &Type| Signature {
+            type_parameters: Vec::new(),
+            parameters: parameters.iter().map(|parameter| checker.ty(parameter)).collect(),
+            return_type: checker.ty(return_type),
+            line: 0,
+            column: 0,
+        };
+        for property in &class.properties {
+            let ty = self.ty(&property.type_);
+            info.fields.insert(property.name.clone(), FieldInfo { ty, constant: property.setter.is_none(), line: 0, column: 0 });
+        }
+        for method in &class.methods {
+            info.methods.insert(method.name.clone(), signature(self, &method.parameters, &method.return_type));
+        }
+        if let Some(constructor) = &class.constructor {
+            info.constructor = Some(signature(self, &constructor.parameters, &primitive(TypeKind::Void)));
+        }
+        info
+    }
+
+    fn class_info(&mut self, class: &ClassDeclaration) -> ClassInfo {
+        let depth = self.type_parameters.len();
+        self.type_parameters.extend(class.type_parameters.iter().cloned());
+        let superclass = class.superclass.as_ref().map(|superclass| {
+            let arguments = class.superclass_type_arguments.iter().map(|type_| self.ty(type_)).collect();
+            (self.type_key(&superclass.name), arguments)
+        });
+        let mut info = ClassInfo {
+            type_parameters: class.type_parameters.clone(),
+            superclass,
+            interfaces: Vec::new(),
+            fields: HashMap::new(),
+            methods: HashMap::new(),
+            constructor: None,
+            static_fields: HashMap::new(),
+            static_methods: HashMap::new(),
+            visibility: HashMap::new(),
+            module: self.module,
+            host: false,
+        };
+        for member in &class.body {
+            let (line, column) = (member.statement.line, member.statement.column);
+            if let Some(name) = member_name(&member.statement) {
+                info.visibility.insert(name.to_string(), member.visibility);
+            }
+            let (fields, methods) = if member.is_static {
+                (&mut info.static_fields, &mut info.static_methods)
+            } else {
+                (&mut info.fields, &mut info.methods)
+            };
+            match &member.statement.kind {
+                StatementKind::VarDeclaration(field) => {
+                    let ty = self.ty(&field.type_);
+                    fields.insert(field.name.clone(), FieldInfo { ty, constant: false, line, column });
+                }
+                StatementKind::ConstantDeclaration(constant) => {
+                    if let StatementKind::VarDeclaration(field) = &constant.statement.kind {
+                        let ty = self.ty(&field.type_);
+                        fields.insert(field.name.clone(), FieldInfo { ty, constant: true, line, column });
+                    }
+                }
+                StatementKind::FunctionDeclaration(method) => {
+                    let signature = self.signature(&method.type_parameters, &method.parameters, Some(&method.return_type), line, column);
+                    methods.insert(method.name.clone(), signature);
+                }
+                StatementKind::ConstructorDeclaration(constructor) => {
+                    info.constructor = Some(self.signature(&[], &constructor.parameters, None, line, column));
+                }
+                _ => {}
+            }
+        }
+        for interface in &class.interfaces {
+            let key = self.type_key(&interface.name);
+            if self.interfaces.contains_key(&key) {
+                info.interfaces.push(key);
+            } else if self.classes.contains_key(&key) {
+                self.error(
+                    format!("`{}` is a class, not an interface, and can only be inherited with `extends`", interface.name),
+                    interface.line,
+                    interface.column,
+                );
+            } else {
+                self.error(format!("Unknown interface `{}`", interface.name), interface.line, interface.column);
+            }
+        }
+        self.type_parameters.truncate(depth);
+        info
+    }
+
+    /// Checks that a superclass is a class and does not lead back to the class itself.
+    fn check_superclass(&mut self, class: &ClassDeclaration) {
+        let superclass = match &class.superclass {
+            Some(superclass) => superclass,
+            None => return,
+        };
+        let key = self.type_key(&class.name);
+        let superclass_key = self.type_key(&superclass.name);
+        if !self.classes.contains_key(&superclass_key) {
+            self.error(format!("`{}` is not a class", superclass.name), superclass.line, superclass.column);
+            self.classes.get_mut(&key).unwrap().superclass = None;
+            return;
+        }
+        if self.classes[&superclass_key].host {
+            self.error(format!("`{}` is implemented by the host and cannot be extended", superclass.name), superclass.line, superclass.column);
+            self.classes.get_mut(&key).unwrap().superclass = None;
+            return;
+        }
+        let expected = self.classes[&superclass_key].type_parameters.len();
+        if class.superclass_type_arguments.len() != expected {
+            self.error(
+                format!("`{}` expects {} type argument(s) but was given {}", superclass.name, expected, class.superclass_type_arguments.len()),
+                superclass.line,
+                superclass.column,
+            );
+        }
+        let mut ancestor = Some(superclass_key);
+        for _ in 0..self.classes.len() {
+            match ancestor {
+                Some(name) if name == key => {
+                    self.error(format!("Class `{}` inherits from itself", class.name), superclass.line, superclass.column);
+                    // Break the cycle so that lookups through superclasses terminate.
+                    self.classes.get_mut(&key).unwrap().superclass = None;
+                    return;
+                }
+                Some(name) => ancestor = self.classes.get(&name).and_then(|info| info.superclass.clone()).map(|(superclass, _)| superclass),
+                None => return,
+            }
+        }
+    }
+
+    fn signature(&mut self, type_parameters: &[String], parameters: &[Parameter], return_type: Option<&Type>, line: usize, column: usize) -> Signature {
+        let depth = self.type_parameters.len();
+        self.type_parameters.extend(type_parameters.iter().cloned());
+        let signature = Signature {
+            type_parameters: type_parameters.to_vec(),
+            parameters: parameters.iter().map(|parameter| self.ty(&parameter.type_)).collect(),
+            return_type: return_type.map_or(Ty::Void, |return_type| self.ty(return_type)),
+            line,
+            column,
+        };
+        self.type_parameters.truncate(depth);
+        signature
+    }
+
+    /// The type a top-level declaration gives its name.
+    fn declared_type(&mut self, statement: &Statement) -> Ty {
+        match &statement.kind {
+            StatementKind::VarDeclaration(variable) => self.ty(&variable.type_),
+            StatementKind::ConstantDeclaration(constant) => self.declared_type(&constant.statement),
+            StatementKind::FunctionDeclaration(function) => {
+                self.signature(&function.type_parameters, &function.parameters, Some(&function.return_type), statement.line, statement.column).ty()
+            }
+            StatementKind::ClassDeclaration(class) => Ty::Class(self.type_key(&class.name)),
+            StatementKind::EnumDeclaration(enum_) => Ty::EnumType(self.type_key(&enum_.name)),
+            _ => Ty::Unknown,
+        }
+    }
+
+    fn ty(&mut self, type_: &Type) -> Ty {
+        match &type_.kind {
+            TypeKind::Array(element) => Ty::Array(Box::new(self.ty(element))),
+            TypeKind::Map(key, value) => Ty::Map(Box::new(self.ty(key)), Box::new(self.ty(value))),
+            TypeKind::String => Ty::String,
+            TypeKind::Char => Ty::Char,
+            TypeKind::Integer => Ty::Int,
+            TypeKind::Float => Ty::Float,
+            TypeKind::Bool => Ty::Bool,
+            TypeKind::Void => Ty::Void,
+            TypeKind::Function(parameters, return_type) => {
+                let parameters = parameters.iter().map(|parameter| self.ty(parameter)).collect();
+                Ty::Function(parameters, Box::new(self.ty(return_type)))
+            }
+            TypeKind::Named(name, arguments) => {
+                let arguments: Vec<Ty> = arguments.iter().map(|argument| self.ty(argument)).collect();
+                let key = self.type_key(name);
+                let expected = if self.type_parameters.contains(name) {
+                    0
+                } else {
+                    self.classes.get(&key).map_or(0, |info| info.type_parameters.len())
+                };
+                if arguments.len() != expected {
+                    self.error(format!("`{}` expects {} type argument(s) but was given {}", name, expected, arguments.len()), type_.line, type_.column);
+                }
+                if self.type_parameters.contains(name) {
+                    Ty::Param(name.clone())
+                } else if let Some((module, file, line, column)) = self.private_types.get(name).cloned() {
+                    let (_, short_name) = name.split_once('.').unwrap_or_default();
+                    self.report(
+                        Diagnostic::error(format!("`{}` is private to module `{}`", short_name, module), type_.line, type_.column)
+                            .with_label_in(file, "declared here without `export` or `public`".to_string(), line, column),
+                    );
+                    Ty::Unknown
+                } else if self.classes.contains_key(&key) {
+                    let arguments = if arguments.len() == expected { arguments } else { vec![Ty::Unknown; expected] };
+                    Ty::Object(key, arguments)
+                } else if self.interfaces.contains_key(&key) {
+                    Ty::Interface(key)
+                } else if self.enums.contains_key(&key) {
+                    Ty::Enum(key)
+                } else {
+                    self.error(format!("Unknown type `{}`", name), type_.line, type_.column);
+                    Ty::Unknown
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, ty: Ty) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Ty {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).cloned()).unwrap_or(Ty::Unknown)
+    }
+
+    /// The type arguments a class is instantiated with inside its own body.
+    fn own_arguments(&self, class: &str) -> Vec<Ty> {
+        self.classes.get(class).map(|info| info.type_parameters.iter().cloned().map(Ty::Param).collect()).unwrap_or_default()
+    }
+
+    /// `class` instantiated with `arguments`, followed by each of its superclasses with the
+    /// type arguments that instantiation passes on to them.
+    fn ancestors(&self, class: &str, arguments: &[Ty]) -> Vec<(String, Vec<Ty>)> {
+        let mut ancestors = Vec::new();
+        let mut current = Some((class.to_string(), arguments.to_vec()));
+        while let Some((class, arguments)) = current {
+            let info = match self.classes.get(&class) {
+                Some(info) => info,
+                None => break,
+            };
+            let bindings = bind(&info.type_parameters, &arguments);
+            current = info.superclass.as_ref().map(|(superclass, arguments)| {
+                (superclass.clone(), arguments.iter().map(|argument| substitute(argument, &bindings)).collect())
+            });
+            ancestors.push((class, arguments));
+        }
+        ancestors
+    }
+
+    fn is_subclass(&self, class: &str, arguments: &[Ty], ancestor: &str, ancestor_arguments: &[Ty]) -> bool {
+        self.ancestors(class, arguments).iter().any(|(class, arguments)| {
+            class == ancestor
+                && arguments.len() == ancestor_arguments.len()
+                && arguments.iter().zip(ancestor_arguments).all(|(a, b)| self.same(a, b))
+        })
+    }
+
+    /// Whether `class` or one of its superclasses declares that it implements `interface`.
+    fn implements(&self, class: &str, interface: &str) -> bool {
+        self.ancestors(class, &[])
+            .iter()
+            .any(|(class, _)| self.classes[class].interfaces.iter().any(|implemented| implemented == interface))
+    }
+
+    /// Finds a field declared by `class` or one of its superclasses.
+    fn find_field(&self, class: &str, arguments: &[Ty], name: &str) -> Option<(String, FieldInfo)> {
+        self.ancestors(class, arguments).into_iter().find_map(|(class, arguments)| {
+            let info = &self.classes[&class];
+            let mut field = info.fields.get(name)?.clone();
+            field.ty = substitute(&field.ty, &bind(&info.type_parameters, &arguments));
+            Some((class, field))
+        })
+    }
+
+    /// Finds the method an instance of `class` dispatches `name` to statically.
+    fn find_method(&self, class: &str, arguments: &[Ty], name: &str) -> Option<(String, Signature)> {
+        self.ancestors(class, arguments).into_iter().find_map(|(class, arguments)| {
+            let info = &self.classes[&class];
+            let method = info.methods.get(name)?.substitute(&bind(&info.type_parameters, &arguments));
+            Some((class, method))
+        })
+    }
+
+    /// Finds a static field declared by `class` or one of its superclasses.
+    fn find_static_field(&self, class: &str, name: &str) -> Option<(String, FieldInfo)> {
+        self.ancestors(class, &[]).into_iter().find_map(|(class, _)| Some((class.clone(), self.classes[&class].static_fields.get(name)?.clone())))
+    }
+
+    /// Finds a static method declared by `class` or one of its superclasses.
+    fn find_static_method(&self, class: &str, name: &str) -> Option<(String, Signature)> {
+        self.ancestors(class, &[]).into_iter().find_map(|(class, _)| Some((class.clone(), self.classes[&class].static_methods.get(name)?.clone())))
+    }
+
+    /// The constructor that instantiating `class` runs, which may be inherited.
+    fn find_constructor(&self, class: &str, arguments: &[Ty]) -> Option<Signature> {
+        self.ancestors(class, arguments).into_iter().find_map(|(class, arguments)| {
+            let info = &self.classes[&class];
+            Some(info.constructor.as_ref()?.substitute(&bind(&info.type_parameters, &arguments)))
+        })
+    }
+
+    /// Reports an access to the member `name` of `class`, or one it inherits, from code that cannot see it.
+    fn check_access(&mut self, class: &str, name: &str, line: usize, column: usize) {
+        let owner = self.ancestors(class, &[]).into_iter().find_map(|(class, _)| {
+            let visibility = *self.classes[&class].visibility.get(name)?;
+            Some((class, visibility))
+        });
+        let (owner, visibility) = match owner {
+            Some(owner) => owner,
+            None => return,
+        };
+        let accessible = match visibility {
+            Visibility::Public => true,
+            Visibility::Private => self.enclosing.as_deref() == Some(owner.as_str()),
+            Visibility::Protected => self
+                .enclosing
+                .as_ref()
+                .is_some_and(|enclosing| self.ancestors(enclosing, &[]).iter().any(|(ancestor, _)| *ancestor == owner)),
+        };
+        if accessible {
+            return;
+        }
+        let info = &self.classes[&owner];
+        let (message, declaration) = if let Some(field) = info.fields.get(name).or_else(|| info.static_fields.get(name)) {
+            (format!("Field `{}` of `{}` is {}", name, owner, visibility), (field.line, field.column))
+        } else if let Some(method) = info.methods.get(name).or_else(|| info.static_methods.get(name)) {
+            (format!("Method `{}` of `{}` is {}", name, owner, visibility), (method.line, method.column))
+        } else {
+            let constructor = info.constructor.as_ref().map_or((0, 0), |constructor| (constructor.line, constructor.column));
+            (format!("Constructor of `{}` is {}", owner, visibility), constructor)
+        };
+        let diagnostic = Diagnostic::error(message, line, column).with_label_in(info.module, format!("declared {} here", visibility), declaration.0, declaration.1);
+        self.report(diagnostic);
+    }
+
+    /// Whether a value of type `from` can be stored where a `to` is expected.
+    fn assignable(&self, from: &Ty, to: &Ty) -> bool {
+        match (from, to) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Null, Ty::Object(..) | Ty::Interface(_) | Ty::Enum(_) | Ty::Array(_) | Ty::Map(..) | Ty::Function(..)) => true,
+            (Ty::Object(from, from_arguments), Ty::Object(to, to_arguments)) => self.is_subclass(from, from_arguments, to, to_arguments),
+            (Ty::Object(from, _), Ty::Interface(to)) => self.implements(from, to),
+            // Containers are mutable, so their element types must match exactly.
+            (Ty::Array(from), Ty::Array(to)) => self.same(from, to),
+            (Ty::Map(from_key, from_value), Ty::Map(to_key, to_value)) => self.same(from_key, to_key) && self.same(from_value, to_value),
+            (Ty::Function(from_parameters, from_return), Ty::Function(to_parameters, to_return)) => {
+                from_parameters.len() == to_parameters.len()
+                    && from_parameters.iter().zip(to_parameters).all(|(from, to)| self.assignable(to, from))
+                    && self.assignable(from_return, to_return)
+            }
+            (from, to) => from == to,
+        }
+    }
+
+    /// Whether `a` and `b` are assignable to each other. Nested types are compared once rather
+    /// than once in each direction, which would take exponential time in how deeply they nest.
+    fn same(&self, a: &Ty, b: &Ty) -> bool {
+        match (a, b) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) => true,
+            (Ty::Array(a), Ty::Array(b)) => self.same(a, b),
+            (Ty::Map(a_key, a_value), Ty::Map(b_key, b_value)) => self.same(a_key, b_key) && self.same(a_value, b_value),
+            (Ty::Function(a_parameters, a_return), Ty::Function(b_parameters, b_return)) => {
+                a_parameters.len() == b_parameters.len()
+                    && a_parameters.iter().zip(b_parameters).all(|(a, b)| self.same(a, b))
+                    && self.same(a_return, b_return)
+            }
+            // Classes cannot inherit from themselves, so only instances of one class are subclasses of each other.
+            (Ty::Object(a, a_arguments), Ty::Object(b, b_arguments)) => {
+                a == b && a_arguments.len() == b_arguments.len() && a_arguments.iter().zip(b_arguments).all(|(a, b)| self.same(a, b))
+            }
+            (a, b) => self.assignable(a, b) && self.assignable(b, a),
+        }
+    }
+
+    /// Whether a method with `signature` can be called wherever one with `expected` is expected.
+    fn conforms(&self, signature: &Signature, expected: &Signature) -> bool {
+        signature.type_parameters.len() == expected.type_parameters.len()
+            && signature.parameters.len() == expected.parameters.len()
+            && signature.parameters.iter().zip(&expected.parameters).all(|(a, b)| self.same(a, b))
+            && self.assignable(&signature.return_type, &expected.return_type)
+    }
+
+    fn expect_type(&mut self, expected: &Ty, found: &Ty, what: &str, line: usize, column: usize) {
+        if !self.assignable(found, expected) {
+            self.error(format!("Expected `{}` for {}, found `{}`", expected, what, found), line, column);
+        }
+    }
+
+    fn check_block(&mut self, statements: &[Statement]) {
+        self.scopes.push(HashMap::new());
+        self.declare_types(statements);
+        for (i, statement) in statements.iter().enumerate() {
+            self.check_statement(statement);
+            let jumps = matches!(statement.kind, StatementKind::Return(_) | StatementKind::Throw(_) | StatementKind::Break | StatementKind::Continue);
+            if i + 1 < statements.len() && jumps {
+                let next = &statements[i + 1];
+                self.report(Diagnostic::warning("Unreachable statement".to_string(), next.line, next.column));
+            }
+        }
+        self.scopes.pop();
+    }
+
+    fn check_branch(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Block(block) => self.check_block(&block.statements),
+            _ => self.check_block(std::slice::from_ref(statement)),
+        }
+    }
+
+    fn check_function(&mut self, parameters: &[Parameter], signature: &Signature, body: &[Statement], name: &str) {
+        let previous = self.return_type.replace(signature.return_type.clone());
+        let depth = self.type_parameters.len();
+        self.type_parameters.extend(signature.type_parameters.iter().cloned());
+        self.scopes.push(HashMap::new());
+        for (parameter, ty) in parameters.iter().zip(&signature.parameters) {
+            if *ty == Ty::Void {
+                self.error(format!("Parameter `{}` cannot have type `void`", parameter.name), parameter.type_.line, parameter.type_.column);
+            }
+            self.declare(&parameter.name, ty.clone());
+        }
+        self.check_block(body);
+        self.scopes.pop();
+        self.type_parameters.truncate(depth);
+        self.return_type = previous;
+
+        if signature.return_type != Ty::Void && !body.iter().any(returns) {
+            self.error(format!("`{}` does not return a value on every path", name), signature.line, signature.column);
+        }
+    }
+
+    fn check_variable(&mut self, variable: &VarDeclaration, line: usize, column: usize) -> Ty {
+        let ty = self.ty(&variable.type_);
+        if ty == Ty::Void {
+            self.error(format!("Variable `{}` cannot have type `void`", variable.name), line, column);
+        }
+        if let Some(initializer) = &variable.initializer {
+            let found = self.check_expecting(initializer, &ty);
+            let what = format!("`{}`", variable.name);
+            self.expect_type(&ty, &found, &what, initializer.line, initializer.column);
+        }
+        ty
+    }
+
+    fn check_class(&mut self, class: &ClassDeclaration, line: usize, column: usize) {
+        let key = self.type_key(&class.name);
+        let info = match self.classes.get(&key) {
+            Some(info) => info.clone(),
+            None => return,
+        };
+        for interface in &info.interfaces {
+            self.check_conformance(&key, interface, line, column);
+        }
+        let previous = self.class.replace(key.clone());
+        let enclosing = self.enclosing.replace(key);
+        let depth = self.type_parameters.len();
+        self.type_parameters.extend(class.type_parameters.iter().cloned());
+        for member in &class.body {
+            let (line, column) = (member.statement.line, member.statement.column);
+            if member.is_static {
+                self.check_static_member(&info, &member.statement);
+                continue;
+            }
+            match &member.statement.kind {
+                StatementKind::VarDeclaration(field) => self.check_field(class, field, line, column),
+                StatementKind::ConstantDeclaration(constant) => {
+                    if let StatementKind::VarDeclaration(field) = &constant.statement.kind {
+                        self.check_field(class, field, line, column);
+                    }
+                }
+                StatementKind::FunctionDeclaration(method) => {
+                    let signature = &info.methods[&method.name];
+                    self.check_override(class, &method.name, signature);
+                    self.check_method_body(&method.parameters, signature, &method.body, &method.name);
+                }
+                StatementKind::ConstructorDeclaration(constructor) => {
+                    self.check_constructor(class, constructor, line, column);
+                }
+                _ => {}
+            }
+        }
+        self.type_parameters.truncate(depth);
+        self.class = previous;
+        self.enclosing = enclosing;
+    }
+
+    /// Checks a static field or method, which has no `this` and overrides nothing.
+    fn check_static_member(&mut self, info: &ClassInfo, member: &Statement) {
+        let previous = self.class.take();
+        match &member.kind {
+            StatementKind::VarDeclaration(field) => {
+                self.check_variable(field, member.line, member.column);
+            }
+            StatementKind::ConstantDeclaration(constant) => {
+                if let StatementKind::VarDeclaration(field) = &constant.statement.kind {
+                    self.check_variable(field, member.line, member.column);
+                }
+            }
+            StatementKind::FunctionDeclaration(method) => {
+                let signature = &info.static_methods[&method.name];
+                self.check_function(&method.parameters, signature, &method.body, &method.name);
+            }
+            _ => {}
+        }
+        self.class = previous;
+    }
+
+    /// Checks that a class, with the methods it inherits, provides every method of an interface it implements.
+    fn check_conformance(&mut self, class: &str, interface: &str, line: usize, column: usize) {
+        let mut required: Vec<(String, Signature)> = self.interfaces[interface].methods.clone().into_iter().collect();
+        required.sort_by_key(|(_, signature)| (signature.line, signature.column));
+        for (name, expected) in required {
+            match self.find_method(class, &self.own_arguments(class), &name) {
+                Some((owner, signature)) if self.conforms(&signature, &expected) => {
+                    let visibility = self.classes[&owner].visibility[&name];
+                    if visibility != Visibility::Public {
+                        let module = self.classes[&owner].module;
+                        self.report(
+                            Diagnostic::error(format!("Method `{}.{}` implements `{}.{}` and must be public", owner, name, interface, name), line, column)
+                                .with_label_in(module, format!("declared {} here", visibility), signature.line, signature.column),
+                        );
+                    }
+                }
+                Some((owner, signature)) => self.report(
+                    Diagnostic::error(
+                        format!(
+                            "Method `{}.{}` does not match `{}.{}`: expected `{}`, found `{}`",
+                            owner,
+                            name,
+                            interface,
+                            name,
+                            expected.ty(),
+                            signature.ty()
+                        ),
+                        signature.line,
+                        signature.column,
+                    )
+                    .with_label("interface method declared here".to_string(), expected.line, expected.column),
+                ),
+                None => self.report(
+                    Diagnostic::error(format!("Class `{}` does not implement `{}.{}`", class, interface, name), line, column)
+                        .with_label("interface method declared here".to_string(), expected.line, expected.column),
+                ),
+            }
+        }
+    }
+
+    fn check_field(&mut self, class: &ClassDeclaration, field: &VarDeclaration, line: usize, column: usize) {
+        if let Some((superclass, arguments)) = self.classes.get(&self.type_key(&class.name)).and_then(|info| info.superclass.clone()) {
+            if let Some((owner, inherited)) = self.find_field(&superclass, &arguments, &field.name) {
+                let module = self.classes[&owner].module;
+                self.report(
+                    Diagnostic::error(format!("Field `{}` is already declared by superclass `{}`", field.name, owner), line, column)
+                        .with_label_in(module, "inherited field declared here".to_string(), inherited.line, inherited.column),
+                );
+            }
+        }
+        // Field initializers run before the constructor and cannot refer to `this`.
+        let previous = self.class.take();
+        self.check_variable(field, line, column);
+        self.class = previous;
+    }
+
+    /// Checks that a method overriding an inherited one can be called in its place.
+    fn check_override(&mut self, class: &ClassDeclaration, name: &str, signature: &Signature) {
+        let (superclass, arguments) = match self.classes.get(&self.type_key(&class.name)).and_then(|info| info.superclass.clone()) {
+            Some(superclass) => superclass,
+            None => return,
+        };
+        if let Some((owner, overridden)) = self.find_method(&superclass, &arguments, name) {
+            let module = self.classes[&owner].module;
+            let visibility = self.classes[&self.type_key(&class.name)].visibility[name];
+            let overridden_visibility = self.classes[&owner].visibility[name];
+            if visibility < overridden_visibility {
+                self.report(
+                    Diagnostic::error(
+                        format!("Method `{}` overrides {} `{}.{}` and cannot be made {}", name, overridden_visibility, owner, name, visibility),
+                        signature.line,
+                        signature.column,
+                    )
+                    .with_label_in(module, "overridden method declared here".to_string(), overridden.line, overridden.column),
+                );
+            }
+            if !self.conforms(signature, &overridden) {
+                self.report(
+                    Diagnostic::error(
+                        format!(
+                            "Method `{}` overrides `{}.{}` with an incompatible signature: expected `{}`, found `{}`",
+                            name,
+                            owner,
+                            name,
+                            overridden.ty(),
+                            signature.ty()
+                        ),
+                        signature.line,
+                        signature.column,
+                    )
+                    .with_label_in(module, "overridden method declared here".to_string(), overridden.line, overridden.column),
+                );
+            }
+        } else if let Some((owner, field)) = self.find_field(&superclass, &arguments, name) {
+            let module = self.classes[&owner].module;
+            self.report(
+                Diagnostic::error(format!("Method `{}` conflicts with field `{}.{}`", name, owner, name), signature.line, signature.column)
+                    .with_label_in(module, "field declared here".to_string(), field.line, field.column),
+            );
+        }
+    }
+
+    fn check_method_body(&mut self, parameters: &[Parameter], signature: &Signature, body: &[Statement], name: &str) {
+        // `this` lives in a scope around the parameters, just like at runtime.
+        let this = self.class.clone().map_or(Ty::Unknown, |class| {
+            let arguments = self.own_arguments(&class);
+            Ty::Object(class, arguments)
+        });
+        self.scopes.push(HashMap::from([("this".to_string(), this)]));
+        self.check_function(parameters, signature, body, name);
+        self.scopes.pop();
+    }
+
+    fn check_constructor(&mut self, class: &ClassDeclaration, constructor: &ConstructorDeclaration, line: usize, column: usize) {
+        if let Some(superclass) = &class.superclass {
+            let calls_super = matches!(
+                constructor.body.first().map(|statement| &statement.kind),
+                Some(StatementKind::Expression(Expression { kind: ExpressionKind::Super(super_call), .. })) if super_call.method.is_none()
+            );
+            let takes_arguments = self.find_constructor(&self.type_key(&superclass.name), &[]).is_some_and(|signature| !signature.parameters.is_empty());
+            if !calls_super && takes_arguments {
+                self.error(
+                    format!("Constructor of `{}` must start with `super(...)` because the constructor of `{}` takes arguments", class.name, superclass.name),
+                    line,
+                    column,
+                );
+            }
+        }
+        for statement in constructor.body.iter().skip(1) {
+            if let StatementKind::Expression(Expression { kind: ExpressionKind::Super(super_call), line, column }) = &statement.kind {
+                if super_call.method.is_none() {
+                    self.error("`super(...)` must be the first statement of a constructor".to_string(), *line, *column);
+                }
+            }
+        }
+        let signature = self.signature(&[], &constructor.parameters, None, line, column);
+        self.check_method_body(&constructor.parameters, &signature, &constructor.body, "constructor");
+    }
+
+    fn check_main(&mut self, main: &FunctionDeclaration, statement: &Statement) {
+        let parameters: Vec<Ty> = main.parameters.iter().map(|parameter| self.ty(&parameter.type_)).collect();
+        let return_type = self.ty(&main.return_type);
+        let valid_parameters = match parameters.as_slice() {
+            [] => true,
+            [Ty::Array(element)] => **element == Ty::String,
+            _ => false,
+        };
+        if !main.type_parameters.is_empty() {
+            self.error("`main` cannot have type parameters".to_string(), statement.line, statement.column);
+        }
+        if !valid_parameters {
+            self.error("`main` must take no parameters or a single `array<string>`".to_string(), statement.line, statement.column);
+        }
+        if !matches!(return_type, Ty::Int | Ty::Void) {
+            self.error(format!("`main` must return `int` or `void`, not `{}`", return_type), main.return_type.line, main.return_type.column);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        let (line, column) = (statement.line, statement.column);
+        match &statement.kind {
+            StatementKind::Expression(expression) => {
+                self.check_expression(expression);
+            }
+            StatementKind::VarDeclaration(variable) => {
+                let ty = self.check_variable(variable, line, column);
+                self.declare(&variable.name, ty);
+            }
+            StatementKind::ConstantDeclaration(constant) => {
+                if let StatementKind::VarDeclaration(variable) = &constant.statement.kind {
+                    let ty = self.check_variable(variable, line, column);
+                    self.declare(&variable.name, ty);
+                }
+            }
+            StatementKind::FunctionDeclaration(function) => {
+                let signature = self.signature(&function.type_parameters, &function.parameters, Some(&function.return_type), line, column);
+                self.declare(&function.name, signature.ty());
+                // Functions do not see the `this` of an enclosing method.
+                let previous = self.class.take();
+                self.check_function(&function.parameters, &signature, &function.body, &function.name);
+                self.class = previous;
+            }
+            StatementKind::ClassDeclaration(class) => {
+                self.declare(&class.name, Ty::Class(self.type_key(&class.name)));
+                self.check_class(class, line, column);
+            }
+            StatementKind::EnumDeclaration(enum_) => self.declare(&enum_.name, Ty::EnumType(self.type_key(&enum_.name))),
+            StatementKind::ConstructorDeclaration(_)
+            | StatementKind::InterfaceDeclaration(_)
+            | StatementKind::Import(_)
+            | StatementKind::Export(_) => {}
+            StatementKind::Return(return_statement) => {
+                let expected = match &self.return_type {
+                    Some(expected) => expected.clone(),
+                    None => return,
+                };
+                match &return_statement.expression {
+                    Some(expression) => {
+                        let found = self.check_expecting(expression, &expected);
+                        if expected == Ty::Void {
+                            self.error("Cannot return a value from a function returning `void`".to_string(), expression.line, expression.column);
+                        } else {
+                            self.expect_type(&expected, &found, "return value", expression.line, expression.column);
+                        }
+                    }
+                    None if expected != Ty::Void => {
+                        self.error(format!("Expected a return value of type `{}`", expected), line, column);
+                    }
+                    None => {}
+                }
+            }
+            StatementKind::Block(block) => self.check_block(&block.statements),
+            StatementKind::If(if_statement) => {
+                self.check_condition(&if_statement.condition);
+                self.check_branch(&if_statement.then_branch);
+                if let Some(else_branch) = &if_statement.else_branch {
+                    self.check_branch(else_branch);
+                }
+            }
+            StatementKind::While(while_statement) => {
+                self.check_condition(&while_statement.condition);
+                self.check_branch(&while_statement.body);
+            }
+            StatementKind::For(for_statement) => {
+                let iterable = &for_statement.iterable;
+                let element = match self.check_expression(iterable) {
+                    Ty::Array(element) => *element,
+                    Ty::Map(key, _) => *key,
+                    Ty::String => Ty::Char,
+                    Ty::Unknown => Ty::Unknown,
+                    ty => {
+                        self.error(format!("Cannot iterate over `{}`", ty), iterable.line, iterable.column);
+                        Ty::Unknown
+                    }
+                };
+                self.scopes.push(HashMap::from([(for_statement.name.clone(), element)]));
+                match &for_statement.body.kind {
+                    StatementKind::Block(block) => self.check_block(&block.statements),
+                    _ => self.check_statement(&for_statement.body),
+                }
+                self.scopes.pop();
+            }
+            StatementKind::Throw(throw) => {
+                let ty = self.check_expression(&throw.expression);
+                if ty == Ty::Null || !self.assignable(&ty, &Ty::Object("Error".to_string(), Vec::new())) {
+                    let expression = &throw.expression;
+                    self.error(format!("Only instances of `Error` can be thrown, found `{}`", ty), expression.line, expression.column);
+                }
+            }
+            StatementKind::Try(try_statement) => self.check_try(try_statement),
+            StatementKind::Break | StatementKind::Continue => {}
+        }
+    }
+
+    fn check_try(&mut self, try_statement: &Try) {
+        self.check_block(&try_statement.body);
+        let mut caught: Vec<String> = Vec::new();
+        for catch in &try_statement.catches {
+            let (line, column) = (catch.class.line, catch.class.column);
+            let ty = match self.lookup(&catch.class.name) {
+                Ty::Class(class) if self.is_subclass(&class, &[], "Error", &[]) => {
+                    // A clause for a superclass of an earlier one catches everything the later one would.
+                    if let Some(earlier) = caught.iter().find(|earlier| self.is_subclass(&class, &[], earlier, &[])) {
+                        let message = format!("Unreachable catch clause: `{}` is already caught as `{}`", class, earlier);
+                        self.report(Diagnostic::warning(message, line, column));
+                    }
+                    caught.push(class.clone());
+                    let arguments = vec![Ty::Unknown; self.classes[&class].type_parameters.len()];
+                    Ty::Object(class, arguments)
+                }
+                Ty::Class(class) => {
+                    self.error(format!("`{}` does not extend `Error` and cannot be caught", class), line, column);
+                    Ty::Unknown
+                }
+                Ty::Unknown => Ty::Unknown,
+                ty => {
+                    self.error(format!("Expected an error class to catch, found `{}`", ty), line, column);
+                    Ty::Unknown
+                }
+            };
+            self.scopes.push(HashMap::from([(catch.name.clone(), ty)]));
+            self.check_block(&catch.body);
+            self.scopes.pop();
+        }
+        if let Some(finally) = &try_statement.finally {
+            self.check_block(finally);
+        }
+    }
+
+    fn check_condition(&mut self, condition: &Expression) {
+        let ty = self.check_expression(condition);
+        self.expect_type(&Ty::Bool, &ty, "condition", condition.line, condition.column);
+    }
+
+    /// Reports type arguments given to a function without type parameters.
+    fn check_not_generic(&mut self, callee: &str, type_arguments: &[Ty], line: usize, column: usize) {
+        if !type_arguments.is_empty() {
+            self.error(format!("{} is not generic, so it takes no type arguments", callee), line, column);
+        }
+    }
+
+    fn check_arguments(&mut self, callee: &str, parameters: &[Ty], arguments: &[Expression], line: usize, column: usize) {
+        if parameters.len() != arguments.len() {
+            for argument in arguments {
+                self.check_expression(argument);
+            }
+            self.error(
+                format!("{} expects {} argument(s) but was given {}", callee, parameters.len(), arguments.len()),
+                line,
+                column,
+            );
+            return;
+        }
+        for (i, (parameter, argument)) in parameters.iter().zip(arguments).enumerate() {
+            let found = self.check_expecting(argument, parameter);
+            let what = format!("argument {} of {}", i + 1, callee);
+            self.expect_type(parameter, &found, &what, argument.line, argument.column);
+        }
+    }
+
+    /// Checks an expression whose type is known from context, which container literals take on.
+    fn check_expecting(&mut self, expression: &Expression, expected: &Ty) -> Ty {
+        match (&expression.kind, expected) {
+            (ExpressionKind::Parenthesized(parenthesized), _) => self.check_expecting(&parenthesized.expression, expected),
+            (ExpressionKind::Array(array), Ty::Array(element_type)) => {
+                for element in &array.elements {
+                    let found = self.check_expecting(element, element_type);
+                    self.expect_type(element_type, &found, "array element", element.line, element.column);
+                }
+                expected.clone()
+            }
+            (ExpressionKind::Map(map), Ty::Map(key_type, value_type)) => {
+                for (key, value) in &map.entries {
+                    let found = self.check_expecting(key, key_type);
+                    self.expect_type(key_type, &found, "map key", key.line, key.column);
+                    let found = self.check_expecting(value, value_type);
+                    self.expect_type(value_type, &found, "map value", value.line, value.column);
+                }
+                expected.clone()
+            }
+            (ExpressionKind::New(new), _) => self.check_new(new, expected, expression.line, expression.column),
+            _ => self.check_expression(expression),
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) -> Ty {
+        let (line, column) = (expression.line, expression.column);
+        match &expression.kind {
+            ExpressionKind::Literal(literal) => literal_type(literal),
+            ExpressionKind::Parenthesized(parenthesized) => self.check_expression(&parenthesized.expression),
+            ExpressionKind::Variable(variable) => self.lookup(&variable.name),
+            ExpressionKind::Binary(binary) => self.check_binary(binary, line, column),
+            ExpressionKind::Unary(unary) => {
+                let operand = self.check_expression(&unary.operand);
+                match (&unary.operator, &operand) {
+                    (_, Ty::Unknown) => Ty::Unknown,
+                    (TokenKind::Minus, Ty::Int) => {
+                        if let Some(integer) = constant_int(&unary.operand).filter(|integer| integer.checked_neg().is_none()) {
+                            self.error(format!("Integer overflow in `-({})`", integer), line, column);
+                        }
+                        operand
+                    }
+                    (TokenKind::Minus, Ty::Float) => operand,
+                    (TokenKind::Bang, Ty::Bool) => Ty::Bool,
+                    (operator, operand) => {
+                        self.error(format!("Cannot apply `{}` to `{}`", operator_symbol(operator), operand), line, column);
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExpressionKind::Call(call) => {
+                let ty = self.check_expression(&call.callee);
+                // Functions called by name are referred to by it in errors.
+                let callee = match &call.callee.kind {
+                    ExpressionKind::Variable(variable) => format!("`{}`", variable.name),
+                    _ => "the function".to_string(),
+                };
+                let type_arguments: Vec<Ty> = call.type_arguments.iter().map(|type_| self.ty(type_)).collect();
+                match ty {
+                    Ty::Function(parameters, return_type) => {
+                        self.check_not_generic(&callee, &type_arguments, line, column);
+                        self.check_arguments(&callee, &parameters, &call.arguments, line, column);
+                        *return_type
+                    }
+                    function @ Ty::Generic(..) => self.check_generic_call(&callee, &function, &type_arguments, &call.arguments, line, column),
+                    Ty::Unknown => {
+                        for argument in &call.arguments {
+                            self.check_expression(argument);
+                        }
+                        Ty::Unknown
+                    }
+                    Ty::Class(name) => {
+                        let type_arguments = Some(type_arguments).filter(|type_arguments| !type_arguments.is_empty());
+                        self.check_instantiation(&name, type_arguments, &call.arguments, line, column)
+                    }
+                    ty => {
+                        for argument in &call.arguments {
+                            self.check_expression(argument);
+                        }
+                        self.error(format!("Value of type `{}` is not callable", ty), line, column);
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExpressionKind::MethodCall(method_call) => {
+                let receiver = self.check_expression(&method_call.receiver);
+                if let Ty::EnumType(enum_) = &receiver {
+                    return self.check_variant(enum_, &method_call.name, Some(&method_call.arguments), line, column);
+                }
+                let type_arguments: Vec<Ty> = method_call.type_arguments.iter().map(|type_| self.ty(type_)).collect();
+                match self.member_type(&receiver, &method_call.name, line, column) {
+                    Ty::Function(parameters, return_type) => {
+                        let callee = format!("`{}`", method_call.name);
+                        self.check_not_generic(&callee, &type_arguments, line, column);
+                        self.check_arguments(&callee, &parameters, &method_call.arguments, line, column);
+                        *return_type
+                    }
+                    function @ Ty::Generic(..) => {
+                        let callee = format!("`{}`", method_call.name);
+                        self.check_generic_call(&callee, &function, &type_arguments, &method_call.arguments, line, column)
+                    }
+                    Ty::Unknown => {
+                        for argument in &method_call.arguments {
+                            self.check_expression(argument);
+                        }
+                        Ty::Unknown
+                    }
+                    ty => {
+                        self.error(format!("`{}` of type `{}` is not callable", method_call.name, ty), line, column);
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExpressionKind::Member(member) => {
+                let callee = self.check_expression(&member.callee);
+                let ty = self.member_type(&callee, &member.name, line, column);
+                // Builtin methods are not values of their own.
+                if matches!(callee, Ty::Array(_) | Ty::Map(..)) && ty != Ty::Unknown {
+                    self.error(format!("`{}.{}` can only be called", callee, member.name), line, column);
+                }
+                ty
+            }
+            ExpressionKind::Super(super_call) => self.check_super(super_call, line, column),
+            ExpressionKind::New(new) => self.check_new(new, &Ty::Unknown, line, column),
+            ExpressionKind::Index(index) => {
+                let callee = self.check_expression(&index.callee);
+                let key = self.check_expression(&index.index);
+                self.index_type(&callee, &key, line, column)
+            }
+            ExpressionKind::Slice(slice) => {
+                let callee = self.check_expression(&slice.callee);
+                for bound in [&slice.start, &slice.end] {
+                    let ty = self.check_expression(bound);
+                    self.expect_type(&Ty::Int, &ty, "slice bound", bound.line, bound.column);
+                }
+                match callee {
+                    Ty::Array(_) | Ty::String | Ty::Unknown => callee,
+                    ty => {
+                        self.error(format!("Cannot slice `{}`", ty), line, column);
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExpressionKind::Match(match_expression) => self.check_match(match_expression, line, column),
+            ExpressionKind::Cast(cast) => {
+                let from = self.check_expression(&cast.expression);
+                let to = self.ty(&cast.type_);
+                match (&from, &to) {
+                    (Ty::Unknown, _) | (_, Ty::Unknown) | (Ty::Int | Ty::Float, Ty::Int | Ty::Float) => to,
+                    _ => {
+                        self.error(format!("Cannot convert `{}` to `{}`", from, to), line, column);
+                        Ty::Unknown
+                    }
+                }
+            }
+            ExpressionKind::Lambda(lambda) => {
+                let signature = self.signature(&[], &lambda.parameters, Some(&lambda.return_type), line, column);
+                self.check_function(&lambda.parameters, &signature, &lambda.body, "lambda");
+                signature.ty()
+            }
+            ExpressionKind::Array(array) => {
+                let mut element = Ty::Unknown;
+                for expression in &array.elements {
+                    let ty = self.check_expression(expression);
+                    element = self.unify(element, ty, "array element", expression);
+                }
+                Ty::Array(Box::new(element))
+            }
+            ExpressionKind::Map(map) => {
+                let (mut key_type, mut value_type) = (Ty::Unknown, Ty::Unknown);
+                for (key, value) in &map.entries {
+                    let ty = self.check_expression(key);
+                    if !matches!(ty, Ty::Bool | Ty::Int | Ty::Char | Ty::String | Ty::Unknown) {
+                        self.error(format!("`{}` cannot be used as a map key", ty), key.line, key.column);
+                    }
+                    key_type = self.unify(key_type, ty, "map key", key);
+                    let ty = self.check_expression(value);
+                    value_type = self.unify(value_type, ty, "map value", value);
+                }
+                Ty::Map(Box::new(key_type), Box::new(value_type))
+            }
+            ExpressionKind::Assign(assign) => {
+                let target = match &assign.target.kind {
+                    ExpressionKind::Member(member) => {
+                        let callee = self.check_expression(&member.callee);
+                        self.check_field_assignment(&callee, &member.name, line, column);
+                        self.member_type(&callee, &member.name, assign.target.line, assign.target.column)
+                    }
+                    _ => self.check_expression(&assign.target),
+                };
+                let value = self.check_expecting(&assign.value, &target);
+                self.expect_type(&target, &value, "assignment", assign.value.line, assign.value.column);
+                target
+            }
+        }
+    }
+
+    fn check_new(&mut self, new: &Instantiation, expected: &Ty, line: usize, column: usize) -> Ty {
+        match self.check_expression(&new.class) {
+            Ty::Class(name) => {
+                let type_arguments = if !new.type_arguments.is_empty() {
+                    Some(new.type_arguments.iter().map(|type_| self.ty(type_)).collect())
+                } else {
+                    // `var stack: Stack<int> = new Stack();` takes its type arguments from the variable.
+                    match expected {
+                        Ty::Object(class, type_arguments) if *class == name => Some(type_arguments.clone()),
+                        _ => None,
+                    }
+                };
+                self.check_instantiation(&name, type_arguments, &new.arguments, line, column)
+            }
+            Ty::Unknown => {
+                for argument in &new.arguments {
+                    self.check_expression(argument);
+                }
+                Ty::Unknown
+            }
+            ty => {
+                self.error(format!("Cannot instantiate `{}`, which is not a class", ty), new.class.line, new.class.column);
+                Ty::Unknown
+            }
+        }
+    }
+
+    /// Checks the instantiation of `class`, inferring its type arguments from the constructor arguments when none are given.
+    fn check_instantiation(&mut self, class: &str, type_arguments: Option<Vec<Ty>>, arguments: &[Expression], line: usize, column: usize) -> Ty {
+        let type_parameters = self.classes.get(class).map(|info| info.type_parameters.clone()).unwrap_or_default();
+        let callee = format!("the constructor of `{}`", class);
+        self.check_access(class, "constructor", line, column);
+        if self.classes.get(class).is_some_and(|info| info.host && info.constructor.is_none()) {
+            self.error(format!("`{}` has no constructor and can only be created by the host", class), line, column);
+        }
+        match type_arguments {
+            None if !type_parameters.is_empty() => {
+                let own_arguments = self.own_arguments(class);
+                let parameters = self.find_constructor(class, &own_arguments).map(|constructor| constructor.parameters).unwrap_or_default();
+                // The constructor of a generic class is checked like a generic function returning an instance.
+                let constructor = Ty::Generic(type_parameters, parameters, Box::new(Ty::Object(class.to_string(), own_arguments)));
+                self.check_generic_call(&callee, &constructor, &[], arguments, line, column)
+            }
+            type_arguments => {
+                let mut type_arguments = type_arguments.unwrap_or_default();
+                if type_arguments.len() != type_parameters.len() {
+                    self.error(
+                        format!("`{}` expects {} type argument(s) but was given {}", class, type_parameters.len(), type_arguments.len()),
+                        line,
+                        column,
+                    );
+                    type_arguments = vec![Ty::Unknown; type_parameters.len()];
+                }
+                let parameters = self.find_constructor(class, &type_arguments).map(|constructor| constructor.parameters).unwrap_or_default();
+                self.check_arguments(&callee, &parameters, arguments, line, column);
+                Ty::Object(class.to_string(), type_arguments)
+            }
+        }
+    }
+
+    /// Checks a call to a generic function, inferring its type arguments from the types of the
+    /// arguments unless they are given.
+    fn check_generic_call(&mut self, callee: &str, function: &Ty, type_arguments: &[Ty], arguments: &[Expression], line: usize, column: usize) -> Ty {
+        let (type_parameters, parameters, return_type) = match function {
+            Ty::Generic(type_parameters, parameters, return_type) => (type_parameters, parameters, &**return_type),
+            _ => return Ty::Unknown,
+        };
+        if !type_arguments.is_empty() {
+            let bindings = if type_arguments.len() == type_parameters.len() {
+                bind(type_parameters, type_arguments)
+            } else {
+                let message = format!("{} expects {} type argument(s) but was given {}", callee, type_parameters.len(), type_arguments.len());
+                self.error(message, line, column);
+                bind(type_parameters, &[])
+            };
+            let parameters: Vec<Ty> = parameters.iter().map(|parameter| substitute(parameter, &bindings)).collect();
+            self.check_arguments(callee, &parameters, arguments, line, column);
+            return substitute(return_type, &bindings);
+        }
+        if parameters.len() != arguments.len() {
+            self.check_arguments(callee, parameters, arguments, line, column);
+            return substitute(return_type, &bind(type_parameters, &[]));
+        }
+        let found: Vec<Ty> = arguments.iter().map(|argument| self.check_expression(argument)).collect();
+        let mut bindings = HashMap::new();
+        for (parameter, found) in parameters.iter().zip(&found) {
+            infer(parameter, found, type_parameters, &mut bindings);
+        }
+        for type_parameter in type_parameters {
+            if !bindings.contains_key(type_parameter) {
+                self.error(format!("Cannot infer type argument `{}` of {}", type_parameter, callee), line, column);
+                bindings.insert(type_parameter.clone(), Ty::Unknown);
+            }
+        }
+        for (i, ((parameter, argument), found)) in parameters.iter().zip(arguments).zip(&found).enumerate() {
+            let what = format!("argument {} of {}", i + 1, callee);
+            self.expect_type(&substitute(parameter, &bindings), found, &what, argument.line, argument.column);
+        }
+        substitute(return_type, &bindings)
+    }
+
+    fn check_match(&mut self, match_expression: &Match, line: usize, column: usize) -> Ty {
+        let subject = self.check_expression(&match_expression.subject);
+        let mut result = Ty::Unknown;
+        // Rows of the patterns seen so far that match unconditionally.
+        let mut rows: Vec<Vec<Space>> = Vec::new();
+        for arm in &match_expression.arms {
+            let mut bindings = Vec::new();
+            let space = self.check_pattern(&arm.pattern, &subject, &mut bindings);
+            if !self.useful(&rows, std::slice::from_ref(&space), std::slice::from_ref(&subject)) {
+                self.report(Diagnostic::warning("Unreachable match arm".to_string(), arm.pattern.line, arm.pattern.column));
+            }
+            if arm.guard.is_none() {
+                rows.push(vec![space]);
+            }
+
+            self.scopes.push(bindings.into_iter().collect());
+            if let Some(guard) = &arm.guard {
+                self.check_condition(guard);
+            }
+            let ty = self.check_expression(&arm.body);
+            result = self.unify(result, ty, "match arm", &arm.body);
+            self.scopes.pop();
+        }
+        if subject != Ty::Unknown {
+            if let Some(witness) = self.missing(&rows, std::slice::from_ref(&subject)) {
+                self.error(format!("Non-exhaustive match: `{}` is not covered", witness[0]), line, column);
+            }
+        }
+        result
+    }
+
+    /// Checks a pattern against the type of the value it is matched with, collecting the names it binds.
+    fn check_pattern(&mut self, pattern: &Pattern, ty: &Ty, bindings: &mut Vec<(String, Ty)>) -> Space {
+        let (line, column) = (pattern.line, pattern.column);
+        match &pattern.kind {
+            PatternKind::Wildcard => Space::Any,
+            PatternKind::Binding(name) => {
+                bindings.push((name.clone(), ty.clone()));
+                Space::Any
+            }
+            PatternKind::Literal(literal) => {
+                let found = literal_type(literal);
+                if !self.assignable(&found, ty) {
+                    self.error(format!("Expected a pattern of type `{}`, found `{}`", ty, found), line, column);
+                }
+                let constructor = match &literal.kind {
+                    LiteralKind::Bool(boolean) => Constructor::Bool(*boolean),
+                    LiteralKind::Integer(integer) => Constructor::Range(*integer as i128, *integer as i128),
+                    LiteralKind::Char(character) => Constructor::Range(*character as i128, *character as i128),
+                    kind => Constructor::Literal(format!("{:?}", kind)),
+                };
+                Space::Constructor(constructor, vec![])
+            }
+            PatternKind::Range(range) => {
+                let (start, end) = (literal_type(&range.start), literal_type(&range.end));
+                if start != end || !matches!(start, Ty::Int | Ty::Float | Ty::Char) {
+                    self.error(format!("Range patterns need `int`, `float` or `char` bounds of the same type, found `{}` and `{}`", start, end), line, column);
+                } else if !self.assignable(&start, ty) {
+                    self.error(format!("Expected a pattern of type `{}`, found `{}`", ty, start), line, column);
+                }
+                let bound = |literal: &Literal| match literal.kind {
+                    LiteralKind::Integer(integer) => Some(integer as i128),
+                    LiteralKind::Char(character) => Some(character as i128),
+                    _ => None,
+                };
+                match (bound(&range.start), bound(&range.end)) {
+                    (Some(start), Some(end)) => {
+                        let end = if range.inclusive { end } else { end - 1 };
+                        if start > end {
+                            self.error("Range pattern matches nothing".to_string(), line, column);
+                        }
+                        Space::Constructor(Constructor::Range(start, end), vec![])
+                    }
+                    _ => Space::Constructor(Constructor::Literal(format!("{:?}", range)), vec![]),
+                }
+            }
+            PatternKind::Variant(pattern) => {
+                let enum_ = match self.lookup(&pattern.enum_.name) {
+                    Ty::EnumType(enum_) => enum_,
+                    found => {
+                        self.error(format!("`{}` is not an enum", pattern.enum_.name), line, column);
+                        for field in &pattern.fields {
+                            self.check_pattern(field, &Ty::Unknown, bindings);
+                        }
+                        return if found == Ty::Unknown { Space::Any } else { Space::Constructor(Constructor::Literal(String::new()), vec![]) };
+                    }
+                };
+                if !self.assignable(&Ty::Enum(enum_.clone()), ty) {
+                    self.error(format!("Expected a pattern of type `{}`, found `{}`", ty, enum_), line, column);
+                }
+                let variants = self.enums.get(&enum_).map(|info| info.variants.clone()).unwrap_or_default();
+                let tag = match variants.iter().position(|variant| variant.name == pattern.variant) {
+                    Some(tag) => tag,
+                    None => {
+                        self.error(format!("`{}` has no variant `{}`", enum_, pattern.variant), line, column);
+                        return Space::Constructor(Constructor::Literal(String::new()), vec![]);
+                    }
+                };
+                let payload = &variants[tag].payload;
+                if payload.len() != pattern.fields.len() {
+                    self.error(
+                        format!("Variant `{}.{}` has {} value(s) but the pattern has {}", enum_, pattern.variant, payload.len(), pattern.fields.len()),
+                        line,
+                        column,
+                    );
+                }
+                let fields = (0..payload.len())
+                    .map(|i| match pattern.fields.get(i) {
+                        Some(field) => self.check_pattern(field, &payload[i], bindings),
+                        None => Space::Any,
+                    })
+                    .collect();
+                Space::Constructor(Constructor::Variant(tag), fields)
+            }
+        }
+    }
+
+    /// Every constructor of a type whose values can be enumerated, or `None` for types like `int`.
+    fn constructors(&self, ty: &Ty) -> Option<Vec<Constructor>> {
+        match ty {
+            Ty::Bool => Some(vec![Constructor::Bool(true), Constructor::Bool(false)]),
+            Ty::Enum(enum_) => self.enums.get(enum_).map(|info| (0..info.variants.len()).map(Constructor::Variant).collect()),
+            _ => None,
+        }
+    }
+
+    fn field_types(&self, ty: &Ty, constructor: &Constructor) -> Vec<Ty> {
+        match (ty, constructor) {
+            (Ty::Enum(enum_), Constructor::Variant(tag)) => self.enums[enum_].variants[*tag].payload.clone(),
+            _ => vec![],
+        }
+    }
+
+    /// The rows that still apply once the first value is known to be built with `constructor`.
+    fn specialize(&self, rows: &[Vec<Space>], constructor: &Constructor, arity: usize) -> Vec<Vec<Space>> {
+        rows.iter()
+            .filter_map(|row| {
+                let mut fields = match &row[0] {
+                    Space::Any => vec![Space::Any; arity],
+                    Space::Constructor(head, fields) if head.covers(constructor) => fields.clone(),
+                    Space::Constructor(..) => return None,
+                };
+                fields.extend_from_slice(&row[1..]);
+                Some(fields)
+            })
+            .collect()
+    }
+
+    /// The rows that still apply to a first value none of their constructors match.
+    fn default_rows(&self, rows: &[Vec<Space>]) -> Vec<Vec<Space>> {
+        rows.iter().filter(|row| matches!(row[0], Space::Any)).map(|row| row[1..].to_vec()).collect()
+    }
+
+    fn covered(&self, rows: &[Vec<Space>], constructor: &Constructor) -> bool {
+        rows.iter().any(|row| matches!(&row[0], Space::Constructor(head, _) if head.covers(constructor)))
+    }
+
+    /// Whether a row of patterns matches some values that none of `rows` match.
+    fn useful(&self, rows: &[Vec<Space>], vector: &[Space], types: &[Ty]) -> bool {
+        let (head, rest) = match vector.split_first() {
+            Some(split) => split,
+            None => return rows.is_empty(),
+        };
+        let expand = |constructor: &Constructor, fields: Vec<Space>| {
+            let mut types_ = self.field_types(&types[0], constructor);
+            types_.extend_from_slice(&types[1..]);
+            let mut vector = fields;
+            vector.extend_from_slice(rest);
+            (types_, vector)
+        };
+        match head {
+            Space::Constructor(constructor, fields) => {
+                let (types, vector) = expand(constructor, fields.clone());
+                self.useful(&self.specialize(rows, constructor, fields.len()), &vector, &types)
+            }
+            Space::Any => match self.constructors(&types[0]) {
+                Some(all) if all.iter().all(|constructor| self.covered(rows, constructor)) => all.iter().any(|constructor| {
+                    let arity = self.field_types(&types[0], constructor).len();
+                    let (types, vector) = expand(constructor, vec![Space::Any; arity]);
+                    self.useful(&self.specialize(rows, constructor, arity), &vector, &types)
+                }),
+                _ => self.useful(&self.default_rows(rows), rest, &types[1..]),
+            },
+        }
+    }
+
+    /// Finds values, written as patterns, that none of `rows` match.
+    fn missing(&self, rows: &[Vec<Space>], types: &[Ty]) -> Option<Vec<String>> {
+        let (ty, rest) = match types.split_first() {
+            Some(split) => split,
+            None => return if rows.is_empty() { Some(vec![]) } else { None },
+        };
+        let all = match self.constructors(ty) {
+            Some(all) => all,
+            None => {
+                let mut witness = self.missing(&self.default_rows(rows), rest)?;
+                witness.insert(0, "_".to_string());
+                return Some(witness);
+            }
+        };
+        match all.iter().find(|constructor| !self.covered(rows, constructor)) {
+            Some(constructor) => {
+                let mut witness = self.missing(&self.default_rows(rows), rest)?;
+                let arity = self.field_types(ty, constructor).len();
+                witness.insert(0, self.describe(ty, constructor, vec!["_".to_string(); arity]));
+                Some(witness)
+            }
+            None => all.iter().find_map(|constructor| {
+                let mut types = self.field_types(ty, constructor);
+                let arity = types.len();
+                types.extend_from_slice(rest);
+                let mut witness = self.missing(&self.specialize(rows, constructor, arity), &types)?;
+                let fields = witness.drain(..arity).collect();
+                witness.insert(0, self.describe(ty, constructor, fields));
+                Some(witness)
+            }),
+        }
+    }
+
+    fn describe(&self, ty: &Ty, constructor: &Constructor, fields: Vec<String>) -> String {
+        match (ty, constructor) {
+            (Ty::Enum(enum_), Constructor::Variant(tag)) => {
+                let name = &self.enums[enum_].variants[*tag].name;
+                if fields.is_empty() {
+                    format!("{}.{}", enum_, name)
+                } else {
+                    format!("{}.{}({})", enum_, name, fields.join(", "))
+                }
+            }
+            (_, Constructor::Bool(boolean)) => boolean.to_string(),
+            _ => "_".to_string(),
+        }
+    }
+
+    /// Folds the type of one more element into the element type of a container literal.
+    fn unify(&mut self, current: Ty, next: Ty, what: &str, expression: &Expression) -> Ty {
+        if self.assignable(&next, &current) && current != Ty::Unknown && current != Ty::Null {
+            current
+        } else if self.assignable(&current, &next) {
+            next
+        } else {
+            self.error(format!("Mismatched {} types `{}` and `{}`", what, current, next), expression.line, expression.column);
+            current
+        }
+    }
+
+    fn check_field_assignment(&mut self, callee: &Ty, name: &str, line: usize, column: usize) {
+        let (field, method) = match callee {
+            Ty::Object(class, arguments) => (self.find_field(class, arguments, name), self.find_method(class, arguments, name)),
+            Ty::Class(class) => (self.find_static_field(class, name), self.find_static_method(class, name)),
+            _ => return,
+        };
+        if let Some((owner, field)) = field {
+            if field.constant && self.classes[&owner].host {
+                self.error(format!("Property `{}.{}` is read-only", owner, name), line, column);
+            } else if field.constant {
+                let module = self.classes[&owner].module;
+                self.report(
+                    Diagnostic::error(format!("Cannot assign to constant field `{}.{}`", owner, name), line, column)
+                        .with_label_in(module, "field declared here".to_string(), field.line, field.column),
+                );
+            }
+        } else if let Some((owner, _)) = method {
+            self.error(format!("Cannot assign to method `{}.{}`", owner, name), line, column);
+        }
+    }
+
+    /// Checks the construction of an enum variant, with `arguments` if it is followed by parentheses.
+    fn check_variant(&mut self, enum_: &str, name: &str, arguments: Option<&[Expression]>, line: usize, column: usize) -> Ty {
+        let variant = self.enums.get(enum_).and_then(|info| info.variants.iter().find(|variant| variant.name == name)).cloned();
+        match (variant, arguments) {
+            (Some(variant), Some(arguments)) => {
+                let callee = format!("`{}.{}`", enum_, name);
+                self.check_arguments(&callee, &variant.payload, arguments, line, column);
+            }
+            (Some(variant), None) if !variant.payload.is_empty() => {
+                self.error(format!("Variant `{}.{}` takes {} value(s)", enum_, name, variant.payload.len()), line, column);
+            }
+            (Some(_), None) => {}
+            (None, arguments) => {
+                for argument in arguments.unwrap_or_default() {
+                    self.check_expression(argument);
+                }
+                self.error(format!("`{}` has no variant `{}`", enum_, name), line, column);
+                return Ty::Unknown;
+            }
+        }
+        Ty::Enum(enum_.to_string())
+    }
+
+    fn member_type(&mut self, receiver: &Ty, name: &str, line: usize, column: usize) -> Ty {
+        match receiver {
+            Ty::Unknown => Ty::Unknown,
+            Ty::EnumType(enum_) => self.check_variant(enum_, name, None, line, column),
+            Ty::Object(class, arguments) => {
+                if let Some((_, field)) = self.find_field(class, arguments, name) {
+                    self.check_access(class, name, line, column);
+                    field.ty
+                } else if let Some((_, method)) = self.find_method(class, arguments, name) {
+                    self.check_access(class, name, line, column);
+                    method.ty()
+                } else if let Some(owner) =
+                    self.find_static_field(class, name).map(|(owner, _)| owner).or_else(|| self.find_static_method(class, name).map(|(owner, _)| owner))
+                {
+                    self.error(format!("`{}` is static and must be accessed as `{}.{}`", name, owner, name), line, column);
+                    Ty::Unknown
+                } else {
+                    self.error(format!("`{}` has no member `{}`", class, name), line, column);
+                    Ty::Unknown
+                }
+            }
+            Ty::Class(class) => {
+                if let Some((_, field)) = self.find_static_field(class, name) {
+                    self.check_access(class, name, line, column);
+                    field.ty
+                } else if let Some((_, method)) = self.find_static_method(class, name) {
+                    self.check_access(class, name, line, column);
+                    method.ty()
+                } else if self.find_field(class, &[], name).is_some() || self.find_method(class, &[], name).is_some() {
+                    self.error(format!("`{}.{}` is not static and can only be accessed on an instance", class, name), line, column);
+                    Ty::Unknown
+                } else {
+                    self.error(format!("`{}` has no static member `{}`", class, name), line, column);
+                    Ty::Unknown
+                }
+            }
+            Ty::String => match self.natives.get(&format!("{}.{}", STRING_METHODS, name)) {
+                Some(Ty::Function(parameters, return_type)) if parameters.first() == Some(&Ty::String) => {
+                    Ty::Function(parameters[1..].to_vec(), return_type.clone())
+                }
+                _ => {
+                    self.error(format!("`string` has no method `{}`", name), line, column);
+                    Ty::Unknown
+                }
+            },
+            Ty::Array(element) => match array_method(element, name) {
+                Some(method) => {
+                    if name == "sort" && !matches!(**element, Ty::Int | Ty::Float | Ty::Char | Ty::String | Ty::Unknown) {
+                        self.error(format!("Cannot sort `{}`, whose elements are not ordered", receiver), line, column);
+                    }
+                    method
+                }
+                None => {
+                    self.error(format!("`{}` has no method `{}`", receiver, name), line, column);
+                    Ty::Unknown
+                }
+            },
+            Ty::Map(key, value) => match map_method(key, value, name) {
+                Some(method) => method,
+                None => {
+                    self.error(format!("`{}` has no method `{}`", receiver, name), line, column);
+                    Ty::Unknown
+                }
+            },
+            Ty::Interface(interface) => match self.interfaces.get(interface).and_then(|info| info.methods.get(name)) {
+                Some(method) => method.ty(),
+                None => {
+                    self.error(format!("`{}` has no method `{}`", interface, name), line, column);
+                    Ty::Unknown
+                }
+            },
+            ty => {
+                self.error(format!("`{}` has no member `{}`", ty, name), line, column);
+                Ty::Unknown
+            }
+        }
+    }
+
+    fn index_type(&mut self, callee: &Ty, key: &Ty, line: usize, column: usize) -> Ty {
+        match callee {
+            Ty::Unknown => Ty::Unknown,
+            Ty::Array(element) => {
+                self.expect_type(&Ty::Int, key, "array index", line, column);
+                (**element).clone()
+            }
+            Ty::String => {
+                self.expect_type(&Ty::Int, key, "string index", line, column);
+                Ty::Char
+            }
+            Ty::Map(key_type, value_type) => {
+                self.expect_type(key_type, key, "map key", line, column);
+                (**value_type).clone()
+            }
+            ty => {
+                self.error(format!("Cannot index `{}`", ty), line, column);
+                Ty::Unknown
+            }
+        }
+    }
+
+    fn check_super(&mut self, super_call: &SuperCall, line: usize, column: usize) -> Ty {
+        let superclass = self
+            .class
+            .as_ref()
+            .and_then(|class| self.classes.get(class))
+            .and_then(|info| info.superclass.clone());
+        let (superclass, arguments) = match superclass {
+            Some(superclass) => superclass,
+            None => {
+                for argument in &super_call.arguments {
+                    self.check_expression(argument);
+                }
+                return Ty::Unknown;
+            }
+        };
+        match &super_call.method {
+            None => {
+                if self.return_type != Some(Ty::Void) {
+                    self.error("`super(...)` can only be called from a constructor".to_string(), line, column);
+                }
+                self.check_access(&superclass, "constructor", line, column);
+                let parameters = self.find_constructor(&superclass, &arguments).map(|constructor| constructor.parameters).unwrap_or_default();
+                let callee = format!("the constructor of `{}`", superclass);
+                self.check_arguments(&callee, &parameters, &super_call.arguments, line, column);
+                Ty::Void
+            }
+            Some(name) => match self.find_method(&superclass, &arguments, name) {
+                Some((owner, method)) => {
+                    self.check_access(&superclass, name, line, column);
+                    let callee = format!("`{}.{}`", owner, name);
+                    self.check_arguments(&callee, &method.parameters, &super_call.arguments, line, column);
+                    method.return_type
+                }
+                None => {
+                    self.error(format!("`{}` has no method `{}`", superclass, name), line, column);
+                    Ty::Unknown
+                }
+            },
+        }
+    }
+
+    fn check_binary(&mut self, binary: &Binary, line: usize, column: usize) -> Ty {
+        let left = self.check_expression(&binary.left);
+        let right = self.check_expression(&binary.right);
+        let symbol = operator_symbol(&binary.operator);
+        if left == Ty::Unknown || right == Ty::Unknown {
+            return match binary.operator {
+                TokenKind::Plus | TokenKind::Minus | TokenKind::Star | TokenKind::Slash | TokenKind::Percent => {
+                    if left == Ty::Unknown { right } else { left }
+                }
+                _ => Ty::Bool,
+            };
+        }
+        let result = match binary.operator {
+            TokenKind::And | TokenKind::Or => (left == Ty::Bool && right == Ty::Bool).then_some(Ty::Bool),
+            TokenKind::EqualEqual | TokenKind::BangEqual => {
+                (self.assignable(&left, &right) || self.assignable(&right, &left)).then_some(Ty::Bool)
+            }
+            TokenKind::Plus if left == Ty::String && right == Ty::String => Some(Ty::String),
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Star | TokenKind::Slash | TokenKind::Percent => {
+                (left == right && matches!(left, Ty::Int | Ty::Float)).then(|| left.clone())
+            }
+            TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => {
+                (left == right && matches!(left, Ty::Int | Ty::Float | Ty::Char | Ty::String)).then_some(Ty::Bool)
+            }
+            _ => None,
+        };
+        if result == Some(Ty::Int) {
+            self.check_constant_arithmetic(binary, line, column);
+        }
+        match result {
+            Some(ty) => ty,
+            // Ints and floats are never converted implicitly.
+            None if matches!((&left, &right), (Ty::Int, Ty::Float) | (Ty::Float, Ty::Int)) => {
+                let operand = if left == Ty::Int { &binary.left } else { &binary.right };
+                self.report(
+                    Diagnostic::error(format!("Cannot apply `{}` to `{}` and `{}`", symbol, left, right), line, column)
+                        .with_label("convert this operand with `as float`".to_string(), operand.line, operand.column),
+                );
+                Ty::Unknown
+            }
+            None => {
+                self.error(format!("Cannot apply `{}` to `{}` and `{}`", symbol, left, right), line, column);
+                Ty::Unknown
+            }
+        }
+    }
+
+    /// Reports int arithmetic on constants that is certain to fail when it runs. Operands
+    /// that overflow themselves have already been reported, so they are not constant here.
+    fn check_constant_arithmetic(&mut self, binary: &Binary, line: usize, column: usize) {
+        let (Some(left), Some(right)) = (constant_int(&binary.left), constant_int(&binary.right)) else {
+            return;
+        };
+        if right == 0 && matches!(binary.operator, TokenKind::Slash | TokenKind::Percent) {
+            self.error("Division by zero".to_string(), line, column);
+        } else if fold(&binary.operator, left, right).is_none() {
+            self.error(format!("Integer overflow in `{} {} {}`", left, operator_symbol(&binary.operator), right), line, column);
+        }
+    }
+}
+
+/// The value of an int expression made only of literals, if it can be computed without error.
+fn constant_int(expression: &Expression) -> Option<i64> {
+    match &expression.kind {
+        ExpressionKind::Literal(Literal { kind: LiteralKind::Integer(integer), .. }) => Some(*integer),
+        ExpressionKind::Parenthesized(parenthesized) => constant_int(&parenthesized.expression),
+        ExpressionKind::Unary(unary) if unary.operator == TokenKind::Minus => constant_int(&unary.operand)?.checked_neg(),
+        ExpressionKind::Binary(binary) => fold(&binary.operator, constant_int(&binary.left)?, constant_int(&binary.right)?),
+        _ => None,
+    }
+}
+
+/// Applies an int arithmetic operator the way the interpreter does, with `None` where it would fail.
+fn fold(operator: &TokenKind, left: i64, right: i64) -> Option<i64> {
+    match operator {
+        TokenKind::Plus => left.checked_add(right),
+        TokenKind::Minus => left.checked_sub(right),
+        TokenKind::Star => left.checked_mul(right),
+        TokenKind::Slash => left.checked_div(right),
+        TokenKind::Percent if right != 0 => Some(left.wrapping_rem(right)),
+        _ => None,
+    }
+}
+
+/// The name a class member is looked up by, with constructors under `constructor`.
+fn member_name(statement: &Statement) -> Option<&str> {
+    match &statement.kind {
+        StatementKind::VarDeclaration(field) => Some(&field.name),
+        StatementKind::ConstantDeclaration(constant) => member_name(&constant.statement),
+        StatementKind::FunctionDeclaration(method) => Some(&method.name),
+        StatementKind::ConstructorDeclaration(_) => Some("constructor"),
+        _ => None,
+    }
+}
+
+/// The name of the type a top-level statement declares, if any.
+fn type_name(statement: &Statement) -> Option<&str> {
+    match &statement.kind {
+        StatementKind::ClassDeclaration(class) => Some(&class.name),
+        StatementKind::InterfaceDeclaration(interface) => Some(&interface.name),
+        StatementKind::EnumDeclaration(enum_) => Some(&enum_.name),
+        _ => None,
+    }
+}
+
+/// Binds each type parameter to its argument, or to `Unknown` where an argument is missing.
+fn bind(type_parameters: &[String], arguments: &[Ty]) -> HashMap<String, Ty> {
+    type_parameters
+        .iter()
+        .enumerate()
+        .map(|(i, type_parameter)| (type_parameter.clone(), arguments.get(i).cloned().unwrap_or(Ty::Unknown)))
+        .collect()
+}
+
+/// The type of a builtin method of arrays of `element`.
+fn array_method(element: &Ty, name: &str) -> Option<Ty> {
+    let function = |parameters: Vec<Ty>, return_type: Ty| Ty::Function(parameters, Box::new(return_type));
+    let element = element.clone();
+    let array = Ty::Array(Box::new(element.clone()));
+    // The type parameter of `map` and `reduce` must not capture one the element type refers to.
+    let result = fresh_parameter(&element);
+    let method = match name {
+        "len" => function(vec![], Ty::Int),
+        "push" => function(vec![element], Ty::Void),
+        "pop" => function(vec![], element),
+        "insert" => function(vec![Ty::Int, element], Ty::Void),
+        "remove" => function(vec![Ty::Int], element),
+        "contains" => function(vec![element], Ty::Bool),
+        "sort" | "reverse" => function(vec![], Ty::Void),
+        "filter" => function(vec![function(vec![element], Ty::Bool)], array),
+        "map" => Ty::Generic(
+            vec![result.clone()],
+            vec![function(vec![element], Ty::Param(result.clone()))],
+            Box::new(Ty::Array(Box::new(Ty::Param(result)))),
+        ),
+        "reduce" => {
+            let accumulator = Ty::Param(result.clone());
+            Ty::Generic(vec![result], vec![accumulator.clone(), function(vec![accumulator.clone(), element], accumulator.clone())], Box::new(accumulator))
+        }
+        _ => return None,
+    };
+    Some(method)
+}
+
+/// The type of a builtin method of maps from `key` to `value`.
+fn map_method(key: &Ty, value: &Ty, name: &str) -> Option<Ty> {
+    let function = |parameters: Vec<Ty>, return_type: Ty| Ty::Function(parameters, Box::new(return_type));
+    let (key, value) = (key.clone(), value.clone());
+    let method = match name {
+        "len" => function(vec![], Ty::Int),
+        "get" => function(vec![key, value.clone()], value),
+        "set" => function(vec![key, value], Ty::Void),
+        "remove" => function(vec![key], value),
+        "contains_key" => function(vec![key], Ty::Bool),
+        "keys" => function(vec![], Ty::Array(Box::new(key))),
+        "values" => function(vec![], Ty::Array(Box::new(value))),
+        _ => return None,
+    };
+    Some(method)
+}
+
+/// A name for a new type parameter that `ty` does not already refer to.
+fn fresh_parameter(ty: &Ty) -> String {
+    let mut name = "U".to_string();
+    while mentions(ty, &name) {
+        name.push('U');
+    }
+    name
+}
+
+/// Whether `ty` refers to the type parameter `name`.
+fn mentions(ty: &Ty, name: &str) -> bool {
+    match ty {
+        Ty::Param(parameter) => parameter == name,
+        Ty::Array(element) => mentions(element, name),
+        Ty::Map(key, value) => mentions(key, name) || mentions(value, name),
+        Ty::Function(parameters, return_type) | Ty::Generic(_, parameters, return_type) => {
+            parameters.iter().any(|parameter| mentions(parameter, name)) || mentions(return_type, name)
+        }
+        Ty::Object(_, arguments) => arguments.iter().any(|argument| mentions(argument, name)),
+        _ => false,
+    }
+}
+
+/// Replaces the type parameters in `ty` with the types they are bound to.
+fn substitute(ty: &Ty, bindings: &HashMap<String, Ty>) -> Ty {
+    let substitute_all = |types: &[Ty]| types.iter().map(|ty| substitute(ty, bindings)).collect();
+    match ty {
+        Ty::Param(name) => bindings.get(name).cloned().unwrap_or_else(|| ty.clone()),
+        Ty::Array(element) => Ty::Array(Box::new(substitute(element, bindings))),
+        Ty::Map(key, value) => Ty::Map(Box::new(substitute(key, bindings)), Box::new(substitute(value, bindings))),
+        Ty::Function(parameters, return_type) => Ty::Function(substitute_all(parameters), Box::new(substitute(return_type, bindings))),
+        Ty::Generic(type_parameters, parameters, return_type) => {
+            let mut bindings = bindings.clone();
+            for type_parameter in type_parameters {
+                bindings.remove(type_parameter);
+            }
+            let parameters = parameters.iter().map(|parameter| substitute(parameter, &bindings)).collect();
+            Ty::Generic(type_parameters.clone(), parameters, Box::new(substitute(return_type, &bindings)))
+        }
+        Ty::Object(name, arguments) => Ty::Object(name.clone(), substitute_all(arguments)),
+        _ => ty.clone(),
+    }
+}
+
+/// Binds the type parameters that occur in `expected` by matching it against the type `found`.
+///
+/// The first binding of a type parameter wins; the caller checks the arguments against it afterwards.
+fn infer(expected: &Ty, found: &Ty, type_parameters: &[String], bindings: &mut HashMap<String, Ty>) {
+    match (expected, found) {
+        (_, Ty::Unknown | Ty::Null) => {}
+        (Ty::Param(name), _) if type_parameters.contains(name) => {
+            bindings.entry(name.clone()).or_insert_with(|| found.clone());
+        }
+        (Ty::Array(expected), Ty::Array(found)) => infer(expected, found, type_parameters, bindings),
+        (Ty::Map(expected_key, expected_value), Ty::Map(found_key, found_value)) => {
+            infer(expected_key, found_key, type_parameters, bindings);
+            infer(expected_value, found_value, type_parameters, bindings);
+        }
+        (Ty::Function(expected_parameters, expected_return), Ty::Function(found_parameters, found_return)) => {
+            for (expected, found) in expected_parameters.iter().zip(found_parameters) {
+                infer(expected, found, type_parameters, bindings);
+            }
+            infer(expected_return, found_return, type_parameters, bindings);
+        }
+        (Ty::Object(expected, expected_arguments), Ty::Object(found, found_arguments)) if expected == found => {
+            for (expected, found) in expected_arguments.iter().zip(found_arguments) {
+                infer(expected, found, type_parameters, bindings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether control can never reach the end of a statement normally.
+fn returns(statement: &Statement) -> bool {
+    match &statement.kind {
+        StatementKind::Return(_) | StatementKind::Throw(_) => true,
+        StatementKind::Try(try_statement) => {
+            let returns_from_all = try_statement.body.iter().any(returns) && try_statement.catches.iter().all(|catch| catch.body.iter().any(returns));
+            returns_from_all || try_statement.finally.as_ref().is_some_and(|finally| finally.iter().any(returns))
+        }
+        StatementKind::Block(block) => block.statements.iter().any(returns),
+        StatementKind::If(if_statement) => {
+            returns(&if_statement.then_branch) && if_statement.else_branch.as_deref().is_some_and(returns)
+        }
+        StatementKind::While(while_statement) => {
+            let infinite = matches!(
+                &while_statement.condition.kind,
+                ExpressionKind::Literal(Literal { kind: LiteralKind::Bool(true), .. })
+            );
+            infinite && !breaks(&while_statement.body)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a loop body contains a `break` that leaves that loop.
+fn breaks(statement: &Statement) -> bool {
+    match &statement.kind {
+        StatementKind::Break => true,
+        StatementKind::Block(block) => block.statements.iter().any(breaks),
+        StatementKind::If(if_statement) => breaks(&if_statement.then_branch) || if_statement.else_branch.as_deref().is_some_and(breaks),
+        StatementKind::Try(try_statement) => {
+            try_statement.body.iter().any(breaks)
+                || try_statement.catches.iter().any(|catch| catch.body.iter().any(breaks))
+                || try_statement.finally.as_ref().is_some_and(|finally| finally.iter().any(breaks))
+        }
+        _ => false,
+    }
+}
+
+fn literal_type(literal: &Literal) -> Ty {
+    match literal.kind {
+        LiteralKind::String(_) => Ty::String,
+        LiteralKind::Char(_) => Ty::Char,
+        LiteralKind::Integer(_) => Ty::Int,
+        LiteralKind::Float(_) => Ty::Float,
+        LiteralKind::Bool(_) => Ty::Bool,
+        LiteralKind::Null => Ty::Null,
+    }
+}
+
+pub(crate) fn operator_symbol(operator: &TokenKind) -> &'static str {
+    match operator {
+        TokenKind::Plus => "+",
+        TokenKind::Minus => "-",
+        TokenKind::Star => "*",
+        TokenKind::Slash => "/",
+        TokenKind::Percent => "%",
+        TokenKind::Bang => "!",
+        TokenKind::EqualEqual => "==",
+        TokenKind::BangEqual => "!=",
+        TokenKind::Less => "<",
+        TokenKind::LessEqual => "<=",
+        TokenKind::Greater => ">",
+        TokenKind::GreaterEqual => ">=",
+        TokenKind::And => "&&",
+        TokenKind::Or => "||",
+        _ => "?",
+    }
+}
+
+/// A host class with no members yet.
+fn host_class_skeleton() -> ClassInfo {
+    ClassInfo {
+        type_parameters: Vec::new(),
+        superclass: None,
+        interfaces: Vec::new(),
+        fields: HashMap::new(),
+        methods: HashMap::new(),
+        constructor: None,
+        static_fields: HashMap::new(),
+        static_methods: HashMap::new(),
+        visibility: HashMap::new(),
+        module: 0,
+        host: true,
+    }
+}
@@ -0,0 +1,1091 @@
+use crate::interpreter::literal_value;
+use crate::parser::*;
+use crate::resolver::global_name;
+use crate::token::TokenKind;
+use crate::value::{Enum, Field, Initializer, Value};
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Where compiled code reads and assigns a variable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Access {
+    /// A slot of the frame of the running function.
+    Local(u32),
+    /// A slot of the environment this many links up from the current one, which is the
+    /// closure of the function unless it keeps its scopes in environments.
+    Environment(u32, u32),
+    Global(u32),
+    Native(u32),
+    NativeClass(u32),
+    NativeConstant(u32),
+}
+
+/// An operator of a `Unary` or `Binary` instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Negate,
+    Not,
+}
+
+impl Operator {
+    fn binary(token: &TokenKind) -> Option<Operator> {
+        match token {
+            TokenKind::Plus => Some(Operator::Add),
+            TokenKind::Minus => Some(Operator::Subtract),
+            TokenKind::Star => Some(Operator::Multiply),
+            TokenKind::Slash => Some(Operator::Divide),
+            TokenKind::Percent => Some(Operator::Remainder),
+            TokenKind::EqualEqual => Some(Operator::Equal),
+            TokenKind::BangEqual => Some(Operator::NotEqual),
+            TokenKind::Less => Some(Operator::Less),
+            TokenKind::LessEqual => Some(Operator::LessEqual),
+            TokenKind::Greater => Some(Operator::Greater),
+            TokenKind::GreaterEqual => Some(Operator::GreaterEqual),
+            _ => None,
+        }
+    }
+
+    /// The token the operator is written with, which runtime errors name.
+    pub fn token(self) -> TokenKind {
+        match self {
+            Operator::Add => TokenKind::Plus,
+            Operator::Subtract | Operator::Negate => TokenKind::Minus,
+            Operator::Multiply => TokenKind::Star,
+            Operator::Divide => TokenKind::Slash,
+            Operator::Remainder => TokenKind::Percent,
+            Operator::Equal => TokenKind::EqualEqual,
+            Operator::NotEqual => TokenKind::BangEqual,
+            Operator::Less => TokenKind::Less,
+            Operator::LessEqual => TokenKind::LessEqual,
+            Operator::Greater => TokenKind::Greater,
+            Operator::GreaterEqual => TokenKind::GreaterEqual,
+            Operator::Not => TokenKind::Bang,
+        }
+    }
+}
+
+/// An instruction of the stack machine. Operands index the tables of the chunk the
+/// instruction is in, or are jump targets within its code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Constant(u32),
+    Pop,
+    Load(Access),
+    /// Assigns the value on top of the stack, leaving it there.
+    Store(Access),
+    /// Pops the value on top of the stack into a slot of the frame.
+    SetLocal(u32),
+    /// Pops the value on top of the stack into the next slot of the current environment.
+    Define,
+    PushScope,
+    PopScope,
+    Unary(Operator),
+    Binary(Operator),
+    Jump(u32),
+    /// Pops a condition and jumps if it is false.
+    JumpIfFalse(u32),
+    /// Pops the left operand of `&&`, and if it is false pushes it back and jumps past the right one.
+    And(u32),
+    /// Pops the left operand of `||`, and if it is true pushes it back and jumps past the right one.
+    Or(u32),
+    /// Checks that the value on top of the stack is a bool.
+    Condition,
+    /// Calls the callee below this many arguments.
+    Call(u32),
+    /// Calls the method named by the name, on the receiver below this many arguments.
+    CallMethod(u32, u32),
+    SuperCall(u32),
+    /// Instantiates the class below this many arguments.
+    New(u32),
+    Index,
+    /// Assigns the value below the callee and key, leaving the value.
+    SetIndex,
+    Slice,
+    GetMember(u32),
+    /// Assigns the value below the callee, leaving the value.
+    SetMember(u32),
+    Array(u32),
+    /// Checks that the value on top of the stack can be a map key.
+    MapKey,
+    /// Builds a map from this many keys each followed by its value.
+    Map(u32),
+    Cast(u32),
+    Closure(u32),
+    /// Pops what a `for` loop runs over into the slot, with the position reached in the next.
+    Iterate(u32),
+    /// Pushes the next element of the loop over the slot, or jumps when there is none.
+    Next(u32, u32),
+    Throw,
+    Return,
+    /// Leaves a `try`, `catch` or `finally` block by breaking out of a loop around it.
+    Break,
+    /// Leaves a `try`, `catch` or `finally` block by continuing a loop around it.
+    Continue,
+    Try(u32),
+    MatchArm(u32),
+    /// Fails because no arm matched the subject in the slot.
+    NoMatch(u32),
+    /// Creates a class, popping its superclass first if it has one.
+    Class(u32),
+    Enum(u32),
+    /// Pops a class and initializes its static fields.
+    Statics,
+    /// Fails with the message in the names.
+    Fail(u32),
+}
+
+/// A compiled function body, top level of a module or field initializer.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    /// Line and column of the source each instruction was compiled from.
+    pub positions: Vec<(u32, u32)>,
+    pub constants: Vec<Value>,
+    /// Names of members and methods, and the messages of `Fail`.
+    pub names: Vec<String>,
+    pub types: Vec<Type>,
+    pub functions: Vec<Prototype>,
+    pub supers: Vec<SuperCall>,
+    pub tries: Vec<TryBlock>,
+    pub arms: Vec<MatchArm>,
+    pub classes: Vec<ClassPrototype>,
+    pub enums: Vec<Enum>,
+    /// Slots of the frame, for variables and for what loops and matches keep while they run.
+    pub locals: u32,
+    /// Whether the function keeps its scopes in environments, which closures created in it
+    /// need to capture. Other functions keep their variables in the slots of their frame.
+    pub environments: bool,
+}
+
+impl Chunk {
+    pub fn position(&self, pc: usize) -> (usize, usize) {
+        let (line, column) = self.positions[pc];
+        (line as usize, column as usize)
+    }
+}
+
+/// A function declared in another, with the code it is compiled to once first called.
+#[derive(Debug, Clone)]
+pub struct Prototype {
+    pub declaration: Rc<FunctionDeclaration>,
+    pub code: Rc<OnceCell<Chunk>>,
+}
+
+/// The members of a class declaration, which the classes it declares are created from.
+#[derive(Debug, Clone)]
+pub struct ClassPrototype {
+    pub name: String,
+    /// Whether the class extends another, which is on the stack when the class is created.
+    pub superclass: bool,
+    pub fields: Vec<Field>,
+    pub static_fields: Vec<Field>,
+    pub methods: Vec<Prototype>,
+    pub static_methods: Vec<Prototype>,
+    pub constructor: Option<Prototype>,
+    /// Whether the constructor starts by calling the superclass constructor itself.
+    pub calls_super: bool,
+}
+
+/// A call through `super`, with where the superclass and `this` are.
+#[derive(Debug, Clone)]
+pub struct SuperCall {
+    pub method: Option<String>,
+    pub superclass: Access,
+    pub object: Access,
+    pub arguments: u32,
+}
+
+/// The blocks of a `try` statement, which are laid out after its `Try` instruction, each
+/// as a range of code run on its own.
+#[derive(Debug, Clone, Default)]
+pub struct TryBlock {
+    pub body: (u32, u32),
+    pub catches: Vec<CatchBlock>,
+    pub finally: Option<(u32, u32)>,
+    /// Where to go once the statement completes, and where a `break` or `continue` out of one
+    /// of its blocks is carried out.
+    pub after: u32,
+    pub on_break: u32,
+    pub on_continue: u32,
+}
+
+/// A `catch` clause, whose code starts by binding the caught error it finds on the stack.
+#[derive(Debug, Clone)]
+pub struct CatchBlock {
+    pub class: Option<Access>,
+    pub name: String,
+    pub position: (u32, u32),
+    pub code: (u32, u32),
+}
+
+/// An arm of a `match`, which binds what its pattern captures to the slots starting at
+/// `bindings`, or to a new environment.
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    /// Where the enums its variant patterns name are, in the order they are matched.
+    pub enums: Vec<Option<Access>>,
+    pub subject: u32,
+    pub bindings: u32,
+    /// Where to go if the pattern does not match.
+    pub next: u32,
+}
+
+struct Loop {
+    /// Where `continue` goes, which is always before the body.
+    top: u32,
+    /// Jumps to the end of the loop, patched once it is known.
+    breaks: Vec<usize>,
+    /// Environment scopes open outside of the loop body.
+    scopes: usize,
+    /// `try`, `catch` and `finally` blocks open outside of the loop.
+    blocks: usize,
+}
+
+/// Compiles the body of a function, keeping its scopes in environments only if it
+/// creates closures that capture them.
+pub fn compile(function: &FunctionDeclaration) -> Chunk {
+    compile_with(|compiler| compiler.compile_function(function))
+}
+
+/// Compiles the top level of a module, whose globals take the slots from `base` on, to code
+/// run in the global environment.
+pub fn compile_module(statements: &[Statement], base: u32) -> Chunk {
+    compile_with(|compiler| compiler.top_level(statements, base))
+}
+
+/// Compiles a field initializer to code run in the environment its class was declared in,
+/// which returns the value of the field.
+pub fn compile_initializer(expression: &Expression) -> Chunk {
+    compile_with(|compiler| {
+        compiler.expression(expression);
+        compiler.emit(Instruction::Return);
+    })
+}
+
+fn compile_with(compile: impl Fn(&mut Compiler)) -> Chunk {
+    let mut compiler = Compiler::new(false);
+    compile(&mut compiler);
+    if compiler.captures {
+        compiler = Compiler::new(true);
+        compile(&mut compiler);
+    }
+    compiler.chunk
+}
+
+/// Collects the members of a class declaration, making the initializer of each field with `initializer`.
+pub(crate) fn class_prototype(declaration: &ClassDeclaration, initializer: &mut dyn FnMut(&Expression) -> Initializer) -> ClassPrototype {
+    let mut prototype = ClassPrototype {
+        name: declaration.name.clone(),
+        superclass: declaration.superclass.is_some(),
+        fields: Vec::new(),
+        static_fields: Vec::new(),
+        methods: Vec::new(),
+        static_methods: Vec::new(),
+        constructor: None,
+        calls_super: false,
+    };
+    for member in &declaration.body {
+        let statement = match &member.statement.kind {
+            StatementKind::ConstantDeclaration(constant) => &constant.statement,
+            _ => &member.statement,
+        };
+        match &statement.kind {
+            StatementKind::VarDeclaration(field) => {
+                let field = Field {
+                    name: field.name.clone(),
+                    type_: field.type_.clone(),
+                    initializer: field.initializer.as_ref().map(&mut *initializer),
+                };
+                match member.is_static {
+                    true => prototype.static_fields.push(field),
+                    false => prototype.fields.push(field),
+                }
+            }
+            StatementKind::FunctionDeclaration(method) => {
+                let method = Prototype {
+                    declaration: Rc::new(method.clone()),
+                    code: Rc::default(),
+                };
+                match member.is_static {
+                    true => prototype.static_methods.push(method),
+                    false => prototype.methods.push(method),
+                }
+            }
+            StatementKind::ConstructorDeclaration(constructor) => {
+                // Constructors are called like a method named `constructor` that returns nothing.
+                let declaration = FunctionDeclaration::new(
+                    "constructor".to_string(),
+                    Vec::new(),
+                    constructor.parameters.clone(),
+                    Type::new(TypeKind::Void, statement.line, statement.column),
+                    constructor.body.clone(),
+                );
+                prototype.calls_super = calls_super(&declaration);
+                prototype.constructor = Some(Prototype {
+                    declaration: Rc::new(declaration),
+                    code: Rc::default(),
+                });
+            }
+            _ => {}
+        }
+    }
+    prototype
+}
+
+/// Whether a constructor starts by explicitly calling the superclass constructor.
+fn calls_super(constructor: &FunctionDeclaration) -> bool {
+    match constructor.body.first().map(|statement| &statement.kind) {
+        Some(StatementKind::Expression(expression)) => {
+            matches!(&expression.kind, ExpressionKind::Super(super_call) if super_call.method.is_none())
+        }
+        _ => false,
+    }
+}
+
+struct Compiler {
+    chunk: Chunk,
+    /// The frame slots of the variables of each scope open, mirroring the environments
+    /// the resolver assigned their slots in.
+    scopes: Vec<Vec<u32>>,
+    next_local: u32,
+    loops: Vec<Loop>,
+    blocks: usize,
+    /// Whether the function creates a closure, which needs its scopes kept in environments.
+    captures: bool,
+    position: (u32, u32),
+}
+
+impl Compiler {
+    fn new(environments: bool) -> Self {
+        Compiler {
+            chunk: Chunk { environments, ..Chunk::default() },
+            scopes: Vec::new(),
+            next_local: 0,
+            loops: Vec::new(),
+            blocks: 0,
+            captures: false,
+            position: (0, 0),
+        }
+    }
+
+    fn compile_function(&mut self, function: &FunctionDeclaration) {
+        // The environment of the call holds the parameters, which the caller provides.
+        self.scopes.push(Vec::new());
+        for _ in &function.parameters {
+            self.declare_slot();
+        }
+        self.statements(&function.body);
+    }
+
+    /// Compiles the top level of a module the way the interpreter runs it: functions and enums
+    /// are bound before anything runs, then classes once their superclass exists, and then the
+    /// other statements run in order.
+    fn top_level(&mut self, statements: &[Statement], base: u32) {
+        let mut slots = HashMap::new();
+        for name in statements.iter().filter_map(global_name) {
+            slots.insert(name, base + slots.len() as u32);
+        }
+        let mut classes = Vec::new();
+        for statement in statements {
+            self.at(statement.line, statement.column);
+            match &statement.kind {
+                StatementKind::FunctionDeclaration(function) => {
+                    self.captures = true;
+                    self.closure(function);
+                    self.set_global(slots[function.name.as_str()]);
+                }
+                StatementKind::EnumDeclaration(enum_) => {
+                    self.enum_declaration(enum_);
+                    self.set_global(slots[enum_.name.as_str()]);
+                }
+                StatementKind::ClassDeclaration(class) => classes.push((statement, class)),
+                _ => {}
+            }
+        }
+        while !classes.is_empty() {
+            let ready = classes.iter().position(|(_, class)| match &class.superclass {
+                Some(superclass) => !classes.iter().any(|(_, other)| other.name == superclass.name),
+                None => true,
+            });
+            let (statement, class) = classes.remove(ready.unwrap_or(0));
+            self.at(statement.line, statement.column);
+            self.class_declaration(class);
+            self.set_global(slots[class.name.as_str()]);
+        }
+        for statement in statements {
+            self.at(statement.line, statement.column);
+            let variable = match &statement.kind {
+                StatementKind::VarDeclaration(variable) => Some(variable),
+                StatementKind::ConstantDeclaration(constant) => match &constant.statement.kind {
+                    StatementKind::VarDeclaration(variable) => Some(variable),
+                    _ => None,
+                },
+                _ => None,
+            };
+            match (&statement.kind, variable) {
+                (StatementKind::FunctionDeclaration(_) | StatementKind::EnumDeclaration(_), _) => {}
+                // Static fields are initialized when the class declaration is reached, like a global.
+                (StatementKind::ClassDeclaration(class), _) => {
+                    self.emit(Instruction::Load(Access::Global(slots[class.name.as_str()])));
+                    self.emit(Instruction::Statics);
+                }
+                (_, Some(variable)) => {
+                    match &variable.initializer {
+                        Some(initializer) => self.expression(initializer),
+                        None => self.constant(Value::Null),
+                    }
+                    self.at(statement.line, statement.column);
+                    self.set_global(slots[variable.name.as_str()]);
+                }
+                _ => self.statement(statement),
+            }
+        }
+    }
+
+    fn set_global(&mut self, slot: u32) {
+        self.emit(Instruction::Store(Access::Global(slot)));
+        self.emit(Instruction::Pop);
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.positions.push(self.position);
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> u32 {
+        self.chunk.code.len() as u32
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let target = self.here();
+        match &mut self.chunk.code[at] {
+            Instruction::Jump(to) | Instruction::JumpIfFalse(to) | Instruction::And(to) | Instruction::Or(to) | Instruction::Next(_, to) => *to = target,
+            instruction => unreachable!("{:?} is not a jump", instruction),
+        }
+    }
+
+    fn at(&mut self, line: usize, column: usize) {
+        self.position = (line as u32, column as u32);
+    }
+
+    fn constant(&mut self, value: Value) {
+        // Floats are kept apart, since `0.0` and `-0.0` are equal and NaN is not equal to itself.
+        let existing = match value {
+            Value::Float(_) => None,
+            _ => self.chunk.constants.iter().position(|constant| *constant == value),
+        };
+        let index = existing.unwrap_or_else(|| {
+            self.chunk.constants.push(value);
+            self.chunk.constants.len() - 1
+        });
+        self.emit(Instruction::Constant(index as u32));
+    }
+
+    fn name(&mut self, name: &str) -> u32 {
+        match self.chunk.names.iter().position(|existing| existing == name) {
+            Some(index) => index as u32,
+            None => {
+                self.chunk.names.push(name.to_string());
+                self.chunk.names.len() as u32 - 1
+            }
+        }
+    }
+
+    fn fail(&mut self, message: String) {
+        let message = self.name(&message);
+        self.emit(Instruction::Fail(message));
+    }
+
+    /// The next free slot of the frame, taken until the construct it is for ends.
+    fn temporary(&mut self) -> u32 {
+        self.next_local += 1;
+        self.chunk.locals = self.chunk.locals.max(self.next_local);
+        self.next_local - 1
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(Vec::new());
+        if self.chunk.environments {
+            self.emit(Instruction::PushScope);
+        }
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+        if self.chunk.environments {
+            self.emit(Instruction::PopScope);
+        }
+    }
+
+    /// Gives the next variable of the innermost scope its slot.
+    fn declare_slot(&mut self) -> u32 {
+        let slot = self.temporary();
+        self.scopes.last_mut().expect("declaration outside of a scope").push(slot);
+        slot
+    }
+
+    /// Declares the next variable of the innermost scope, initialized to the value on the stack.
+    fn declare(&mut self) {
+        let slot = self.declare_slot();
+        if self.chunk.environments {
+            self.emit(Instruction::Define);
+        } else {
+            self.emit(Instruction::SetLocal(slot));
+        }
+    }
+
+    fn access(&self, binding: Binding) -> Access {
+        match binding {
+            Binding::Global(slot) => Access::Global(slot as u32),
+            Binding::Local { depth, slot } if self.chunk.environments => Access::Environment(depth as u32, slot as u32),
+            Binding::Local { depth, slot } if depth < self.scopes.len() => Access::Local(self.scopes[self.scopes.len() - 1 - depth][slot]),
+            Binding::Local { depth, slot } => Access::Environment((depth - self.scopes.len()) as u32, slot as u32),
+            Binding::Native(index) => Access::Native(index as u32),
+            Binding::NativeClass(index) => Access::NativeClass(index as u32),
+            Binding::NativeConstant(index) => Access::NativeConstant(index as u32),
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    /// Compiles statements in a scope of their own, like a block.
+    fn block(&mut self, statements: &[Statement]) {
+        // Slots of variables that went out of scope are reused.
+        let locals = self.next_local;
+        self.begin_scope();
+        self.statements(statements);
+        self.end_scope();
+        self.next_local = locals;
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        self.at(statement.line, statement.column);
+        match &statement.kind {
+            StatementKind::Expression(expression) => match variable_assignment(expression).map(|(binding, value)| (self.access(binding), value)) {
+                // The value of an assignment to a slot of the frame is not needed afterwards.
+                Some((Access::Local(slot), value)) => {
+                    self.expression(value);
+                    self.at(expression.line, expression.column);
+                    self.emit(Instruction::SetLocal(slot));
+                }
+                _ => {
+                    self.expression(expression);
+                    self.emit(Instruction::Pop);
+                }
+            },
+            StatementKind::VarDeclaration(variable) => self.variable(variable),
+            StatementKind::ConstantDeclaration(constant) => {
+                if let StatementKind::VarDeclaration(variable) = &constant.statement.kind {
+                    self.variable(variable);
+                }
+            }
+            StatementKind::FunctionDeclaration(function) => {
+                self.captures = true;
+                self.closure(function);
+                self.declare();
+            }
+            StatementKind::ClassDeclaration(class) => {
+                self.captures = true;
+                self.class_declaration(class);
+                self.at(statement.line, statement.column);
+                self.declare();
+            }
+            StatementKind::EnumDeclaration(enum_) => {
+                self.captures = true;
+                self.enum_declaration(enum_);
+                self.declare();
+            }
+            StatementKind::ConstructorDeclaration(_)
+            | StatementKind::InterfaceDeclaration(_)
+            | StatementKind::Import(_)
+            | StatementKind::Export(_) => {}
+            StatementKind::Return(return_statement) => {
+                match &return_statement.expression {
+                    Some(expression) => self.expression(expression),
+                    None => self.constant(Value::Null),
+                }
+                self.at(statement.line, statement.column);
+                self.emit(Instruction::Return);
+            }
+            StatementKind::Block(block) => self.block(&block.statements),
+            StatementKind::If(if_statement) => {
+                self.expression(&if_statement.condition);
+                self.at(if_statement.condition.line, if_statement.condition.column);
+                let otherwise = self.emit(Instruction::JumpIfFalse(0));
+                self.block(branch(&if_statement.then_branch));
+                match &if_statement.else_branch {
+                    Some(else_branch) => {
+                        let end = self.emit(Instruction::Jump(0));
+                        self.patch(otherwise);
+                        self.block(branch(else_branch));
+                        self.patch(end);
+                    }
+                    None => self.patch(otherwise),
+                }
+            }
+            StatementKind::While(while_statement) => {
+                let top = self.here();
+                self.expression(&while_statement.condition);
+                self.at(while_statement.condition.line, while_statement.condition.column);
+                let exit = self.emit(Instruction::JumpIfFalse(0));
+                self.begin_loop(top);
+                self.block(branch(&while_statement.body));
+                self.emit(Instruction::Jump(top));
+                self.end_loop();
+                self.patch(exit);
+            }
+            StatementKind::For(for_statement) => {
+                let locals = self.next_local;
+                let elements = self.temporary();
+                self.temporary();
+                self.expression(&for_statement.iterable);
+                self.at(for_statement.iterable.line, for_statement.iterable.column);
+                self.emit(Instruction::Iterate(elements));
+                let top = self.here();
+                let exit = self.emit(Instruction::Next(elements, 0));
+                self.begin_loop(top);
+                // Each element gets a scope holding it and the variables of the body.
+                self.begin_scope();
+                self.declare();
+                self.statements(branch(&for_statement.body));
+                self.end_scope();
+                self.emit(Instruction::Jump(top));
+                self.end_loop();
+                self.patch(exit);
+                self.next_local = locals;
+            }
+            StatementKind::Throw(throw) => {
+                self.expression(&throw.expression);
+                self.at(statement.line, statement.column);
+                self.emit(Instruction::Throw);
+            }
+            StatementKind::Try(try_statement) => self.try_statement(try_statement),
+            StatementKind::Break => self.break_statement(),
+            StatementKind::Continue => self.continue_statement(),
+        }
+    }
+
+    fn variable(&mut self, variable: &VarDeclaration) {
+        match &variable.initializer {
+            Some(initializer) => self.expression(initializer),
+            None => self.constant(Value::Null),
+        }
+        self.declare();
+    }
+
+    fn closure(&mut self, function: &FunctionDeclaration) {
+        self.chunk.functions.push(Prototype {
+            declaration: Rc::new(function.clone()),
+            code: Rc::new(OnceCell::new()),
+        });
+        let index = self.chunk.functions.len() as u32 - 1;
+        self.emit(Instruction::Closure(index));
+    }
+
+    fn class_declaration(&mut self, declaration: &ClassDeclaration) {
+        let prototype = class_prototype(declaration, &mut |expression| Initializer::Code(Rc::new(compile_initializer(expression))));
+        // Creating the class fails at its superclass if that is not a class.
+        if let Some(superclass) = &declaration.superclass {
+            self.at(superclass.line, superclass.column);
+            match superclass.binding {
+                Some(binding) => {
+                    let access = self.access(binding);
+                    self.emit(Instruction::Load(access));
+                }
+                None => self.fail(format!("Unresolved variable `{}`", superclass.name)),
+            }
+        }
+        self.chunk.classes.push(prototype);
+        let index = self.chunk.classes.len() as u32 - 1;
+        self.emit(Instruction::Class(index));
+    }
+
+    fn enum_declaration(&mut self, declaration: &EnumDeclaration) {
+        self.chunk.enums.push(Enum {
+            name: declaration.name.clone(),
+            variants: declaration.variants.iter().map(|variant| (variant.name.clone(), variant.payload.len())).collect(),
+        });
+        let index = self.chunk.enums.len() as u32 - 1;
+        self.emit(Instruction::Enum(index));
+    }
+
+    fn begin_loop(&mut self, top: u32) {
+        self.loops.push(Loop {
+            top,
+            breaks: Vec::new(),
+            scopes: self.scopes.len(),
+            blocks: self.blocks,
+        });
+    }
+
+    fn end_loop(&mut self) {
+        let finished = self.loops.pop().expect("loop ended twice");
+        for at in finished.breaks {
+            self.patch(at);
+        }
+    }
+
+    /// The scopes to leave to get back to the loop, or `None` if that means leaving a
+    /// block of a `try`, which is done by the `Try` instruction running it.
+    fn exit_loop(&mut self) -> Option<u32> {
+        let current = self.loops.last().expect("`break` outside of a loop");
+        if current.blocks < self.blocks {
+            return None;
+        }
+        let (top, scopes) = (current.top, current.scopes);
+        if self.chunk.environments {
+            for _ in scopes..self.scopes.len() {
+                self.emit(Instruction::PopScope);
+            }
+        }
+        Some(top)
+    }
+
+    fn break_statement(&mut self) {
+        match self.exit_loop() {
+            Some(_) => {
+                let at = self.emit(Instruction::Jump(0));
+                self.loops.last_mut().expect("`break` outside of a loop").breaks.push(at);
+            }
+            None => {
+                self.emit(Instruction::Break);
+            }
+        }
+    }
+
+    fn continue_statement(&mut self) {
+        match self.exit_loop() {
+            Some(top) => self.emit(Instruction::Jump(top)),
+            None => self.emit(Instruction::Continue),
+        };
+    }
+
+    /// Compiles code run on its own by a `Try` instruction, in a scope of its own.
+    fn try_block(&mut self, statements: &[Statement]) -> (u32, u32) {
+        let start = self.here();
+        self.blocks += 1;
+        self.block(statements);
+        self.blocks -= 1;
+        (start, self.here())
+    }
+
+    fn try_statement(&mut self, try_statement: &Try) {
+        let index = self.chunk.tries.len();
+        self.chunk.tries.push(TryBlock::default());
+        self.emit(Instruction::Try(index as u32));
+        let body = self.try_block(&try_statement.body);
+        let mut catches = Vec::new();
+        for catch in &try_statement.catches {
+            let start = self.here();
+            self.blocks += 1;
+            let locals = self.next_local;
+            self.begin_scope();
+            self.declare();
+            self.statements(&catch.body);
+            self.end_scope();
+            self.next_local = locals;
+            self.blocks -= 1;
+            catches.push(CatchBlock {
+                class: catch.class.binding.map(|binding| self.access(binding)),
+                name: catch.class.name.clone(),
+                position: (catch.class.line as u32, catch.class.column as u32),
+                code: (start, self.here()),
+            });
+        }
+        let finally = try_statement.finally.as_ref().map(|finally| self.try_block(finally));
+        let inside_loop = !self.loops.is_empty();
+        let on_break = self.here();
+        if inside_loop {
+            self.break_statement();
+        }
+        let on_continue = self.here();
+        if inside_loop {
+            self.continue_statement();
+        }
+        self.chunk.tries[index] = TryBlock {
+            body,
+            catches,
+            finally,
+            after: self.here(),
+            on_break,
+            on_continue,
+        };
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        self.at(expression.line, expression.column);
+        match &expression.kind {
+            ExpressionKind::Literal(literal) => self.constant(literal_value(literal)),
+            ExpressionKind::Parenthesized(parenthesized) => self.expression(&parenthesized.expression),
+            ExpressionKind::Variable(variable) => match variable.binding {
+                Some(binding) => {
+                    let access = self.access(binding);
+                    self.emit(Instruction::Load(access));
+                }
+                None => self.fail(format!("Unresolved variable `{}`", variable.name)),
+            },
+            ExpressionKind::Binary(binary) if matches!(binary.operator, TokenKind::And | TokenKind::Or) => {
+                self.expression(&binary.left);
+                self.at(binary.left.line, binary.left.column);
+                let end = match binary.operator {
+                    TokenKind::And => self.emit(Instruction::And(0)),
+                    _ => self.emit(Instruction::Or(0)),
+                };
+                self.expression(&binary.right);
+                self.at(binary.right.line, binary.right.column);
+                self.emit(Instruction::Condition);
+                self.patch(end);
+            }
+            ExpressionKind::Binary(binary) => {
+                self.expression(&binary.left);
+                self.expression(&binary.right);
+                self.at(expression.line, expression.column);
+                let operator = Operator::binary(&binary.operator).expect("the parser only produces binary operators");
+                self.emit(Instruction::Binary(operator));
+            }
+            ExpressionKind::Unary(unary) => {
+                self.expression(&unary.operand);
+                self.at(expression.line, expression.column);
+                let operator = match unary.operator {
+                    TokenKind::Minus => Operator::Negate,
+                    _ => Operator::Not,
+                };
+                self.emit(Instruction::Unary(operator));
+            }
+            ExpressionKind::Call(call) => {
+                self.expression(&call.callee);
+                self.expressions(&call.arguments);
+                self.at(expression.line, expression.column);
+                self.emit(Instruction::Call(call.arguments.len() as u32));
+            }
+            ExpressionKind::MethodCall(method_call) => {
+                self.expression(&method_call.receiver);
+                self.expressions(&method_call.arguments);
+                self.at(expression.line, expression.column);
+                let name = self.name(&method_call.name);
+                self.emit(Instruction::CallMethod(name, method_call.arguments.len() as u32));
+            }
+            ExpressionKind::Super(super_call) => {
+                self.expressions(&super_call.arguments);
+                self.at(expression.line, expression.column);
+                match super_call.binding {
+                    // The environment holding `this` is always nested directly inside the one holding the superclass.
+                    Some(Binding::Local { depth, slot }) if depth > 0 => {
+                        self.chunk.supers.push(SuperCall {
+                            method: super_call.method.clone(),
+                            superclass: self.access(Binding::Local { depth, slot }),
+                            object: self.access(Binding::Local { depth: depth - 1, slot: 0 }),
+                            arguments: super_call.arguments.len() as u32,
+                        });
+                        let index = self.chunk.supers.len() as u32 - 1;
+                        self.emit(Instruction::SuperCall(index));
+                    }
+                    _ => self.fail("Unresolved `super`".to_string()),
+                }
+            }
+            ExpressionKind::New(new) => {
+                self.expression(&new.class);
+                self.expressions(&new.arguments);
+                self.at(expression.line, expression.column);
+                self.emit(Instruction::New(new.arguments.len() as u32));
+            }
+            ExpressionKind::Index(index) => {
+                self.expression(&index.callee);
+                self.expression(&index.index);
+                self.at(expression.line, expression.column);
+                self.emit(Instruction::Index);
+            }
+            ExpressionKind::Slice(slice) => {
+                self.expression(&slice.callee);
+                self.expression(&slice.start);
+                self.expression(&slice.end);
+                self.at(expression.line, expression.column);
+                self.emit(Instruction::Slice);
+            }
+            ExpressionKind::Member(member) => {
+                self.expression(&member.callee);
+                self.at(expression.line, expression.column);
+                let name = self.name(&member.name);
+                self.emit(Instruction::GetMember(name));
+            }
+            ExpressionKind::Array(array) => {
+                self.expressions(&array.elements);
+                self.at(expression.line, expression.column);
+                self.emit(Instruction::Array(array.elements.len() as u32));
+            }
+            ExpressionKind::Map(map) => {
+                for (key, value) in &map.entries {
+                    self.expression(key);
+                    self.at(key.line, key.column);
+                    self.emit(Instruction::MapKey);
+                    self.expression(value);
+                }
+                self.at(expression.line, expression.column);
+                self.emit(Instruction::Map(map.entries.len() as u32));
+            }
+            ExpressionKind::Assign(assign) => self.assign(&assign.target, &assign.value),
+            ExpressionKind::Lambda(lambda) => {
+                self.captures = true;
+                self.closure(lambda);
+            }
+            ExpressionKind::Cast(cast) => {
+                self.expression(&cast.expression);
+                self.at(expression.line, expression.column);
+                self.chunk.types.push(cast.type_.clone());
+                let index = self.chunk.types.len() as u32 - 1;
+                self.emit(Instruction::Cast(index));
+            }
+            ExpressionKind::Match(match_expression) => self.match_expression(match_expression, expression),
+        }
+    }
+
+    fn expressions(&mut self, expressions: &[Expression]) {
+        for expression in expressions {
+            self.expression(expression);
+        }
+    }
+
+    fn assign(&mut self, target: &Expression, value: &Expression) {
+        self.expression(value);
+        match &target.kind {
+            ExpressionKind::Variable(variable) => {
+                self.at(target.line, target.column);
+                match variable.binding {
+                    Some(Binding::Native(_) | Binding::NativeClass(_) | Binding::NativeConstant(_)) => {
+                        self.fail(format!("Cannot assign to native `{}`", variable.name));
+                    }
+                    Some(binding) => {
+                        let access = self.access(binding);
+                        self.emit(Instruction::Store(access));
+                    }
+                    None => self.fail(format!("Unresolved variable `{}`", variable.name)),
+                }
+            }
+            ExpressionKind::Index(index) => {
+                self.expression(&index.callee);
+                self.expression(&index.index);
+                self.at(target.line, target.column);
+                self.emit(Instruction::SetIndex);
+            }
+            ExpressionKind::Member(member) => {
+                self.expression(&member.callee);
+                self.at(target.line, target.column);
+                let name = self.name(&member.name);
+                self.emit(Instruction::SetMember(name));
+            }
+            _ => {
+                self.at(target.line, target.column);
+                self.fail("Invalid assignment target".to_string());
+            }
+        }
+    }
+
+    fn match_expression(&mut self, match_expression: &Match, expression: &Expression) {
+        let locals = self.next_local;
+        let subject = self.temporary();
+        self.expression(&match_expression.subject);
+        self.emit(Instruction::SetLocal(subject));
+        let mut ends = Vec::new();
+        for arm in &match_expression.arms {
+            let arm_locals = self.next_local;
+            self.at(arm.pattern.line, arm.pattern.column);
+            // The scope of the arm is opened by `MatchArm`, and only kept if the pattern matches.
+            self.scopes.push(Vec::new());
+            let mut enums = Vec::new();
+            self.enums(&arm.pattern, &mut enums);
+            let bindings = self.next_local;
+            for _ in 0..bound(&arm.pattern) {
+                self.declare_slot();
+            }
+            let index = self.chunk.arms.len();
+            self.chunk.arms.push(MatchArm {
+                pattern: arm.pattern.clone(),
+                enums,
+                subject,
+                bindings,
+                next: 0,
+            });
+            self.emit(Instruction::MatchArm(index as u32));
+            let guard = arm.guard.as_ref().map(|guard| {
+                self.expression(guard);
+                self.at(guard.line, guard.column);
+                self.emit(Instruction::JumpIfFalse(0))
+            });
+            self.expression(&arm.body);
+            self.end_scope();
+            ends.push(self.emit(Instruction::Jump(0)));
+            if let Some(guard) = guard {
+                self.patch(guard);
+                if self.chunk.environments {
+                    self.emit(Instruction::PopScope);
+                }
+            }
+            self.chunk.arms[index].next = self.here();
+            self.next_local = arm_locals;
+        }
+        self.at(expression.line, expression.column);
+        self.emit(Instruction::NoMatch(subject));
+        for end in ends {
+            self.patch(end);
+        }
+        self.next_local = locals;
+    }
+
+    /// Where the enums of the variant patterns in `pattern` are, in the order they are matched.
+    fn enums(&self, pattern: &Pattern, enums: &mut Vec<Option<Access>>) {
+        if let PatternKind::Variant(variant) = &pattern.kind {
+            enums.push(variant.enum_.binding.map(|binding| self.access(binding)));
+            for field in &variant.fields {
+                self.enums(field, enums);
+            }
+        }
+    }
+}
+
+/// The statements of the branch of an `if` or the body of a loop, which are in a scope of
+/// their own whether or not they are a block.
+fn branch(statement: &Statement) -> &[Statement] {
+    match &statement.kind {
+        StatementKind::Block(block) => &block.statements,
+        _ => std::slice::from_ref(statement),
+    }
+}
+
+/// The binding of the variable an expression assigns and the value it assigns, if it assigns a variable.
+fn variable_assignment(expression: &Expression) -> Option<(Binding, &Expression)> {
+    match &expression.kind {
+        ExpressionKind::Assign(assign) => match &assign.target.kind {
+            ExpressionKind::Variable(variable) => Some((variable.binding?, &assign.value)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The number of variables a pattern binds.
+pub(crate) fn bound(pattern: &Pattern) -> usize {
+    match &pattern.kind {
+        PatternKind::Binding(_) => 1,
+        PatternKind::Variant(variant) => variant.fields.iter().map(bound).sum(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,254 @@
+use crate::value::{MapKey, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// A Wally value that did not have the shape a Rust type required.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionError {
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Expected {}, found {}", self.expected, self.found)
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Rust types that can be passed to Wally code.
+pub trait IntoValue {
+    fn into_value(self) -> Value;
+}
+
+/// Rust types that Wally values can be converted back into.
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self, ConversionError>;
+}
+
+/// Argument lists of calls from Rust into Wally: tuples of values convertible
+/// with `IntoValue`, like `(1, "two")`, or a `Vec<Value>`.
+pub trait IntoArguments {
+    fn into_arguments(self) -> Vec<Value>;
+}
+
+fn mismatch<T>(expected: &'static str, value: &Value) -> Result<T, ConversionError> {
+    Err(ConversionError { expected, found: value.type_name() })
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Value {
+        self
+    }
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        Ok(value)
+    }
+}
+
+impl IntoValue for () {
+    fn into_value(self) -> Value {
+        Value::Null
+    }
+}
+
+impl FromValue for () {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Null => Ok(()),
+            value => mismatch("null", &value),
+        }
+    }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Value {
+        Value::Integer(self)
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Integer(integer) => Ok(integer),
+            value => mismatch("int", &value),
+        }
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value(self) -> Value {
+        Value::Integer(self as i64)
+    }
+}
+
+impl FromValue for i32 {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Integer(integer) => i32::try_from(integer).map_err(|_| ConversionError { expected: "int within the range of i32", found: "int" }),
+            value => mismatch("int", &value),
+        }
+    }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Value {
+        Value::Float(self)
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Float(float) => Ok(float),
+            value => mismatch("float", &value),
+        }
+    }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Value {
+        Value::Bool(self)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Bool(boolean) => Ok(boolean),
+            value => mismatch("bool", &value),
+        }
+    }
+}
+
+impl IntoValue for char {
+    fn into_value(self) -> Value {
+        Value::Char(self)
+    }
+}
+
+impl FromValue for char {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Char(character) => Ok(character),
+            value => mismatch("char", &value),
+        }
+    }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Value {
+        Value::string(self)
+    }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Value {
+        Value::string(&self)
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::String(string) => Ok(string.to_string()),
+            value => mismatch("string", &value),
+        }
+    }
+}
+
+/// `None` is passed as `null`, and `null` converts back into `None`.
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Value {
+        self.map_or(Value::Null, IntoValue::into_value)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Value {
+        Value::array(self.into_iter().map(IntoValue::into_value).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        match value {
+            Value::Array(elements) => elements.borrow().iter().cloned().map(T::from_value).collect(),
+            value => mismatch("array", &value),
+        }
+    }
+}
+
+impl<T: IntoValue> IntoValue for BTreeMap<String, T> {
+    fn into_value(self) -> Value {
+        Value::map(self.into_iter().map(|(key, value)| (MapKey::String(key.into()), value.into_value())).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for BTreeMap<String, T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        map_entries(value)?.collect()
+    }
+}
+
+impl<T: IntoValue> IntoValue for HashMap<String, T> {
+    fn into_value(self) -> Value {
+        Value::map(self.into_iter().map(|(key, value)| (MapKey::String(key.into()), value.into_value())).collect())
+    }
+}
+
+impl<T: FromValue> FromValue for HashMap<String, T> {
+    fn from_value(value: Value) -> Result<Self, ConversionError> {
+        map_entries(value)?.collect()
+    }
+}
+
+/// The entries of a map with string keys, converted one at a time.
+fn map_entries<T: FromValue>(value: Value) -> Result<impl Iterator<Item = Result<(String, T), ConversionError>>, ConversionError> {
+    let entries = match value {
+        Value::Map(entries) => entries.borrow().clone(),
+        value => return mismatch("map", &value),
+    };
+    Ok(entries.into_iter().map(|(key, value)| match key {
+        MapKey::String(key) => Ok((key.to_string(), T::from_value(value)?)),
+        key => mismatch("string key", &key.to_value()),
+    }))
+}
+
+impl IntoArguments for Vec<Value> {
+    fn into_arguments(self) -> Vec<Value> {
+        self
+    }
+}
+
+macro_rules! tuple_arguments {
+    ($($name:ident),*) => {
+        impl<$($name: IntoValue),*> IntoArguments for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn into_arguments(self) -> Vec<Value> {
+                let ($($name,)*) = self;
+                vec![$($name.into_value()),*]
+            }
+        }
+    };
+}
+
+tuple_arguments!();
+tuple_arguments!(A);
+tuple_arguments!(A, B);
+tuple_arguments!(A, B, C);
+tuple_arguments!(A, B, C, D);
+tuple_arguments!(A, B, C, D, E);
+tuple_arguments!(A, B, C, D, E, F);
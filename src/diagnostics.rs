@@ -0,0 +1,110 @@
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A secondary location attached to a diagnostic, such as the declaration an error refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    /// The file the label points into when it is not the one the diagnostic is in,
+    /// as an index into the files given to `render_in`.
+    pub file: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub labels: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, line: usize, column: usize) -> Self {
+        Self { severity: Severity::Error, message, line, column, labels: Vec::new() }
+    }
+
+    pub fn warning(message: String, line: usize, column: usize) -> Self {
+        Self { severity: Severity::Warning, message, line, column, labels: Vec::new() }
+    }
+
+    pub fn with_label(mut self, message: String, line: usize, column: usize) -> Self {
+        self.labels.push(Label { message, line, column, file: None });
+        self
+    }
+
+    /// Adds a label pointing into another file of the program, such as a declaration in an imported module.
+    pub fn with_label_in(mut self, file: usize, message: String, line: usize, column: usize) -> Self {
+        self.labels.push(Label { message, line, column, file: Some(file) });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Renders a diagnostic with the offending source lines, in the style of rustc.
+///
+/// ```text
+/// error: cannot apply `+` to `int` and `float`
+///  --> main.wly:3:14
+///   |
+/// 3 |     return a + 1.5;
+///   |              ^
+/// ```
+pub fn render(diagnostic: &Diagnostic, file: &str, source: &str) -> String {
+    render_in(diagnostic, &[(file, source)], 0)
+}
+
+/// Renders a diagnostic found in `files[file]`, given as pairs of path and source,
+/// whose labels may point into any of the other files.
+pub fn render_in(diagnostic: &Diagnostic, files: &[(&str, &str)], file: usize) -> String {
+    let severity = match diagnostic.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let lines = |file: usize| -> Vec<&str> { files.get(file).map_or(Vec::new(), |(_, source)| source.lines().collect()) };
+    let path = |file: usize| files.get(file).map_or("", |(path, _)| *path);
+    let gutter = diagnostic
+        .labels
+        .iter()
+        .map(|label| label.line)
+        .chain(std::iter::once(diagnostic.line))
+        .max()
+        .unwrap_or(1)
+        .to_string()
+        .len();
+
+    let mut output = String::new();
+    let _ = writeln!(output, "{}: {}", severity, diagnostic.message);
+    render_snippet(&mut output, &lines(file), path(file), diagnostic.line, diagnostic.column, None, gutter);
+    for label in &diagnostic.labels {
+        let file = label.file.unwrap_or(file);
+        render_snippet(&mut output, &lines(file), path(file), label.line, label.column, Some(&label.message), gutter);
+    }
+    output
+}
+
+fn render_snippet(output: &mut String, lines: &[&str], file: &str, line: usize, column: usize, note: Option<&str>, gutter: usize) {
+    if let Some(note) = note {
+        let _ = writeln!(output, "{:>gutter$} = note: {}", "", note, gutter = gutter);
+    }
+    let _ = writeln!(output, "{:>gutter$}--> {}:{}:{}", "", file, line, column, gutter = gutter);
+    let text = match line.checked_sub(1).and_then(|index| lines.get(index)) {
+        Some(text) => text,
+        None => return,
+    };
+    let _ = writeln!(output, "{:>gutter$} |", "", gutter = gutter);
+    let _ = writeln!(output, "{:>gutter$} | {}", line, text, gutter = gutter);
+    // Tabs are kept so the caret lines up with the source as the terminal renders it.
+    let padding: String = text.chars().take(column.saturating_sub(1)).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+    let _ = writeln!(output, "{:>gutter$} | {}^", "", padding, gutter = gutter);
+}
@@ -0,0 +1,389 @@
+use crate::bytecode::{FormatError, Program};
+use crate::checker::check;
+use crate::convert::{ConversionError, FromValue, IntoArguments, IntoValue};
+use crate::diagnostics::{render, render_in};
+use crate::interpreter::{Interpreter, RuntimeError};
+use crate::limits::{CancelHandle, Limit, Limits};
+use crate::module::{load, load_source, LoadError, Module};
+use crate::native::{Capability, NativeClass, Natives};
+use crate::process;
+use crate::parser::{StatementKind, Type, TypeKind};
+use crate::resolver::resolve;
+use crate::value::Value;
+use crate::vm::Backend;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Compiles and runs Wally programs on behalf of a Rust host.
+///
+/// An engine holds one program at a time. `eval` and `eval_file` load, check and
+/// run the top level of a new program, replacing the one evaluated before, after
+/// which its functions can be called and its globals read and written.
+///
+/// Programs cannot touch files, the environment or the host process unless the
+/// engine is allowed to let them, with [`Engine::allow`]. Programs that are not
+/// trusted can also be bounded in what they use with [`Engine::set_limits`], and
+/// stopped from another thread through [`Engine::cancel_handle`].
+///
+/// ```
+/// use wally_lang::Engine;
+///
+/// let mut engine = Engine::new();
+/// engine.eval("var greeting: string = \"Hello\"; fn add(a: int, b: int): int { return a + b; }").unwrap();
+///
+/// let sum: i64 = engine.call("add", (1, 2)).unwrap();
+/// assert_eq!(sum, 3);
+///
+/// engine.set("greeting", "Hi").unwrap();
+/// assert_eq!(engine.get::<String>("greeting").unwrap(), "Hi");
+/// ```
+pub struct Engine {
+    /// The directory imports are relative to, if not the one of the file evaluated.
+    root: Option<PathBuf>,
+    natives: Natives,
+    capabilities: HashSet<Capability>,
+    limits: Limits,
+    cancel: CancelHandle,
+    backend: Backend,
+    interpreter: Interpreter,
+    /// Declared types of the variables of the program, the only globals the host may assign.
+    variables: HashMap<String, Type>,
+    warnings: Vec<String>,
+    /// Path and source of each module of the program, to render its runtime errors with.
+    files: Vec<(String, String)>,
+}
+
+/// Why an engine could not evaluate a program or carry out a request on it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The program has syntax or type errors, rendered like the command line does.
+    Compile(String),
+    Runtime(RuntimeError),
+    /// The program declares no global, or the engine no host class, of this name.
+    Undefined(String),
+    /// The global is a constant, function or type rather than a variable.
+    NotAssignable(String),
+    Conversion(ConversionError),
+    /// The bytecode given to `eval_bytecode` cannot be run.
+    Bytecode(FormatError),
+}
+
+impl Error {
+    /// The limit a run was stopped by, for errors from exceeding one or being cancelled.
+    pub fn limit(&self) -> Option<Limit> {
+        match self {
+            Error::Runtime(error) => error.limit,
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Compile(diagnostics) => write!(f, "{}", diagnostics.trim_end()),
+            Error::Runtime(error) => write!(f, "runtime error: {}", error),
+            Error::Undefined(name) => write!(f, "`{}` is not defined", name),
+            Error::NotAssignable(name) => write!(f, "`{}` is not a variable and cannot be assigned", name),
+            Error::Conversion(error) => write!(f, "{}", error),
+            Error::Bytecode(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<RuntimeError> for Error {
+    fn from(error: RuntimeError) -> Self {
+        Error::Runtime(error)
+    }
+}
+
+impl From<ConversionError> for Error {
+    fn from(error: ConversionError) -> Self {
+        Error::Conversion(error)
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(error: LoadError) -> Self {
+        Error::Compile(render(&error.diagnostic, &error.path.display().to_string(), &error.source))
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    /// Creates an engine that provides the builtin native functions, grants no capabilities
+    /// and holds no program yet.
+    pub fn new() -> Engine {
+        let natives = Natives::new();
+        Engine {
+            root: None,
+            interpreter: Interpreter::new(Rc::new(natives.clone())),
+            natives,
+            capabilities: HashSet::new(),
+            limits: Limits::new(),
+            cancel: CancelHandle::new(),
+            backend: Backend::default(),
+            variables: HashMap::new(),
+            warnings: Vec::new(),
+            files: Vec::new(),
+        }
+    }
+
+    /// Makes imports relative to `root`. By default they are relative to the directory of
+    /// the file evaluated, or the current directory for source strings. Programs can only
+    /// import files inside it.
+    pub fn set_root(&mut self, root: impl Into<PathBuf>) {
+        self.root = Some(root.into());
+    }
+
+    /// Lets programs use the natives that need `capability`, including the one evaluated already.
+    pub fn allow(&mut self, capability: Capability) {
+        self.capabilities.insert(capability);
+        self.interpreter.allow(capability);
+    }
+
+    /// Takes back `capability`, including from the program evaluated already.
+    pub fn deny(&mut self, capability: Capability) {
+        self.capabilities.remove(&capability);
+        self.interpreter.deny(capability);
+    }
+
+    /// Bounds what each run uses from now on, including calls into the program evaluated already.
+    /// A run that exceeds a limit fails with a runtime error that names it in its `limit`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.interpreter.set_limits(limits);
+    }
+
+    /// Chooses how function bodies run, including those of the program evaluated already.
+    /// They are compiled to bytecode by default.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+        self.interpreter.set_backend(backend);
+    }
+
+    /// A handle that stops the runs of this engine, which can be sent to another thread.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    /// Sets what `process.args` returns to the programs evaluated from now on.
+    pub fn set_arguments(&mut self, arguments: Vec<String>) {
+        process::set_arguments(&mut self.natives, arguments);
+    }
+
+    /// Registers a native function for the programs evaluated from now on, replacing
+    /// any builtin of the same name.
+    pub fn register(&mut self, name: &str, parameters: Vec<Type>, return_type: Type, function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static) {
+        self.natives.define(name, parameters, return_type, function);
+    }
+
+    /// Registers a host class for the programs evaluated from now on, replacing any
+    /// registered before under the same name.
+    pub fn register_class(&mut self, class: NativeClass) {
+        self.natives.define_class(class);
+    }
+
+    /// Wraps a Rust value in an instance of the registered host class `class`, to pass to scripts.
+    pub fn instance(&self, class: &str, data: impl Any) -> Result<Value, Error> {
+        match self.natives.class_index(class) {
+            Some(index) => Ok(Value::host(self.natives.class(index), data)),
+            None => Err(Error::Undefined(class.to_string())),
+        }
+    }
+
+    /// Evaluates a program given as source code.
+    pub fn eval(&mut self, source: &str) -> Result<(), Error> {
+        let root = self.root.clone().unwrap_or_else(|| PathBuf::from("."));
+        let modules = load_source(&root, Path::new("<eval>"), source.to_string(), &self.natives)?;
+        self.run(modules)
+    }
+
+    /// Evaluates the program in the file at `path`.
+    pub fn eval_file(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let modules = self.load_file(path.as_ref())?;
+        self.run(modules)
+    }
+
+    /// Compiles a program given as source code to the bytes of a `.wlyc` file, which
+    /// `eval_bytecode` runs without the source.
+    pub fn compile(&mut self, source: &str) -> Result<Vec<u8>, Error> {
+        let root = self.root.clone().unwrap_or_else(|| PathBuf::from("."));
+        let modules = load_source(&root, Path::new("<eval>"), source.to_string(), &self.natives)?;
+        let modules = self.check(modules)?;
+        Ok(Program::compile(&modules).to_bytes(&self.natives))
+    }
+
+    /// Like `compile`, for the program in the file at `path`.
+    pub fn compile_file(&mut self, path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
+        let modules = self.load_file(path.as_ref())?;
+        let modules = self.check(modules)?;
+        Ok(Program::compile(&modules).to_bytes(&self.natives))
+    }
+
+    /// Evaluates a program compiled by `compile`, or by `wallyc <file> -o <output>`. It runs
+    /// as bytecode whatever the backend, and its runtime errors render without source lines.
+    pub fn eval_bytecode(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let program = Program::from_bytes(bytes, &self.natives).map_err(Error::Bytecode)?;
+        self.warnings.clear();
+        self.files = program.modules.iter().map(|module| (module.path.clone(), String::new())).collect();
+        self.variables = program.modules.last().map(|entry| entry.variables.iter().cloned().collect()).unwrap_or_default();
+        self.restart();
+        for module in &program.modules {
+            self.interpreter.interpret_compiled(module)?;
+        }
+        Ok(())
+    }
+
+    fn load_file(&self, path: &Path) -> Result<Vec<Module>, Error> {
+        let root = self.root.clone().unwrap_or_else(|| match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        });
+        Ok(load(&root, path, &self.natives)?)
+    }
+
+    /// Warnings the program evaluated last compiled with, rendered like the command line does.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Renders an error like the command line does: compile errors as they are, and runtime
+    /// errors with the source lines of their location and of each call in their stack trace.
+    pub fn render(&self, error: &Error) -> String {
+        match error {
+            Error::Runtime(error) => {
+                let files: Vec<(&str, &str)> = self.files.iter().map(|(path, source)| (path.as_str(), source.as_str())).collect();
+                error.render(&files)
+            }
+            Error::Compile(_) => format!("{}\n", error),
+            error => format!("error: {}\n", error),
+        }
+    }
+
+    /// Calls a global function of the program, or instantiates a global class, by name.
+    pub fn call<R: FromValue>(&mut self, name: &str, arguments: impl IntoArguments) -> Result<R, Error> {
+        let callee = self.interpreter.global(name).ok_or_else(|| Error::Undefined(name.to_string()))?;
+        let arguments = arguments.into_arguments();
+        // The checker never saw these arguments, so they are at least checked to be of the right kind.
+        let parameters: Vec<&Type> = match &callee {
+            Value::Function(function) => function.declaration.parameters.iter().map(|parameter| &parameter.type_).collect(),
+            Value::Native(native) => native.parameters.iter().collect(),
+            _ => Vec::new(),
+        };
+        for (argument, parameter) in arguments.iter().zip(parameters) {
+            check_kind(argument, parameter)?;
+        }
+        self.interpreter.reset_usage();
+        let result = self.interpreter.call_value(callee, arguments, 0, 0)?;
+        Ok(R::from_value(result)?)
+    }
+
+    /// Reads a global of the program.
+    pub fn get<T: FromValue>(&self, name: &str) -> Result<T, Error> {
+        let value = self.interpreter.global(name).ok_or_else(|| Error::Undefined(name.to_string()))?;
+        Ok(T::from_value(value)?)
+    }
+
+    /// Assigns a global variable of the program.
+    pub fn set(&mut self, name: &str, value: impl IntoValue) -> Result<(), Error> {
+        let value = value.into_value();
+        match self.variables.get(name) {
+            Some(type_) => check_kind(&value, type_)?,
+            None if self.interpreter.global(name).is_some() => return Err(Error::NotAssignable(name.to_string())),
+            None => return Err(Error::Undefined(name.to_string())),
+        }
+        self.interpreter.set_global(name, value);
+        Ok(())
+    }
+
+    /// Resolves and checks a loaded program and runs the top level of each of its modules.
+    fn run(&mut self, modules: Vec<Module>) -> Result<(), Error> {
+        let modules = self.check(modules)?;
+        self.files = modules.iter().map(|module| (module.path.display().to_string(), module.source.clone())).collect();
+        self.variables.clear();
+        if let Some(entry) = modules.last() {
+            for statement in &entry.statements {
+                if let StatementKind::VarDeclaration(variable) = &statement.kind {
+                    self.variables.insert(variable.name.clone(), variable.type_.clone());
+                }
+            }
+        }
+        self.restart();
+        for module in modules {
+            self.interpreter.interpret(module.statements)?;
+        }
+        Ok(())
+    }
+
+    /// Resolves and checks a loaded program, keeping its warnings.
+    fn check(&mut self, mut modules: Vec<Module>) -> Result<Vec<Module>, Error> {
+        let diagnostics = match resolve(&mut modules, &self.natives) {
+            Ok(()) => check(&modules, &self.natives),
+            Err(errors) => errors,
+        };
+        let paths: Vec<String> = modules.iter().map(|module| module.path.display().to_string()).collect();
+        let files: Vec<(&str, &str)> = paths.iter().zip(&modules).map(|(path, module)| (path.as_str(), module.source.as_str())).collect();
+        let (errors, warnings): (Vec<_>, Vec<_>) = diagnostics.iter().partition(|(_, diagnostic)| diagnostic.is_error());
+        self.warnings = warnings.iter().map(|(index, diagnostic)| render_in(diagnostic, &files, *index)).collect();
+        if !errors.is_empty() {
+            return Err(Error::Compile(errors.iter().map(|(index, diagnostic)| render_in(diagnostic, &files, *index)).collect()));
+        }
+        Ok(modules)
+    }
+
+    /// Replaces the interpreter with a fresh one for a new program.
+    fn restart(&mut self) {
+        self.interpreter = Interpreter::new(Rc::new(self.natives.clone()));
+        for capability in &self.capabilities {
+            self.interpreter.allow(*capability);
+        }
+        self.interpreter.set_limits(self.limits);
+        self.interpreter.set_cancel_handle(self.cancel.clone());
+        self.interpreter.set_backend(self.backend);
+    }
+}
+
+/// Checks that a value passed in by the host is of the kind of value `type_` declares,
+/// down to each element of arrays and each entry of maps. Classes, enums, type parameters
+/// and the signatures of functions are not checked any further.
+fn check_kind(value: &Value, type_: &Type) -> Result<(), ConversionError> {
+    let expected = match (&type_.kind, value) {
+        (TypeKind::Named(..), _) => return Ok(()),
+        (TypeKind::Array(element), Value::Array(elements)) => {
+            return elements.borrow().iter().try_for_each(|value| check_kind(value, element));
+        }
+        (TypeKind::Map(key, element), Value::Map(entries)) => {
+            return entries.borrow().iter().try_for_each(|(entry, value)| {
+                check_kind(&entry.to_value(), key)?;
+                check_kind(value, element)
+            });
+        }
+        (TypeKind::Integer, Value::Integer(_))
+        | (TypeKind::Float, Value::Float(_))
+        | (TypeKind::Bool, Value::Bool(_))
+        | (TypeKind::Char, Value::Char(_))
+        | (TypeKind::String, Value::String(_))
+        | (TypeKind::Function(..), Value::Function(_) | Value::Native(_)) => return Ok(()),
+        (TypeKind::Integer, _) => "int",
+        (TypeKind::Float, _) => "float",
+        (TypeKind::Bool, _) => "bool",
+        (TypeKind::Char, _) => "char",
+        (TypeKind::String, _) => "string",
+        (TypeKind::Array(_), _) => "array",
+        (TypeKind::Map(..), _) => "map",
+        (TypeKind::Function(..), _) => "function",
+        (TypeKind::Void, _) => "void",
+    };
+    Err(ConversionError { expected, found: value.type_name() })
+}
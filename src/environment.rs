@@ -0,0 +1,98 @@
+use crate::value::{release, Value};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A runtime scope holding the values of the variables declared in it.
+///
+/// Values are stored in declaration order, so the slot the resolver assigns to
+/// a declaration is its index here. Each environment links to the one it is
+/// nested in; closures keep their defining environment alive through this link.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: RefCell<Vec<Value>>,
+    parent: Option<Rc<Environment>>,
+}
+
+impl Environment {
+    pub fn new(parent: Option<Rc<Environment>>) -> Rc<Environment> {
+        Rc::new(Environment {
+            values: RefCell::new(Vec::new()),
+            parent,
+        })
+    }
+
+    /// Defines the next slot of this environment.
+    pub fn define(&self, value: Value) {
+        self.values.borrow_mut().push(value);
+    }
+
+    pub fn get(&self, depth: usize, slot: usize) -> Value {
+        self.ancestor(depth).values.borrow()[slot].clone()
+    }
+
+    pub fn assign(&self, depth: usize, slot: usize, value: Value) {
+        self.ancestor(depth).values.borrow_mut()[slot] = value;
+    }
+
+    /// Like `get`, but `None` if the slot does not exist, for code the resolver did not check.
+    pub fn try_get(&self, depth: usize, slot: usize) -> Option<Value> {
+        self.try_ancestor(depth)?.values.borrow().get(slot).cloned()
+    }
+
+    /// Like `assign`, but returns whether the slot exists instead of panicking.
+    pub fn try_assign(&self, depth: usize, slot: usize, value: Value) -> bool {
+        match self.try_ancestor(depth) {
+            Some(environment) => match environment.values.borrow_mut().get_mut(slot) {
+                Some(target) => {
+                    *target = value;
+                    true
+                }
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    /// Moves the values of `environment` into `values` if nothing else refers to it, so
+    /// `release` can drop them.
+    pub(crate) fn release_into(environment: Rc<Environment>, values: &mut Vec<Value>) {
+        if let Some(mut environment) = Rc::into_inner(environment) {
+            values.append(environment.values.get_mut());
+        }
+    }
+
+    /// The environment this one is nested in.
+    pub fn parent(&self) -> Option<Rc<Environment>> {
+        self.parent.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.borrow().is_empty()
+    }
+
+    fn ancestor(&self, depth: usize) -> &Environment {
+        let mut environment = self;
+        for _ in 0..depth {
+            environment = environment.parent.as_deref().expect("resolved depth exceeds environment chain");
+        }
+        environment
+    }
+
+    fn try_ancestor(&self, depth: usize) -> Option<&Environment> {
+        let mut environment = self;
+        for _ in 0..depth {
+            environment = environment.parent.as_deref()?;
+        }
+        Some(environment)
+    }
+}
+
+impl Drop for Environment {
+    fn drop(&mut self) {
+        release(std::mem::take(self.values.get_mut()));
+    }
+}
@@ -0,0 +1,62 @@
+use crate::native::{primitive, text, Capability, Natives};
+use crate::parser::{Type, TypeKind};
+use crate::value::Value;
+use std::fs;
+use std::io::Write;
+
+/// Registers the `fs` module. Reading needs the `ReadFiles` capability and changing
+/// anything the `WriteFiles` one.
+///
+/// Relative paths are relative to the working directory of the host process, and
+/// failures are runtime errors naming the path.
+pub fn define(natives: &mut Natives) {
+    let string = || primitive(TypeKind::String);
+    let void = || primitive(TypeKind::Void);
+
+    natives.define_gated(Capability::ReadFiles, "fs.read", vec![string()], string(), |arguments| {
+        let path = text(&arguments[0])?;
+        fs::read_to_string(path).map(|contents| Value::string(&contents)).map_err(|error| failed("read", path, error))
+    });
+    natives.define_gated(Capability::ReadFiles, "fs.exists", vec![string()], primitive(TypeKind::Bool), |arguments| {
+        Ok(Value::Bool(fs::metadata(text(&arguments[0])?).is_ok()))
+    });
+    // Entries are listed by name, sorted so listings do not depend on the platform.
+    natives.define_gated(Capability::ReadFiles, "fs.list", vec![string()], Type::new(TypeKind::Array(Box::new(string())), 0, 0), |arguments| {
+        let path = text(&arguments[0])?;
+        let mut names = Vec::new();
+        for entry in fs::read_dir(path).map_err(|error| failed("list", path, error))? {
+            let entry = entry.map_err(|error| failed("list", path, error))?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(Value::array(names.iter().map(|name| Value::string(name)).collect()))
+    });
+    natives.define_gated(Capability::WriteFiles, "fs.write", vec![string(), string()], void(), |arguments| {
+        let path = text(&arguments[0])?;
+        fs::write(path, text(&arguments[1])?).map(|_| Value::Null).map_err(|error| failed("write", path, error))
+    });
+    natives.define_gated(Capability::WriteFiles, "fs.append", vec![string(), string()], void(), |arguments| {
+        let path = text(&arguments[0])?;
+        let contents = text(&arguments[1])?;
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map(|_| Value::Null)
+            .map_err(|error| failed("append to", path, error))
+    });
+    // Directories are only removed when empty.
+    natives.define_gated(Capability::WriteFiles, "fs.remove", vec![string()], void(), |arguments| {
+        let path = text(&arguments[0])?;
+        let removed = match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => fs::remove_dir(path),
+            _ => fs::remove_file(path),
+        };
+        removed.map(|_| Value::Null).map_err(|error| failed("remove", path, error))
+    });
+}
+
+fn failed(action: &str, path: &str, error: std::io::Error) -> String {
+    format!("Could not {} `{}`: {}", action, path, error)
+}
@@ -1,122 +1,1750 @@
+use crate::bytecode::CompiledModule;
+use crate::checker::operator_symbol;
+use crate::compiler::{class_prototype, ClassPrototype, Prototype};
+use crate::diagnostics::{render_in, Diagnostic};
+use crate::environment::Environment;
+use crate::limits::{CancelHandle, Limit, Limits};
+use crate::native::{Capability, NativeClass, NativeFunction, Natives, STRING_METHODS};
 use crate::parser::*;
+use crate::resolver::global_name;
+use crate::token::TokenKind;
+use crate::vm::Backend;
+use crate::value::{Class, Enum, Field, Function, HostObject, Initializer, MapKey, Object, Value, Variant};
+use std::any::Any;
+use std::cell::{RefCell, RefMut};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct Interpreter {
-    pub global_scope: Scope,
+    pub globals: Rc<Environment>,
+    global_slots: HashMap<String, usize>,
+    pub(crate) environment: Rc<Environment>,
+    pub(crate) natives: Rc<Natives>,
+    /// Index of the module whose code is running, and the number interpreted so far.
+    pub(crate) module: usize,
+    modules: usize,
+    /// What the natives called may access, nothing unless granted.
+    capabilities: HashSet<Capability>,
+    limits: Limits,
+    usage: Usage,
+    /// Wally functions active at the moment.
+    depth: usize,
+    cancel: CancelHandle,
+    backend: Backend,
+    /// Whether the program was compiled ahead of time, which leaves no syntax tree to walk.
+    precompiled: bool,
+    /// The `Error` class of the prelude, which errors raised by the interpreter are caught as.
+    error_class: Option<Rc<Class>>,
+    /// Emptied vectors the bytecode VM reuses for arguments, frames and operand stacks, so
+    /// calls do not allocate once they have run a few times.
+    pub(crate) spare: Vec<Vec<Value>>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Scope {
-    pub classes: Vec<Class>,
-    pub functions: Vec<Function>,
-    pub variables: Vec<Variable>,
+/// How many steps run between checks for cancellation.
+const CANCEL_INTERVAL: u64 = 1024;
+
+/// What the current run has used of what its limits bound.
+#[derive(Debug, Clone, Copy, Default)]
+struct Usage {
+    steps: u64,
+    /// The step up to which the run can go on without checking its limits or cancellation.
+    unchecked: u64,
+    values: u64,
+    bytes: u64,
 }
 
-#[derive(Debug, Clone)]
-pub struct Class {
-    pub name: String,
-    pub local_scope: Scope,
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    /// Index of the module the error was raised in, once known.
+    pub file: Option<usize>,
+    /// The object a `throw` raised, or `None` for errors raised by the interpreter itself.
+    pub thrown: Option<Value>,
+    /// The limit that stopped the run, for errors `catch` clauses pass on.
+    pub limit: Option<Limit>,
+    /// The calls the error propagated out of, innermost first.
+    pub trace: Vec<Frame>,
 }
 
-#[derive(Debug, Clone)]
-pub struct Function {
-    pub name: String,
-    pub parameters: Vec<Parameter>,
-    pub local_scope: Scope,
+/// A function call on the Wally call stack, with where it was called from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    /// Index of the module of the call, in the order the modules were interpreted.
+    pub file: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, line: usize, column: usize) -> Self {
+        Self { message, line, column, file: None, thrown: None, limit: None, trace: Vec::new() }
+    }
+
+    /// The error as a diagnostic in its module, with a label at each call it propagated out of.
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(self.message.clone(), self.line, self.column);
+        // Calls made by the host rather than from Wally code are at line 0 and have no source to show.
+        for shown in abbreviate(self.trace.iter().filter(|frame| frame.line > 0)) {
+            match shown {
+                Shown::Calls(frame, 1) => {
+                    diagnostic = diagnostic.with_label_in(frame.file, format!("in `{}`, called here", frame.function), frame.line, frame.column);
+                }
+                Shown::Calls(frame, count) => {
+                    let message = format!("in `{}`, called here {} times in a row", frame.function, count);
+                    diagnostic = diagnostic.with_label_in(frame.file, message, frame.line, frame.column);
+                }
+                Shown::Omitted(count) => {
+                    if let Some(label) = diagnostic.labels.last_mut() {
+                        label.message += &format!(", from {} calls not shown", count);
+                    }
+                }
+            }
+        }
+        diagnostic
+    }
+
+    /// Renders the error and its stack trace like compile errors, given the path and source of
+    /// each module of the program.
+    pub fn render(&self, files: &[(&str, &str)]) -> String {
+        // Errors raised outside of any module, like calling a missing `main`, are reported in the entry module.
+        let file = self.file.unwrap_or(files.len().saturating_sub(1));
+        render_in(&self.to_diagnostic(), files, file)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)?;
+        // Each function was left from where it called the next one in, or where the error was raised.
+        let locations = std::iter::once((self.line, self.column)).chain(self.trace.iter().map(|frame| (frame.line, frame.column)));
+        for shown in abbreviate(self.trace.iter().map(|frame| &frame.function).zip(locations)) {
+            match shown {
+                Shown::Calls((function, (line, column)), count) => {
+                    write!(f, "\n    in `{}` at line {}, column {}", function, line, column)?;
+                    if count > 1 {
+                        write!(f, "\n    ... repeated {} more times", count - 1)?;
+                    }
+                }
+                Shown::Omitted(count) => write!(f, "\n    ... {} calls not shown", count)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How many runs of calls a stack trace shows at its innermost and at its outermost end.
+const TRACE_ENDS: usize = 10;
+
+/// Part of a stack trace as it is shown.
+enum Shown<T> {
+    /// A call, and how many times in a row it was made.
+    Calls(T, usize),
+    /// Calls left out of the middle of a long trace.
+    Omitted(usize),
 }
 
+/// Shortens a stack trace for showing, since one that hit the call depth limit is a thousand calls
+/// long: calls that repeat the one before are shown once, and if that still leaves more than
+/// `TRACE_ENDS` at each end, the ones in between are left out.
+fn abbreviate<T: PartialEq>(calls: impl IntoIterator<Item = T>) -> Vec<Shown<T>> {
+    let mut runs: Vec<(T, usize)> = Vec::new();
+    for call in calls {
+        match runs.last_mut() {
+            Some((last, count)) if *last == call => *count += 1,
+            _ => runs.push((call, 1)),
+        }
+    }
+    let mut shown = Vec::new();
+    let omitted = runs.len().saturating_sub(2 * TRACE_ENDS);
+    let mut runs = runs.into_iter();
+    if omitted > 0 {
+        shown.extend(runs.by_ref().take(TRACE_ENDS).map(|(call, count)| Shown::Calls(call, count)));
+        shown.push(Shown::Omitted(runs.by_ref().take(omitted).map(|(_, count)| count).sum()));
+    }
+    shown.extend(runs.map(|(call, count)| Shown::Calls(call, count)));
+    shown
+}
+
+/// How control leaves a statement.
 #[derive(Debug, Clone)]
-pub struct Variable {
-    pub name: String,
-    pub _type: Type,
-    pub value: Expression,
+pub enum Flow {
+    Normal,
+    Return(Value),
+    Break,
+    Continue,
 }
 
 impl Interpreter {
-    pub fn new() -> Interpreter {
+    /// Creates an interpreter for programs resolved against `natives`.
+    pub fn new(natives: Rc<Natives>) -> Interpreter {
+        let globals = Environment::new(None);
         Interpreter {
-            global_scope: Scope {
-                classes: Vec::new(),
-                functions: Vec::new(),
-                variables: Vec::new(),
-            },
+            environment: globals.clone(),
+            globals,
+            global_slots: HashMap::new(),
+            natives,
+            module: 0,
+            modules: 0,
+            capabilities: HashSet::new(),
+            limits: Limits::new(),
+            usage: Usage::default(),
+            depth: 0,
+            cancel: CancelHandle::new(),
+            backend: Backend::default(),
+            precompiled: false,
+            error_class: None,
+            spare: Vec::new(),
         }
     }
 
-    pub fn interpret(&mut self, statements: Vec<Statement>) {
+    /// Lets the program call the natives that need `capability`.
+    pub fn allow(&mut self, capability: Capability) {
+        self.capabilities.insert(capability);
+    }
+
+    /// Stops the program from calling the natives that need `capability`.
+    pub fn deny(&mut self, capability: Capability) {
+        self.capabilities.remove(&capability);
+    }
+
+    /// Whether the program may call the natives that need `capability`.
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Bounds what runs use from now on, without resetting what the current one has used.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+        self.usage.unchecked = 0;
+    }
+
+    /// Stops running whenever `cancel` is cancelled.
+    pub fn set_cancel_handle(&mut self, cancel: CancelHandle) {
+        self.cancel = cancel;
+        self.usage.unchecked = 0;
+    }
+
+    /// Chooses how function bodies are run. The top level of modules is interpreted unless it
+    /// was compiled ahead of time.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    /// Starts a new run, with nothing used of its limits yet.
+    pub fn reset_usage(&mut self) {
+        self.usage = Usage::default();
+    }
+
+    /// Counts a step towards the step limit, and stops if the run was cancelled.
+    #[inline]
+    pub(crate) fn step(&mut self, line: usize, column: usize) -> Result<(), RuntimeError> {
+        self.usage.steps += 1;
+        if self.usage.steps > self.usage.unchecked {
+            return self.check(line, column);
+        }
+        Ok(())
+    }
+
+    /// Stops a run that used up its steps or was cancelled. Cancellation is only looked at
+    /// every `CANCEL_INTERVAL` steps, and on the first step of a run.
+    #[cold]
+    #[inline(never)]
+    fn check(&mut self, line: usize, column: usize) -> Result<(), RuntimeError> {
+        if self.limits.steps.is_some_and(|steps| self.usage.steps > steps) || self.cancel.is_cancelled() {
+            return Err(self.stopped(line, column));
+        }
+        let next = self.usage.steps + CANCEL_INTERVAL;
+        self.usage.unchecked = self.limits.steps.map_or(next, |steps| steps.min(next));
+        Ok(())
+    }
+
+    /// The error a run that ran out of steps or was cancelled stops with.
+    #[cold]
+    fn stopped(&self, line: usize, column: usize) -> RuntimeError {
+        match self.limits.steps.filter(|steps| self.usage.steps > *steps) {
+            Some(steps) => limit_exceeded(Limit::Steps, format!("Step limit of {} exceeded", steps), line, column),
+            None => limit_exceeded(Limit::Cancelled, "Execution was cancelled".to_string(), line, column),
+        }
+    }
+
+    /// Counts `values` new values holding `bytes` bytes towards the allocation limits.
+    fn allocate(&mut self, values: u64, bytes: usize, line: usize, column: usize) -> Result<(), RuntimeError> {
+        self.usage.values += values;
+        self.usage.bytes += bytes as u64;
+        if let Some(values) = self.limits.values.filter(|values| self.usage.values > *values) {
+            return Err(limit_exceeded(Limit::Values, format!("Allocation limit of {} values exceeded", values), line, column));
+        }
+        if let Some(bytes) = self.limits.bytes.filter(|bytes| self.usage.bytes > *bytes) {
+            return Err(limit_exceeded(Limit::Bytes, format!("Allocation limit of {} bytes exceeded", bytes), line, column));
+        }
+        Ok(())
+    }
+
+    /// Counts a value that was just created towards the allocation limits and passes it on.
+    pub(crate) fn allocated(&mut self, value: Value, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        if let Some(bytes) = footprint(&value) {
+            self.allocate(1, bytes, line, column)?;
+        }
+        Ok(value)
+    }
+
+    /// Runs the top level of a resolved module.
+    ///
+    /// Every top-level declaration gets its global slot before anything runs and
+    /// functions are bound straight away, so a global may be initialized by
+    /// calling a function declared further down the file. Modules are run one
+    /// after another in dependency order, each taking the next run of global
+    /// slots, and `global` finds the names of the one run last.
+    pub fn interpret(&mut self, statements: Vec<Statement>) -> Result<(), RuntimeError> {
+        self.module = self.modules;
+        self.modules += 1;
+        let result = self.run_top_level(&statements);
+        self.find_error_class();
+        result.map_err(|mut error| {
+            error.file.get_or_insert(self.module);
+            error
+        })
+    }
+
+    /// Runs the top level of a module compiled ahead of time, like `interpret`. Programs
+    /// compiled ahead of time always run as bytecode, whatever the backend.
+    pub fn interpret_compiled(&mut self, module: &CompiledModule) -> Result<(), RuntimeError> {
+        self.module = self.modules;
+        self.modules += 1;
+        self.precompiled = true;
+        self.global_slots.clear();
+        for name in &module.globals {
+            self.global_slots.insert(name.clone(), self.globals.len());
+            self.globals.define(Value::Null);
+        }
+        let previous = std::mem::replace(&mut self.environment, self.globals.clone());
+        let result = self.run_in_place(&module.code);
+        self.environment = previous;
+        self.find_error_class();
+        result.map(|_| ()).map_err(|mut error| {
+            error.file.get_or_insert(self.module);
+            error
+        })
+    }
+
+    /// Keeps hold of the `Error` class once the prelude, which is always the first module, has run.
+    fn find_error_class(&mut self) {
+        if self.module == 0 {
+            if let Some(Value::Class(class)) = self.global("Error") {
+                self.error_class = Some(class);
+            }
+        }
+    }
+
+    fn run_top_level(&mut self, statements: &[Statement]) -> Result<(), RuntimeError> {
+        self.global_slots.clear();
+        for statement in statements {
+            if let Some(name) = global_name(statement) {
+                self.global_slots.insert(name.to_string(), self.globals.len());
+                self.globals.define(Value::Null);
+            }
+        }
+        let mut classes = Vec::new();
         for statement in statements {
-            match statement.kind.clone() {
+            match &statement.kind {
                 StatementKind::FunctionDeclaration(function) => {
-                    self.global_scope.functions.push(Function {
-                        name: function.name,
-                        parameters: function.parameters,
-                        local_scope: Scope {
-                            classes: vec![],
-                            functions: vec![],
-                            variables: vec![],
-                        },
-                    });
+                    let value = self.make_function(function);
+                    self.globals.assign(0, self.global_slots[&function.name], value);
                 }
-                StatementKind::VarDeclaration(variable) => {
-                    self.global_scope.variables.push(Variable {
-                        name: variable.name,
-                        _type: variable.type_,
-                        value: variable.initializer.unwrap(),
-                    });
+                StatementKind::EnumDeclaration(enum_) => {
+                    let value = self.make_enum(enum_);
+                    self.globals.assign(0, self.global_slots[&enum_.name], value);
                 }
+                StatementKind::ClassDeclaration(class) => classes.push(class),
+                _ => {}
+            }
+        }
+        // A class can only be created once its superclass exists, whatever order they are declared in.
+        while !classes.is_empty() {
+            let ready = classes.iter().position(|class| match &class.superclass {
+                Some(superclass) => !classes.iter().any(|other| other.name == superclass.name),
+                None => true,
+            });
+            let class = classes.remove(ready.unwrap_or(0));
+            let value = self.make_class(class)?;
+            self.globals.assign(0, self.global_slots[&class.name], value);
+        }
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::FunctionDeclaration(_) | StatementKind::EnumDeclaration(_) => {}
+                // Static fields are initialized when the class declaration is reached, like a global.
                 StatementKind::ClassDeclaration(class) => {
-                    let local_scope = self.interpret_class_body(class.body);
-                    self.global_scope.classes.push(Class {
-                        name: class.name,
-                        local_scope,
-                    });
+                    if let Value::Class(class) = self.globals.get(0, self.global_slots[&class.name]) {
+                        self.initialize_statics(&class)?;
+                    }
+                }
+                _ => {
+                    self.execute(statement)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Initializes the static fields of a class in declaration order.
+    pub(crate) fn initialize_statics(&mut self, class: &Rc<Class>) -> Result<(), RuntimeError> {
+        let previous = std::mem::replace(&mut self.environment, class.closure.clone());
+        let mut result = Ok(());
+        for field in &class.static_fields {
+            match self.initial_value(field) {
+                Ok(value) => {
+                    class.statics.borrow_mut().insert(field.name.clone(), value);
+                }
+                Err(error) => {
+                    result = Err(error);
+                    break;
                 }
-                _ => {}
             }
         }
+        self.environment = previous;
+        result
     }
 
-    pub fn interpret_class_body(&mut self, statements: Vec<Statement>) -> Scope {
-        let mut local_scope = Scope {
-            classes: vec![],
-            functions: vec![],
-            variables: vec![],
+    /// Evaluates the initializer of a field in the current environment.
+    fn initial_value(&mut self, field: &Field) -> Result<Value, RuntimeError> {
+        match &field.initializer {
+            Some(Initializer::Expression(expression)) => self.evaluate(expression),
+            Some(Initializer::Code(chunk)) => self.run_in_place(chunk),
+            None => Ok(default_value(&field.type_)),
+        }
+    }
+
+    /// Returns the current value of a global variable or function.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.global_slots.get(name).map(|slot| self.globals.get(0, *slot))
+    }
+
+    /// Sets a global of the module run last, returning `false` if it declares no such global.
+    pub fn set_global(&mut self, name: &str, value: Value) -> bool {
+        match self.global_slots.get(name) {
+            Some(slot) => {
+                self.globals.assign(0, *slot, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Calls `main`, passing `args` if it declares a parameter, and returns its exit code.
+    pub fn run_main(&mut self, args: Vec<String>) -> Result<i64, RuntimeError> {
+        let main = match self.global("main") {
+            Some(Value::Function(main)) => main,
+            _ => return Err(RuntimeError::new("No `main` function found".to_string(), 1, 1)),
         };
-        for statement in statements {
-            match statement.kind.clone() {
-                StatementKind::FunctionDeclaration(function) => {
-                    let ls = self.interpret_function_body(function.body);
-                    local_scope.functions.push(Function {
-                        name: function.name,
-                        parameters: function.parameters,
-                        local_scope: ls
-                    });
+        let arguments = if main.declaration.parameters.is_empty() {
+            vec![]
+        } else {
+            vec![Value::array(args.iter().map(|arg| Value::string(arg)).collect())]
+        };
+        match self.call_function(&main, arguments, 0, 0)? {
+            Value::Integer(code) => Ok(code),
+            Value::Null => Ok(0),
+            value => Err(RuntimeError::new(format!("`main` must return an int, got {}", value.type_name()), 1, 1)),
+        }
+    }
+
+    fn make_function(&self, declaration: &FunctionDeclaration) -> Value {
+        Value::Function(Rc::new(Function {
+            declaration: Rc::new(declaration.clone()),
+            closure: self.environment.clone(),
+            name: declaration.name.clone(),
+            module: self.module,
+            code: Rc::default(),
+        }))
+    }
+
+    fn make_class(&self, declaration: &ClassDeclaration) -> Result<Value, RuntimeError> {
+        let superclass = match &declaration.superclass {
+            Some(superclass) => {
+                let value = self.lookup(&superclass.name, superclass.binding, superclass.line, superclass.column)?;
+                Some(superclass_of(&declaration.name, value, superclass.line, superclass.column)?)
+            }
+            None => None,
+        };
+        let prototype = class_prototype(declaration, &mut |expression| Initializer::Expression(Rc::new(expression.clone())));
+        Ok(Value::Class(self.create_class(&prototype, superclass)))
+    }
+
+    /// Creates a class declared in the current environment.
+    pub(crate) fn create_class(&self, prototype: &ClassPrototype, superclass: Option<Rc<Class>>) -> Rc<Class> {
+        // Methods of a subclass close over an environment holding the superclass for `super`.
+        let method_closure = match &superclass {
+            Some(superclass) => {
+                let environment = Environment::new(Some(self.environment.clone()));
+                environment.define(Value::Class(superclass.clone()));
+                environment
+            }
+            None => self.environment.clone(),
+        };
+        let method = |method: &Prototype, closure: &Rc<Environment>| {
+            Rc::new(Function {
+                declaration: method.declaration.clone(),
+                closure: closure.clone(),
+                name: format!("{}.{}", prototype.name, method.declaration.name),
+                module: self.module,
+                code: method.code.clone(),
+            })
+        };
+
+        let mut field_slots = superclass.as_ref().map(|superclass| superclass.field_slots.clone()).unwrap_or_default();
+        for field in &prototype.fields {
+            // Slots stay dense even for a field declared twice, which only a damaged file has.
+            let slot = field_slots.len();
+            field_slots.entry(field.name.clone()).or_insert(slot);
+        }
+        let mut methods = superclass.as_ref().map(|superclass| superclass.methods.clone()).unwrap_or_default();
+        for prototype in &prototype.methods {
+            methods.insert(prototype.declaration.name.clone(), method(prototype, &method_closure));
+        }
+        let statics = prototype.static_fields.iter().map(|field| (field.name.clone(), default_value(&field.type_))).collect();
+        Rc::new(Class {
+            name: prototype.name.clone(),
+            superclass,
+            fields: prototype.fields.clone(),
+            field_slots,
+            methods,
+            constructor: prototype.constructor.as_ref().map(|constructor| method(constructor, &method_closure)),
+            calls_super: prototype.calls_super,
+            static_fields: prototype.static_fields.clone(),
+            statics: RefCell::new(statics),
+            static_methods: prototype.static_methods.iter().map(|static_method| (static_method.declaration.name.clone(), method(static_method, &self.environment))).collect(),
+            closure: self.environment.clone(),
+        })
+    }
+
+    fn make_enum(&self, declaration: &EnumDeclaration) -> Value {
+        Value::Enum(Rc::new(Enum {
+            name: declaration.name.clone(),
+            variants: declaration.variants.iter().map(|variant| (variant.name.clone(), variant.payload.len())).collect(),
+        }))
+    }
+
+    /// Constructs the variant `name` of an enum with the given payload.
+    fn construct_variant(&self, enum_: &Rc<Enum>, name: &str, payload: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        let tag = match enum_.variants.iter().position(|(variant, _)| variant == name) {
+            Some(tag) => tag,
+            None => return Err(RuntimeError::new(format!("`{}` has no variant `{}`", enum_.name, name), line, column)),
+        };
+        let arity = enum_.variants[tag].1;
+        if payload.len() != arity {
+            return Err(RuntimeError::new(
+                format!("Variant `{}.{}` takes {} value(s) but was given {}", enum_.name, name, arity, payload.len()),
+                line,
+                column,
+            ));
+        }
+        Ok(Value::Variant(Rc::new(Variant {
+            enum_: enum_.clone(),
+            tag,
+            payload,
+        })))
+    }
+
+    /// Calls anything callable: functions, and classes, which are called to instantiate them.
+    pub fn call_value(&mut self, callee: Value, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        match callee {
+            Value::Function(function) => self.call_function(&function, arguments, line, column),
+            Value::Native(native) => {
+                if arguments.len() != native.parameters.len() {
+                    return Err(RuntimeError::new(
+                        format!("Function `{}` expects {} argument(s), got {}", native.name, native.parameters.len(), arguments.len()),
+                        line,
+                        column,
+                    ));
                 }
-                _ => {}
+                if let Some(capability) = native.capability.filter(|capability| !self.allows(*capability)) {
+                    return Err(RuntimeError::new(format!("`{}` needs the {:?} capability, which is not granted", native.name, capability), line, column));
+                }
+                // Natives that can build results much larger than their arguments are charged up front.
+                if let Some(size) = &native.size {
+                    self.allocate(1, size(&arguments), line, column)?;
+                    return (native.function)(arguments).map_err(|message| RuntimeError::new(message, line, column));
+                }
+                let result = (native.function)(arguments).map_err(|message| RuntimeError::new(message, line, column))?;
+                self.allocated(result, line, column)
             }
+            Value::Class(class) => self.instantiate(&class, arguments, line, column),
+            Value::HostClass(class) => instantiate_host(&class, arguments, line, column),
+            callee => Err(RuntimeError::new(format!("{} is not callable", callee.type_name()), line, column)),
+        }
+    }
+
+    /// Instantiates a class or host class for `new`.
+    pub(crate) fn instantiate_value(&mut self, class: Value, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        match class {
+            Value::Class(class) => self.instantiate(&class, arguments, line, column),
+            Value::HostClass(class) => instantiate_host(&class, arguments, line, column),
+            value => Err(RuntimeError::new(format!("Cannot instantiate {}", value.type_name()), line, column)),
         }
-        local_scope
     }
 
-    pub fn interpret_function_body(&mut self, statements: Vec<Statement>) -> Scope {
-        let mut local_scope = Scope {
-            classes: vec![],
-            functions: vec![],
-            variables: vec![],
+    /// Allocates an object, runs its field initializers in declaration order and then its constructor.
+    pub fn instantiate(&mut self, class: &Rc<Class>, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        let object = Rc::new(Object {
+            class: class.clone(),
+            fields: RefCell::new(vec![Value::Null; class.field_slots.len()]),
+        });
+        self.allocate(1, class.field_slots.len() * size_of::<Value>(), line, column)?;
+        // Field initializers can instantiate the class again, so they count as a frame like a call does.
+        self.enter(line, column)?;
+        let initialized = self.initialize_fields(class, &object);
+        self.depth -= 1;
+        initialized?;
+        self.construct(class, &object, arguments, line, column)?;
+        Ok(Value::Object(object))
+    }
+
+    /// Initializes the fields of `class` and its superclasses, base class first.
+    fn initialize_fields(&mut self, class: &Rc<Class>, object: &Rc<Object>) -> Result<(), RuntimeError> {
+        if let Some(superclass) = &class.superclass {
+            self.initialize_fields(superclass, object)?;
+        }
+        let previous = std::mem::replace(&mut self.environment, class.closure.clone());
+        let mut result = Ok(());
+        for field in &class.fields {
+            match self.initial_value(field) {
+                Ok(value) => object.fields.borrow_mut()[class.field_slots[&field.name]] = value,
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+        self.environment = previous;
+        result
+    }
+
+    /// Runs the constructor `class` declares, or the one it inherits if it declares none.
+    ///
+    /// A constructor that does not start with `super(...)` implicitly calls the
+    /// superclass constructor without arguments first.
+    fn construct(&mut self, class: &Rc<Class>, object: &Rc<Object>, arguments: Vec<Value>, line: usize, column: usize) -> Result<(), RuntimeError> {
+        match (&class.constructor, &class.superclass) {
+            (Some(constructor), superclass) => {
+                if let Some(superclass) = superclass {
+                    if !class.calls_super {
+                        self.construct(superclass, object, vec![], line, column)?;
+                    }
+                }
+                self.call_bound(constructor, object, arguments, line, column)?;
+                Ok(())
+            }
+            (None, Some(superclass)) => self.construct(superclass, object, arguments, line, column),
+            (None, None) if !arguments.is_empty() => Err(RuntimeError::new(
+                format!("Class `{}` has no constructor but was given {} argument(s)", class.name, arguments.len()),
+                line,
+                column,
+            )),
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Binds a method to an object by closing over an environment that holds `this`.
+    fn bind(&self, method: &Function, object: &Rc<Object>) -> Function {
+        Function {
+            declaration: method.declaration.clone(),
+            closure: with_this(method, object),
+            name: method.name.clone(),
+            module: method.module,
+            code: method.code.clone(),
+        }
+    }
+
+    /// Calls a method on an object, like calling it bound to the object but without building
+    /// the bound function.
+    fn call_bound(&mut self, method: &Function, object: &Rc<Object>, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        self.call_in(method, with_this(method, object), arguments, line, column)
+    }
+
+    pub fn call_function(&mut self, function: &Function, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        self.call_in(function, function.closure.clone(), arguments, line, column)
+    }
+
+    /// Counts a frame towards the call depth, which `depth` is taken back down from when it ends.
+    fn enter(&mut self, line: usize, column: usize) -> Result<(), RuntimeError> {
+        if let Some(call_depth) = self.limits.call_depth.filter(|call_depth| self.depth >= *call_depth) {
+            return Err(limit_exceeded(Limit::CallDepth, format!("Call depth limit of {} exceeded", call_depth), line, column));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Calls `function` with `closure` as the environment its body runs in.
+    fn call_in(&mut self, function: &Function, closure: Rc<Environment>, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        let declaration = &function.declaration;
+        if arguments.len() != declaration.parameters.len() {
+            return Err(RuntimeError::new(
+                format!("Function `{}` expects {} argument(s), got {}", declaration.name, declaration.parameters.len(), arguments.len()),
+                line,
+                column,
+            ));
+        }
+        self.enter(line, column)?;
+        let caller = std::mem::replace(&mut self.module, function.module);
+        let result = match self.backend {
+            Backend::Tree if !self.precompiled => {
+                let environment = Environment::new(Some(closure));
+                for value in arguments {
+                    environment.define(value);
+                }
+                self.execute_in(&declaration.body, environment)
+            }
+            _ => self.run_compiled(function, closure, arguments),
         };
+        self.depth -= 1;
+        self.module = caller;
+        match result {
+            Ok(Flow::Return(value)) => Ok(value),
+            Ok(_) => Ok(Value::Null),
+            Err(mut error) => {
+                // The innermost call an error leaves is the one it was raised in.
+                error.file.get_or_insert(function.module);
+                error.trace.push(Frame {
+                    function: function.name.clone(),
+                    file: caller,
+                    line,
+                    column,
+                });
+                Err(error)
+            }
+        }
+    }
+
+    /// Executes statements in `environment`, restoring the current environment afterwards.
+    fn execute_in(&mut self, statements: &[Statement], environment: Rc<Environment>) -> Result<Flow, RuntimeError> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let mut flow = Ok(Flow::Normal);
         for statement in statements {
-            match statement.kind.clone() {
-                StatementKind::VarDeclaration(variable) => {
-                    local_scope.variables.push(Variable {
-                        name: variable.name,
-                        _type: variable.type_,
-                        value: variable.initializer.unwrap(),
-                    });
+            flow = self.execute(statement);
+            if !matches!(flow, Ok(Flow::Normal)) {
+                break;
+            }
+        }
+        self.environment = previous;
+        flow
+    }
+
+    pub fn execute_block(&mut self, statements: &[Statement]) -> Result<Flow, RuntimeError> {
+        let environment = Environment::new(Some(self.environment.clone()));
+        self.execute_in(statements, environment)
+    }
+
+    /// Executes the branch of an `if` or the body of a `while`, which always gets its own scope.
+    fn execute_branch(&mut self, statement: &Statement) -> Result<Flow, RuntimeError> {
+        match &statement.kind {
+            StatementKind::Block(block) => self.execute_block(&block.statements),
+            _ => self.execute_block(std::slice::from_ref(statement)),
+        }
+    }
+
+    pub fn execute(&mut self, statement: &Statement) -> Result<Flow, RuntimeError> {
+        self.step(statement.line, statement.column)?;
+        match &statement.kind {
+            StatementKind::Expression(expression) => {
+                self.evaluate(expression)?;
+            }
+            StatementKind::VarDeclaration(variable) => {
+                self.declare_variable(variable)?;
+            }
+            StatementKind::ConstantDeclaration(constant) => {
+                if let StatementKind::VarDeclaration(variable) = &constant.statement.kind {
+                    self.declare_variable(variable)?;
                 }
-                _ => {}
             }
+            StatementKind::FunctionDeclaration(function) => {
+                let value = self.make_function(function);
+                self.define(&function.name, value);
+            }
+            StatementKind::ClassDeclaration(class) => {
+                let value = self.make_class(class)?;
+                self.define(&class.name, value);
+            }
+            StatementKind::EnumDeclaration(enum_) => {
+                let value = self.make_enum(enum_);
+                self.define(&enum_.name, value);
+            }
+            // Interfaces only exist for the checker; calls through them dispatch on the object's class.
+            StatementKind::ConstructorDeclaration(_)
+            | StatementKind::InterfaceDeclaration(_)
+            | StatementKind::Import(_)
+            | StatementKind::Export(_) => {}
+            StatementKind::Return(return_statement) => {
+                let value = match &return_statement.expression {
+                    Some(expression) => self.evaluate(expression)?,
+                    None => Value::Null,
+                };
+                return Ok(Flow::Return(value));
+            }
+            StatementKind::Block(block) => {
+                return self.execute_block(&block.statements);
+            }
+            StatementKind::If(if_statement) => {
+                if self.evaluate_condition(&if_statement.condition)? {
+                    return self.execute_branch(&if_statement.then_branch);
+                } else if let Some(else_branch) = &if_statement.else_branch {
+                    return self.execute_branch(else_branch);
+                }
+            }
+            StatementKind::While(while_statement) => {
+                while self.evaluate_condition(&while_statement.condition)? {
+                    match self.execute_branch(&while_statement.body)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+            }
+            StatementKind::For(for_statement) => {
+                let body = match &for_statement.body.kind {
+                    StatementKind::Block(block) => &block.statements[..],
+                    _ => std::slice::from_ref(&*for_statement.body),
+                };
+                // The loop runs over the elements as they were when it started, even if the body changes them.
+                let iterable = &for_statement.iterable;
+                let elements = elements(self.evaluate(iterable)?, iterable.line, iterable.column)?;
+                for element in elements {
+                    let environment = Environment::new(Some(self.environment.clone()));
+                    environment.define(element);
+                    match self.execute_in(body, environment)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+            }
+            StatementKind::Throw(throw) => {
+                let value = self.evaluate(&throw.expression)?;
+                return Err(thrown(value, statement.line, statement.column));
+            }
+            StatementKind::Try(try_statement) => {
+                let mut result = self.execute_block(&try_statement.body);
+                if let Err(error) = result {
+                    result = self.catch(&try_statement.catches, error);
+                }
+                if let Some(finally) = &try_statement.finally {
+                    let flow = self.execute_block(finally);
+                    // Nothing a `finally` block does can keep the run going past a limit.
+                    if result.as_ref().is_err_and(|error| error.limit.is_some()) {
+                        return result;
+                    }
+                    // Otherwise a `finally` block that returns, breaks or throws replaces the outcome of the rest.
+                    match flow? {
+                        Flow::Normal => {}
+                        flow => return Ok(flow),
+                    }
+                }
+                return result;
+            }
+            StatementKind::Break => return Ok(Flow::Break),
+            StatementKind::Continue => return Ok(Flow::Continue),
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Runs the first clause that catches `error`, or passes the error on if none does.
+    ///
+    /// Errors from exceeding a limit are never caught, so a program cannot keep running past one.
+    fn catch(&mut self, catches: &[Catch], error: RuntimeError) -> Result<Flow, RuntimeError> {
+        if error.limit.is_some() {
+            return Err(error);
+        }
+        for catch in catches {
+            let (line, column) = (catch.class.line, catch.class.column);
+            let class = self.lookup(&catch.class.name, catch.class.binding, line, column)?;
+            let Some(caught) = self.caught(class, &error, line, column)? else {
+                continue;
+            };
+            let environment = Environment::new(Some(self.environment.clone()));
+            environment.define(caught);
+            return self.execute_in(&catch.body, environment);
+        }
+        Err(error)
+    }
+
+    /// The value a clause catching `class` binds for `error`, or `None` if it does not catch it.
+    pub(crate) fn caught(&mut self, class: Value, error: &RuntimeError, line: usize, column: usize) -> Result<Option<Value>, RuntimeError> {
+        let class = match class {
+            Value::Class(class) => class,
+            value => return Err(RuntimeError::new(format!("Cannot catch {}", value.type_name()), line, column)),
+        };
+        match &error.thrown {
+            Some(Value::Object(object)) if object.class.is_subclass_of(&class) => Ok(Some(Value::Object(object.clone()))),
+            // Errors raised by the interpreter itself are caught as a plain `Error`, and by nothing more specific.
+            None if self.error_class.as_ref().is_some_and(|error_class| Rc::ptr_eq(error_class, &class)) => {
+                self.instantiate(&class, vec![Value::string(&error.message)], line, column).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Defines a variable in the current environment, or fills in its slot at the top level.
+    fn define(&mut self, name: &str, value: Value) {
+        if Rc::ptr_eq(&self.environment, &self.globals) {
+            self.globals.assign(0, self.global_slots[name], value);
+        } else {
+            self.environment.define(value);
+        }
+    }
+
+    fn declare_variable(&mut self, variable: &VarDeclaration) -> Result<(), RuntimeError> {
+        let value = match &variable.initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => Value::Null,
+        };
+        self.define(&variable.name, value);
+        Ok(())
+    }
+
+    fn evaluate_condition(&mut self, condition: &Expression) -> Result<bool, RuntimeError> {
+        match self.evaluate(condition)? {
+            Value::Bool(boolean) => Ok(boolean),
+            value => Err(RuntimeError::new(format!("Condition must be a bool, got {}", value.type_name()), condition.line, condition.column)),
+        }
+    }
+
+    fn lookup(&self, name: &str, binding: Option<Binding>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        match binding {
+            Some(Binding::Global(slot)) => Ok(self.globals.get(0, slot)),
+            Some(Binding::Local { depth, slot }) => Ok(self.environment.get(depth, slot)),
+            Some(Binding::Native(index)) => Ok(Value::Native(self.natives.get(index).clone())),
+            Some(Binding::NativeClass(index)) => Ok(Value::HostClass(self.natives.class(index).clone())),
+            Some(Binding::NativeConstant(index)) => Ok(self.natives.constant(index).value.clone()),
+            None => Err(RuntimeError::new(format!("Unresolved variable `{}`", name), line, column)),
+        }
+    }
+
+    pub fn evaluate(&mut self, expression: &Expression) -> Result<Value, RuntimeError> {
+        self.step(expression.line, expression.column)?;
+        match &expression.kind {
+            ExpressionKind::Literal(literal) => self.allocated(literal_value(literal), expression.line, expression.column),
+            ExpressionKind::Parenthesized(parenthesized) => self.evaluate(&parenthesized.expression),
+            ExpressionKind::Variable(variable) => self.lookup(&variable.name, variable.binding, variable.line, variable.column),
+            ExpressionKind::Binary(binary) => self.evaluate_binary(binary, expression.line, expression.column),
+            ExpressionKind::Unary(unary) => {
+                let operand = self.evaluate(&unary.operand)?;
+                apply_unary(&unary.operator, operand, expression.line, expression.column)
+            }
+            ExpressionKind::Call(call) => {
+                let callee = self.evaluate(&call.callee)?;
+                let arguments = self.evaluate_arguments(&call.arguments)?;
+                self.call_value(callee, arguments, expression.line, expression.column)
+            }
+            ExpressionKind::MethodCall(method_call) => {
+                let receiver = self.evaluate(&method_call.receiver)?;
+                let arguments = self.evaluate_arguments(&method_call.arguments)?;
+                self.call_method(receiver, &method_call.name, arguments, expression.line, expression.column)
+            }
+            ExpressionKind::Super(super_call) => {
+                let arguments = self.evaluate_arguments(&super_call.arguments)?;
+                self.call_super(super_call, arguments, expression.line, expression.column)
+            }
+            ExpressionKind::New(new) => {
+                let class = self.evaluate(&new.class)?;
+                let arguments = self.evaluate_arguments(&new.arguments)?;
+                self.instantiate_value(class, arguments, expression.line, expression.column)
+            }
+            ExpressionKind::Index(index) => {
+                let callee = self.evaluate(&index.callee)?;
+                let key = self.evaluate(&index.index)?;
+                self.evaluate_index(callee, key, expression.line, expression.column)
+            }
+            ExpressionKind::Slice(slice) => {
+                let callee = self.evaluate(&slice.callee)?;
+                let start = self.evaluate(&slice.start)?;
+                let end = self.evaluate(&slice.end)?;
+                self.evaluate_slice(callee, start, end, expression.line, expression.column)
+            }
+            ExpressionKind::Member(member) => {
+                let callee = self.evaluate(&member.callee)?;
+                self.get_member(callee, &member.name, expression.line, expression.column)
+            }
+            // Lambdas capture the environment they are evaluated in, like nested functions.
+            ExpressionKind::Lambda(lambda) => Ok(self.make_function(lambda)),
+            ExpressionKind::Cast(cast) => {
+                let value = self.evaluate(&cast.expression)?;
+                convert(value, &cast.type_, expression.line, expression.column)
+            }
+            ExpressionKind::Match(match_expression) => {
+                let subject = self.evaluate(&match_expression.subject)?;
+                for arm in &match_expression.arms {
+                    // The enums a pattern names are resolved from inside its arm, like its guard and body.
+                    let environment = Environment::new(Some(self.environment.clone()));
+                    let previous = std::mem::replace(&mut self.environment, environment.clone());
+                    let result = match self.match_pattern(&arm.pattern, &subject, &environment) {
+                        Ok(false) => Ok(None),
+                        Ok(true) => match &arm.guard {
+                            Some(guard) => match self.evaluate_condition(guard) {
+                                Ok(true) => self.evaluate(&arm.body).map(Some),
+                                Ok(false) => Ok(None),
+                                Err(error) => Err(error),
+                            },
+                            None => self.evaluate(&arm.body).map(Some),
+                        },
+                        Err(error) => Err(error),
+                    };
+                    self.environment = previous;
+                    if let Some(value) = result? {
+                        return Ok(value);
+                    }
+                }
+                Err(RuntimeError::new(format!("No match arm matches {}", subject), expression.line, expression.column))
+            }
+            ExpressionKind::Array(array) => {
+                let mut elements = Vec::with_capacity(array.elements.len());
+                for element in &array.elements {
+                    elements.push(self.evaluate(element)?);
+                }
+                self.allocated(Value::array(elements), expression.line, expression.column)
+            }
+            ExpressionKind::Map(map) => {
+                let mut entries = BTreeMap::new();
+                for (key, value) in &map.entries {
+                    let key_value = self.evaluate(key)?;
+                    let key_value = match key_value.to_map_key() {
+                        Some(key_value) => key_value,
+                        None => return Err(RuntimeError::new(format!("{} cannot be used as a map key", key_value.type_name()), key.line, key.column)),
+                    };
+                    let value = self.evaluate(value)?;
+                    entries.insert(key_value, value);
+                }
+                self.allocated(Value::map(entries), expression.line, expression.column)
+            }
+            ExpressionKind::Assign(assign) => {
+                let value = self.evaluate(&assign.value)?;
+                self.assign(&assign.target, value.clone())?;
+                Ok(value)
+            }
+        }
+    }
+
+    /// Tests a value against a pattern, defining the values it binds in `environment` in order.
+    fn match_pattern(&mut self, pattern: &Pattern, value: &Value, environment: &Rc<Environment>) -> Result<bool, RuntimeError> {
+        let mut bound = Vec::new();
+        let matched = match_value(pattern, value, &mut |enum_| self.lookup(&enum_.name, enum_.binding, enum_.line, enum_.column), &mut bound)?;
+        for value in bound {
+            environment.define(value);
+        }
+        Ok(matched)
+    }
+
+    fn assign(&mut self, target: &Expression, value: Value) -> Result<(), RuntimeError> {
+        match &target.kind {
+            ExpressionKind::Variable(variable) => match variable.binding {
+                Some(Binding::Global(slot)) => {
+                    self.globals.assign(0, slot, value);
+                    Ok(())
+                }
+                Some(Binding::Local { depth, slot }) => {
+                    self.environment.assign(depth, slot, value);
+                    Ok(())
+                }
+                Some(Binding::Native(_) | Binding::NativeClass(_) | Binding::NativeConstant(_)) => {
+                    Err(RuntimeError::new(format!("Cannot assign to native `{}`", variable.name), target.line, target.column))
+                }
+                None => Err(RuntimeError::new(format!("Unresolved variable `{}`", variable.name), target.line, target.column)),
+            },
+            ExpressionKind::Index(index) => {
+                let callee = self.evaluate(&index.callee)?;
+                let key = self.evaluate(&index.index)?;
+                self.assign_index(callee, key, value, target.line, target.column)
+            }
+            ExpressionKind::Member(member) => {
+                let callee = self.evaluate(&member.callee)?;
+                self.assign_member(callee, &member.name, value, target.line, target.column)
+            }
+            _ => Err(RuntimeError::new("Invalid assignment target".to_string(), target.line, target.column)),
+        }
+    }
+
+    /// Assigns an element of an array or an entry of a map.
+    pub(crate) fn assign_index(&mut self, callee: Value, key: Value, value: Value, line: usize, column: usize) -> Result<(), RuntimeError> {
+        match (callee, key) {
+            (Value::Array(elements), Value::Integer(i)) => {
+                let mut elements = elements.borrow_mut();
+                let length = elements.len();
+                match usize::try_from(i).ok().filter(|i| *i < length) {
+                    Some(i) => {
+                        elements[i] = value;
+                        Ok(())
+                    }
+                    None => Err(RuntimeError::new(format!("Index {} out of bounds for array of length {}", i, length), line, column)),
+                }
+            }
+            (Value::Map(entries), key) => match key.to_map_key() {
+                Some(key) => {
+                    if !entries.borrow().contains_key(&key) {
+                        self.allocate(0, size_of::<MapKey>() + size_of::<Value>(), line, column)?;
+                    }
+                    entries.borrow_mut().insert(key, value);
+                    Ok(())
+                }
+                None => Err(RuntimeError::new(format!("{} cannot be used as a map key", key.type_name()), line, column)),
+            },
+            (callee, key) => Err(RuntimeError::new(
+                format!("Cannot assign to {} indexed by {}", callee.type_name(), key.type_name()),
+                line,
+                column,
+            )),
+        }
+    }
+
+    /// Assigns a field of an object, a static field of a class or a property of a host object.
+    pub(crate) fn assign_member(&mut self, callee: Value, name: &str, value: Value, line: usize, column: usize) -> Result<(), RuntimeError> {
+        match callee {
+            Value::Object(object) => {
+                if object.set(name, value) {
+                    Ok(())
+                } else {
+                    Err(RuntimeError::new(format!("`{}` has no field `{}`", object.class.name, name), line, column))
+                }
+            }
+            Value::Class(class) => {
+                if class.set_static(name, value) {
+                    Ok(())
+                } else {
+                    Err(RuntimeError::new(format!("`{}` has no static field `{}`", class.name, name), line, column))
+                }
+            }
+            Value::HostObject(object) => {
+                let setter = match object.class.property(name) {
+                    Some(property) => property.setter.as_ref(),
+                    None => None,
+                };
+                match setter {
+                    Some(setter) => {
+                        let mut data = borrow_host(&object).map_err(|message| RuntimeError::new(message, line, column))?;
+                        setter(data.as_mut(), value).map_err(|message| RuntimeError::new(message, line, column))
+                    }
+                    None => Err(RuntimeError::new(
+                        format!("`{}` has no assignable property `{}`", object.class.name, name),
+                        line,
+                        column,
+                    )),
+                }
+            }
+            callee => Err(RuntimeError::new(format!("Cannot assign to member `{}` of {}", name, callee.type_name()), line, column)),
+        }
+    }
+
+    fn evaluate_arguments(&mut self, arguments: &[Expression]) -> Result<Vec<Value>, RuntimeError> {
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+        Ok(values)
+    }
+
+    /// Reads a field, or a method bound to its object so it can be called later.
+    pub(crate) fn get_member(&mut self, callee: Value, name: &str, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        match &callee {
+            Value::Object(object) => {
+                if let Some(value) = object.get(name) {
+                    return Ok(value);
+                }
+                if let Some(method) = object.class.methods.get(name) {
+                    return Ok(Value::Function(Rc::new(self.bind(method, object))));
+                }
+                Err(RuntimeError::new(format!("`{}` has no member `{}`", object.class.name, name), line, column))
+            }
+            // Variants without a payload are referred to without parentheses.
+            Value::Enum(enum_) => self.construct_variant(enum_, name, vec![], line, column),
+            Value::Class(class) => match class.get_static(name) {
+                Some(value) => Ok(value),
+                None => Err(RuntimeError::new(format!("`{}` has no static member `{}`", class.name, name), line, column)),
+            },
+            Value::HostObject(object) => {
+                if let Some(property) = object.class.property(name) {
+                    let data = object.data.try_borrow().map_err(|_| RuntimeError::new(in_use(object), line, column))?;
+                    return (property.getter)(data.as_ref()).map_err(|message| RuntimeError::new(message, line, column));
+                }
+                if object.class.method(name).is_some() {
+                    return Ok(bind_host_method(object, name));
+                }
+                Err(RuntimeError::new(format!("`{}` has no member `{}`", object.class.name, name), line, column))
+            }
+            _ => Err(RuntimeError::new(format!("{} has no member `{}`", callee.type_name(), name), line, column)),
+        }
+    }
+
+    /// Calls the superclass constructor or a superclass method on `this`.
+    fn call_super(&mut self, super_call: &SuperCall, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        // The environment holding `this` is always nested directly inside the one holding the superclass.
+        let (superclass, object) = match super_call.binding {
+            Some(Binding::Local { depth, slot }) if depth > 0 => (self.environment.get(depth, slot), self.environment.get(depth - 1, 0)),
+            _ => return Err(RuntimeError::new("Unresolved `super`".to_string(), line, column)),
+        };
+        self.call_super_on(superclass, object, super_call.method.as_deref(), arguments, line, column)
+    }
+
+    /// Calls the superclass constructor, or the superclass method `method`, given the superclass and `this`.
+    pub(crate) fn call_super_on(
+        &mut self,
+        superclass: Value,
+        object: Value,
+        method: Option<&str>,
+        arguments: Vec<Value>,
+        line: usize,
+        column: usize,
+    ) -> Result<Value, RuntimeError> {
+        let (superclass, object) = match (superclass, object) {
+            (Value::Class(superclass), Value::Object(object)) => (superclass, object),
+            _ => return Err(RuntimeError::new("`super` used outside of a method".to_string(), line, column)),
+        };
+        match method {
+            None => {
+                self.construct(&superclass, &object, arguments, line, column)?;
+                Ok(Value::Null)
+            }
+            Some(name) => match superclass.methods.get(name) {
+                Some(method) => self.call_bound(method, &object, arguments, line, column),
+                None => Err(RuntimeError::new(format!("`{}` has no method `{}`", superclass.name, name), line, column)),
+            },
+        }
+    }
+
+    /// Calls a method on a receiver, dispatching on the class of the object it is called on.
+    pub fn call_method(&mut self, receiver: Value, name: &str, mut arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        match &receiver {
+            Value::Object(object) => {
+                if let Some(method) = object.class.methods.get(name) {
+                    return self.call_bound(method, object, arguments, line, column);
+                }
+            }
+            Value::Enum(enum_) => return self.construct_variant(enum_, name, arguments, line, column),
+            Value::HostObject(object) if object.class.method(name).is_some() => {
+                return call_host_method(object, name, arguments).map_err(|message| RuntimeError::new(message, line, column));
+            }
+            Value::Array(elements) => return self.call_array_method(elements, name, arguments, line, column),
+            Value::Map(entries) => return self.call_map_method(entries, name, arguments, line, column),
+            Value::String(_) => {
+                if let Some(index) = self.natives.index_of(&format!("{}.{}", STRING_METHODS, name)) {
+                    let method = Value::Native(self.natives.get(index).clone());
+                    arguments.insert(0, receiver);
+                    return self.call_value(method, arguments, line, column);
+                }
+            }
+            _ => {}
+        }
+        // Fields holding functions are called like methods.
+        let callee = self.get_member(receiver, name, line, column)?;
+        self.call_value(callee, arguments, line, column)
+    }
+
+    fn call_array_method(
+        &mut self,
+        elements: &Rc<RefCell<Vec<Value>>>,
+        name: &str,
+        arguments: Vec<Value>,
+        line: usize,
+        column: usize,
+    ) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+        let mut argument = || arguments.next().unwrap_or(Value::Null);
+        let out_of_bounds = |index: i64, length: usize| RuntimeError::new(format!("Index {} out of bounds for array of length {}", index, length), line, column);
+        match name {
+            "len" => Ok(Value::Integer(elements.borrow().len() as i64)),
+            "push" => {
+                self.allocate(0, size_of::<Value>(), line, column)?;
+                elements.borrow_mut().push(argument());
+                Ok(Value::Null)
+            }
+            "pop" => elements.borrow_mut().pop().ok_or_else(|| RuntimeError::new("Cannot pop from an empty array".to_string(), line, column)),
+            "insert" => {
+                let (index, element) = (integer(argument(), line, column)?, argument());
+                self.allocate(0, size_of::<Value>(), line, column)?;
+                let mut elements = elements.borrow_mut();
+                // Inserting at the length appends.
+                match usize::try_from(index).ok().filter(|i| *i <= elements.len()) {
+                    Some(i) => {
+                        elements.insert(i, element);
+                        Ok(Value::Null)
+                    }
+                    None => Err(out_of_bounds(index, elements.len())),
+                }
+            }
+            "remove" => {
+                let index = integer(argument(), line, column)?;
+                let mut elements = elements.borrow_mut();
+                match usize::try_from(index).ok().filter(|i| *i < elements.len()) {
+                    Some(i) => Ok(elements.remove(i)),
+                    None => Err(out_of_bounds(index, elements.len())),
+                }
+            }
+            "contains" => {
+                let element = argument();
+                Ok(Value::Bool(elements.borrow().contains(&element)))
+            }
+            "sort" => {
+                let mut elements = elements.borrow_mut();
+                if let Some(pair) = elements.windows(2).find(|pair| compare(&pair[0], &pair[1]).is_none()) {
+                    let message = format!("Cannot sort an array holding both {} and {}", pair[0].type_name(), pair[1].type_name());
+                    return Err(RuntimeError::new(message, line, column));
+                }
+                // Every pair of elements was just found to be comparable.
+                elements.sort_by(|a, b| compare(a, b).unwrap_or(Ordering::Equal));
+                Ok(Value::Null)
+            }
+            "reverse" => {
+                elements.borrow_mut().reverse();
+                Ok(Value::Null)
+            }
+            // Callbacks see the elements as they were when the method was called, even if they change them.
+            "map" => {
+                let function = argument();
+                let snapshot = elements.borrow().clone();
+                let mut mapped = Vec::with_capacity(snapshot.len());
+                for element in snapshot {
+                    mapped.push(self.call_value(function.clone(), vec![element], line, column)?);
+                }
+                self.allocated(Value::array(mapped), line, column)
+            }
+            "filter" => {
+                let function = argument();
+                let snapshot = elements.borrow().clone();
+                let mut kept = Vec::new();
+                for element in snapshot {
+                    if self.call_value(function.clone(), vec![element.clone()], line, column)? == Value::Bool(true) {
+                        kept.push(element);
+                    }
+                }
+                self.allocated(Value::array(kept), line, column)
+            }
+            "reduce" => {
+                let (mut accumulator, function) = (argument(), argument());
+                let snapshot = elements.borrow().clone();
+                for element in snapshot {
+                    accumulator = self.call_value(function.clone(), vec![accumulator, element], line, column)?;
+                }
+                Ok(accumulator)
+            }
+            _ => Err(RuntimeError::new(format!("array has no method `{}`", name), line, column)),
+        }
+    }
+
+    fn call_map_method(
+        &mut self,
+        entries: &Rc<RefCell<BTreeMap<MapKey, Value>>>,
+        name: &str,
+        arguments: Vec<Value>,
+        line: usize,
+        column: usize,
+    ) -> Result<Value, RuntimeError> {
+        let mut arguments = arguments.into_iter();
+        let key = arguments.next().unwrap_or(Value::Null);
+        let map_key = || key.to_map_key().ok_or_else(|| RuntimeError::new(format!("{} cannot be used as a map key", key.type_name()), line, column));
+        match name {
+            "len" => Ok(Value::Integer(entries.borrow().len() as i64)),
+            "keys" => {
+                let keys = Value::array(entries.borrow().keys().map(MapKey::to_value).collect());
+                self.allocated(keys, line, column)
+            }
+            "values" => {
+                let values = Value::array(entries.borrow().values().cloned().collect());
+                self.allocated(values, line, column)
+            }
+            "contains_key" => Ok(Value::Bool(entries.borrow().contains_key(&map_key()?))),
+            // The second argument is returned for keys the map does not contain.
+            "get" => {
+                let value = entries.borrow().get(&map_key()?).cloned();
+                Ok(value.unwrap_or_else(|| arguments.next().unwrap_or(Value::Null)))
+            }
+            "set" => {
+                let (map_key, value) = (map_key()?, arguments.next().unwrap_or(Value::Null));
+                if !entries.borrow().contains_key(&map_key) {
+                    self.allocate(0, size_of::<MapKey>() + size_of::<Value>(), line, column)?;
+                }
+                entries.borrow_mut().insert(map_key, value);
+                Ok(Value::Null)
+            }
+            "remove" => {
+                let removed = entries.borrow_mut().remove(&map_key()?);
+                removed.ok_or_else(|| RuntimeError::new(format!("Key {} not found in map", key), line, column))
+            }
+            _ => Err(RuntimeError::new(format!("map has no method `{}`", name), line, column)),
+        }
+    }
+
+    pub(crate) fn evaluate_index(&mut self, callee: Value, key: Value, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        match (callee, key) {
+            (Value::Array(elements), Value::Integer(i)) => {
+                let elements = elements.borrow();
+                match usize::try_from(i).ok().and_then(|i| elements.get(i)) {
+                    Some(element) => Ok(element.clone()),
+                    None => Err(RuntimeError::new(format!("Index {} out of bounds for array of length {}", i, elements.len()), line, column)),
+                }
+            }
+            (Value::String(string), Value::Integer(i)) => match usize::try_from(i).ok().and_then(|i| string.chars().nth(i)) {
+                Some(character) => Ok(Value::Char(character)),
+                None => Err(RuntimeError::new(format!("Index {} out of bounds for string of length {}", i, string.chars().count()), line, column)),
+            },
+            (Value::Map(entries), key) => match key.to_map_key() {
+                Some(map_key) => match entries.borrow().get(&map_key) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(RuntimeError::new(format!("Key {} not found in map", key), line, column)),
+                },
+                None => Err(RuntimeError::new(format!("{} cannot be used as a map key", key.type_name()), line, column)),
+            },
+            (callee, key) => Err(RuntimeError::new(format!("Cannot index {} with {}", callee.type_name(), key.type_name()), line, column)),
+        }
+    }
+
+    pub(crate) fn evaluate_slice(&mut self, callee: Value, start: Value, end: Value, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        let (start, end) = match (start, end) {
+            (Value::Integer(start), Value::Integer(end)) => (start, end),
+            (start, end) => return Err(RuntimeError::new(format!("Slice bounds must be ints, got {} and {}", start.type_name(), end.type_name()), line, column)),
+        };
+        let length = match &callee {
+            Value::Array(elements) => elements.borrow().len(),
+            Value::String(string) => string.chars().count(),
+            callee => return Err(RuntimeError::new(format!("Cannot slice {}", callee.type_name()), line, column)),
+        };
+        if start < 0 || end < start || end as usize > length {
+            return Err(RuntimeError::new(format!("Slice {}:{} out of bounds for length {}", start, end, length), line, column));
+        }
+        let (start, end) = (start as usize, end as usize);
+        let slice = match callee {
+            Value::Array(elements) => Value::array(elements.borrow()[start..end].to_vec()),
+            Value::String(string) => Value::string(&string.chars().skip(start).take(end - start).collect::<String>()),
+            _ => unreachable!(),
+        };
+        self.allocated(slice, line, column)
+    }
+
+    fn evaluate_binary(&mut self, binary: &Binary, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        // Logical operators short-circuit, so the right operand is only evaluated when needed.
+        if binary.operator == TokenKind::And || binary.operator == TokenKind::Or {
+            let left = self.evaluate_condition(&binary.left)?;
+            if left == (binary.operator == TokenKind::Or) {
+                return Ok(Value::Bool(left));
+            }
+            return Ok(Value::Bool(self.evaluate_condition(&binary.right)?));
         }
-        local_scope
+
+        let left = self.evaluate(&binary.left)?;
+        let right = self.evaluate(&binary.right)?;
+        self.apply_binary(&binary.operator, left, right, line, column)
     }
 
+    /// Applies an operator other than `&&` and `||`, which short-circuit, to its operands.
+    pub(crate) fn apply_binary(&mut self, operator: &TokenKind, left: Value, right: Value, line: usize, column: usize) -> Result<Value, RuntimeError> {
+        let error = |left: &Value, right: &Value| {
+            RuntimeError::new(
+                format!("Cannot apply {:?} to {} and {}", operator, left.type_name(), right.type_name()),
+                line,
+                column,
+            )
+        };
+        // Integer arithmetic is checked: results that do not fit in an int are errors rather than wrapping around.
+        let checked = |a: &i64, b: &i64, result: Option<i64>| match result {
+            Some(result) => Ok(Value::Integer(result)),
+            None => Err(RuntimeError::new(format!("Integer overflow in `{} {} {}`", a, operator_symbol(operator), b), line, column)),
+        };
+        match operator {
+            TokenKind::EqualEqual => Ok(Value::Bool(left == right)),
+            TokenKind::BangEqual => Ok(Value::Bool(left != right)),
+            TokenKind::Plus => match (&left, &right) {
+                (Value::Integer(a), Value::Integer(b)) => checked(a, b, a.checked_add(*b)),
+                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+                (Value::String(a), Value::String(b)) => {
+                    self.allocate(1, a.len() + b.len(), line, column)?;
+                    Ok(Value::string(&format!("{}{}", a, b)))
+                }
+                _ => Err(error(&left, &right)),
+            },
+            TokenKind::Minus => match (&left, &right) {
+                (Value::Integer(a), Value::Integer(b)) => checked(a, b, a.checked_sub(*b)),
+                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a - b)),
+                _ => Err(error(&left, &right)),
+            },
+            TokenKind::Star => match (&left, &right) {
+                (Value::Integer(a), Value::Integer(b)) => checked(a, b, a.checked_mul(*b)),
+                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a * b)),
+                _ => Err(error(&left, &right)),
+            },
+            TokenKind::Slash => match (&left, &right) {
+                (Value::Integer(_), Value::Integer(0)) => Err(RuntimeError::new("Division by zero".to_string(), line, column)),
+                (Value::Integer(a), Value::Integer(b)) => checked(a, b, a.checked_div(*b)),
+                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a / b)),
+                _ => Err(error(&left, &right)),
+            },
+            TokenKind::Percent => match (&left, &right) {
+                (Value::Integer(_), Value::Integer(0)) => Err(RuntimeError::new("Division by zero".to_string(), line, column)),
+                // The remainder of dividing by -1 is always 0, even for the one division that overflows.
+                (Value::Integer(a), Value::Integer(b)) => Ok(Value::Integer(a.wrapping_rem(*b))),
+                (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a % b)),
+                _ => Err(error(&left, &right)),
+            },
+            TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual => {
+                let ordering = match (&left, &right) {
+                    (Value::Integer(a), Value::Integer(b)) => a.partial_cmp(b),
+                    (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+                    (Value::Char(a), Value::Char(b)) => a.partial_cmp(b),
+                    (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+                    _ => return Err(error(&left, &right)),
+                };
+                // Comparisons involving NaN are false, as in IEEE 754.
+                let result = match ordering {
+                    Some(ordering) => match operator {
+                        TokenKind::Less => ordering.is_lt(),
+                        TokenKind::LessEqual => ordering.is_le(),
+                        TokenKind::Greater => ordering.is_gt(),
+                        _ => ordering.is_ge(),
+                    },
+                    None => false,
+                };
+                Ok(Value::Bool(result))
+            }
+            _ => Err(error(&left, &right)),
+        }
+    }
+}
+
+/// The class a class declaration named `name` extends, which must be a class declared in Wally.
+pub(crate) fn superclass_of(name: &str, value: Value, line: usize, column: usize) -> Result<Rc<Class>, RuntimeError> {
+    match value {
+        Value::Class(class) => Ok(class),
+        value => Err(RuntimeError::new(format!("Class `{}` cannot extend {}", name, value.type_name()), line, column)),
+    }
+}
+
+pub(crate) fn literal_value(literal: &Literal) -> Value {
+    match &literal.kind {
+        LiteralKind::String(string) => Value::string(string),
+        LiteralKind::Char(character) => Value::Char(*character),
+        LiteralKind::Integer(integer) => Value::Integer(*integer),
+        LiteralKind::Float(float) => Value::Float(*float),
+        LiteralKind::Bool(boolean) => Value::Bool(*boolean),
+        LiteralKind::Null => Value::Null,
+    }
+}
+
+/// Tests a value against a pattern, collecting the values it binds in order. The enums variant
+/// patterns name are looked up with `enum_of`.
+pub(crate) fn match_value(
+    pattern: &Pattern,
+    value: &Value,
+    enum_of: &mut dyn FnMut(&Variable) -> Result<Value, RuntimeError>,
+    bound: &mut Vec<Value>,
+) -> Result<bool, RuntimeError> {
+    match &pattern.kind {
+        PatternKind::Wildcard => Ok(true),
+        PatternKind::Binding(_) => {
+            bound.push(value.clone());
+            Ok(true)
+        }
+        PatternKind::Literal(literal) => Ok(*value == literal_value(literal)),
+        PatternKind::Range(range) => {
+            let ordering = |a: &Value, b: &Value| match (a, b) {
+                (Value::Integer(a), Value::Integer(b)) => a.partial_cmp(b),
+                (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+                (Value::Char(a), Value::Char(b)) => a.partial_cmp(b),
+                _ => None,
+            };
+            let above_start = ordering(value, &literal_value(&range.start)).is_some_and(|ordering| ordering.is_ge());
+            let below_end = ordering(value, &literal_value(&range.end)).is_some_and(|ordering| ordering.is_lt() || (range.inclusive && ordering.is_eq()));
+            Ok(above_start && below_end)
+        }
+        PatternKind::Variant(pattern) => {
+            let enum_ = match enum_of(&pattern.enum_)? {
+                Value::Enum(enum_) => enum_,
+                other => {
+                    return Err(RuntimeError::new(
+                        format!("Expected an enum in pattern, found {}", other.type_name()),
+                        pattern.enum_.line,
+                        pattern.enum_.column,
+                    ))
+                }
+            };
+            let variant = match value {
+                Value::Variant(variant) if Rc::ptr_eq(&variant.enum_, &enum_) && variant.name() == pattern.variant => variant,
+                _ => return Ok(false),
+            };
+            for (field, value) in pattern.fields.iter().zip(&variant.payload) {
+                if !match_value(field, value, enum_of, bound)? {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+    }
+}
+
+/// The int a builtin method was passed for a parameter declared as `int`.
+fn integer(value: Value, line: usize, column: usize) -> Result<i64, RuntimeError> {
+    match value {
+        Value::Integer(integer) => Ok(integer),
+        value => Err(RuntimeError::new(format!("Expected an int, found {}", value.type_name()), line, column)),
+    }
+}
+
+/// Orders two elements of an array being sorted, or `None` unless both are ints, floats, chars
+/// or strings. Floats are ordered totally, with NaN after infinity.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Float(a), Value::Float(b)) => Some(a.total_cmp(b)),
+        (Value::Char(a), Value::Char(b)) => Some(a.cmp(b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+/// Converts between ints and floats. Floats are truncated toward zero, and those
+/// no int can represent are an error rather than being clamped.
+pub(crate) fn convert(value: Value, type_: &Type, line: usize, column: usize) -> Result<Value, RuntimeError> {
+    match (value, &type_.kind) {
+        (Value::Integer(integer), TypeKind::Float) => Ok(Value::Float(integer as f64)),
+        (Value::Float(float), TypeKind::Integer) => {
+            let truncated = float.trunc();
+            // i64::MAX is not a float, so the upper bound is the power of two above it.
+            if truncated.is_nan() || truncated < i64::MIN as f64 || truncated >= 9_223_372_036_854_775_808.0 {
+                return Err(RuntimeError::new(format!("{:?} cannot be converted to int", float), line, column));
+            }
+            Ok(Value::Integer(truncated as i64))
+        }
+        (value @ Value::Integer(_), TypeKind::Integer) | (value @ Value::Float(_), TypeKind::Float) => Ok(value),
+        // The checker only allows conversions between numbers.
+        (value, _) => Err(RuntimeError::new(format!("Cannot convert {} with `as`", value.type_name()), line, column)),
+    }
+}
+
+/// Creates an instance of a host class by running its Rust constructor.
+fn instantiate_host(class: &Rc<NativeClass>, arguments: Vec<Value>, line: usize, column: usize) -> Result<Value, RuntimeError> {
+    let constructor = match &class.constructor {
+        Some(constructor) => constructor,
+        None => return Err(RuntimeError::new(format!("`{}` can only be created by the host", class.name), line, column)),
+    };
+    if arguments.len() != constructor.parameters.len() {
+        return Err(RuntimeError::new(
+            format!("Constructor of `{}` expects {} argument(s), got {}", class.name, constructor.parameters.len(), arguments.len()),
+            line,
+            column,
+        ));
+    }
+    let data = (constructor.function)(arguments).map_err(|message| RuntimeError::new(message, line, column))?;
+    Ok(Value::HostObject(Rc::new(HostObject {
+        class: class.clone(),
+        data: RefCell::new(data),
+    })))
+}
+
+fn call_host_method(object: &Rc<HostObject>, name: &str, arguments: Vec<Value>) -> Result<Value, String> {
+    let method = match object.class.method(name) {
+        Some(method) => method,
+        None => return Err(format!("`{}` has no method `{}`", object.class.name, name)),
+    };
+    if arguments.len() != method.parameters.len() {
+        return Err(format!("Method `{}.{}` expects {} argument(s), got {}", object.class.name, name, method.parameters.len(), arguments.len()));
+    }
+    let mut data = borrow_host(object)?;
+    (method.function)(data.as_mut(), arguments)
+}
+
+/// A host method bound to its object, as a native function that can be called later.
+fn bind_host_method(object: &Rc<HostObject>, name: &str) -> Value {
+    let method = object.class.method(name).expect("bound method exists");
+    let (object, method_name) = (object.clone(), name.to_string());
+    Value::Native(Rc::new(NativeFunction {
+        name: format!("{}.{}", object.class.name, name),
+        parameters: method.parameters.clone(),
+        return_type: method.return_type.clone(),
+        // Failures are raised where the bound method is called, like those of any other native.
+        function: Box::new(move |arguments| call_host_method(&object, &method_name, arguments)),
+        capability: None,
+        size: None,
+    }))
+}
+
+/// Borrows the Rust value of a host object for a method or setter to change.
+fn borrow_host(object: &HostObject) -> Result<RefMut<'_, Box<dyn Any>>, String> {
+    object.data.try_borrow_mut().map_err(|_| in_use(object))
+}
+
+fn in_use(object: &HostObject) -> String {
+    format!("`{}` object is already in use by one of its methods", object.class.name)
+}
+
+pub(crate) fn apply_unary(operator: &TokenKind, operand: Value, line: usize, column: usize) -> Result<Value, RuntimeError> {
+    match (operator, operand) {
+        (TokenKind::Minus, Value::Integer(integer)) => match integer.checked_neg() {
+            Some(negated) => Ok(Value::Integer(negated)),
+            None => Err(RuntimeError::new(format!("Integer overflow in `-({})`", integer), line, column)),
+        },
+        (TokenKind::Minus, Value::Float(float)) => Ok(Value::Float(-float)),
+        (TokenKind::Bang, Value::Bool(boolean)) => Ok(Value::Bool(!boolean)),
+        (operator, operand) => Err(RuntimeError::new(format!("Cannot apply {:?} to {}", operator, operand.type_name()), line, column)),
+    }
+}
+
+/// The elements a `for` loop runs over, as they are when it starts.
+pub(crate) fn elements(iterable: Value, line: usize, column: usize) -> Result<Vec<Value>, RuntimeError> {
+    match iterable {
+        Value::Array(elements) => Ok(elements.borrow().clone()),
+        Value::Map(entries) => Ok(entries.borrow().keys().map(MapKey::to_value).collect()),
+        Value::String(string) => Ok(string.chars().map(Value::Char).collect()),
+        value => Err(RuntimeError::new(format!("Cannot iterate over {}", value.type_name()), line, column)),
+    }
+}
+
+fn limit_exceeded(limit: Limit, message: String, line: usize, column: usize) -> RuntimeError {
+    let mut error = RuntimeError::new(message, line, column);
+    error.limit = Some(limit);
+    error
+}
+
+/// An estimate of the bytes a value holds outside of itself, or `None` for values that are
+/// not counted as allocations.
+fn footprint(value: &Value) -> Option<usize> {
+    match value {
+        Value::String(string) => Some(string.len()),
+        Value::Array(elements) => Some(elements.borrow().len() * size_of::<Value>()),
+        Value::Map(entries) => Some(entries.borrow().len() * (size_of::<MapKey>() + size_of::<Value>())),
+        Value::Object(object) => Some(object.fields.borrow().len() * size_of::<Value>()),
+        _ => None,
+    }
+}
+
+/// The environment a method bound to `object` closes over, which holds `this`.
+fn with_this(method: &Function, object: &Rc<Object>) -> Rc<Environment> {
+    let environment = Environment::new(Some(method.closure.clone()));
+    environment.define(Value::Object(object.clone()));
+    environment
+}
+
+/// The error that unwinds the interpreter when `value` is thrown.
+pub(crate) fn thrown(value: Value, line: usize, column: usize) -> RuntimeError {
+    let object = match &value {
+        Value::Object(object) => object,
+        value => return RuntimeError::new(format!("Only instances of `Error` can be thrown, got {}", value.type_name()), line, column),
+    };
+    let message = match object.get("message") {
+        Some(Value::String(message)) => format!("Uncaught `{}`: {}", object.class.name, message),
+        _ => format!("Uncaught `{}`", object.class.name),
+    };
+    let mut error = RuntimeError::new(message, line, column);
+    error.thrown = Some(value);
+    error
+}
+
+/// The value a field without an initializer starts out with.
+pub fn default_value(type_: &Type) -> Value {
+    match type_.kind {
+        TypeKind::Integer => Value::Integer(0),
+        TypeKind::Float => Value::Float(0.0),
+        TypeKind::Bool => Value::Bool(false),
+        TypeKind::Char => Value::Char('\0'),
+        TypeKind::String => Value::string(""),
+        _ => Value::Null,
+    }
 }
@@ -1,13 +1,15 @@
+use crate::diagnostics::Diagnostic;
 use crate::token::{
     Token,
     TokenKind,
 };
 
-pub fn lex(source: &str) -> Vec<Token> {
+/// Splits `source` into tokens, failing on the first malformed one.
+pub fn lex(source: &str) -> Result<Vec<Token>, Diagnostic> {
     let mut tokens: Vec<Token> = Vec::new();
-    let mut chars = source.chars().peekable();
+    let mut chars = source.char_indices().peekable();
     let mut line = 1;
-    let mut column = 1;
+    let mut line_start = 0;
 
     let kws = {
         let mut m = std::collections::HashMap::new();
@@ -27,19 +29,60 @@ pub fn lex(source: &str) -> Vec<Token> {
         m.insert("class", TokenKind::ClassKw);
         m.insert("return", TokenKind::ReturnKw);
         m.insert("constructor", TokenKind::ConstructorKw);
+        m.insert("void", TokenKind::VoidKw);
+        m.insert("if", TokenKind::IfKw);
+        m.insert("else", TokenKind::ElseKw);
+        m.insert("while", TokenKind::WhileKw);
+        m.insert("for", TokenKind::ForKw);
+        m.insert("in", TokenKind::InKw);
+        m.insert("break", TokenKind::BreakKw);
+        m.insert("continue", TokenKind::ContinueKw);
+        m.insert("this", TokenKind::ThisKw);
+        m.insert("new", TokenKind::NewKw);
+        m.insert("extends", TokenKind::ExtendsKw);
+        m.insert("super", TokenKind::SuperKw);
+        m.insert("interface", TokenKind::InterfaceKw);
+        m.insert("implements", TokenKind::ImplementsKw);
+        m.insert("enum", TokenKind::EnumKw);
+        m.insert("match", TokenKind::MatchKw);
+        m.insert("import", TokenKind::ImportKw);
+        m.insert("export", TokenKind::ExportKw);
+        m.insert("public", TokenKind::PublicKw);
+        m.insert("private", TokenKind::PrivateKw);
+        m.insert("protected", TokenKind::ProtectedKw);
+        m.insert("static", TokenKind::StaticKw);
+        m.insert("throw", TokenKind::ThrowKw);
+        m.insert("try", TokenKind::TryKw);
+        m.insert("catch", TokenKind::CatchKw);
+        m.insert("finally", TokenKind::FinallyKw);
+        m.insert("as", TokenKind::AsKw);
         m
     };
 
-    while let Some(c) = chars.next() {
+    while let Some((start, c)) = chars.next() {
+        // Columns are counted in characters, so they stay correct after multi-character tokens.
+        let column = source[line_start..start].chars().count() + 1;
         match c {
             '+' => tokens.push(Token::new(TokenKind::Plus, '+'.to_string(), line, column)),
             '-' => tokens.push(Token::new(TokenKind::Minus, '-'.to_string(), line, column)),
             '*' => tokens.push(Token::new(TokenKind::Star, '*'.to_string(), line, column)),
-            '/' => tokens.push(Token::new(TokenKind::Slash, '/'.to_string(), line, column)),
+            '/' => {
+                if let Some((_, '/')) = chars.peek() {
+                    // Line comment, skip to the end of the line.
+                    while let Some(&(_, c)) = chars.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        chars.next();
+                    }
+                } else {
+                    tokens.push(Token::new(TokenKind::Slash, '/'.to_string(), line, column));
+                }
+            }
             '%' => tokens.push(Token::new(TokenKind::Percent, '%'.to_string(), line, column)),
             '^' => tokens.push(Token::new(TokenKind::Caret, '^'.to_string(), line, column)),
             '!' => {
-                if let Some('=') = chars.peek() {
+                if let Some((_, '=')) = chars.peek() {
                     chars.next();
                     tokens.push(Token::new(TokenKind::BangEqual, "!=".to_string(), line, column));
                 } else {
@@ -47,15 +90,18 @@ pub fn lex(source: &str) -> Vec<Token> {
                 }
             }
             '=' => {
-                if let Some('=') = chars.peek() {
+                if let Some((_, '=')) = chars.peek() {
                     chars.next();
                     tokens.push(Token::new(TokenKind::EqualEqual, "==".to_string(), line, column));
+                } else if let Some((_, '>')) = chars.peek() {
+                    chars.next();
+                    tokens.push(Token::new(TokenKind::FatArrow, "=>".to_string(), line, column));
                 } else {
                     tokens.push(Token::new(TokenKind::Equal, '='.to_string(), line, column));
                 }
             }
             '>' => {
-                if let Some('=') = chars.peek() {
+                if let Some((_, '=')) = chars.peek() {
                     chars.next();
                     tokens.push(Token::new(TokenKind::GreaterEqual, ">=".to_string(), line, column));
                 } else {
@@ -63,7 +109,7 @@ pub fn lex(source: &str) -> Vec<Token> {
                 }
             }
             '<' => {
-                if let Some('=') = chars.peek() {
+                if let Some((_, '=')) = chars.peek() {
                     chars.next();
                     tokens.push(Token::new(TokenKind::LessEqual, "<=".to_string(), line, column));
                 } else {
@@ -71,7 +117,7 @@ pub fn lex(source: &str) -> Vec<Token> {
                 }
             }
             '&' => {
-                if let Some('&') = chars.peek() {
+                if let Some((_, '&')) = chars.peek() {
                     chars.next();
                     tokens.push(Token::new(TokenKind::And, "&&".to_string(), line, column));
                 } else {
@@ -79,7 +125,7 @@ pub fn lex(source: &str) -> Vec<Token> {
                 }
             }
             '|' => {
-                if let Some('|') = chars.peek() {
+                if let Some((_, '|')) = chars.peek() {
                     chars.next();
                     tokens.push(Token::new(TokenKind::Or, "||".to_string(), line, column));
                 } else {
@@ -93,7 +139,19 @@ pub fn lex(source: &str) -> Vec<Token> {
             '[' => tokens.push(Token::new(TokenKind::LeftBracket, '['.to_string(), line, column)),
             ']' => tokens.push(Token::new(TokenKind::RightBracket, ']'.to_string(), line, column)),
             ',' => tokens.push(Token::new(TokenKind::Comma, ','.to_string(), line, column)),
-            '.' => tokens.push(Token::new(TokenKind::Dot, '.'.to_string(), line, column)),
+            '.' => {
+                if let Some((_, '.')) = chars.peek() {
+                    chars.next();
+                    if let Some((_, '=')) = chars.peek() {
+                        chars.next();
+                        tokens.push(Token::new(TokenKind::DotDotEqual, "..=".to_string(), line, column));
+                    } else {
+                        tokens.push(Token::new(TokenKind::DotDot, "..".to_string(), line, column));
+                    }
+                } else {
+                    tokens.push(Token::new(TokenKind::Dot, '.'.to_string(), line, column));
+                }
+            }
             ':' => tokens.push(Token::new(TokenKind::Colon, ':'.to_string(), line, column)),
             ';' => tokens.push(Token::new(TokenKind::Semicolon, ';'.to_string(), line, column)),
             '?' => tokens.push(Token::new(TokenKind::Question, '?'.to_string(), line, column)),
@@ -103,11 +161,11 @@ pub fn lex(source: &str) -> Vec<Token> {
             '~' => tokens.push(Token::new(TokenKind::Tilde, '~'.to_string(), line, column)),
             '\'' => {
                 let mut value = String::new();
-                while let Some(c) = chars.next() {
+                while let Some((_, c)) = chars.next() {
                     if c == '\'' {
                         break;
                     } else if c == '\\' {
-                        if let Some(c) = chars.next() {
+                        if let Some((_, c)) = chars.next() {
                             match c {
                                 'n' => value.push('\n'),
                                 'r' => value.push('\r'),
@@ -118,24 +176,24 @@ pub fn lex(source: &str) -> Vec<Token> {
                                 '\\' => value.push('\\'),
                                 _ => {
                                     tokens.push(Token::new(
-                                        TokenKind::Error(format!("Invalid escape sequence: \\{}", c.to_string())),
+                                        TokenKind::Error(format!("Invalid escape sequence: \\{}", c)),
                                         c.to_string(),
                                         line,
                                         column,
                                     ));
-                                    return tokens;
+                                    break;
                                 }
                             }
                         } else {
                             tokens.push(Token::new(TokenKind::Error("Invalid escape sequence: \\ at end of file".to_string()), c.to_string(), line, column));
-                            return tokens;
+                            break;
                         }
                     } else {
                         value.push(c);
                     }
                 }
 
-                if value.len() == 1 {
+                if value.chars().count() == 1 {
                     tokens.push(Token::new(TokenKind::Character(value.chars().next().unwrap()), value.to_string(), line, column));
                 } else {
                     tokens.push(Token::new(TokenKind::Error(format!("Invalid character literal: '{}'", value)), value.to_string(), line, column));
@@ -143,11 +201,12 @@ pub fn lex(source: &str) -> Vec<Token> {
             }
             '"' => {
                 let mut value = String::new();
-                while let Some(c) = chars.next() {
+                let (token_line, token_column) = (line, column);
+                while let Some((offset, c)) = chars.next() {
                     if c == '"' {
                         break;
                     } else if c == '\\' {
-                        if let Some(c) = chars.next() {
+                        if let Some((_, c)) = chars.next() {
                             match c {
                                 'n' => value.push('\n'),
                                 'r' => value.push('\r'),
@@ -166,25 +225,28 @@ pub fn lex(source: &str) -> Vec<Token> {
                             break;
                         }
                     } else {
+                        if c == '\n' {
+                            line += 1;
+                            line_start = offset + 1;
+                        }
                         value.push(c);
                     }
                 }
-                tokens.push(Token::new(TokenKind::String(value.clone()), value.to_string(), line, column));
+                tokens.push(Token::new(TokenKind::String(value.clone()), value.to_string(), token_line, token_column));
             }
-            '_' => tokens.push(Token::new(TokenKind::Underscore, '_'.to_string(), line, column)),
             ' ' => (),
             '\t' => (),
             '\r' => (),
             '\n' => {
                 line += 1;
-                column = 1;
+                line_start = start + 1;
             }
             _ => {
-                if c.is_digit(10) {
+                if c.is_ascii_digit() {
                     let mut number = String::new();
                     number.push(c);
-                    while let Some(&c) = chars.peek() {
-                        if c.is_digit(10) {
+                    while let Some(&(_, c)) = chars.peek() {
+                        if c.is_ascii_digit() {
                             number.push(c);
                             chars.next();
                         } else {
@@ -192,11 +254,15 @@ pub fn lex(source: &str) -> Vec<Token> {
                         }
                     }
 
-                    if let Some('.') = chars.peek() {
+                    // Only treat the dot as a decimal point when a digit follows it.
+                    let mut lookahead = chars.clone();
+                    let is_float = matches!(lookahead.next(), Some((_, '.')))
+                        && matches!(lookahead.next(), Some((_, c)) if c.is_ascii_digit());
+                    if is_float {
                         number.push('.');
                         chars.next();
-                        while let Some(&c) = chars.peek() {
-                            if c.is_digit(10) {
+                        while let Some(&(_, c)) = chars.peek() {
+                            if c.is_ascii_digit() {
                                 number.push(c);
                                 chars.next();
                             } else {
@@ -205,12 +271,15 @@ pub fn lex(source: &str) -> Vec<Token> {
                         }
                         tokens.push(Token::new(TokenKind::Float(number.parse().unwrap()), number, line, column));
                     } else {
-                        tokens.push(Token::new(TokenKind::Integer(number.parse().unwrap()), number, line, column));
+                        match number.parse() {
+                            Ok(integer) => tokens.push(Token::new(TokenKind::Integer(integer), number, line, column)),
+                            Err(_) => tokens.push(Token::new(TokenKind::Error(format!("Integer literal out of range: {}", number)), number, line, column)),
+                        }
                     }
                 } else if c.is_alphabetic() || c == '_' {
                     let mut identifier = String::new();
                     identifier.push(c);
-                    while let Some(&c) = chars.peek() {
+                    while let Some(&(_, c)) = chars.peek() {
                         if c.is_alphanumeric() || c == '_' {
                             identifier.push(c);
                             chars.next();
@@ -219,8 +288,10 @@ pub fn lex(source: &str) -> Vec<Token> {
                         }
                     }
 
-                    if kws.contains_key(&identifier.as_str()) {
-                        tokens.push(Token::new(kws.get(identifier.as_str()).unwrap().clone(), identifier, line, column));
+                    if identifier == "_" {
+                        tokens.push(Token::new(TokenKind::Underscore, identifier, line, column));
+                    } else if let Some(kind) = kws.get(identifier.as_str()) {
+                        tokens.push(Token::new(kind.clone(), identifier, line, column));
                     } else {
                         tokens.push(Token::new(TokenKind::Identifier(identifier.clone()), identifier, line, column));
                     }
@@ -229,10 +300,19 @@ pub fn lex(source: &str) -> Vec<Token> {
                 }
             }
         }
-        column += 1;
     }
 
-    // tokens.push(Token::new(TokenKind::Eof, "".to_string(), line, column));
+    let column = source[line_start..].chars().count() + 1;
+    tokens.push(Token::new(TokenKind::Eof, "".to_string(), line, column));
+
+    // The magnitude of the minimum int is left for the parser, which accepts it after a `-`.
+    match tokens.iter().find(|token| matches!(token.kind, TokenKind::Error(_)) && !is_minimum_magnitude(token)) {
+        Some(Token { kind: TokenKind::Error(message), line, column, .. }) => Err(Diagnostic::error(message.clone(), *line, *column)),
+        _ => Ok(tokens),
+    }
+}
 
-    return tokens;
-}
\ No newline at end of file
+/// Whether `token` is the magnitude of the minimum int, which is out of range unless negated.
+pub(crate) fn is_minimum_magnitude(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Error(_)) && token.lexeme.parse::<u64>() == Ok(i64::MIN.unsigned_abs())
+}
@@ -0,0 +1,43 @@
+//! Wally is an object-oriented, statically typed language inspired by Rust, Java and C.
+//!
+//! This crate holds its whole pipeline, from the lexer through the parser, module
+//! loader, resolver and type checker to the interpreter and the bytecode compiler
+//! and virtual machine that run function bodies, the `.wlyc` format programs compiled
+//! ahead of time are stored in, and an [`Engine`] that drives it for Rust programs
+//! embedding Wally.
+//!
+//! Values cross between Rust and Wally through [`IntoValue`] and [`FromValue`]:
+//!
+//! ```
+//! use wally_lang::Engine;
+//!
+//! let mut engine = Engine::new();
+//! engine.eval("fn lengths(words: array<string>): map<string, int> { var result: map<string, int> = {}; result[words[0]] = 5; return result; }").unwrap();
+//! let lengths: std::collections::HashMap<String, i64> = engine.call("lengths", (vec!["hello"],)).unwrap();
+//! assert_eq!(lengths["hello"], 5);
+//! ```
+pub mod checker;
+pub mod bytecode;
+pub mod compiler;
+pub mod convert;
+pub mod diagnostics;
+pub mod engine;
+pub mod environment;
+pub mod fs;
+pub mod interpreter;
+pub mod lexer;
+pub mod limits;
+pub mod math;
+pub mod module;
+pub mod native;
+pub mod parser;
+pub mod process;
+pub mod resolver;
+pub mod strings;
+pub mod token;
+pub mod value;
+pub mod vm;
+
+pub use convert::{ConversionError, FromValue, IntoArguments, IntoValue};
+pub use engine::{Engine, Error};
+pub use value::Value;
@@ -0,0 +1,100 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Bounds on what a program may use while it runs, for running programs that are not trusted.
+///
+/// Each bound applies to one run: evaluating a program, or one call into it from the host.
+/// Nothing is bounded unless set, except the call depth: recursing deeper than the stack
+/// allows aborts the whole process rather than failing the run. Hosts running untrusted
+/// programs should also set `values`, since otherwise a program can use all the memory there is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Statements executed and expressions evaluated, or instructions executed for functions
+    /// compiled to bytecode.
+    pub steps: Option<u64>,
+    /// Wally functions active at once, which keeps deep recursion from overflowing the Rust stack.
+    /// Each call takes a few kilobytes of it, and tens in unoptimized builds.
+    pub call_depth: Option<usize>,
+    /// Strings, arrays, maps and objects created.
+    pub values: Option<u64>,
+    /// An estimate of the bytes those values hold, including what arrays and maps grow by.
+    /// Values are reference counted and not tracked once created, so this counts what was
+    /// allocated rather than what is still alive. Concatenations, growing arrays and maps, and
+    /// natives whose results can outgrow their arguments are checked before they allocate.
+    pub bytes: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self { steps: None, call_depth: Some(Self::DEFAULT_CALL_DEPTH), values: None, bytes: None }
+    }
+}
+
+impl Limits {
+    /// The call depth runs are limited to unless set otherwise, which release builds fit in the
+    /// 8 MiB stack of a main thread. Unoptimized builds do not, so they need a bigger stack or a
+    /// lower limit, as do threads with smaller stacks.
+    pub const DEFAULT_CALL_DEPTH: usize = 1_000;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_steps(mut self, steps: u64) -> Self {
+        self.steps = Some(steps);
+        self
+    }
+
+    pub fn with_call_depth(mut self, call_depth: usize) -> Self {
+        self.call_depth = Some(call_depth);
+        self
+    }
+
+    pub fn with_values(mut self, values: u64) -> Self {
+        self.values = Some(values);
+        self
+    }
+
+    pub fn with_bytes(mut self, bytes: u64) -> Self {
+        self.bytes = Some(bytes);
+        self
+    }
+}
+
+/// The limit a run was stopped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    Steps,
+    CallDepth,
+    Values,
+    Bytes,
+    Cancelled,
+}
+
+/// Stops the runs of an engine from another thread.
+///
+/// Once cancelled, every run fails until the handle is reset, so a run cannot start just
+/// after the cancellation meant to stop it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the run in progress at its next step.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.cancelled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -1,31 +1,145 @@
-mod lexer;
-mod token;
-mod parser;
-mod interpreter;
-
-use crate::lexer::lex;
-use crate::parser::{parse, dump_ast};
-use crate::interpreter::Interpreter;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
+use wally_lang::bytecode::{Program, MAGIC};
+use wally_lang::checker::check;
+use wally_lang::diagnostics::{render, render_in};
+use wally_lang::interpreter::{Interpreter, RuntimeError};
+use wally_lang::limits::Limits;
+use wally_lang::module::load;
+use wally_lang::native::{Capability, Natives};
+use wally_lang::parser::dump_ast;
+use wally_lang::resolver::resolve;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::thread;
+
+/// How deeply scripts can recurse.
+const CALL_DEPTH: usize = 10_000;
+
+/// The stack scripts run on, which is large enough for `CALL_DEPTH` calls even in unoptimized
+/// builds. It is only reserved, and pages of it are only used once the script gets that deep.
+const STACK_SIZE: usize = 1 << 30;
 
 fn main() {
-    // read file
-    let file = File::open("examples/test.wly").unwrap();
-    let mut reader = BufReader::new(file);
-    let mut contents = String::new();
-    reader.read_to_string(&mut contents).unwrap();
+    let runner = match thread::Builder::new().stack_size(STACK_SIZE).spawn(run) {
+        Ok(runner) => runner,
+        Err(error) => {
+            eprintln!("error: Could not start the interpreter: {}", error);
+            process::exit(1);
+        }
+    };
+    // `run` exits the process when it is done, so it only returns by panicking.
+    if runner.join().is_err() {
+        process::exit(101);
+    }
+}
 
-    let tokens = lex(&contents);
-    let statements = parse(tokens);
+fn run() {
+    let mut args = std::env::args().skip(1).peekable();
+    let mut root = None;
+    if args.peek().map(String::as_str) == Some("--root") {
+        args.next();
+        root = args.next().map(PathBuf::from);
+    }
+    let path = match args.next() {
+        Some(path) => PathBuf::from(path),
+        None => {
+            eprintln!("usage: wally [--root <dir>] <file> [args...]");
+            process::exit(2);
+        }
+    };
+    // Imports are relative to the directory of the entry file unless a root is given.
+    let root = root.unwrap_or_else(|| match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    });
 
-    println!("{:#?}", statements);
+    let args: Vec<String> = args.collect();
+    let mut natives = Natives::new();
+    wally_lang::process::set_arguments(&mut natives, args.clone());
+    let natives = Rc::new(natives);
+    if let Ok(bytes) = std::fs::read(&path) {
+        if bytes.starts_with(&MAGIC) {
+            run_compiled(&path, &bytes, natives, args);
+        }
+    }
+    let mut modules = match load(&root, &path, &natives) {
+        Ok(modules) => modules,
+        Err(error) => {
+            eprint!("{}", render(&error.diagnostic, &error.path.display().to_string(), &error.source));
+            process::exit(1);
+        }
+    };
 
-    dump_ast(&statements);    
+    if std::env::var_os("WALLY_DUMP_AST").is_some() {
+        for module in &modules {
+            dump_ast(&module.statements);
+        }
+    }
 
-    let mut interpreter = Interpreter::new();
-    interpreter.interpret(statements);
+    // Type checking assumes every name resolved, so it only runs on programs that did.
+    let diagnostics = match resolve(&mut modules, &natives) {
+        Ok(()) => check(&modules, &natives),
+        Err(errors) => errors,
+    };
+    let paths: Vec<String> = modules.iter().map(|module| module.path.display().to_string()).collect();
+    let sources: Vec<String> = modules.iter().map(|module| module.source.clone()).collect();
+    let files: Vec<(&str, &str)> = paths.iter().zip(&sources).map(|(path, source)| (path.as_str(), source.as_str())).collect();
+    for (index, diagnostic) in &diagnostics {
+        eprint!("{}", render_in(diagnostic, &files, *index));
+    }
+    if diagnostics.iter().any(|(_, diagnostic)| diagnostic.is_error()) {
+        process::exit(1);
+    }
+
+    let mut interpreter = trusted(natives);
+    let mut result = Ok(());
+    for module in modules {
+        result = result.and_then(|_| interpreter.interpret(module.statements));
+    }
+    exit(result.and_then(|_| interpreter.run_main(args)), &files);
+}
+
+/// Runs a program compiled by `wallyc`, whose errors can only point at lines of a source that is not there.
+fn run_compiled(path: &Path, bytes: &[u8], natives: Rc<Natives>, args: Vec<String>) -> ! {
+    let program = match Program::from_bytes(bytes, &natives) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("error: {}: {}", path.display(), error);
+            process::exit(1);
+        }
+    };
+    let files: Vec<(&str, &str)> = program.modules.iter().map(|module| (module.path.as_str(), "")).collect();
+    let mut interpreter = trusted(natives);
+    let mut result = Ok(());
+    for module in &program.modules {
+        result = result.and_then(|_| interpreter.interpret_compiled(module));
+    }
+    exit(result.and_then(|_| interpreter.run_main(args)), &files);
+}
+
+/// An interpreter for scripts run from the command line, which are trusted like any other
+/// program the user runs.
+fn trusted(natives: Rc<Natives>) -> Interpreter {
+    let mut interpreter = Interpreter::new(natives);
+    interpreter.set_limits(Limits::new().with_call_depth(CALL_DEPTH));
+    for capability in Capability::ALL {
+        interpreter.allow(capability);
+    }
+    interpreter
+}
 
-    println!("{:#?}", interpreter);
+fn exit(result: Result<i64, RuntimeError>, files: &[(&str, &str)]) -> ! {
+    match result {
+        Ok(code) => match i32::try_from(code) {
+            Ok(code) => process::exit(code),
+            Err(_) => {
+                eprintln!("error: Exit status {} returned by `main` is out of range", code);
+                process::exit(1);
+            }
+        },
+        Err(error) => {
+            eprint!("{}", error.render(files));
+            process::exit(1);
+        }
+    }
 }
@@ -0,0 +1,74 @@
+use crate::native::{integer, number, primitive, Natives};
+use crate::parser::TypeKind;
+use crate::value::Value;
+use std::f64::consts;
+
+/// Registers the `math` module.
+///
+/// Functions follow the types of their arguments rather than converting them:
+/// `abs`, `min` and `max` take ints, `fabs`, `fmin` and `fmax` their float
+/// counterparts, and everything else takes floats. Other numbers are converted
+/// explicitly with `as`. Float functions follow IEEE 754, so `sqrt(-1.0)` is NaN
+/// rather than an error, while int arithmetic that overflows is an error unless
+/// it goes through the `wrapping_` or `saturating_` functions.
+pub fn define(natives: &mut Natives) {
+    let float = || primitive(TypeKind::Float);
+    let int = || primitive(TypeKind::Integer);
+
+    for (name, function) in [
+        ("sqrt", f64::sqrt as fn(f64) -> f64),
+        ("exp", f64::exp),
+        ("ln", f64::ln),
+        ("log10", f64::log10),
+        ("sin", f64::sin),
+        ("cos", f64::cos),
+        ("tan", f64::tan),
+        ("asin", f64::asin),
+        ("acos", f64::acos),
+        ("atan", f64::atan),
+        ("floor", f64::floor),
+        ("ceil", f64::ceil),
+        // Halfway cases round away from zero.
+        ("round", f64::round),
+        ("trunc", f64::trunc),
+        ("fabs", f64::abs),
+    ] {
+        natives.define(&format!("math.{}", name), vec![float()], float(), move |arguments| Ok(Value::Float(function(number(&arguments[0])?))));
+    }
+    for (name, function) in [("pow", f64::powf as fn(f64, f64) -> f64), ("atan2", f64::atan2), ("fmin", f64::min), ("fmax", f64::max)] {
+        natives.define(&format!("math.{}", name), vec![float(), float()], float(), move |arguments| {
+            Ok(Value::Float(function(number(&arguments[0])?, number(&arguments[1])?)))
+        });
+    }
+
+    // NaN is the only value not equal to itself, which is easy to forget.
+    natives.define("math.is_nan", vec![float()], primitive(TypeKind::Bool), |arguments| Ok(Value::Bool(number(&arguments[0])?.is_nan())));
+
+    natives.define("math.abs", vec![int()], int(), |arguments| {
+        let integer = integer(&arguments[0])?;
+        integer.checked_abs().map(Value::Integer).ok_or_else(|| format!("The absolute value of {} is out of the range of int", integer))
+    });
+    natives.define("math.min", vec![int(), int()], int(), |arguments| Ok(Value::Integer(integer(&arguments[0])?.min(integer(&arguments[1])?))));
+    natives.define("math.max", vec![int(), int()], int(), |arguments| Ok(Value::Integer(integer(&arguments[0])?.max(integer(&arguments[1])?))));
+    // Arithmetic operators treat overflow as an error; these opt into the other behaviours.
+    for (name, function) in [
+        ("wrapping_add", i64::wrapping_add as fn(i64, i64) -> i64),
+        ("wrapping_sub", i64::wrapping_sub),
+        ("wrapping_mul", i64::wrapping_mul),
+        ("saturating_add", i64::saturating_add),
+        ("saturating_sub", i64::saturating_sub),
+        ("saturating_mul", i64::saturating_mul),
+    ] {
+        natives.define(&format!("math.{}", name), vec![int(), int()], int(), move |arguments| {
+            Ok(Value::Integer(function(integer(&arguments[0])?, integer(&arguments[1])?)))
+        });
+    }
+
+    natives.define_constant("math.PI", float(), Value::Float(consts::PI));
+    natives.define_constant("math.TAU", float(), Value::Float(consts::TAU));
+    natives.define_constant("math.E", float(), Value::Float(consts::E));
+    natives.define_constant("math.INFINITY", float(), Value::Float(f64::INFINITY));
+    natives.define_constant("math.NAN", float(), Value::Float(f64::NAN));
+    natives.define_constant("math.MAX_INT", int(), Value::Integer(i64::MAX));
+    natives.define_constant("math.MIN_INT", int(), Value::Integer(i64::MIN));
+}
@@ -1,4 +0,0 @@
-pub mod lexer;
-pub mod token;
-pub mod parser;
-pub mod interpreter;
\ No newline at end of file
@@ -0,0 +1,211 @@
+use crate::diagnostics::Diagnostic;
+use crate::lexer::lex;
+use crate::native::Natives;
+use crate::parser::*;
+use crate::resolver::global_name;
+use std::path::{Component, Path, PathBuf};
+
+/// One source file of a program.
+#[derive(Debug, Clone)]
+pub struct Module {
+    /// The dotted path of the module relative to the project root, like `geometry.shapes`.
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    /// Top-level statements, with imports taken out and exported declarations unwrapped.
+    pub statements: Vec<Statement>,
+    /// Names other modules can access through the namespace they import this module as.
+    pub exports: Vec<String>,
+    /// The namespace each import declares, with the index of the module it refers to.
+    pub imports: Vec<(String, usize)>,
+    /// The namespace each import of a native module declares, with the name of that module.
+    pub native_imports: Vec<(String, String)>,
+}
+
+/// An error that stopped a module graph from loading, located in the file it was found in.
+#[derive(Debug, Clone)]
+pub struct LoadError {
+    pub diagnostic: Diagnostic,
+    pub path: PathBuf,
+    pub source: String,
+}
+
+/// Source of the prelude, whose exports every module sees without importing it.
+const PRELUDE: &str = include_str!("prelude.wly");
+
+/// Loads the module at `entry` and every module it imports, directly or indirectly.
+///
+/// Modules come back in dependency order, so each one follows everything it
+/// imports and the entry module is last. That is the order they are resolved,
+/// checked and run in. The prelude always comes first.
+///
+/// Imports of the native modules in `natives` take precedence over files of the same name.
+pub fn load(root: &Path, entry: &Path, natives: &Natives) -> Result<Vec<Module>, LoadError> {
+    let source = std::fs::read_to_string(entry).map_err(|error| LoadError {
+        diagnostic: Diagnostic::error(format!("Cannot read `{}`: {}", entry.display(), error), 1, 1),
+        path: entry.to_path_buf(),
+        source: String::new(),
+    })?;
+    let path = entry.canonicalize().unwrap_or_else(|_| entry.to_path_buf());
+    load_source(root, &path, source, natives)
+}
+
+/// Like `load`, for an entry module whose source is given rather than read from `entry`.
+pub fn load_source(root: &Path, entry: &Path, source: String, natives: &Natives) -> Result<Vec<Module>, LoadError> {
+    let mut loader = Loader {
+        root: root.canonicalize().unwrap_or_else(|_| root.to_path_buf()),
+        modules: Vec::new(),
+        loading: Vec::new(),
+        native_modules: natives.modules(),
+    };
+    loader.load(PathBuf::from("prelude.wly"), PRELUDE.to_string())?;
+    loader.load(entry.to_path_buf(), source)?;
+    Ok(loader.modules)
+}
+
+struct Loader {
+    root: PathBuf,
+    modules: Vec<Module>,
+    /// Modules whose imports are being loaded, outermost first.
+    loading: Vec<(PathBuf, String)>,
+    native_modules: Vec<String>,
+}
+
+impl Loader {
+    fn load(&mut self, path: PathBuf, source: String) -> Result<usize, LoadError> {
+        let name = self.module_name(&path);
+        let statements = match lex(&source).and_then(parse) {
+            Ok(statements) => statements,
+            Err(diagnostic) => return Err(LoadError { diagnostic, path, source }),
+        };
+        self.loading.push((path.clone(), name.clone()));
+
+        let mut module = Module {
+            name,
+            path,
+            source,
+            statements: Vec::new(),
+            exports: Vec::new(),
+            imports: Vec::new(),
+            native_imports: Vec::new(),
+        };
+        for statement in statements {
+            let (line, column) = (statement.line, statement.column);
+            match statement.kind {
+                StatementKind::Import(import) => {
+                    let namespace = import.namespace();
+                    let mut imported = module.imports.iter().map(|(other, _)| other).chain(module.native_imports.iter().map(|(other, _)| other));
+                    if imported.any(|other| *other == namespace) {
+                        return Err(error(&module, format!("`{}` is already imported", namespace), line, column));
+                    }
+                    match self.native_module(&import) {
+                        Some(native) => module.native_imports.push((namespace, native)),
+                        None => {
+                            let imported = self.load_import(&import, &module, line, column)?;
+                            module.imports.push((namespace, imported));
+                        }
+                    }
+                }
+                StatementKind::Export(exported) => {
+                    match exported_name(&exported) {
+                        Some(name) => module.exports.push(name.to_string()),
+                        None => return Err(error(&module, "Only declarations can be exported".to_string(), line, column)),
+                    }
+                    module.statements.push(*exported);
+                }
+                kind => module.statements.push(Statement::new(kind, line, column)),
+            }
+        }
+
+        self.loading.pop();
+        self.modules.push(module);
+        Ok(self.modules.len() - 1)
+    }
+
+    /// The native module an import refers to, if any.
+    fn native_module(&self, import: &Import) -> Option<String> {
+        match &import.path {
+            ImportPath::Module(segments) => self.native_modules.iter().find(|module| **module == segments.join(".")).cloned(),
+            ImportPath::File(_) => None,
+        }
+    }
+
+    /// Loads the file an import refers to, which must be inside the root.
+    fn load_import(&mut self, import: &Import, importer: &Module, line: usize, column: usize) -> Result<usize, LoadError> {
+        let (relative, description) = match &import.path {
+            ImportPath::Module(segments) => (PathBuf::from(segments.join("/")).with_extension("wly"), format!("module `{}`", segments.join("."))),
+            ImportPath::File(file) => (PathBuf::from(file), format!("`{}`", file)),
+        };
+        // Paths leaving the root are rejected before anything is read, so imports cannot tell
+        // which files outside it exist.
+        let outside = || error(importer, format!("Cannot import {}, which is outside the root `{}`", description, self.root.display()), line, column);
+        let path = match contained(&relative) {
+            Some(relative) => self.root.join(relative),
+            None => return Err(outside()),
+        };
+        let not_found = || error(importer, format!("Cannot find {} at `{}`", description, path.display()), line, column);
+        let path = path.canonicalize().map_err(|_| not_found())?;
+        // Symbolic links inside the root can still point out of it.
+        if !path.starts_with(&self.root) {
+            return Err(outside());
+        }
+        let source = std::fs::read_to_string(&path).map_err(|_| not_found())?;
+
+        if let Some(start) = self.loading.iter().position(|(loading, _)| *loading == path) {
+            let mut cycle: Vec<&str> = self.loading[start..].iter().map(|(_, name)| name.as_str()).collect();
+            cycle.push(&self.loading[start].1);
+            return Err(error(importer, format!("Import cycle: {}", cycle.join(" -> ")), line, column));
+        }
+        // A module imported from several places is only loaded once.
+        if let Some(index) = self.modules.iter().position(|module| module.path == path) {
+            return Ok(index);
+        }
+        self.load(path, source)
+    }
+
+    /// Names a module by its path relative to the project root, so `geometry/shapes.wly` is `geometry.shapes`.
+    fn module_name(&self, path: &Path) -> String {
+        // The prelude and entry files outside the root are named after the file alone.
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative,
+            Err(_) => Path::new(path.file_name().unwrap_or_default()),
+        };
+        let segments: Vec<String> = relative.with_extension("").iter().map(|segment| segment.to_string_lossy().into_owned()).collect();
+        segments.join(".")
+    }
+}
+
+/// `path` with its `.` and `..` components resolved without reading the file system, or `None`
+/// if it is absolute or `..` leaves the directory it is relative to.
+fn contained(path: &Path) -> Option<PathBuf> {
+    let mut contained = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => contained.push(part),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !contained.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(contained)
+}
+
+fn error(module: &Module, message: String, line: usize, column: usize) -> LoadError {
+    LoadError {
+        diagnostic: Diagnostic::error(message, line, column),
+        path: module.path.clone(),
+        source: module.source.clone(),
+    }
+}
+
+/// The name an exported declaration makes available, including interfaces, which only name a type.
+fn exported_name(statement: &Statement) -> Option<&str> {
+    match &statement.kind {
+        StatementKind::InterfaceDeclaration(interface) => Some(&interface.name),
+        _ => global_name(statement),
+    }
+}
@@ -0,0 +1,422 @@
+use crate::parser::{Type, TypeKind};
+use crate::{fs, math, process, strings};
+use crate::value::Value;
+use std::any::Any;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+/// The native module whose functions taking a string first can also be called as
+/// methods of strings, so `name.trim()` is `strings.trim(name)`.
+pub const STRING_METHODS: &str = "strings";
+
+/// The Rust implementation of a native function. It is only called with as many
+/// arguments as the function declares, of the declared types, and returns the
+/// message of a runtime error on failure.
+pub type NativeImpl = dyn Fn(Vec<Value>) -> Result<Value, String>;
+
+/// Creates the Rust value a host object wraps from the constructor arguments.
+pub type ConstructorImpl = dyn Fn(Vec<Value>) -> Result<Box<dyn Any>, String>;
+
+/// Runs a method of a host class on the Rust value of the object it is called on.
+pub type MethodImpl = dyn Fn(&mut dyn Any, Vec<Value>) -> Result<Value, String>;
+
+/// An upper bound on the bytes the result of a native function holds, worked out from its arguments.
+pub type SizeImpl = dyn Fn(&[Value]) -> usize;
+
+pub type GetterImpl = dyn Fn(&dyn Any) -> Result<Value, String>;
+
+pub type SetterImpl = dyn Fn(&mut dyn Any, Value) -> Result<(), String>;
+
+/// Access to the world outside a program, which natives that need it can only use
+/// once the interpreter is granted it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Reading files and directories, with `fs.read`, `fs.list` and `fs.exists`.
+    ReadFiles,
+    /// Creating, changing and deleting files, with `fs.write`, `fs.append` and `fs.remove`.
+    WriteFiles,
+    /// Reading environment variables, with `process.env`.
+    Environment,
+    /// Ending the host process, with `process.exit`.
+    Exit,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 4] = [Capability::ReadFiles, Capability::WriteFiles, Capability::Environment, Capability::Exit];
+}
+
+/// A function implemented in Rust that Wally code calls like any other.
+pub struct NativeFunction {
+    pub name: String,
+    pub parameters: Vec<Type>,
+    pub return_type: Type,
+    pub function: Box<NativeImpl>,
+    /// The capability calls need, if any.
+    pub capability: Option<Capability>,
+    /// For natives whose result can be much larger than their arguments, a bound on its size,
+    /// which is checked against the byte limit before the native runs.
+    pub size: Option<Box<SizeImpl>>,
+}
+
+impl fmt::Debug for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeFunction({})", self.name)
+    }
+}
+
+/// A value provided by the host, like `math.PI`, which scripts can read but not assign.
+#[derive(Debug)]
+pub struct NativeConstant {
+    pub name: String,
+    pub type_: Type,
+    pub value: Value,
+}
+
+/// The native functions a program can call, looked up by the resolver, typed by
+/// the checker and called by the interpreter.
+///
+/// Natives are visible in every module without an import, but a global of the
+/// same name declared by the module takes precedence.
+#[derive(Debug, Clone, Default)]
+pub struct Natives {
+    functions: Vec<Rc<NativeFunction>>,
+    classes: Vec<Rc<NativeClass>>,
+    constants: Vec<Rc<NativeConstant>>,
+}
+
+impl Natives {
+    /// A registry holding the builtins every program can use.
+    pub fn new() -> Self {
+        let mut natives = Self::default();
+        natives.define("print", vec![primitive(TypeKind::String)], primitive(TypeKind::Void), |arguments| {
+            print!("{}", arguments[0]);
+            // Prompts are usually printed without a newline right before reading a line.
+            io::stdout().flush().map_err(|error| format!("Could not write to stdout: {}", error))?;
+            Ok(Value::Null)
+        });
+        natives.define("println", vec![primitive(TypeKind::String)], primitive(TypeKind::Void), |arguments| {
+            println!("{}", arguments[0]);
+            Ok(Value::Null)
+        });
+        natives.define("eprintln", vec![primitive(TypeKind::String)], primitive(TypeKind::Void), |arguments| {
+            eprintln!("{}", arguments[0]);
+            Ok(Value::Null)
+        });
+        natives.define("read_line", vec![], primitive(TypeKind::String), |_| {
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line).map_err(|error| format!("Could not read from stdin: {}", error))?;
+            // The line is returned without its terminator, and as an empty string at the end of input.
+            if line.ends_with('\n') {
+                line.pop();
+                if line.ends_with('\r') {
+                    line.pop();
+                }
+            }
+            Ok(Value::string(&line))
+        });
+        strings::define(&mut natives);
+        math::define(&mut natives);
+        fs::define(&mut natives);
+        process::define(&mut natives);
+        natives
+    }
+
+    /// Registers a native function, replacing any previously registered under the same name.
+    pub fn define(&mut self, name: &str, parameters: Vec<Type>, return_type: Type, function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static) {
+        self.insert(NativeFunction {
+            name: name.to_string(),
+            parameters,
+            return_type,
+            function: Box::new(function),
+            capability: None,
+            size: None,
+        });
+    }
+
+    /// Registers a native function that can only be called by interpreters granted `capability`.
+    pub fn define_gated(
+        &mut self,
+        capability: Capability,
+        name: &str,
+        parameters: Vec<Type>,
+        return_type: Type,
+        function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static,
+    ) {
+        self.insert(NativeFunction {
+            name: name.to_string(),
+            parameters,
+            return_type,
+            function: Box::new(function),
+            capability: Some(capability),
+            size: None,
+        });
+    }
+
+    /// Registers a native function whose result can be much larger than its arguments, with
+    /// `size` bounding the bytes of the result from the arguments.
+    pub fn define_sized(
+        &mut self,
+        name: &str,
+        parameters: Vec<Type>,
+        return_type: Type,
+        size: impl Fn(&[Value]) -> usize + 'static,
+        function: impl Fn(Vec<Value>) -> Result<Value, String> + 'static,
+    ) {
+        self.insert(NativeFunction {
+            name: name.to_string(),
+            parameters,
+            return_type,
+            function: Box::new(function),
+            capability: None,
+            size: Some(Box::new(size)),
+        });
+    }
+
+    fn insert(&mut self, native: NativeFunction) {
+        let (name, native) = (native.name.clone(), Rc::new(native));
+        match self.index_of(&name) {
+            Some(index) => self.functions[index] = native,
+            None => self.functions.push(native),
+        }
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.functions.iter().position(|function| function.name == name)
+    }
+
+    pub fn get(&self, index: usize) -> &Rc<NativeFunction> {
+        &self.functions[index]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rc<NativeFunction>> {
+        self.functions.iter()
+    }
+
+    /// Names of the native modules, which group the functions and constants registered
+    /// under a qualified name like `strings.trim`.
+    pub fn modules(&self) -> Vec<String> {
+        let mut modules: Vec<String> = Vec::new();
+        let names = self.functions.iter().map(|function| &function.name).chain(self.constants.iter().map(|constant| &constant.name));
+        for name in names {
+            if let Some((module, _)) = name.rsplit_once('.') {
+                if !modules.iter().any(|other| other == module) {
+                    modules.push(module.to_string());
+                }
+            }
+        }
+        modules
+    }
+
+    /// Registers a native class, replacing any previously registered under the same name.
+    pub fn define_class(&mut self, class: NativeClass) {
+        let class = Rc::new(class);
+        match self.class_index(&class.name) {
+            Some(index) => self.classes[index] = class,
+            None => self.classes.push(class),
+        }
+    }
+
+    pub fn class_index(&self, name: &str) -> Option<usize> {
+        self.classes.iter().position(|class| class.name == name)
+    }
+
+    pub fn class(&self, index: usize) -> &Rc<NativeClass> {
+        &self.classes[index]
+    }
+
+    pub fn classes(&self) -> impl Iterator<Item = &Rc<NativeClass>> {
+        self.classes.iter()
+    }
+
+    /// Registers a native constant, replacing any previously registered under the same name.
+    pub fn define_constant(&mut self, name: &str, type_: Type, value: Value) {
+        let constant = Rc::new(NativeConstant { name: name.to_string(), type_, value });
+        match self.constant_index(name) {
+            Some(index) => self.constants[index] = constant,
+            None => self.constants.push(constant),
+        }
+    }
+
+    pub fn constant_index(&self, name: &str) -> Option<usize> {
+        self.constants.iter().position(|constant| constant.name == name)
+    }
+
+    pub fn constant(&self, index: usize) -> &Rc<NativeConstant> {
+        &self.constants[index]
+    }
+
+    pub fn constants(&self) -> impl Iterator<Item = &Rc<NativeConstant>> {
+        self.constants.iter()
+    }
+}
+
+/// A class implemented in Rust, whose instances each wrap a value of some Rust type.
+///
+/// Scripts use it like a class declared with `class`: they instantiate it with
+/// `new`, call its methods and read its properties, and assign those that have
+/// a setter. Host classes cannot be extended.
+///
+/// ```
+/// use wally_lang::native::{primitive, NativeClass};
+/// use wally_lang::parser::TypeKind;
+/// use wally_lang::Value;
+///
+/// struct Counter {
+///     count: i64,
+/// }
+///
+/// let class = NativeClass::new("Counter")
+///     .with_constructor(vec![], |_| Ok(Counter { count: 0 }))
+///     .with_method("increment", vec![], primitive(TypeKind::Void), |counter: &mut Counter, _| {
+///         counter.count += 1;
+///         Ok(Value::Null)
+///     })
+///     .with_property("count", primitive(TypeKind::Integer), |counter: &Counter| Value::Integer(counter.count));
+/// ```
+pub struct NativeClass {
+    pub name: String,
+    /// Without a constructor, only the host can create instances.
+    pub constructor: Option<NativeConstructor>,
+    pub methods: Vec<NativeMethod>,
+    pub properties: Vec<NativeProperty>,
+}
+
+pub struct NativeConstructor {
+    pub parameters: Vec<Type>,
+    pub function: Box<ConstructorImpl>,
+}
+
+pub struct NativeMethod {
+    pub name: String,
+    pub parameters: Vec<Type>,
+    pub return_type: Type,
+    pub function: Box<MethodImpl>,
+}
+
+/// A field-like member of a host class, read-only unless it has a setter.
+pub struct NativeProperty {
+    pub name: String,
+    pub type_: Type,
+    pub getter: Box<GetterImpl>,
+    pub setter: Option<Box<SetterImpl>>,
+}
+
+impl NativeClass {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), constructor: None, methods: Vec::new(), properties: Vec::new() }
+    }
+
+    /// Lets scripts instantiate the class, creating the Rust value from the arguments.
+    pub fn with_constructor<T: 'static>(mut self, parameters: Vec<Type>, function: impl Fn(Vec<Value>) -> Result<T, String> + 'static) -> Self {
+        self.constructor = Some(NativeConstructor {
+            parameters,
+            function: Box::new(move |arguments| Ok(Box::new(function(arguments)?) as Box<dyn Any>)),
+        });
+        self
+    }
+
+    pub fn with_method<T: 'static>(
+        mut self,
+        name: &str,
+        parameters: Vec<Type>,
+        return_type: Type,
+        function: impl Fn(&mut T, Vec<Value>) -> Result<Value, String> + 'static,
+    ) -> Self {
+        let description = format!("{}.{}", self.name, name);
+        self.methods.push(NativeMethod {
+            name: name.to_string(),
+            parameters,
+            return_type,
+            function: Box::new(move |this, arguments| function(downcast_mut(this, &description, "called")?, arguments)),
+        });
+        self
+    }
+
+    /// Adds a read-only property.
+    pub fn with_property<T: 'static>(mut self, name: &str, type_: Type, getter: impl Fn(&T) -> Value + 'static) -> Self {
+        let description = format!("{}.{}", self.name, name);
+        self.properties.push(NativeProperty {
+            name: name.to_string(),
+            type_,
+            // The host can wrap any Rust value in an instance, so one of another type is an error, as it is for methods.
+            getter: Box::new(move |this| Ok(getter(downcast_ref(this, &description)?))),
+            setter: None,
+        });
+        self
+    }
+
+    /// Adds a property scripts can assign, which `setter` is called with the new value for.
+    pub fn with_mutable_property<T: 'static>(
+        mut self,
+        name: &str,
+        type_: Type,
+        getter: impl Fn(&T) -> Value + 'static,
+        setter: impl Fn(&mut T, Value) -> Result<(), String> + 'static,
+    ) -> Self {
+        let description = format!("{}.{}", self.name, name);
+        self = self.with_property(name, type_, getter);
+        if let Some(property) = self.properties.last_mut() {
+            property.setter = Some(Box::new(move |this, value| setter(downcast_mut(this, &description, "assigned")?, value)));
+        }
+        self
+    }
+
+    pub fn method(&self, name: &str) -> Option<&NativeMethod> {
+        self.methods.iter().find(|method| method.name == name)
+    }
+
+    pub fn property(&self, name: &str) -> Option<&NativeProperty> {
+        self.properties.iter().find(|property| property.name == name)
+    }
+}
+
+impl fmt::Debug for NativeClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NativeClass({})", self.name)
+    }
+}
+
+/// The Rust value a getter reads, or an error if the instance wraps another type.
+fn downcast_ref<'a, T: 'static>(this: &'a dyn Any, member: &str) -> Result<&'a T, String> {
+    this.downcast_ref().ok_or_else(|| format!("`{}` was read on an instance of another type", member))
+}
+
+/// The Rust value a method or setter works on, or an error if the instance wraps another type.
+fn downcast_mut<'a, T: 'static>(this: &'a mut dyn Any, member: &str, action: &str) -> Result<&'a mut T, String> {
+    this.downcast_mut().ok_or_else(|| format!("`{}` was {} on an instance of another type", member, action))
+}
+
+/// A type that does not refer to any declaration, as used in the signatures of builtins.
+pub fn primitive(kind: TypeKind) -> Type {
+    Type::new(kind, 0, 0)
+}
+
+/// The string passed for a parameter declared as `string`. The checker guarantees one for
+/// Wally callers, so anything else is an error rather than a default.
+pub fn text(value: &Value) -> Result<&str, String> {
+    match value {
+        Value::String(string) => Ok(string),
+        value => Err(mismatch("a string", value)),
+    }
+}
+
+/// Like `text`, for parameters declared as `int`.
+pub fn integer(value: &Value) -> Result<i64, String> {
+    match value {
+        Value::Integer(integer) => Ok(*integer),
+        value => Err(mismatch("an int", value)),
+    }
+}
+
+/// Like `text`, for parameters declared as `float`.
+pub fn number(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Float(float) => Ok(*float),
+        value => Err(mismatch("a float", value)),
+    }
+}
+
+fn mismatch(expected: &str, value: &Value) -> String {
+    format!("Expected {} argument, found {}", expected, value.type_name())
+}
@@ -1,7 +1,19 @@
+use crate::diagnostics::Diagnostic;
+use crate::lexer::is_minimum_magnitude;
 use crate::token::{
     TokenKind,
     Token,
 };
+use std::cell::Cell;
+
+/// How deeply statements, expressions, types and patterns may nest. Every pass over a program
+/// recurses into its syntax tree, so this keeps deeply nested ones from overflowing the stack.
+pub const MAX_NESTING: usize = 128;
+
+thread_local! {
+    /// How deeply the syntax being parsed nests.
+    static NESTING: Cell<usize> = const { Cell::new(0) };
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Statement {
@@ -24,9 +36,22 @@ pub enum StatementKind {
     ConstantDeclaration(ConstantDeclaration),
     FunctionDeclaration(FunctionDeclaration),
     ClassDeclaration(ClassDeclaration),
+    InterfaceDeclaration(InterfaceDeclaration),
+    EnumDeclaration(EnumDeclaration),
     ConstructorDeclaration(ConstructorDeclaration),
+    Import(Import),
+    /// A top-level declaration other modules can import.
+    Export(Box<Statement>),
 
     Return(Return),
+    Block(Block),
+    If(If),
+    While(While),
+    For(For),
+    Throw(Throw),
+    Try(Try),
+    Break,
+    Continue,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -42,6 +67,36 @@ impl VarDeclaration {
     }
 }
 
+/// `import geometry.shapes;` or `import "util.wly";`, both resolved relative to the project root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub path: ImportPath,
+}
+
+impl Import {
+    pub fn new(path: ImportPath) -> Self {
+        Self { path }
+    }
+
+    /// The name the imported module's exports are accessed through, like `shapes` in `shapes.area(...)`.
+    pub fn namespace(&self) -> String {
+        match &self.path {
+            ImportPath::Module(segments) => segments.last().cloned().unwrap_or_default(),
+            ImportPath::File(file) => {
+                let stem = std::path::Path::new(file).file_stem().map(|stem| stem.to_string_lossy().into_owned());
+                stem.unwrap_or_default()
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportPath {
+    /// Dotted module path, where `geometry.shapes` is the file `geometry/shapes.wly`.
+    Module(Vec<String>),
+    File(String),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConstantDeclaration {
     pub statement: Box<Statement>,
@@ -56,26 +111,94 @@ impl ConstantDeclaration {
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionDeclaration {
     pub name: String,
+    pub type_parameters: Vec<String>,
     pub parameters: Vec<Parameter>,
     pub return_type: Type,
     pub body: Vec<Statement>,
 }
 
 impl FunctionDeclaration {
-    pub fn new(name: String, parameters: Vec<Parameter>, return_type: Type, body: Vec<Statement>) -> Self {
-        Self { name, parameters, return_type, body }
+    pub fn new(name: String, type_parameters: Vec<String>, parameters: Vec<Parameter>, return_type: Type, body: Vec<Statement>) -> Self {
+        Self { name, type_parameters, parameters, return_type, body }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ClassDeclaration {
     pub name: String,
-    pub body: Vec<Statement>,
+    pub type_parameters: Vec<String>,
+    pub superclass: Option<Variable>,
+    /// Type arguments of the superclass, as in `extends Base<T>`.
+    pub superclass_type_arguments: Vec<Type>,
+    pub interfaces: Vec<Variable>,
+    pub body: Vec<ClassMember>,
 }
 
 impl ClassDeclaration {
-    pub fn new(name: String, body: Vec<Statement>) -> Self {
-        Self { name, body }
+    pub fn new(
+        name: String,
+        type_parameters: Vec<String>,
+        superclass: Option<Variable>,
+        superclass_type_arguments: Vec<Type>,
+        interfaces: Vec<Variable>,
+        body: Vec<ClassMember>,
+    ) -> Self {
+        Self { name, type_parameters, superclass, superclass_type_arguments, interfaces, body }
+    }
+}
+
+/// A named set of method signatures that classes can declare they implement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterfaceDeclaration {
+    pub name: String,
+    pub methods: Vec<MethodSignature>,
+}
+
+impl InterfaceDeclaration {
+    pub fn new(name: String, methods: Vec<MethodSignature>) -> Self {
+        Self { name, methods }
+    }
+}
+
+/// A type whose values are exactly one of its variants, each carrying its own payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDeclaration {
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+}
+
+impl EnumDeclaration {
+    pub fn new(name: String, variants: Vec<EnumVariant>) -> Self {
+        Self { name, variants }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant {
+    pub name: String,
+    pub payload: Vec<Type>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl EnumVariant {
+    pub fn new(name: String, payload: Vec<Type>, line: usize, column: usize) -> Self {
+        Self { name, payload, line, column }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodSignature {
+    pub name: String,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Type,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl MethodSignature {
+    pub fn new(name: String, parameters: Vec<Parameter>, return_type: Type, line: usize, column: usize) -> Self {
+        Self { name, parameters, return_type, line, column }
     }
 }
 
@@ -93,15 +216,139 @@ impl Parameter {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Return {
-    pub expression: Expression,
+    pub expression: Option<Expression>,
 }
 
 impl Return {
+    pub fn new(expression: Option<Expression>) -> Self {
+        Self { expression }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub statements: Vec<Statement>,
+}
+
+impl Block {
+    pub fn new(statements: Vec<Statement>) -> Self {
+        Self { statements }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct If {
+    pub condition: Expression,
+    pub then_branch: Box<Statement>,
+    pub else_branch: Option<Box<Statement>>,
+}
+
+impl If {
+    pub fn new(condition: Expression, then_branch: Box<Statement>, else_branch: Option<Box<Statement>>) -> Self {
+        Self { condition, then_branch, else_branch }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Throw {
+    pub expression: Expression,
+}
+
+impl Throw {
     pub fn new(expression: Expression) -> Self {
         Self { expression }
     }
 }
 
+/// `try { ... } catch (e: ErrorType) { ... } finally { ... }`, with any number of catch clauses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Try {
+    pub body: Vec<Statement>,
+    pub catches: Vec<Catch>,
+    pub finally: Option<Vec<Statement>>,
+}
+
+impl Try {
+    pub fn new(body: Vec<Statement>, catches: Vec<Catch>, finally: Option<Vec<Statement>>) -> Self {
+        Self { body, catches, finally }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+    pub name: String,
+    /// The class of errors the clause catches, resolved like a variable so it can be matched at runtime.
+    pub class: Variable,
+    pub body: Vec<Statement>,
+}
+
+impl Catch {
+    pub fn new(name: String, class: Variable, body: Vec<Statement>) -> Self {
+        Self { name, class, body }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct While {
+    pub condition: Expression,
+    pub body: Box<Statement>,
+}
+
+impl While {
+    pub fn new(condition: Expression, body: Box<Statement>) -> Self {
+        Self { condition, body }
+    }
+}
+
+/// `for (name in iterable) body`, over the elements of an array, the keys of a map or the characters of a string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct For {
+    pub name: String,
+    pub iterable: Expression,
+    pub body: Box<Statement>,
+}
+
+impl For {
+    pub fn new(name: String, iterable: Expression, body: Box<Statement>) -> Self {
+        Self { name, iterable, body }
+    }
+}
+
+
+/// A field, method or constructor of a class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassMember {
+    pub visibility: Visibility,
+    /// Whether the member belongs to the class itself, accessed as `ClassName.member`, instead of its instances.
+    pub is_static: bool,
+    pub statement: Statement,
+}
+
+impl ClassMember {
+    pub fn new(visibility: Visibility, is_static: bool, statement: Statement) -> Self {
+        Self { visibility, is_static, statement }
+    }
+}
+
+/// Where a class member can be accessed from, ordered from least to most visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Visibility {
+    /// Only inside the class that declares it.
+    Private,
+    /// Inside the class that declares it and its subclasses.
+    Protected,
+    Public,
+}
+
+impl std::fmt::Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Visibility::Private => write!(f, "private"),
+            Visibility::Protected => write!(f, "protected"),
+            Visibility::Public => write!(f, "public"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConstructorDeclaration {
@@ -142,6 +389,15 @@ pub enum ExpressionKind {
     Member(Member),
     Array(Array),
     Map(Map),
+    Assign(Assign),
+    MethodCall(Box<MethodCall>),
+    New(Box<Instantiation>),
+    Super(Box<SuperCall>),
+    Match(Box<Match>),
+    /// An anonymous function, as in `fn(x: int): int => x * 2`.
+    Lambda(Box<FunctionDeclaration>),
+    /// An explicit conversion, as in `count as float`.
+    Cast(Cast),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -154,6 +410,11 @@ pub enum TypeKind {
     Integer,
     Float,
     Bool,
+    Void,
+    /// A function taking the given parameter types, as in `fn(int, int): int`.
+    Function(Vec<Type>, Box<Type>),
+    /// A class, interface, enum or type parameter, with type arguments as in `Stack<int>`.
+    Named(String, Vec<Type>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -211,7 +472,6 @@ pub enum LiteralKind {
     Float(f64),
     Bool(bool),
     Null,
-    Map(Vec<(Expression, Expression)>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -227,28 +487,166 @@ impl Literal {
     }
 }
 
+/// Where a name lives at runtime, filled in by the resolver.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Binding {
+    /// A slot of the global environment.
+    Global(usize),
+    /// A slot of the environment `depth` levels up from the current one.
+    Local { depth: usize, slot: usize },
+    /// A function of the native registry, by its index there.
+    Native(usize),
+    /// A class of the native registry, by its index there.
+    NativeClass(usize),
+    /// A constant of the native registry, by its index there.
+    NativeConstant(usize),
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Variable {
     pub name: String,
     pub line: usize,
     pub column: usize,
+    pub binding: Option<Binding>,
 }
 
 impl Variable {
     pub fn new(name: String, line: usize, column: usize) -> Self {
-        Self { name, line, column }
+        Self { name, line, column, binding: None }
     }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Call {
-    pub callee: String,
+    pub callee: Expression,
+    /// Type arguments given explicitly, as in `first<int>(xs)`, or empty to have them inferred.
+    pub type_arguments: Vec<Type>,
     pub arguments: Vec<Expression>,
 }
 
 impl Call {
-    pub fn new(callee: String, arguments: Vec<Expression>) -> Self {
-        Self { callee, arguments }
+    pub fn new(callee: Expression, type_arguments: Vec<Type>, arguments: Vec<Expression>) -> Self {
+        Self { callee, type_arguments, arguments }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MethodCall {
+    pub receiver: Expression,
+    pub name: String,
+    /// Type arguments given explicitly, or empty to have them inferred.
+    pub type_arguments: Vec<Type>,
+    pub arguments: Vec<Expression>,
+}
+
+impl MethodCall {
+    pub fn new(receiver: Expression, name: String, type_arguments: Vec<Type>, arguments: Vec<Expression>) -> Self {
+        Self { receiver, name, type_arguments, arguments }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instantiation {
+    pub class: Expression,
+    pub type_arguments: Vec<Type>,
+    pub arguments: Vec<Expression>,
+}
+
+impl Instantiation {
+    pub fn new(class: Expression, type_arguments: Vec<Type>, arguments: Vec<Expression>) -> Self {
+        Self { class, type_arguments, arguments }
+    }
+}
+
+/// `super(...)` when `method` is `None`, otherwise `super.method(...)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuperCall {
+    pub method: Option<String>,
+    pub arguments: Vec<Expression>,
+    pub binding: Option<Binding>,
+}
+
+impl SuperCall {
+    pub fn new(method: Option<String>, arguments: Vec<Expression>) -> Self {
+        Self { method, arguments, binding: None }
+    }
+}
+
+/// Evaluates the body of the first arm whose pattern matches the subject and whose guard holds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub subject: Expression,
+    pub arms: Vec<MatchArm>,
+}
+
+impl Match {
+    pub fn new(subject: Expression, arms: Vec<MatchArm>) -> Self {
+        Self { subject, arms }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+}
+
+impl MatchArm {
+    pub fn new(pattern: Pattern, guard: Option<Expression>, body: Expression) -> Self {
+        Self { pattern, guard, body }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, line: usize, column: usize) -> Self {
+        Self { kind, line, column }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    /// `_`, which matches anything.
+    Wildcard,
+    Literal(Literal),
+    Range(RangePattern),
+    /// A name, which matches anything and binds it.
+    Binding(String),
+    Variant(VariantPattern),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangePattern {
+    pub start: Literal,
+    pub end: Literal,
+    /// Whether the range was written `start..=end` rather than `start..end`.
+    pub inclusive: bool,
+}
+
+impl RangePattern {
+    pub fn new(start: Literal, end: Literal, inclusive: bool) -> Self {
+        Self { start, end, inclusive }
+    }
+}
+
+/// `Enum.Variant(patterns...)`, matching a variant and destructuring its payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantPattern {
+    pub enum_: Variable,
+    pub variant: String,
+    pub fields: Vec<Pattern>,
+}
+
+impl VariantPattern {
+    pub fn new(enum_: Variable, variant: String, fields: Vec<Pattern>) -> Self {
+        Self { enum_, variant, fields }
     }
 }
 
@@ -302,6 +700,30 @@ impl Binary {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assign {
+    pub target: Box<Expression>,
+    pub value: Box<Expression>,
+}
+
+impl Assign {
+    pub fn new(target: Box<Expression>, value: Box<Expression>) -> Self {
+        Self { target, value }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub expression: Box<Expression>,
+    pub type_: Type,
+}
+
+impl Cast {
+    pub fn new(expression: Box<Expression>, type_: Type) -> Self {
+        Self { expression, type_ }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Unary {
     pub operator: TokenKind,
@@ -314,322 +736,892 @@ impl Unary {
     }
 }
 
-pub fn parse(tokens: Vec<Token>) -> Vec<Statement> {
+/// Parses a whole file, stopping at the first syntax error.
+pub fn parse(tokens: Vec<Token>) -> Result<Vec<Statement>, Diagnostic> {
     let mut statements = Vec::new();
     let mut index = 0;
-    while index < tokens.len() {
-        let statement = parse_statement(&tokens, &mut index);
+    while tokens[index].kind != TokenKind::Eof {
+        let statement = parse_statement(&tokens, &mut index)?;
         statements.push(statement);
     }
-    statements
+    Ok(statements)
 }
-pub fn parse_statement(tokens: &Vec<Token>, index: &mut usize) -> Statement {
+pub fn parse_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
     let token = &tokens[*index];
+    let _nesting = Nesting::enter(token)?;
     match token.kind {
-        TokenKind::VarKw => {
-            let statement = parse_var_statement(tokens, index);
-            statement
-        }
+        TokenKind::VarKw => parse_var_statement(tokens, index),
         TokenKind::ConstKw => {
-            expectc(tokens, index, TokenKind::ConstKw);
-            let statement = parse_var_statement(tokens, index);
-            return Statement::new(StatementKind::ConstantDeclaration(ConstantDeclaration::new(Box::new(statement))), token.line, token.column);
+            expectc(tokens, index, TokenKind::ConstKw)?;
+            let statement = parse_var_statement(tokens, index)?;
+            Ok(Statement::new(StatementKind::ConstantDeclaration(ConstantDeclaration::new(Box::new(statement))), token.line, token.column))
         }
-        TokenKind::FnKw => {
-            return parse_function_statement(tokens, index);
+        // `fn(` starts a lambda rather than a declaration.
+        TokenKind::FnKw if tokens[*index + 1].kind != TokenKind::LeftParen => parse_function_statement(tokens, index),
+        TokenKind::ConstructorKw => parse_constructor_statement(tokens, index),
+        TokenKind::ImportKw => parse_import_statement(tokens, index),
+        // `public` on a top-level declaration exports it, and `private` spells out the default.
+        TokenKind::ExportKw | TokenKind::PublicKw => {
+            *index += 1;
+            let statement = parse_statement(tokens, index)?;
+            Ok(Statement::new(StatementKind::Export(Box::new(statement)), token.line, token.column))
+        }
+        TokenKind::PrivateKw => {
+            expectc(tokens, index, TokenKind::PrivateKw)?;
+            parse_statement(tokens, index)
         }
-        TokenKind::ConstructorKw => {
-            return parse_constructor_statement(tokens, index);
+        TokenKind::ProtectedKw => syntax_error("`protected` only applies to class members".to_string(), token),
+        TokenKind::ClassKw => parse_class_statement(tokens, index),
+        TokenKind::InterfaceKw => parse_interface_statement(tokens, index),
+        TokenKind::EnumKw => parse_enum_statement(tokens, index),
+        TokenKind::ReturnKw => parse_return_statement(tokens, index),
+        TokenKind::IfKw => parse_if_statement(tokens, index),
+        TokenKind::WhileKw => parse_while_statement(tokens, index),
+        TokenKind::ForKw => parse_for_statement(tokens, index),
+        TokenKind::ThrowKw => {
+            expectc(tokens, index, TokenKind::ThrowKw)?;
+            let expression = parse_expression(tokens, index)?;
+            expectc(tokens, index, TokenKind::Semicolon)?;
+            Ok(Statement::new(StatementKind::Throw(Throw::new(expression)), token.line, token.column))
         }
-        TokenKind::ClassKw => {
-            return parse_class_statement(tokens, index);
+        TokenKind::TryKw => parse_try_statement(tokens, index),
+        TokenKind::BreakKw => {
+            expectc(tokens, index, TokenKind::BreakKw)?;
+            expectc(tokens, index, TokenKind::Semicolon)?;
+            Ok(Statement::new(StatementKind::Break, token.line, token.column))
         }
-        TokenKind::ReturnKw => {
-            return parse_return_statement(tokens, index);
+        TokenKind::ContinueKw => {
+            expectc(tokens, index, TokenKind::ContinueKw)?;
+            expectc(tokens, index, TokenKind::Semicolon)?;
+            Ok(Statement::new(StatementKind::Continue, token.line, token.column))
+        }
+        TokenKind::LeftBrace => {
+            let statements = parse_block(tokens, index)?;
+            Ok(Statement::new(StatementKind::Block(Block::new(statements)), token.line, token.column))
         }
         _ => {
-            let expression = parse_expression(tokens, index);
-            Statement::new(StatementKind::Expression(expression), token.line, token.column)
+            let expression = parse_expression(tokens, index)?;
+            expectc(tokens, index, TokenKind::Semicolon)?;
+            Ok(Statement::new(StatementKind::Expression(expression), token.line, token.column))
         }
     }
 }
-pub fn parse_function_statement(tokens: &Vec<Token>, index: &mut usize) -> Statement {
-    let token = &tokens[*index];
-    expectc(tokens, index, TokenKind::FnKw);
-    let name = tokens[*index].lexeme.clone();
-    *index += 1;
-    expectc(tokens, index, TokenKind::LeftParen);
-    let mut parameters = Vec::new();
-    while tokens[*index].kind != TokenKind::RightParen {
-        let name = tokens[*index].lexeme.clone();
-        *index += 1;
-        expectc(tokens, index, TokenKind::Colon);
-        let type_ = parse_type(tokens, index);
-        parameters.push(Parameter::new(name, type_));
-        if tokens[*index].kind == TokenKind::Comma {
-            expectc(tokens, index, TokenKind::Comma);
-        }
-    }
-    expectc(tokens, index, TokenKind::RightParen);
-    expectc(tokens, index, TokenKind::Colon);
-    let return_type = parse_type(tokens, index);
-    expectc(tokens, index, TokenKind::LeftBrace);
+pub fn parse_block(tokens: &[Token], index: &mut usize) -> Result<Vec<Statement>, Diagnostic> {
+    expectc(tokens, index, TokenKind::LeftBrace)?;
     let mut body = Vec::new();
     while tokens[*index].kind != TokenKind::RightBrace {
-        let statement = parse_statement(tokens, index);
+        if tokens[*index].kind == TokenKind::Eof {
+            return syntax_error("Unexpected end of file, expected RightBrace".to_string(), &tokens[*index]);
+        }
+        let statement = parse_statement(tokens, index)?;
         body.push(statement);
     }
-    expectc(tokens, index, TokenKind::RightBrace);
-    Statement::new(StatementKind::FunctionDeclaration(FunctionDeclaration::new(name, parameters, return_type, body)), token.line, token.column)
+    expectc(tokens, index, TokenKind::RightBrace)?;
+    Ok(body)
 }
-pub fn parse_constructor_statement(tokens: &Vec<Token>, index: &mut usize) -> Statement {
-    let token = &tokens[*index];
-    expectc(tokens, index, TokenKind::ConstructorKw);
-    expectc(tokens, index, TokenKind::LeftParen);
+pub fn parse_parameters(tokens: &[Token], index: &mut usize) -> Result<Vec<Parameter>, Diagnostic> {
+    expectc(tokens, index, TokenKind::LeftParen)?;
     let mut parameters = Vec::new();
     while tokens[*index].kind != TokenKind::RightParen {
-        let name = tokens[*index].lexeme.clone();
-        *index += 1;
-        expectc(tokens, index, TokenKind::Colon);
-        let type_ = parse_type(tokens, index);
+        let name = expect_identifier(tokens, index)?;
+        expectc(tokens, index, TokenKind::Colon)?;
+        let type_ = parse_type(tokens, index)?;
         parameters.push(Parameter::new(name, type_));
-        if tokens[*index].kind == TokenKind::Comma {
-            expectc(tokens, index, TokenKind::Comma);
+        if !matchc(tokens, index, TokenKind::Comma) {
+            break;
+        }
+    }
+    expectc(tokens, index, TokenKind::RightParen)?;
+    Ok(parameters)
+}
+/// Parses `<T, U>` after a class or function name, if present.
+pub fn parse_type_parameters(tokens: &[Token], index: &mut usize) -> Result<Vec<String>, Diagnostic> {
+    let mut type_parameters = Vec::new();
+    if matchc(tokens, index, TokenKind::Less) {
+        loop {
+            type_parameters.push(expect_identifier(tokens, index)?);
+            if !matchc(tokens, index, TokenKind::Comma) {
+                break;
+            }
+        }
+        expectc(tokens, index, TokenKind::Greater)?;
+    }
+    Ok(type_parameters)
+}
+/// Parses `<int, string>` after a type name, if present.
+pub fn parse_type_arguments(tokens: &[Token], index: &mut usize) -> Result<Vec<Type>, Diagnostic> {
+    let mut type_arguments = Vec::new();
+    if matchc(tokens, index, TokenKind::Less) {
+        loop {
+            type_arguments.push(parse_type(tokens, index)?);
+            if !matchc(tokens, index, TokenKind::Comma) {
+                break;
+            }
+        }
+        expectc(tokens, index, TokenKind::Greater)?;
+    }
+    Ok(type_arguments)
+}
+pub fn parse_function_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::FnKw)?;
+    let name = expect_identifier(tokens, index)?;
+    let type_parameters = parse_type_parameters(tokens, index)?;
+    let parameters = parse_parameters(tokens, index)?;
+    expectc(tokens, index, TokenKind::Colon)?;
+    let return_type = parse_type(tokens, index)?;
+    let body = parse_block(tokens, index)?;
+    Ok(Statement::new(StatementKind::FunctionDeclaration(FunctionDeclaration::new(name, type_parameters, parameters, return_type, body)), token.line, token.column))
+}
+pub fn parse_import_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::ImportKw)?;
+    let path = match &tokens[*index].kind {
+        TokenKind::String(file) => {
+            *index += 1;
+            ImportPath::File(file.clone())
+        }
+        _ => {
+            let mut segments = vec![expect_identifier(tokens, index)?];
+            while matchc(tokens, index, TokenKind::Dot) {
+                segments.push(expect_identifier(tokens, index)?);
+            }
+            ImportPath::Module(segments)
+        }
+    };
+    expectc(tokens, index, TokenKind::Semicolon)?;
+    Ok(Statement::new(StatementKind::Import(Import::new(path)), token.line, token.column))
+}
+pub fn parse_constructor_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::ConstructorKw)?;
+    let parameters = parse_parameters(tokens, index)?;
+    let body = parse_block(tokens, index)?;
+    Ok(Statement::new(StatementKind::ConstructorDeclaration(ConstructorDeclaration::new(parameters, body)), token.line, token.column))
+}
+pub fn parse_class_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::ClassKw)?;
+    let name = expect_identifier(tokens, index)?;
+    let type_parameters = parse_type_parameters(tokens, index)?;
+    let mut superclass_type_arguments = Vec::new();
+    let superclass = if matchc(tokens, index, TokenKind::ExtendsKw) {
+        let token = &tokens[*index];
+        let superclass = Variable::new(expect_qualified_identifier(tokens, index)?, token.line, token.column);
+        superclass_type_arguments = parse_type_arguments(tokens, index)?;
+        Some(superclass)
+    } else {
+        None
+    };
+    let mut interfaces = Vec::new();
+    if matchc(tokens, index, TokenKind::ImplementsKw) {
+        loop {
+            let token = &tokens[*index];
+            interfaces.push(Variable::new(expect_qualified_identifier(tokens, index)?, token.line, token.column));
+            if !matchc(tokens, index, TokenKind::Comma) {
+                break;
+            }
         }
     }
-    expectc(tokens, index, TokenKind::RightParen);
-    expectc(tokens, index, TokenKind::LeftBrace);
+    expectc(tokens, index, TokenKind::LeftBrace)?;
     let mut body = Vec::new();
     while tokens[*index].kind != TokenKind::RightBrace {
-        let statement = parse_statement(tokens, index);
-        body.push(statement);
+        body.push(parse_class_member(tokens, index)?);
     }
-    expectc(tokens, index, TokenKind::RightBrace);
-    Statement::new(StatementKind::ConstructorDeclaration(ConstructorDeclaration::new(parameters, body)), token.line, token.column)
+    expectc(tokens, index, TokenKind::RightBrace)?;
+    Ok(Statement::new(StatementKind::ClassDeclaration(ClassDeclaration::new(name, type_parameters, superclass, superclass_type_arguments, interfaces, body)), token.line, token.column))
 }
-pub fn parse_class_statement(tokens: &Vec<Token>, index: &mut usize) -> Statement {
+pub fn parse_interface_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
     let token = &tokens[*index];
-    expectc(tokens, index, TokenKind::ClassKw);
-    let name = tokens[*index].lexeme.clone();
-    *index += 1;
-    expectc(tokens, index, TokenKind::LeftBrace);
-    let mut body = Vec::new();
+    expectc(tokens, index, TokenKind::InterfaceKw)?;
+    let name = expect_identifier(tokens, index)?;
+    expectc(tokens, index, TokenKind::LeftBrace)?;
+    let mut methods = Vec::new();
     while tokens[*index].kind != TokenKind::RightBrace {
-        let statement = parse_statement(tokens, index);
-        body.push(statement);
+        // Interface methods are signatures only, terminated by a semicolon instead of a body.
+        let method = &tokens[*index];
+        expectc(tokens, index, TokenKind::FnKw)?;
+        let method_name = expect_identifier(tokens, index)?;
+        let parameters = parse_parameters(tokens, index)?;
+        expectc(tokens, index, TokenKind::Colon)?;
+        let return_type = parse_type(tokens, index)?;
+        expectc(tokens, index, TokenKind::Semicolon)?;
+        methods.push(MethodSignature::new(method_name, parameters, return_type, method.line, method.column));
+    }
+    expectc(tokens, index, TokenKind::RightBrace)?;
+    Ok(Statement::new(StatementKind::InterfaceDeclaration(InterfaceDeclaration::new(name, methods)), token.line, token.column))
+}
+pub fn parse_enum_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::EnumKw)?;
+    let name = expect_identifier(tokens, index)?;
+    expectc(tokens, index, TokenKind::LeftBrace)?;
+    let mut variants = Vec::new();
+    while tokens[*index].kind != TokenKind::RightBrace {
+        let variant = &tokens[*index];
+        let variant_name = expect_identifier(tokens, index)?;
+        let mut payload = Vec::new();
+        if matchc(tokens, index, TokenKind::LeftParen) {
+            while tokens[*index].kind != TokenKind::RightParen {
+                payload.push(parse_type(tokens, index)?);
+                if !matchc(tokens, index, TokenKind::Comma) {
+                    break;
+                }
+            }
+            expectc(tokens, index, TokenKind::RightParen)?;
+        }
+        variants.push(EnumVariant::new(variant_name, payload, variant.line, variant.column));
+        if !matchc(tokens, index, TokenKind::Comma) {
+            break;
+        }
     }
-    expectc(tokens, index, TokenKind::RightBrace);
-    Statement::new(StatementKind::ClassDeclaration(ClassDeclaration::new(name, body)), token.line, token.column)
+    expectc(tokens, index, TokenKind::RightBrace)?;
+    Ok(Statement::new(StatementKind::EnumDeclaration(EnumDeclaration::new(name, variants)), token.line, token.column))
 }
-pub fn parse_var_statement(tokens: &Vec<Token>, index: &mut usize) -> Statement {
+pub fn parse_class_member(tokens: &[Token], index: &mut usize) -> Result<ClassMember, Diagnostic> {
+    // Members are public unless marked otherwise.
+    let visibility = parse_visibility(tokens, index).unwrap_or(Visibility::Public);
+    let is_static = matchc(tokens, index, TokenKind::StaticKw);
     let token = &tokens[*index];
-    expectc(tokens, index, TokenKind::VarKw);
-    let name = tokens[*index].lexeme.clone();
+    match token.kind {
+        TokenKind::ConstructorKw if is_static => syntax_error("Constructors cannot be static".to_string(), token),
+        TokenKind::VarKw | TokenKind::ConstKw | TokenKind::FnKw | TokenKind::ConstructorKw => {
+            Ok(ClassMember::new(visibility, is_static, parse_statement(tokens, index)?))
+        }
+        _ => syntax_error(format!("Expected field, method or constructor, found {:?}", token.kind), token),
+    }
+}
+pub fn parse_visibility(tokens: &[Token], index: &mut usize) -> Option<Visibility> {
+    let visibility = match tokens[*index].kind {
+        TokenKind::PublicKw => Visibility::Public,
+        TokenKind::PrivateKw => Visibility::Private,
+        TokenKind::ProtectedKw => Visibility::Protected,
+        _ => return None,
+    };
     *index += 1;
-    expectc(tokens, index, TokenKind::Colon);
-    let type_ = parse_type(tokens, index);
-    expectc(tokens, index, TokenKind::Equal);
-    let initializer = Some(parse_expression(tokens, index));
-    expectc(tokens, index, TokenKind::Semicolon);
-    Statement::new(
+    Some(visibility)
+}
+pub fn parse_var_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::VarKw)?;
+    let name = expect_identifier(tokens, index)?;
+    expectc(tokens, index, TokenKind::Colon)?;
+    let type_ = parse_type(tokens, index)?;
+    // The initializer may only be left out for fields, which the resolver enforces.
+    let initializer = if matchc(tokens, index, TokenKind::Equal) {
+        Some(parse_expression(tokens, index)?)
+    } else {
+        None
+    };
+    expectc(tokens, index, TokenKind::Semicolon)?;
+    Ok(Statement::new(
         StatementKind::VarDeclaration(VarDeclaration::new(name, type_, initializer)),
         token.line,
         token.column,
-    )
+    ))
+}
+pub fn parse_return_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::ReturnKw)?;
+    let expression = if tokens[*index].kind == TokenKind::Semicolon {
+        None
+    } else {
+        Some(parse_expression(tokens, index)?)
+    };
+    expectc(tokens, index, TokenKind::Semicolon)?;
+    Ok(Statement::new(StatementKind::Return(Return::new(expression)), token.line, token.column))
 }
-pub fn parse_return_statement(tokens: &Vec<Token>, index: &mut usize) -> Statement {
+pub fn parse_if_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
     let token = &tokens[*index];
-    expectc(tokens, index, TokenKind::ReturnKw);
-    let expression = parse_expression(tokens, index);
-    expectc(tokens, index, TokenKind::Semicolon);
-    Statement::new(StatementKind::Return(Return::new(expression)), token.line, token.column)
+    expectc(tokens, index, TokenKind::IfKw)?;
+    expectc(tokens, index, TokenKind::LeftParen)?;
+    let condition = parse_expression(tokens, index)?;
+    expectc(tokens, index, TokenKind::RightParen)?;
+    let then_branch = parse_statement(tokens, index)?;
+    let else_branch = if matchc(tokens, index, TokenKind::ElseKw) {
+        Some(Box::new(parse_statement(tokens, index)?))
+    } else {
+        None
+    };
+    Ok(Statement::new(StatementKind::If(If::new(condition, Box::new(then_branch), else_branch)), token.line, token.column))
 }
-pub fn parse_type(tokens: &Vec<Token>, index: &mut usize) -> Type {
+pub fn parse_while_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::WhileKw)?;
+    expectc(tokens, index, TokenKind::LeftParen)?;
+    let condition = parse_expression(tokens, index)?;
+    expectc(tokens, index, TokenKind::RightParen)?;
+    let body = parse_statement(tokens, index)?;
+    Ok(Statement::new(StatementKind::While(While::new(condition, Box::new(body))), token.line, token.column))
+}
+pub fn parse_for_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::ForKw)?;
+    expectc(tokens, index, TokenKind::LeftParen)?;
+    let name = expect_identifier(tokens, index)?;
+    expectc(tokens, index, TokenKind::InKw)?;
+    let iterable = parse_expression(tokens, index)?;
+    expectc(tokens, index, TokenKind::RightParen)?;
+    let body = parse_statement(tokens, index)?;
+    Ok(Statement::new(StatementKind::For(For::new(name, iterable, Box::new(body))), token.line, token.column))
+}
+pub fn parse_try_statement(tokens: &[Token], index: &mut usize) -> Result<Statement, Diagnostic> {
     let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::TryKw)?;
+    let body = parse_block(tokens, index)?;
+    let mut catches = Vec::new();
+    while matchc(tokens, index, TokenKind::CatchKw) {
+        expectc(tokens, index, TokenKind::LeftParen)?;
+        let name = expect_identifier(tokens, index)?;
+        expectc(tokens, index, TokenKind::Colon)?;
+        let class = &tokens[*index];
+        let class = Variable::new(expect_qualified_identifier(tokens, index)?, class.line, class.column);
+        expectc(tokens, index, TokenKind::RightParen)?;
+        catches.push(Catch::new(name, class, parse_block(tokens, index)?));
+    }
+    let finally = if matchc(tokens, index, TokenKind::FinallyKw) {
+        Some(parse_block(tokens, index)?)
+    } else {
+        None
+    };
+    if catches.is_empty() && finally.is_none() {
+        return syntax_error("Expected `catch` or `finally` after `try` block".to_string(), token);
+    }
+    Ok(Statement::new(StatementKind::Try(Try::new(body, catches, finally)), token.line, token.column))
+}
+pub fn parse_type(tokens: &[Token], index: &mut usize) -> Result<Type, Diagnostic> {
+    let token = &tokens[*index];
+    let _nesting = Nesting::enter(token)?;
     match token.kind {
         TokenKind::ArrayKw => {
-            expectc(tokens, index, TokenKind::ArrayKw);
-            expectc(tokens, index, TokenKind::Less);
-            let type_ = parse_type(tokens, index);
-            expectc(tokens, index, TokenKind::Greater);
-            Type::new(TypeKind::Array(Box::new(type_)), token.line, token.column)
+            expectc(tokens, index, TokenKind::ArrayKw)?;
+            expectc(tokens, index, TokenKind::Less)?;
+            let type_ = parse_type(tokens, index)?;
+            expectc(tokens, index, TokenKind::Greater)?;
+            Ok(Type::new(TypeKind::Array(Box::new(type_)), token.line, token.column))
         }
         TokenKind::MapKw => {
-            expectc(tokens, index, TokenKind::LeftBrace);
-            let key_type = parse_type(tokens, index);
-            expectc(tokens, index, TokenKind::Colon);
-            let value_type = parse_type(tokens, index);
-            expectc(tokens, index, TokenKind::RightBrace);
-            *index += 1;
-            Type::new(TypeKind::Map(Box::new(key_type), Box::new(value_type)), token.line, token.column)
+            expectc(tokens, index, TokenKind::MapKw)?;
+            expectc(tokens, index, TokenKind::Less)?;
+            let key_type = parse_type(tokens, index)?;
+            expectc(tokens, index, TokenKind::Comma)?;
+            let value_type = parse_type(tokens, index)?;
+            expectc(tokens, index, TokenKind::Greater)?;
+            Ok(Type::new(TypeKind::Map(Box::new(key_type), Box::new(value_type)), token.line, token.column))
         }
         TokenKind::StringKw => {
             *index += 1;
-            Type::new(TypeKind::String, token.line, token.column)
+            Ok(Type::new(TypeKind::String, token.line, token.column))
         }
         TokenKind::CharKw => {
             *index += 1;
-            Type::new(TypeKind::Char, token.line, token.column)
+            Ok(Type::new(TypeKind::Char, token.line, token.column))
         }
         TokenKind::IntegerKw => {
             *index += 1;
-            Type::new(TypeKind::Integer, token.line, token.column)
+            Ok(Type::new(TypeKind::Integer, token.line, token.column))
         }
         TokenKind::FloatKw => {
             *index += 1;
-            Type::new(TypeKind::Float, token.line, token.column)
+            Ok(Type::new(TypeKind::Float, token.line, token.column))
         }
         TokenKind::BoolKw => {
             *index += 1;
-            Type::new(TypeKind::Bool, token.line, token.column)
+            Ok(Type::new(TypeKind::Bool, token.line, token.column))
         }
-        _ => {
-            panic!("Expected type, found {:?}", token.kind);
+        TokenKind::VoidKw => {
+            *index += 1;
+            Ok(Type::new(TypeKind::Void, token.line, token.column))
+        }
+        TokenKind::FnKw => {
+            expectc(tokens, index, TokenKind::FnKw)?;
+            expectc(tokens, index, TokenKind::LeftParen)?;
+            let mut parameters = Vec::new();
+            while tokens[*index].kind != TokenKind::RightParen {
+                parameters.push(parse_type(tokens, index)?);
+                if !matchc(tokens, index, TokenKind::Comma) {
+                    break;
+                }
+            }
+            expectc(tokens, index, TokenKind::RightParen)?;
+            expectc(tokens, index, TokenKind::Colon)?;
+            let return_type = parse_type(tokens, index)?;
+            Ok(Type::new(TypeKind::Function(parameters, Box::new(return_type)), token.line, token.column))
+        }
+        TokenKind::Identifier(_) => {
+            let name = expect_qualified_identifier(tokens, index)?;
+            let type_arguments = parse_type_arguments(tokens, index)?;
+            Ok(Type::new(TypeKind::Named(name, type_arguments), token.line, token.column))
         }
+        _ => syntax_error(format!("Expected type, found {:?}", token.kind), token),
     }
 }
-pub fn parse_expression(tokens: &Vec<Token>, index: &mut usize) -> Expression {
-    let token = &tokens[*index];
-    let mut expression = parse_additive_expression(tokens, index);
+pub fn parse_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    parse_assignment_expression(tokens, index)
+}
+pub fn parse_assignment_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let _nesting = Nesting::enter(&tokens[*index])?;
+    let expression = parse_or_expression(tokens, index)?;
+    if tokens[*index].kind == TokenKind::Equal {
+        let token = &tokens[*index];
+        *index += 1;
+        // Assignment is right associative, so `a = b = c` assigns `c` to both.
+        let value = parse_assignment_expression(tokens, index)?;
+        match expression.kind {
+            ExpressionKind::Variable(_) | ExpressionKind::Index(_) | ExpressionKind::Member(_) => {}
+            _ => return Err(Diagnostic::error("Invalid assignment target".to_string(), expression.line, expression.column)),
+        }
+        return Ok(Expression::new(
+            ExpressionKind::Assign(Assign::new(Box::new(expression), Box::new(value))),
+            token.line,
+            token.column,
+        ));
+    }
+    Ok(expression)
+}
+pub fn parse_or_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let mut nesting = Nesting::default();
+    let mut expression = parse_and_expression(tokens, index)?;
+    while tokens[*index].kind == TokenKind::Or {
+        let token = &tokens[*index];
+        nesting.deeper(token)?;
+        let operator = token.kind.clone();
+        *index += 1;
+        let right = parse_and_expression(tokens, index)?;
+        expression = Expression::new(
+            ExpressionKind::Binary(Binary::new(Box::new(expression), operator, Box::new(right))),
+            token.line,
+            token.column,
+        );
+    }
+    Ok(expression)
+}
+pub fn parse_and_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let mut nesting = Nesting::default();
+    let mut expression = parse_equality_expression(tokens, index)?;
+    while tokens[*index].kind == TokenKind::And {
+        let token = &tokens[*index];
+        nesting.deeper(token)?;
+        let operator = token.kind.clone();
+        *index += 1;
+        let right = parse_equality_expression(tokens, index)?;
+        expression = Expression::new(
+            ExpressionKind::Binary(Binary::new(Box::new(expression), operator, Box::new(right))),
+            token.line,
+            token.column,
+        );
+    }
+    Ok(expression)
+}
+pub fn parse_equality_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let mut nesting = Nesting::default();
+    let mut expression = parse_comparison_expression(tokens, index)?;
     while tokens[*index].kind == TokenKind::EqualEqual || tokens[*index].kind == TokenKind::BangEqual {
-        let operator = tokens[*index].kind.clone();
+        let token = &tokens[*index];
+        nesting.deeper(token)?;
+        let operator = token.kind.clone();
         *index += 1;
-        let right = parse_additive_expression(tokens, index);
+        let right = parse_comparison_expression(tokens, index)?;
         expression = Expression::new(
             ExpressionKind::Binary(Binary::new(Box::new(expression), operator, Box::new(right))),
             token.line,
             token.column,
         );
     }
-    expression
+    Ok(expression)
 }
-pub fn parse_additive_expression(tokens: &Vec<Token>, index: &mut usize) -> Expression {
-    let mut expression = parse_multiplicative_expression(tokens, index);
+pub fn parse_comparison_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let mut nesting = Nesting::default();
+    let mut expression = parse_additive_expression(tokens, index)?;
+    while matches!(tokens[*index].kind, TokenKind::Less | TokenKind::LessEqual | TokenKind::Greater | TokenKind::GreaterEqual) {
+        let token = &tokens[*index];
+        nesting.deeper(token)?;
+        let operator = token.kind.clone();
+        *index += 1;
+        let right = parse_additive_expression(tokens, index)?;
+        expression = Expression::new(
+            ExpressionKind::Binary(Binary::new(Box::new(expression), operator, Box::new(right))),
+            token.line,
+            token.column,
+        );
+    }
+    Ok(expression)
+}
+pub fn parse_additive_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let mut nesting = Nesting::default();
+    let mut expression = parse_multiplicative_expression(tokens, index)?;
     while tokens[*index].kind == TokenKind::Plus || tokens[*index].kind == TokenKind::Minus {
         let token = &tokens[*index];
+        nesting.deeper(token)?;
         let operator = token.kind.clone();
         *index += 1;
-        let right = parse_multiplicative_expression(tokens, index);
+        let right = parse_multiplicative_expression(tokens, index)?;
         expression = Expression::new(
             ExpressionKind::Binary(Binary::new(Box::new(expression), operator, Box::new(right))),
             token.line,
             token.column,
         );
     }
-    expression
+    Ok(expression)
 }
-pub fn parse_multiplicative_expression(tokens: &Vec<Token>, index: &mut usize) -> Expression {
-    let mut expression = parse_unary_expression(tokens, index);
-    while tokens[*index].kind == TokenKind::Star || tokens[*index].kind == TokenKind::Slash {
+pub fn parse_multiplicative_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let mut nesting = Nesting::default();
+    let mut expression = parse_cast_expression(tokens, index)?;
+    while matches!(tokens[*index].kind, TokenKind::Star | TokenKind::Slash | TokenKind::Percent) {
         let token = &tokens[*index];
+        nesting.deeper(token)?;
         let operator = token.kind.clone();
         *index += 1;
-        let right = parse_unary_expression(tokens, index);
+        let right = parse_cast_expression(tokens, index)?;
         expression = Expression::new(
             ExpressionKind::Binary(Binary::new(Box::new(expression), operator, Box::new(right))),
             token.line,
             token.column,
         );
     }
-    expression
+    Ok(expression)
 }
-pub fn parse_unary_expression(tokens: &Vec<Token>, index: &mut usize) -> Expression {
+pub fn parse_cast_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    // `-x as float` converts `-x`, as in Rust.
+    let mut nesting = Nesting::default();
+    let mut expression = parse_unary_expression(tokens, index)?;
+    while tokens[*index].kind == TokenKind::AsKw {
+        let token = &tokens[*index];
+        nesting.deeper(token)?;
+        *index += 1;
+        let type_ = parse_type(tokens, index)?;
+        expression = Expression::new(ExpressionKind::Cast(Cast::new(Box::new(expression), type_)), token.line, token.column);
+    }
+    Ok(expression)
+}
+pub fn parse_unary_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
     let token = &tokens[*index];
+    if token.kind == TokenKind::Minus && is_minimum_magnitude(&tokens[*index + 1]) {
+        *index += 2;
+        let literal = Literal::new(LiteralKind::Integer(i64::MIN), token.line, token.column);
+        return Ok(Expression::new(ExpressionKind::Literal(literal), token.line, token.column));
+    }
     if tokens[*index].kind == TokenKind::Bang || tokens[*index].kind == TokenKind::Minus {
+        let _nesting = Nesting::enter(token)?;
         let operator = tokens[*index].kind.clone();
         *index += 1;
-        let right = parse_unary_expression(tokens, index);
-        Expression::new(
+        let right = parse_unary_expression(tokens, index)?;
+        Ok(Expression::new(
             ExpressionKind::Unary(Unary::new(operator, Box::new(right))),
             token.line,
             token.column,
-        )
+        ))
     } else {
-        parse_primary_expression(tokens, index)
+        parse_postfix_expression(tokens, index)
+    }
+}
+pub fn parse_postfix_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let mut expression = parse_primary_expression(tokens, index)?;
+    let mut nesting = Nesting::default();
+    loop {
+        let token = &tokens[*index];
+        if matches!(token.kind, TokenKind::LeftParen | TokenKind::LeftBracket | TokenKind::Dot) {
+            nesting.deeper(token)?;
+        }
+        match token.kind {
+            TokenKind::LeftParen => {
+                let arguments = parse_arguments(tokens, index)?;
+                let (line, column) = (expression.line, expression.column);
+                expression = Expression::new(ExpressionKind::Call(Box::new(Call::new(expression, Vec::new(), arguments))), line, column);
+            }
+            // `first<int>(xs)`, which is told apart from comparisons by the call that follows the type arguments.
+            TokenKind::Less if matches!(expression.kind, ExpressionKind::Variable(_)) && calls_with_type_arguments(tokens, *index) => {
+                let type_arguments = parse_type_arguments(tokens, index)?;
+                let arguments = parse_arguments(tokens, index)?;
+                let (line, column) = (expression.line, expression.column);
+                expression = Expression::new(ExpressionKind::Call(Box::new(Call::new(expression, type_arguments, arguments))), line, column);
+            }
+            TokenKind::LeftBracket => {
+                *index += 1;
+                let start = parse_expression(tokens, index)?;
+                if matchc(tokens, index, TokenKind::Colon) {
+                    let end = parse_expression(tokens, index)?;
+                    expectc(tokens, index, TokenKind::RightBracket)?;
+                    expression = Expression::new(
+                        ExpressionKind::Slice(Slice::new(Box::new(expression), Box::new(start), Box::new(end))),
+                        token.line,
+                        token.column,
+                    );
+                } else {
+                    expectc(tokens, index, TokenKind::RightBracket)?;
+                    expression = Expression::new(
+                        ExpressionKind::Index(Index::new(Box::new(expression), Box::new(start))),
+                        token.line,
+                        token.column,
+                    );
+                }
+            }
+            TokenKind::Dot => {
+                *index += 1;
+                let name = expect_member_name(tokens, index)?;
+                let calls = tokens[*index].kind == TokenKind::LeftParen;
+                if calls || (tokens[*index].kind == TokenKind::Less && calls_with_type_arguments(tokens, *index)) {
+                    let type_arguments = if calls { Vec::new() } else { parse_type_arguments(tokens, index)? };
+                    let arguments = parse_arguments(tokens, index)?;
+                    expression = Expression::new(
+                        ExpressionKind::MethodCall(Box::new(MethodCall::new(expression, name, type_arguments, arguments))),
+                        token.line,
+                        token.column,
+                    );
+                } else {
+                    expression = Expression::new(
+                        ExpressionKind::Member(Member::new(Box::new(expression), name)),
+                        token.line,
+                        token.column,
+                    );
+                }
+            }
+            _ => break,
+        }
     }
+    Ok(expression)
 }
-pub fn parse_primary_expression(tokens: &Vec<Token>, index: &mut usize) -> Expression {
+/// Whether the `<` at `index` starts type arguments followed by the arguments of a call, rather than a comparison.
+fn calls_with_type_arguments(tokens: &[Token], mut index: usize) -> bool {
+    parse_type_arguments(tokens, &mut index).is_ok() && tokens[index].kind == TokenKind::LeftParen
+}
+pub fn parse_arguments(tokens: &[Token], index: &mut usize) -> Result<Vec<Expression>, Diagnostic> {
+    expectc(tokens, index, TokenKind::LeftParen)?;
+    let mut arguments = Vec::new();
+    while tokens[*index].kind != TokenKind::RightParen {
+        arguments.push(parse_expression(tokens, index)?);
+        if !matchc(tokens, index, TokenKind::Comma) {
+            break;
+        }
+    }
+    expectc(tokens, index, TokenKind::RightParen)?;
+    Ok(arguments)
+}
+pub fn parse_primary_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
     let token = &tokens[*index];
     match token.kind.clone() {
         TokenKind::Identifier(identifier) => {
             *index += 1;
-            if tokens[*index].kind == TokenKind::LeftParen {
-                *index += 1;
-                let expression = parse_call_expression(tokens, index);
-                expectc(tokens, index, TokenKind::RightParen);
-                expression
-            } else {
-                Expression::new(ExpressionKind::Variable(Variable::new(identifier, token.line, token.column)), token.line, token.column)
-            }
+            Ok(Expression::new(ExpressionKind::Variable(Variable::new(identifier, token.line, token.column)), token.line, token.column))
         }
         TokenKind::Integer(integer) => {
             *index += 1;
-            Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Integer(integer), token.line, token.column)), token.line, token.column)
+            Ok(Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Integer(integer), token.line, token.column)), token.line, token.column))
         }
         TokenKind::Float(float) => {
             *index += 1;
-            Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Float(float), token.line, token.column)), token.line, token.column)
+            Ok(Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Float(float), token.line, token.column)), token.line, token.column))
         }
         TokenKind::String(string) => {
             *index += 1;
-            Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::String(string), token.line, token.column)), token.line, token.column)
+            Ok(Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::String(string), token.line, token.column)), token.line, token.column))
         }
         TokenKind::Character(character) => {
             *index += 1;
-            Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Char(character), token.line, token.column)), token.line, token.column)
+            Ok(Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Char(character), token.line, token.column)), token.line, token.column))
         }
         TokenKind::TrueKw => {
             *index += 1;
-            Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Bool(true), token.line, token.column)), token.line, token.column)
+            Ok(Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Bool(true), token.line, token.column)), token.line, token.column))
         }
         TokenKind::FalseKw => {
             *index += 1;
-            Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Bool(false), token.line, token.column)), token.line, token.column)
+            Ok(Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Bool(false), token.line, token.column)), token.line, token.column))
         }
         TokenKind::NullKw => {
             *index += 1;
-            Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Null, token.line, token.column)), token.line, token.column)
+            Ok(Expression::new(ExpressionKind::Literal(Literal::new(LiteralKind::Null, token.line, token.column)), token.line, token.column))
+        }
+        TokenKind::ThisKw => {
+            // `this` is resolved like a variable that every method and constructor declares.
+            *index += 1;
+            Ok(Expression::new(ExpressionKind::Variable(Variable::new("this".to_string(), token.line, token.column)), token.line, token.column))
+        }
+        TokenKind::SuperKw => {
+            *index += 1;
+            let method = if matchc(tokens, index, TokenKind::Dot) {
+                Some(expect_identifier(tokens, index)?)
+            } else {
+                None
+            };
+            let arguments = parse_arguments(tokens, index)?;
+            Ok(Expression::new(ExpressionKind::Super(Box::new(SuperCall::new(method, arguments))), token.line, token.column))
+        }
+        TokenKind::NewKw => {
+            *index += 1;
+            let name = expect_identifier(tokens, index)?;
+            let mut class = Expression::new(ExpressionKind::Variable(Variable::new(name, token.line, token.column)), token.line, token.column);
+            while tokens[*index].kind == TokenKind::Dot {
+                let dot = &tokens[*index];
+                *index += 1;
+                let name = expect_identifier(tokens, index)?;
+                class = Expression::new(ExpressionKind::Member(Member::new(Box::new(class), name)), dot.line, dot.column);
+            }
+            let type_arguments = parse_type_arguments(tokens, index)?;
+            let arguments = parse_arguments(tokens, index)?;
+            Ok(Expression::new(ExpressionKind::New(Box::new(Instantiation::new(class, type_arguments, arguments))), token.line, token.column))
         }
+        TokenKind::MatchKw => parse_match_expression(tokens, index),
+        TokenKind::FnKw => parse_lambda_expression(tokens, index),
         TokenKind::LeftParen => {
             *index += 1;
-            let expression = parse_expression(tokens, index);
-            expectc(tokens, index, TokenKind::RightParen);
-            expression
+            let expression = parse_expression(tokens, index)?;
+            expectc(tokens, index, TokenKind::RightParen)?;
+            Ok(Expression::new(ExpressionKind::Parenthesized(Parenthesized::new(Box::new(expression))), token.line, token.column))
         }
-        _ => {
-            panic!("Expected primary expression, found {:?}", token.kind);
+        TokenKind::LeftBracket => {
+            *index += 1;
+            let mut elements = Vec::new();
+            while tokens[*index].kind != TokenKind::RightBracket {
+                elements.push(parse_expression(tokens, index)?);
+                if !matchc(tokens, index, TokenKind::Comma) {
+                    break;
+                }
+            }
+            expectc(tokens, index, TokenKind::RightBracket)?;
+            Ok(Expression::new(ExpressionKind::Array(Array::new(elements)), token.line, token.column))
         }
+        TokenKind::LeftBrace => {
+            *index += 1;
+            let mut entries = Vec::new();
+            while tokens[*index].kind != TokenKind::RightBrace {
+                let key = parse_expression(tokens, index)?;
+                expectc(tokens, index, TokenKind::Colon)?;
+                let value = parse_expression(tokens, index)?;
+                entries.push((key, value));
+                if !matchc(tokens, index, TokenKind::Comma) {
+                    break;
+                }
+            }
+            expectc(tokens, index, TokenKind::RightBrace)?;
+            Ok(Expression::new(ExpressionKind::Map(Map::new(entries)), token.line, token.column))
+        }
+        TokenKind::Error(message) => syntax_error(message, token),
+        _ => syntax_error(format!("Expected primary expression, found {:?}", token.kind), token),
     }
 }
-pub fn parse_call_expression(tokens: &Vec<Token>, index: &mut usize) -> Expression {
+/// Parses `fn(x: int): int => x * 2`, or a lambda with a block body like a function declaration.
+pub fn parse_lambda_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
     let token = &tokens[*index];
-    let expression = parse_primary_expression(tokens, index);
-    let name = match expression.kind.clone() {
-        ExpressionKind::Variable(variable) => variable.name,
-        _ => panic!("Expected variable, found {:?}", expression.kind),
-    };
-    if tokens[*index].kind == TokenKind::LeftParen {
+    expectc(tokens, index, TokenKind::FnKw)?;
+    let parameters = parse_parameters(tokens, index)?;
+    expectc(tokens, index, TokenKind::Colon)?;
+    let return_type = parse_type(tokens, index)?;
+    let body = if tokens[*index].kind == TokenKind::FatArrow {
+        let arrow = &tokens[*index];
         *index += 1;
-        let mut arguments = Vec::new();
-        while tokens[*index].kind != TokenKind::RightParen {
-            arguments.push(parse_expression(tokens, index));
-            if tokens[*index].kind == TokenKind::Comma {
-                *index += 1;
+        let expression = parse_expression(tokens, index)?;
+        vec![Statement::new(StatementKind::Return(Return::new(Some(expression))), arrow.line, arrow.column)]
+    } else {
+        parse_block(tokens, index)?
+    };
+    let lambda = FunctionDeclaration::new("lambda".to_string(), Vec::new(), parameters, return_type, body);
+    Ok(Expression::new(ExpressionKind::Lambda(Box::new(lambda)), token.line, token.column))
+}
+pub fn parse_match_expression(tokens: &[Token], index: &mut usize) -> Result<Expression, Diagnostic> {
+    let token = &tokens[*index];
+    expectc(tokens, index, TokenKind::MatchKw)?;
+    expectc(tokens, index, TokenKind::LeftParen)?;
+    let subject = parse_expression(tokens, index)?;
+    expectc(tokens, index, TokenKind::RightParen)?;
+    expectc(tokens, index, TokenKind::LeftBrace)?;
+    let mut arms = Vec::new();
+    while tokens[*index].kind != TokenKind::RightBrace {
+        let pattern = parse_pattern(tokens, index)?;
+        let guard = if matchc(tokens, index, TokenKind::IfKw) {
+            Some(parse_expression(tokens, index)?)
+        } else {
+            None
+        };
+        expectc(tokens, index, TokenKind::FatArrow)?;
+        let body = parse_expression(tokens, index)?;
+        arms.push(MatchArm::new(pattern, guard, body));
+        if !matchc(tokens, index, TokenKind::Comma) {
+            break;
+        }
+    }
+    expectc(tokens, index, TokenKind::RightBrace)?;
+    Ok(Expression::new(ExpressionKind::Match(Box::new(Match::new(subject, arms))), token.line, token.column))
+}
+pub fn parse_pattern(tokens: &[Token], index: &mut usize) -> Result<Pattern, Diagnostic> {
+    let token = &tokens[*index];
+    let _nesting = Nesting::enter(token)?;
+    match token.kind.clone() {
+        TokenKind::Underscore => {
+            *index += 1;
+            Ok(Pattern::new(PatternKind::Wildcard, token.line, token.column))
+        }
+        TokenKind::Identifier(name) => {
+            *index += 1;
+            if !matchc(tokens, index, TokenKind::Dot) {
+                return Ok(Pattern::new(PatternKind::Binding(name), token.line, token.column));
+            }
+            let mut name = name;
+            let mut variant = expect_identifier(tokens, index)?;
+            // `shapes.Shape.Circle` names a variant of an enum from an imported module.
+            if matchc(tokens, index, TokenKind::Dot) {
+                name = format!("{}.{}", name, variant);
+                variant = expect_identifier(tokens, index)?;
             }
+            let enum_ = Variable::new(name, token.line, token.column);
+            let mut fields = Vec::new();
+            if matchc(tokens, index, TokenKind::LeftParen) {
+                while tokens[*index].kind != TokenKind::RightParen {
+                    fields.push(parse_pattern(tokens, index)?);
+                    if !matchc(tokens, index, TokenKind::Comma) {
+                        break;
+                    }
+                }
+                expectc(tokens, index, TokenKind::RightParen)?;
+            }
+            Ok(Pattern::new(PatternKind::Variant(VariantPattern::new(enum_, variant, fields)), token.line, token.column))
+        }
+        _ => {
+            let start = parse_pattern_literal(tokens, index)?;
+            let inclusive = match tokens[*index].kind {
+                TokenKind::DotDot => false,
+                TokenKind::DotDotEqual => true,
+                _ => return Ok(Pattern::new(PatternKind::Literal(start), token.line, token.column)),
+            };
+            *index += 1;
+            let end = parse_pattern_literal(tokens, index)?;
+            Ok(Pattern::new(PatternKind::Range(RangePattern::new(start, end, inclusive)), token.line, token.column))
         }
-        *index += 1;
-        Expression::new(
-            ExpressionKind::Call(Box::new(Call::new(name, arguments))),
-            token.line,
-            token.column,
-        )
-    } else {
-        expression
     }
-} 
+}
+pub fn parse_pattern_literal(tokens: &[Token], index: &mut usize) -> Result<Literal, Diagnostic> {
+    let token = &tokens[*index];
+    *index += 1;
+    let kind = match token.kind.clone() {
+        TokenKind::Integer(integer) => LiteralKind::Integer(integer),
+        TokenKind::Float(float) => LiteralKind::Float(float),
+        TokenKind::Minus => match tokens[*index].kind {
+            _ if is_minimum_magnitude(&tokens[*index]) => {
+                *index += 1;
+                LiteralKind::Integer(i64::MIN)
+            }
+            TokenKind::Integer(integer) => {
+                *index += 1;
+                LiteralKind::Integer(-integer)
+            }
+            TokenKind::Float(float) => {
+                *index += 1;
+                LiteralKind::Float(-float)
+            }
+            _ => return syntax_error("Expected number after `-` in pattern".to_string(), token),
+        },
+        TokenKind::String(string) => LiteralKind::String(string),
+        TokenKind::Character(character) => LiteralKind::Char(character),
+        TokenKind::TrueKw => LiteralKind::Bool(true),
+        TokenKind::FalseKw => LiteralKind::Bool(false),
+        TokenKind::NullKw => LiteralKind::Null,
+        _ => return syntax_error(format!("Expected pattern, found {:?}", token.kind), token),
+    };
+    Ok(Literal::new(kind, token.line, token.column))
+}
 
 // --- Dumping AST ---
-pub fn dump_ast(ast: &Vec<Statement>) {
+pub fn dump_ast(ast: &[Statement]) {
     for statement in ast {
         dump_statement(statement, 0);
     }
@@ -660,6 +1652,14 @@ pub fn dump_statement(statement: &Statement, indent: usize) {
             println!("{}ClassDeclaration", indent_string);
             dump_class_declaration(class_declaration, indent + 1);
         }
+        StatementKind::InterfaceDeclaration(interface_declaration) => {
+            println!("{}InterfaceDeclaration", indent_string);
+            dump_interface_declaration(interface_declaration, indent + 1);
+        }
+        StatementKind::EnumDeclaration(enum_declaration) => {
+            println!("{}EnumDeclaration", indent_string);
+            dump_enum_declaration(enum_declaration, indent + 1);
+        }
         StatementKind::Return(return_statement) => {
             println!("{}Return", indent_string);
             dump_return(return_statement, indent + 1);
@@ -668,6 +1668,40 @@ pub fn dump_statement(statement: &Statement, indent: usize) {
             println!("{}ConstructorDeclaration", indent_string);
             dump_constructor_declaration(constructor_declaration, indent + 1);
         }
+        StatementKind::Import(import) => {
+            println!("{}Import: {:?}", indent_string, import.path);
+        }
+        StatementKind::Export(statement) => {
+            println!("{}Export", indent_string);
+            dump_statement(statement, indent + 1);
+        }
+        StatementKind::Block(block) => {
+            println!("{}Block", indent_string);
+            for statement in &block.statements {
+                dump_statement(statement, indent + 1);
+            }
+        }
+        StatementKind::If(if_statement) => {
+            println!("{}If", indent_string);
+            dump_if(if_statement, indent + 1);
+        }
+        StatementKind::While(while_statement) => {
+            println!("{}While", indent_string);
+            dump_while(while_statement, indent + 1);
+        }
+        StatementKind::For(for_statement) => {
+            println!("{}For {}", indent_string, for_statement.name);
+            dump_expression(&for_statement.iterable, indent + 1);
+            dump_statement(&for_statement.body, indent + 1);
+        }
+        StatementKind::Throw(throw) => {
+            println!("{}Throw", indent_string);
+            dump_expression(&throw.expression, indent + 1);
+        }
+        StatementKind::Try(try_statement) => {
+            println!("{}Try", indent_string);
+            dump_try(try_statement, indent + 1);
+        }
         _ => {
             println!("{}Statement", indent_string);
         }
@@ -711,6 +1745,49 @@ pub fn dump_expression(expression: &Expression, indent: usize) {
             println!("{}Parenthesized", indent_string);
             dump_parenthesized(parenthesized, indent + 1);
         }
+        ExpressionKind::Assign(assign) => {
+            println!("{}Assign", indent_string);
+            dump_assign(assign, indent + 1);
+        }
+        ExpressionKind::Member(member) => {
+            println!("{}Member: {}", indent_string, member.name);
+            dump_expression(&member.callee, indent + 1);
+        }
+        ExpressionKind::MethodCall(method_call) => {
+            println!("{}MethodCall: {}", indent_string, method_call.name);
+            dump_expression(&method_call.receiver, indent + 1);
+            for argument in &method_call.arguments {
+                dump_expression(argument, indent + 1);
+            }
+        }
+        ExpressionKind::New(new) => {
+            println!("{}New", indent_string);
+            dump_expression(&new.class, indent + 1);
+            for argument in &new.arguments {
+                dump_expression(argument, indent + 1);
+            }
+        }
+        ExpressionKind::Match(match_expression) => {
+            println!("{}Match", indent_string);
+            dump_expression(&match_expression.subject, indent + 1);
+            for arm in &match_expression.arms {
+                println!("{}  Arm: {:?}", indent_string, arm.pattern.kind);
+                if let Some(guard) = &arm.guard {
+                    println!("{}  Guard:", indent_string);
+                    dump_expression(guard, indent + 2);
+                }
+                dump_expression(&arm.body, indent + 2);
+            }
+        }
+        ExpressionKind::Lambda(lambda) => {
+            println!("{}Lambda", indent_string);
+            dump_function_declaration(lambda, indent + 1);
+        }
+        ExpressionKind::Cast(cast) => {
+            println!("{}Cast", indent_string);
+            dump_expression(&cast.expression, indent + 1);
+            println!("{}  Type: {:?}", indent_string, cast.type_.kind);
+        }
         _ => {
             println!("{}Expression", indent_string);
         }
@@ -723,8 +1800,10 @@ pub fn dump_variable_declaration(variable_declaration: &VarDeclaration, indent:
     }
     println!("{}Identifier: {}", indent_string, variable_declaration.name);
     println!("{}Type: {:?}", indent_string, variable_declaration.type_.kind);
-    println!("{}Initializer:", indent_string);
-    dump_expression(&variable_declaration.initializer.as_ref().unwrap().clone(), indent + 1);
+    if let Some(initializer) = &variable_declaration.initializer {
+        println!("{}Initializer:", indent_string);
+        dump_expression(initializer, indent + 1);
+    }
 }
 pub fn dump_function_declaration(function_declaration: &FunctionDeclaration, indent: usize) {
     let mut indent_string = String::new();
@@ -732,6 +1811,9 @@ pub fn dump_function_declaration(function_declaration: &FunctionDeclaration, ind
         indent_string.push_str("  ");
     }
     println!("{}Identifier: {}", indent_string, function_declaration.name);
+    if !function_declaration.type_parameters.is_empty() {
+        println!("{}Type parameters: {}", indent_string, function_declaration.type_parameters.join(", "));
+    }
     println!("{}Parameters:", indent_string);
     for parameter in &function_declaration.parameters {
         dump_parameter(parameter, indent + 1);
@@ -761,9 +1843,46 @@ pub fn dump_class_declaration(class_declaration: &ClassDeclaration, indent: usiz
         indent_string.push_str("  ");
     }
     println!("{}Identifier: {}", indent_string, class_declaration.name);
+    if !class_declaration.type_parameters.is_empty() {
+        println!("{}Type parameters: {}", indent_string, class_declaration.type_parameters.join(", "));
+    }
+    if let Some(superclass) = &class_declaration.superclass {
+        println!("{}Superclass: {}", indent_string, superclass.name);
+        for type_argument in &class_declaration.superclass_type_arguments {
+            println!("{}Type argument: {:?}", indent_string, type_argument.kind);
+        }
+    }
+    for interface in &class_declaration.interfaces {
+        println!("{}Implements: {}", indent_string, interface.name);
+    }
     println!("{}Body:", indent_string);
     for b in &class_declaration.body {
-        dump_statement(b, indent + 1);
+        println!("{}Visibility: {}{}", indent_string, b.visibility, if b.is_static { ", static" } else { "" });
+        dump_statement(&b.statement, indent + 1);
+    }
+}
+pub fn dump_interface_declaration(interface_declaration: &InterfaceDeclaration, indent: usize) {
+    let mut indent_string = String::new();
+    for _ in 0..indent {
+        indent_string.push_str("  ");
+    }
+    println!("{}Identifier: {}", indent_string, interface_declaration.name);
+    for method in &interface_declaration.methods {
+        println!("{}Method: {}, Return type: {:?}", indent_string, method.name, method.return_type.kind);
+        for parameter in &method.parameters {
+            dump_parameter(parameter, indent + 1);
+        }
+    }
+}
+pub fn dump_enum_declaration(enum_declaration: &EnumDeclaration, indent: usize) {
+    let mut indent_string = String::new();
+    for _ in 0..indent {
+        indent_string.push_str("  ");
+    }
+    println!("{}Identifier: {}", indent_string, enum_declaration.name);
+    for variant in &enum_declaration.variants {
+        let payload: Vec<String> = variant.payload.iter().map(|type_| format!("{:?}", type_.kind)).collect();
+        println!("{}Variant: {}({})", indent_string, variant.name, payload.join(", "));
     }
 }
 pub fn dump_constant_declaration(constant_declaration: &ConstantDeclaration, indent: usize) {
@@ -785,8 +1904,56 @@ pub fn dump_return(return_statement: &Return, indent: usize) {
     for _ in 0..indent {
         indent_string.push_str("  ");
     }
-    println!("{}Value:", indent_string);
-    dump_expression(&return_statement.expression, indent + 1);
+    if let Some(expression) = &return_statement.expression {
+        println!("{}Value:", indent_string);
+        dump_expression(expression, indent + 1);
+    }
+}
+pub fn dump_if(if_statement: &If, indent: usize) {
+    let mut indent_string = String::new();
+    for _ in 0..indent {
+        indent_string.push_str("  ");
+    }
+    println!("{}Condition:", indent_string);
+    dump_expression(&if_statement.condition, indent + 1);
+    println!("{}Then:", indent_string);
+    dump_statement(&if_statement.then_branch, indent + 1);
+    if let Some(else_branch) = &if_statement.else_branch {
+        println!("{}Else:", indent_string);
+        dump_statement(else_branch, indent + 1);
+    }
+}
+pub fn dump_while(while_statement: &While, indent: usize) {
+    let mut indent_string = String::new();
+    for _ in 0..indent {
+        indent_string.push_str("  ");
+    }
+    println!("{}Condition:", indent_string);
+    dump_expression(&while_statement.condition, indent + 1);
+    println!("{}Body:", indent_string);
+    dump_statement(&while_statement.body, indent + 1);
+}
+pub fn dump_try(try_statement: &Try, indent: usize) {
+    let mut indent_string = String::new();
+    for _ in 0..indent {
+        indent_string.push_str("  ");
+    }
+    println!("{}Body:", indent_string);
+    for statement in &try_statement.body {
+        dump_statement(statement, indent + 1);
+    }
+    for catch in &try_statement.catches {
+        println!("{}Catch: {}: {}", indent_string, catch.name, catch.class.name);
+        for statement in &catch.body {
+            dump_statement(statement, indent + 1);
+        }
+    }
+    if let Some(finally) = &try_statement.finally {
+        println!("{}Finally:", indent_string);
+        for statement in finally {
+            dump_statement(statement, indent + 1);
+        }
+    }
 }
 pub fn dump_literal(literal: &Literal, indent: usize) {
     let mut indent_string = String::new();
@@ -812,9 +1979,6 @@ pub fn dump_literal(literal: &Literal, indent: usize) {
         LiteralKind::Null => {
             println!("{}Null", indent_string);
         }
-        _ => {
-            println!("{}Literal", indent_string);
-        }
     }
 }
 pub fn dump_variable(variable: &Variable, indent: usize) {
@@ -849,12 +2013,23 @@ pub fn dump_call(call: &Call, indent: usize) {
     for _ in 0..indent {
         indent_string.push_str("  ");
     }
-    println!("{}Callee: {}", indent_string, call.callee);
+    println!("{}Callee:", indent_string);
+    dump_expression(&call.callee, indent + 1);
     println!("{}Arguments:", indent_string);
     for argument in &call.arguments {
         dump_expression(argument, indent + 1);
     }
 }
+pub fn dump_assign(assign: &Assign, indent: usize) {
+    let mut indent_string = String::new();
+    for _ in 0..indent {
+        indent_string.push_str("  ");
+    }
+    println!("{}Target:", indent_string);
+    dump_expression(&assign.target, indent + 1);
+    println!("{}Value:", indent_string);
+    dump_expression(&assign.value, indent + 1);
+}
 pub fn dump_parenthesized(parenthesized: &Parenthesized, indent: usize) {
     let mut indent_string = String::new();
     for _ in 0..indent {
@@ -895,7 +2070,7 @@ pub fn dump_array(array: &Array, indent: usize) {
 }
 
 // --- Helper functions ---
-pub fn matchc(tokens: &Vec<Token>, index: &mut usize, kind: TokenKind) -> bool {
+pub fn matchc(tokens: &[Token], index: &mut usize, kind: TokenKind) -> bool {
     if tokens[*index].kind == kind {
         *index += 1;
         true
@@ -903,16 +2078,72 @@ pub fn matchc(tokens: &Vec<Token>, index: &mut usize, kind: TokenKind) -> bool {
         false
     }
 }
-pub fn expectc(tokens: &Vec<Token>, index: &mut usize, kind: TokenKind) -> String {
-    if *index >= tokens.len() {
-        panic!("Unexpected end of file");
+pub fn expectc(tokens: &[Token], index: &mut usize, kind: TokenKind) -> Result<String, Diagnostic> {
+    match tokens.get(*index) {
+        Some(token) if token.kind == kind => {
+            *index += 1;
+            Ok(token.lexeme.clone())
+        }
+        Some(token) => syntax_error(format!("Expected {:?}, got {:?}", kind, token.kind), token),
+        None => Err(Diagnostic::error("Unexpected end of file".to_string(), 1, 1)),
+    }
+}
+/// Parses a name that may be qualified by the namespace of an imported module, as in `shapes.Circle`.
+pub fn expect_qualified_identifier(tokens: &[Token], index: &mut usize) -> Result<String, Diagnostic> {
+    let mut name = expect_identifier(tokens, index)?;
+    if matchc(tokens, index, TokenKind::Dot) {
+        name = format!("{}.{}", name, expect_identifier(tokens, index)?);
+    }
+    Ok(name)
+}
+/// The name after a `.`, where the `map` keyword names the method of arrays.
+pub fn expect_member_name(tokens: &[Token], index: &mut usize) -> Result<String, Diagnostic> {
+    if matchc(tokens, index, TokenKind::MapKw) {
+        return Ok("map".to_string());
+    }
+    expect_identifier(tokens, index)
+}
+pub fn expect_identifier(tokens: &[Token], index: &mut usize) -> Result<String, Diagnostic> {
+    match &tokens[*index].kind {
+        TokenKind::Identifier(identifier) => {
+            *index += 1;
+            Ok(identifier.clone())
+        }
+        kind => syntax_error(format!("Expected identifier, got {:?}", kind), &tokens[*index]),
+    }
+}
+/// The levels of nesting a parsing function entered, which it leaves when it returns.
+#[derive(Default)]
+struct Nesting {
+    levels: usize,
+}
+
+impl Nesting {
+    fn enter(token: &Token) -> Result<Nesting, Diagnostic> {
+        let mut nesting = Nesting::default();
+        nesting.deeper(token)?;
+        Ok(nesting)
     }
 
-    if tokens[*index].kind == kind {
-        let token = &tokens[*index];
-        *index += 1;
-        token.lexeme.clone()
-    } else {
-        panic!("Expected {:?} at line {}, column {}, got {:?}", kind, tokens[*index].line, tokens[*index].column, tokens[*index].kind);
+    /// Enters another level at `token`, failing if that nests too deeply. Operators that apply
+    /// to what was parsed before them call this once for each time they are applied.
+    fn deeper(&mut self, token: &Token) -> Result<(), Diagnostic> {
+        let depth = NESTING.get();
+        if depth == MAX_NESTING {
+            return syntax_error(format!("Nested too deeply, more than {} levels", MAX_NESTING), token);
+        }
+        NESTING.set(depth + 1);
+        self.levels += 1;
+        Ok(())
+    }
+}
+
+impl Drop for Nesting {
+    fn drop(&mut self) {
+        NESTING.set(NESTING.get() - self.levels);
     }
-}
\ No newline at end of file
+}
+
+fn syntax_error<T>(message: String, token: &Token) -> Result<T, Diagnostic> {
+    Err(Diagnostic::error(message, token.line, token.column))
+}
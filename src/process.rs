@@ -0,0 +1,29 @@
+use crate::native::{integer, primitive, text, Capability, Natives};
+use crate::parser::{Type, TypeKind};
+use crate::value::Value;
+use std::io::{self, Write};
+
+/// Registers the `process` module, with no arguments until the host sets them.
+pub fn define(natives: &mut Natives) {
+    let string = || primitive(TypeKind::String);
+
+    set_arguments(natives, Vec::new());
+    // Unset variables, and those that are not valid Unicode, read as the default.
+    natives.define_gated(Capability::Environment, "process.env", vec![string(), string()], string(), |arguments| {
+        let (name, default) = (text(&arguments[0])?, text(&arguments[1])?);
+        Ok(Value::string(&std::env::var(name).unwrap_or_else(|_| default.to_string())))
+    });
+    // This ends the host too, so `finally` blocks do not run and embedders rarely want to grant it.
+    natives.define_gated(Capability::Exit, "process.exit", vec![primitive(TypeKind::Integer)], primitive(TypeKind::Void), |arguments| {
+        let code = integer(&arguments[0])?;
+        let code = i32::try_from(code).map_err(|_| format!("Exit status {} is out of range", code))?;
+        io::stdout().flush().map_err(|error| format!("Could not write to stdout: {}", error))?;
+        std::process::exit(code)
+    });
+}
+
+/// Sets the arguments `process.args` returns, which the command line passes after the script.
+pub fn set_arguments(natives: &mut Natives, arguments: Vec<String>) {
+    let strings = Type::new(TypeKind::Array(Box::new(primitive(TypeKind::String))), 0, 0);
+    natives.define("process.args", vec![], strings, move |_| Ok(Value::array(arguments.iter().map(|argument| Value::string(argument)).collect())));
+}
@@ -0,0 +1,623 @@
+use crate::diagnostics::Diagnostic;
+use crate::module::Module;
+use crate::native::Natives;
+use crate::parser::*;
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+struct Declaration {
+    name: String,
+    constant: bool,
+    // Globals are allocated up front, so they additionally track whether their
+    // initializer has run by the time top-level code refers to them.
+    defined: bool,
+}
+
+/// Resolves every name in a program to the environment slot it lives in.
+///
+/// Top-level declarations get a global slot each, in source order, so functions
+/// can refer to each other regardless of where they are declared. Locals get a
+/// slot in the environment of the innermost enclosing block or function, in the
+/// order they are declared, which is the order the interpreter defines them in.
+///
+/// All modules share one global environment, each owning the run of slots its
+/// declarations were given. `shapes.area` resolves straight to the slot of
+/// `area` in the module imported as `shapes`.
+#[derive(Debug, Default)]
+pub struct Resolver {
+    globals: Vec<Declaration>,
+    /// Where each global is declared, indexed by slot.
+    declared_at: Vec<(usize, usize)>,
+    modules: Vec<ModuleScope>,
+    /// Index of the module being resolved.
+    module: usize,
+    scopes: Vec<Vec<Declaration>>,
+    functions: usize,
+    loops: usize,
+    /// Whether a static method is being resolved, where neither `this` nor `super` exist.
+    in_static: bool,
+    /// Names of the native functions, by their index in the registry.
+    natives: Vec<String>,
+    /// Names of the host classes, by their index in the registry.
+    native_classes: Vec<String>,
+    /// Names of the native constants, by their index in the registry.
+    native_constants: Vec<String>,
+    errors: Vec<(usize, Diagnostic)>,
+}
+
+#[derive(Debug, Default)]
+struct ModuleScope {
+    name: String,
+    globals: Range<usize>,
+    exports: Vec<String>,
+    imports: Vec<(String, usize)>,
+    native_imports: Vec<(String, String)>,
+}
+
+/// Resolves the modules of a program, which must be in dependency order.
+///
+/// Errors are paired with the index of the module they were found in.
+pub fn resolve(modules: &mut [Module], natives: &Natives) -> Result<(), Vec<(usize, Diagnostic)>> {
+    let mut resolver = Resolver {
+        natives: natives.iter().map(|native| native.name.clone()).collect(),
+        native_classes: natives.classes().map(|class| class.name.clone()).collect(),
+        native_constants: natives.constants().map(|constant| constant.name.clone()).collect(),
+        ..Resolver::default()
+    };
+    for module in modules.iter_mut() {
+        resolver.resolve_module(module);
+    }
+    if resolver.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(resolver.errors)
+    }
+}
+
+/// Returns the name a top-level statement declares in the global environment.
+pub fn global_name(statement: &Statement) -> Option<&str> {
+    match &statement.kind {
+        StatementKind::VarDeclaration(variable) => Some(&variable.name),
+        StatementKind::ConstantDeclaration(constant) => global_name(&constant.statement),
+        StatementKind::FunctionDeclaration(function) => Some(&function.name),
+        StatementKind::ClassDeclaration(class) => Some(&class.name),
+        StatementKind::EnumDeclaration(enum_) => Some(&enum_.name),
+        _ => None,
+    }
+}
+
+impl Resolver {
+    fn resolve_module(&mut self, module: &mut Module) {
+        self.module = self.modules.len();
+        let start = self.globals.len();
+        let statements = &mut module.statements;
+        for statement in statements.iter() {
+            if let Some(name) = global_name(statement) {
+                if self.globals[start..].iter().any(|global| global.name == name) {
+                    self.error(format!("`{}` is already declared", name), statement.line, statement.column);
+                } else if self.prelude_slot(name).is_some() {
+                    self.error(format!("`{}` is already declared in the prelude", name), statement.line, statement.column);
+                }
+                self.declared_at.push((statement.line, statement.column));
+                self.globals.push(Declaration {
+                    name: name.to_string(),
+                    constant: !matches!(statement.kind, StatementKind::VarDeclaration(_)),
+                    defined: matches!(
+                        statement.kind,
+                        StatementKind::FunctionDeclaration(_) | StatementKind::ClassDeclaration(_) | StatementKind::EnumDeclaration(_)
+                    ),
+                });
+            }
+        }
+        self.modules.push(ModuleScope {
+            name: module.name.clone(),
+            globals: start..self.globals.len(),
+            exports: module.exports.clone(),
+            imports: module.imports.clone(),
+            native_imports: module.native_imports.clone(),
+        });
+        for statement in statements.iter_mut() {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn error(&mut self, message: String, line: usize, column: usize) {
+        self.errors.push((self.module, Diagnostic::error(message, line, column)));
+    }
+
+    /// The slot of a global declared by `module`.
+    fn global_slot(&self, module: usize, name: &str) -> Option<usize> {
+        self.modules[module].globals.clone().find(|slot| self.globals[*slot].name == name)
+    }
+
+    /// The slot of a global exported by the prelude, which is the first module and
+    /// visible in every other one without an import.
+    fn prelude_slot(&self, name: &str) -> Option<usize> {
+        let prelude = self.modules.first()?;
+        if !prelude.exports.iter().any(|export| export == name) {
+            return None;
+        }
+        self.global_slot(0, name)
+    }
+
+    /// The module the current module imports as `namespace`, unless a variable by that name hides it.
+    fn namespace(&self, namespace: &str) -> Option<usize> {
+        if self.shadowed(namespace) {
+            return None;
+        }
+        self.modules[self.module].imports.iter().find(|(name, _)| name == namespace).map(|(_, module)| *module)
+    }
+
+    /// The native module the current module imports as `namespace`, unless a variable by that name hides it.
+    fn native_namespace(&self, namespace: &str) -> Option<&str> {
+        if self.shadowed(namespace) {
+            return None;
+        }
+        self.modules[self.module].native_imports.iter().find(|(name, _)| name == namespace).map(|(_, module)| module.as_str())
+    }
+
+    fn shadowed(&self, name: &str) -> bool {
+        self.scopes.iter().any(|scope| scope.iter().any(|declaration| declaration.name == name)) || self.global_slot(self.module, name).is_some()
+    }
+
+    /// Turns `shapes.area` and `shapes.area(...)` into plain references to the name `shapes.area`,
+    /// which `lookup` resolves into the module imported as `shapes`.
+    fn qualify(&self, expression: &mut Expression) {
+        let (line, column) = (expression.line, expression.column);
+        let qualified = |namespace: &Expression, name: &str| match &namespace.kind {
+            ExpressionKind::Variable(variable) if self.namespace(&variable.name).is_some() || self.native_namespace(&variable.name).is_some() => {
+                let name = format!("{}.{}", variable.name, name);
+                Some(Expression::new(ExpressionKind::Variable(Variable::new(name, line, column)), line, column))
+            }
+            _ => None,
+        };
+        match &mut expression.kind {
+            ExpressionKind::Member(member) => {
+                if let Some(variable) = qualified(&member.callee, &member.name) {
+                    *expression = variable;
+                }
+            }
+            ExpressionKind::MethodCall(method_call) => {
+                if let Some(callee) = qualified(&method_call.receiver, &method_call.name) {
+                    let type_arguments = std::mem::take(&mut method_call.type_arguments);
+                    let arguments = std::mem::take(&mut method_call.arguments);
+                    expression.kind = ExpressionKind::Call(Box::new(Call::new(callee, type_arguments, arguments)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Looks up `name` among the exports of the module imported as `namespace`.
+    fn lookup_import(&mut self, namespace: &str, name: &str, line: usize, column: usize) -> Option<(Binding, bool)> {
+        if let Some(module) = self.native_namespace(namespace) {
+            let qualified = format!("{}.{}", module, name);
+            if let Some(index) = self.natives.iter().position(|native| *native == qualified) {
+                return Some((Binding::Native(index), true));
+            }
+            if let Some(index) = self.native_constants.iter().position(|constant| *constant == qualified) {
+                return Some((Binding::NativeConstant(index), true));
+            }
+            self.error(format!("Module `{}` has no `{}`", module, name), line, column);
+            return None;
+        }
+        let module = match self.namespace(namespace) {
+            Some(module) => module,
+            None => {
+                self.error(format!("`{}` is not an imported module", namespace), line, column);
+                return None;
+            }
+        };
+        let scope = &self.modules[module];
+        match self.global_slot(module, name) {
+            // Other modules can read exported globals but never assign them.
+            Some(slot) if scope.exports.iter().any(|export| export == name) => Some((Binding::Global(slot), true)),
+            Some(slot) => {
+                let (declared_line, declared_column) = self.declared_at[slot];
+                let diagnostic = Diagnostic::error(format!("`{}` is private to module `{}`", name, scope.name), line, column)
+                    .with_label_in(module, "declared here without `export` or `public`".to_string(), declared_line, declared_column);
+                self.errors.push((self.module, diagnostic));
+                None
+            }
+            None => {
+                self.error(format!("Module `{}` has no `{}`", scope.name, name), line, column);
+                None
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, constant: bool, line: usize, column: usize) {
+        match self.scopes.last_mut() {
+            Some(scope) => {
+                let redeclared = scope.iter().any(|declaration| declaration.name == name);
+                // Redeclarations still take a slot so later slots line up with the interpreter.
+                scope.push(Declaration {
+                    name: name.to_string(),
+                    constant,
+                    defined: true,
+                });
+                if redeclared {
+                    self.error(format!("`{}` is already declared in this scope", name), line, column);
+                }
+            }
+            None => {
+                if let Some(slot) = self.global_slot(self.module, name) {
+                    self.globals[slot].defined = true;
+                }
+            }
+        }
+    }
+
+    fn lookup(&mut self, name: &str, line: usize, column: usize) -> Option<(Binding, bool)> {
+        if let Some((namespace, name)) = name.split_once('.') {
+            return self.lookup_import(namespace, name, line, column);
+        }
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(slot) = scope.iter().rposition(|declaration| declaration.name == name) {
+                return Some((Binding::Local { depth, slot }, scope[slot].constant));
+            }
+        }
+        if self.global_slot(self.module, name).is_none() {
+            if let Some(slot) = self.prelude_slot(name) {
+                // Like imported names, those of the prelude are read-only.
+                return Some((Binding::Global(slot), true));
+            }
+            if let Some(index) = self.natives.iter().position(|native| native == name) {
+                return Some((Binding::Native(index), true));
+            }
+            if let Some(index) = self.native_classes.iter().position(|class| class == name) {
+                return Some((Binding::NativeClass(index), true));
+            }
+            if let Some(index) = self.native_constants.iter().position(|constant| constant == name) {
+                return Some((Binding::NativeConstant(index), true));
+            }
+        }
+        match self.global_slot(self.module, name) {
+            Some(slot) => {
+                let (defined, constant) = (self.globals[slot].defined, self.globals[slot].constant);
+                // Function bodies run after all globals are initialized, top-level code does not.
+                if !defined && self.functions == 0 {
+                    self.error(format!("`{}` is used before its declaration", name), line, column);
+                }
+                Some((Binding::Global(slot), constant))
+            }
+            None if (name == "this" || name == "super") && self.in_static => {
+                self.error(format!("`{}` cannot be used in a static method", name), line, column);
+                None
+            }
+            None if name == "this" => {
+                self.error("`this` used outside of a method or constructor".to_string(), line, column);
+                None
+            }
+            None if name == "super" => {
+                self.error("`super` used outside of a class that extends another".to_string(), line, column);
+                None
+            }
+            None => {
+                self.error(format!("Undefined variable `{}`", name), line, column);
+                None
+            }
+        }
+    }
+
+    fn resolve_block(&mut self, statements: &mut [Statement]) {
+        self.scopes.push(Vec::new());
+        for statement in statements.iter_mut() {
+            self.resolve_statement(statement);
+        }
+        self.scopes.pop();
+    }
+
+    /// Resolves the branch of an `if` or the body of a `while`, which always gets its own scope.
+    fn resolve_branch(&mut self, statement: &mut Statement) {
+        match &mut statement.kind {
+            StatementKind::Block(block) => self.resolve_block(&mut block.statements),
+            _ => self.resolve_block(std::slice::from_mut(statement)),
+        }
+    }
+
+    fn resolve_function(&mut self, parameters: &[Parameter], body: &mut [Statement]) {
+        let loops = std::mem::replace(&mut self.loops, 0);
+        self.functions += 1;
+        self.scopes.push(Vec::new());
+        for parameter in parameters {
+            self.declare(&parameter.name, false, parameter.type_.line, parameter.type_.column);
+        }
+        for statement in body.iter_mut() {
+            self.resolve_statement(statement);
+        }
+        self.scopes.pop();
+        self.functions -= 1;
+        self.loops = loops;
+    }
+
+    /// Resolves a method or constructor, whose body sees `this` in a scope of its own.
+    fn resolve_method(&mut self, parameters: &[Parameter], body: &mut [Statement]) {
+        self.scopes.push(vec![Declaration {
+            name: "this".to_string(),
+            constant: true,
+            defined: true,
+        }]);
+        self.resolve_function(parameters, body);
+        self.scopes.pop();
+    }
+
+    fn resolve_class(&mut self, class: &mut ClassDeclaration) {
+        if let Some(superclass) = &mut class.superclass {
+            if superclass.name == class.name {
+                self.error(format!("Class `{}` cannot extend itself", class.name), superclass.line, superclass.column);
+            }
+            superclass.binding = self.lookup(&superclass.name, superclass.line, superclass.column).map(|(binding, _)| binding);
+        }
+        let mut constructors = 0;
+        for member in &mut class.body {
+            let (line, column) = (member.statement.line, member.statement.column);
+            match &mut member.statement.kind {
+                StatementKind::VarDeclaration(field) => {
+                    if let Some(initializer) = &mut field.initializer {
+                        self.resolve_expression(initializer);
+                    }
+                }
+                StatementKind::ConstantDeclaration(constant) => {
+                    if let StatementKind::VarDeclaration(field) = &mut constant.statement.kind {
+                        match &mut field.initializer {
+                            Some(initializer) => self.resolve_expression(initializer),
+                            None => self.error(format!("Constant field `{}` must be initialized", field.name), line, column),
+                        }
+                    }
+                }
+                StatementKind::FunctionDeclaration(_) => {}
+                StatementKind::ConstructorDeclaration(_) => {
+                    constructors += 1;
+                    if constructors > 1 {
+                        self.error(format!("Class `{}` declares more than one constructor", class.name), line, column);
+                    }
+                }
+                _ => self.error("Expected field, method or constructor".to_string(), line, column),
+            }
+        }
+
+        // Static methods are called on the class, so they close over neither an object nor its superclass.
+        for member in &mut class.body {
+            if let (true, StatementKind::FunctionDeclaration(method)) = (member.is_static, &mut member.statement.kind) {
+                self.in_static = true;
+                self.resolve_function(&method.parameters, &mut method.body);
+                self.in_static = false;
+            }
+        }
+
+        // Methods of a subclass close over a scope holding the superclass, which `super` refers to.
+        let has_superclass = class.superclass.is_some();
+        if has_superclass {
+            self.scopes.push(vec![Declaration {
+                name: "super".to_string(),
+                constant: true,
+                defined: true,
+            }]);
+        }
+        for member in class.body.iter_mut().filter(|member| !member.is_static) {
+            match &mut member.statement.kind {
+                StatementKind::FunctionDeclaration(method) => self.resolve_method(&method.parameters, &mut method.body),
+                StatementKind::ConstructorDeclaration(constructor) => self.resolve_method(&constructor.parameters, &mut constructor.body),
+                _ => {}
+            }
+        }
+        if has_superclass {
+            self.scopes.pop();
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &mut Statement) {
+        let (line, column) = (statement.line, statement.column);
+        match &mut statement.kind {
+            StatementKind::Expression(expression) => self.resolve_expression(expression),
+            StatementKind::VarDeclaration(variable) => {
+                match &mut variable.initializer {
+                    Some(initializer) => self.resolve_expression(initializer),
+                    None => self.error(format!("Variable `{}` must be initialized", variable.name), line, column),
+                }
+                self.declare(&variable.name, false, line, column);
+            }
+            StatementKind::ConstantDeclaration(constant) => {
+                if let StatementKind::VarDeclaration(variable) = &mut constant.statement.kind {
+                    match &mut variable.initializer {
+                        Some(initializer) => self.resolve_expression(initializer),
+                        None => self.error(format!("Constant `{}` must be initialized", variable.name), line, column),
+                    }
+                    self.declare(&variable.name, true, line, column);
+                }
+            }
+            StatementKind::FunctionDeclaration(function) => {
+                // Declared before its body is resolved so that it can call itself.
+                self.declare(&function.name, true, line, column);
+                self.resolve_function(&function.parameters, &mut function.body);
+            }
+            StatementKind::ClassDeclaration(class) => {
+                // Declared before its body is resolved so that methods can refer to their own class.
+                self.declare(&class.name, true, line, column);
+                self.resolve_class(class);
+            }
+            StatementKind::EnumDeclaration(enum_) => self.declare(&enum_.name, true, line, column),
+            // Interface names are types, which the checker resolves.
+            StatementKind::InterfaceDeclaration(_) => {}
+            StatementKind::ConstructorDeclaration(_) => {
+                self.error("`constructor` outside of a class".to_string(), line, column);
+            }
+            // The module loader takes top-level imports and exports out of the program.
+            StatementKind::Import(_) => self.error("`import` is only allowed at the top level of a module".to_string(), line, column),
+            StatementKind::Export(exported) => {
+                self.error("Only top-level declarations can be exported or made `public`".to_string(), line, column);
+                self.resolve_statement(exported);
+            }
+            StatementKind::Return(return_statement) => {
+                if self.functions == 0 {
+                    self.error("`return` outside of a function".to_string(), line, column);
+                }
+                if let Some(expression) = &mut return_statement.expression {
+                    self.resolve_expression(expression);
+                }
+            }
+            StatementKind::Block(block) => self.resolve_block(&mut block.statements),
+            StatementKind::If(if_statement) => {
+                self.resolve_expression(&mut if_statement.condition);
+                self.resolve_branch(&mut if_statement.then_branch);
+                if let Some(else_branch) = &mut if_statement.else_branch {
+                    self.resolve_branch(else_branch);
+                }
+            }
+            StatementKind::While(while_statement) => {
+                self.resolve_expression(&mut while_statement.condition);
+                self.loops += 1;
+                self.resolve_branch(&mut while_statement.body);
+                self.loops -= 1;
+            }
+            StatementKind::For(for_statement) => {
+                self.resolve_expression(&mut for_statement.iterable);
+                self.loops += 1;
+                // Like a caught error, the loop variable is declared in the same scope as the body.
+                self.scopes.push(Vec::new());
+                self.declare(&for_statement.name, false, line, column);
+                match &mut for_statement.body.kind {
+                    StatementKind::Block(block) => {
+                        for statement in block.statements.iter_mut() {
+                            self.resolve_statement(statement);
+                        }
+                    }
+                    _ => self.resolve_statement(&mut for_statement.body),
+                }
+                self.scopes.pop();
+                self.loops -= 1;
+            }
+            StatementKind::Throw(throw) => self.resolve_expression(&mut throw.expression),
+            StatementKind::Try(try_statement) => {
+                self.resolve_block(&mut try_statement.body);
+                for catch in &mut try_statement.catches {
+                    let class = &mut catch.class;
+                    class.binding = self.lookup(&class.name, class.line, class.column).map(|(binding, _)| binding);
+                    // The caught error is declared in the same scope as the clause's body.
+                    self.scopes.push(Vec::new());
+                    self.declare(&catch.name, false, catch.class.line, catch.class.column);
+                    for statement in catch.body.iter_mut() {
+                        self.resolve_statement(statement);
+                    }
+                    self.scopes.pop();
+                }
+                if let Some(finally) = &mut try_statement.finally {
+                    self.resolve_block(finally);
+                }
+            }
+            StatementKind::Break | StatementKind::Continue => {
+                if self.loops == 0 {
+                    self.error("`break` or `continue` outside of a loop".to_string(), line, column);
+                }
+            }
+        }
+    }
+
+    fn resolve_pattern(&mut self, pattern: &mut Pattern) {
+        match &mut pattern.kind {
+            PatternKind::Wildcard | PatternKind::Literal(_) | PatternKind::Range(_) => {}
+            PatternKind::Binding(name) => self.declare(name, false, pattern.line, pattern.column),
+            PatternKind::Variant(variant) => {
+                let enum_ = &mut variant.enum_;
+                enum_.binding = self.lookup(&enum_.name, enum_.line, enum_.column).map(|(binding, _)| binding);
+                for field in &mut variant.fields {
+                    self.resolve_pattern(field);
+                }
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &mut Expression) {
+        self.qualify(expression);
+        let (line, column) = (expression.line, expression.column);
+        match &mut expression.kind {
+            ExpressionKind::Literal(_) => {}
+            ExpressionKind::Parenthesized(parenthesized) => self.resolve_expression(&mut parenthesized.expression),
+            ExpressionKind::Variable(variable) => {
+                variable.binding = self.lookup(&variable.name, line, column).map(|(binding, _)| binding);
+            }
+            ExpressionKind::Binary(binary) => {
+                self.resolve_expression(&mut binary.left);
+                self.resolve_expression(&mut binary.right);
+            }
+            ExpressionKind::Unary(unary) => self.resolve_expression(&mut unary.operand),
+            ExpressionKind::Cast(cast) => self.resolve_expression(&mut cast.expression),
+            ExpressionKind::Call(call) => {
+                self.resolve_expression(&mut call.callee);
+                for argument in &mut call.arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            ExpressionKind::Index(index) => {
+                self.resolve_expression(&mut index.callee);
+                self.resolve_expression(&mut index.index);
+            }
+            ExpressionKind::Slice(slice) => {
+                self.resolve_expression(&mut slice.callee);
+                self.resolve_expression(&mut slice.start);
+                self.resolve_expression(&mut slice.end);
+            }
+            ExpressionKind::Member(member) => self.resolve_expression(&mut member.callee),
+            ExpressionKind::MethodCall(method_call) => {
+                self.resolve_expression(&mut method_call.receiver);
+                for argument in &mut method_call.arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            ExpressionKind::Super(super_call) => {
+                super_call.binding = self.lookup("super", line, column).map(|(binding, _)| binding);
+                for argument in &mut super_call.arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            ExpressionKind::New(new) => {
+                self.resolve_expression(&mut new.class);
+                for argument in &mut new.arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+            ExpressionKind::Lambda(lambda) => self.resolve_function(&lambda.parameters, &mut lambda.body),
+            ExpressionKind::Match(match_expression) => {
+                self.resolve_expression(&mut match_expression.subject);
+                // Each arm gets a scope holding its bindings, in the order they appear in the pattern.
+                for arm in &mut match_expression.arms {
+                    self.scopes.push(Vec::new());
+                    self.resolve_pattern(&mut arm.pattern);
+                    if let Some(guard) = &mut arm.guard {
+                        self.resolve_expression(guard);
+                    }
+                    self.resolve_expression(&mut arm.body);
+                    self.scopes.pop();
+                }
+            }
+            ExpressionKind::Array(array) => {
+                for element in &mut array.elements {
+                    self.resolve_expression(element);
+                }
+            }
+            ExpressionKind::Map(map) => {
+                for (key, value) in &mut map.entries {
+                    self.resolve_expression(key);
+                    self.resolve_expression(value);
+                }
+            }
+            ExpressionKind::Assign(assign) => {
+                self.resolve_expression(&mut assign.value);
+                if let ExpressionKind::Variable(variable) = &mut assign.target.kind {
+                    if let Some((binding, constant)) = self.lookup(&variable.name, variable.line, variable.column) {
+                        if constant {
+                            self.error(format!("Cannot assign to constant `{}`", variable.name), variable.line, variable.column);
+                        }
+                        variable.binding = Some(binding);
+                    }
+                } else {
+                    self.resolve_expression(&mut assign.target);
+                    if let ExpressionKind::Variable(variable) = &assign.target.kind {
+                        self.error(format!("Cannot assign to `{}` from outside its module", variable.name), variable.line, variable.column);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use crate::native::{integer, primitive, text, Natives};
+use crate::parser::{Type, TypeKind};
+use crate::value::Value;
+
+/// Registers the `strings` module. Every function taking a string first is also a
+/// method of strings, so `strings.trim(name)` can be written `name.trim()`.
+///
+/// Strings are sequences of Unicode scalar values, and lengths and positions count
+/// those rather than bytes, like indexing a string does.
+pub fn define(natives: &mut Natives) {
+    let string = || primitive(TypeKind::String);
+    let int = || primitive(TypeKind::Integer);
+    let strings = || Type::new(TypeKind::Array(Box::new(string())), 0, 0);
+
+    natives.define("strings.len", vec![string()], int(), |arguments| {
+        Ok(Value::Integer(text(&arguments[0])?.chars().count() as i64))
+    });
+    let chars = Type::new(TypeKind::Array(Box::new(primitive(TypeKind::Char))), 0, 0);
+    let chars_size = |arguments: &[Value]| text(&arguments[0]).map_or(0, str::len) * size_of::<Value>();
+    natives.define_sized("strings.chars", vec![string()], chars, chars_size, |arguments| {
+        Ok(Value::array(text(&arguments[0])?.chars().map(Value::Char).collect()))
+    });
+    natives.define("strings.substring", vec![string(), int(), int()], string(), |arguments| {
+        let string = text(&arguments[0])?;
+        let (start, end) = (integer(&arguments[1])?, integer(&arguments[2])?);
+        let length = string.chars().count();
+        match (usize::try_from(start), usize::try_from(end)) {
+            (Ok(start), Ok(end)) if start <= end && end <= length => Ok(Value::string(&string.chars().skip(start).take(end - start).collect::<String>())),
+            _ => Err(format!("Substring {}..{} out of bounds for string of length {}", start, end, length)),
+        }
+    });
+    natives.define("strings.index_of", vec![string(), string()], int(), |arguments| {
+        let string = text(&arguments[0])?;
+        // Byte offsets are turned into the number of characters before them.
+        let index = string.find(text(&arguments[1])?).map_or(-1, |offset| string[..offset].chars().count() as i64);
+        Ok(Value::Integer(index))
+    });
+    // Every part is at most the whole string, and there is one more part than separators.
+    let split_size = |arguments: &[Value]| {
+        let (string, separator) = (text(&arguments[0]).map_or(0, str::len), text(&arguments[1]).map_or(0, str::len));
+        string + (string / separator.max(1) + 1) * size_of::<Value>()
+    };
+    natives.define_sized("strings.split", vec![string(), string()], strings(), split_size, |arguments| {
+        let (string, separator) = (text(&arguments[0])?, text(&arguments[1])?);
+        if separator.is_empty() {
+            return Err("Cannot split on an empty separator".to_string());
+        }
+        Ok(Value::array(string.split(separator).map(Value::string).collect()))
+    });
+    let join_size = |arguments: &[Value]| match &arguments[0] {
+        Value::Array(parts) => {
+            let parts = parts.borrow();
+            let length: usize = parts.iter().map(|part| text(part).map_or(0, str::len)).sum();
+            length + parts.len().saturating_sub(1) * text(&arguments[1]).map_or(0, str::len)
+        }
+        _ => 0,
+    };
+    natives.define_sized("strings.join", vec![strings(), string()], string(), join_size, |arguments| {
+        let parts = match &arguments[0] {
+            Value::Array(parts) => parts.borrow().iter().map(|part| text(part).map(str::to_string)).collect::<Result<Vec<_>, _>>()?,
+            value => return Err(format!("Expected an array argument, found {}", value.type_name())),
+        };
+        Ok(Value::string(&parts.join(text(&arguments[1])?)))
+    });
+    natives.define("strings.trim", vec![string()], string(), |arguments| Ok(Value::string(text(&arguments[0])?.trim())));
+    let replace_size = |arguments: &[Value]| match (text(&arguments[0]), text(&arguments[1]), text(&arguments[2])) {
+        (Ok(string), Ok(from), Ok(to)) if !from.is_empty() => string.len() + string.matches(from).count() * to.len(),
+        _ => 0,
+    };
+    natives.define_sized("strings.replace", vec![string(), string(), string()], string(), replace_size, |arguments| {
+        let (string, from, to) = (text(&arguments[0])?, text(&arguments[1])?, text(&arguments[2])?);
+        if from.is_empty() {
+            return Err("Cannot replace an empty string".to_string());
+        }
+        Ok(Value::string(&string.replace(from, to)))
+    });
+    natives.define("strings.starts_with", vec![string(), string()], primitive(TypeKind::Bool), |arguments| {
+        Ok(Value::Bool(text(&arguments[0])?.starts_with(text(&arguments[1])?)))
+    });
+    natives.define("strings.to_upper", vec![string()], string(), |arguments| Ok(Value::string(&text(&arguments[0])?.to_uppercase())));
+    natives.define("strings.to_lower", vec![string()], string(), |arguments| Ok(Value::string(&text(&arguments[0])?.to_lowercase())));
+    // Parse failures are runtime errors, which scripts can catch as an `Error`.
+    natives.define("strings.parse_int", vec![string()], int(), |arguments| {
+        let string = text(&arguments[0])?;
+        string.trim().parse().map(Value::Integer).map_err(|_| format!("`{}` is not a valid int", string))
+    });
+    natives.define("strings.parse_float", vec![string()], primitive(TypeKind::Float), |arguments| {
+        let string = text(&arguments[0])?;
+        string.trim().parse().map(Value::Float).map_err(|_| format!("`{}` is not a valid float", string))
+    });
+}
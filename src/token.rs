@@ -31,6 +31,33 @@ pub enum TokenKind {
     ClassKw,            // class
     ReturnKw,           // return
     ConstructorKw,      // constructor
+    VoidKw,             // void
+    IfKw,               // if
+    ElseKw,             // else
+    WhileKw,            // while
+    ForKw,              // for
+    InKw,               // in
+    BreakKw,            // break
+    ContinueKw,         // continue
+    ThisKw,             // this
+    NewKw,              // new
+    ExtendsKw,          // extends
+    SuperKw,            // super
+    InterfaceKw,        // interface
+    ImplementsKw,       // implements
+    EnumKw,             // enum
+    MatchKw,            // match
+    ImportKw,           // import
+    ExportKw,           // export
+    PublicKw,           // public
+    PrivateKw,          // private
+    ProtectedKw,        // protected
+    StaticKw,           // static
+    ThrowKw,            // throw
+    TryKw,              // try
+    CatchKw,            // catch
+    FinallyKw,          // finally
+    AsKw,               // as
 
     // Operators
     Plus,               // +
@@ -57,6 +84,9 @@ pub enum TokenKind {
     RightBracket,       // ]
     Comma,              // ,
     Dot,                // .
+    DotDot,             // ..
+    DotDotEqual,        // ..=
+    FatArrow,           // =>
     Colon,              // :
     Semicolon,          // ;
     Question,           // ?
@@ -74,8 +104,6 @@ pub enum TokenKind {
     Float(f64),
     String(String),
     Character(char),
-    Boolean(bool),
-    Null,
 
     // Special
     Error(String),
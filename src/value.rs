@@ -0,0 +1,387 @@
+use crate::compiler::Chunk;
+use crate::environment::Environment;
+use crate::native::{NativeClass, NativeFunction};
+use crate::parser::{Expression, FunctionDeclaration, Type};
+use std::any::Any;
+use std::cell::{OnceCell, Ref, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::rc::Rc;
+
+/// A value produced by evaluating Wally code.
+///
+/// Strings are immutable and shared, arrays and maps are shared by reference
+/// just like objects in Java, so passing an array to a function and pushing
+/// to it is visible to the caller.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    String(Rc<str>),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<BTreeMap<MapKey, Value>>>),
+    Function(Rc<Function>),
+    Native(Rc<NativeFunction>),
+    Class(Rc<Class>),
+    Object(Rc<Object>),
+    HostClass(Rc<NativeClass>),
+    HostObject(Rc<HostObject>),
+    Enum(Rc<Enum>),
+    Variant(Rc<Variant>),
+    Null,
+}
+
+/// A user-defined function together with the environment it was declared in.
+pub struct Function {
+    pub declaration: Rc<FunctionDeclaration>,
+    pub closure: Rc<Environment>,
+    /// The name stack traces show, qualified with the class for methods.
+    pub name: String,
+    /// Index of the module the function was declared in.
+    pub module: usize,
+    /// The bytecode the body is compiled to when the function is first called, shared by every
+    /// function created from the same declaration.
+    pub code: Rc<OnceCell<Chunk>>,
+}
+
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The closure may contain the function itself, so it is left out.
+        write!(f, "Function({})", self.declaration.name)
+    }
+}
+
+/// A class declaration evaluated into something that can be instantiated.
+pub struct Class {
+    pub name: String,
+    pub superclass: Option<Rc<Class>>,
+    /// The fields this class declares itself, initialized after those of its superclass.
+    pub fields: Vec<Field>,
+    /// Slots of every field of an instance, inherited ones first.
+    pub field_slots: HashMap<String, usize>,
+    /// Every method callable on an instance, with overrides replacing the inherited method.
+    pub methods: HashMap<String, Rc<Function>>,
+    pub constructor: Option<Rc<Function>>,
+    /// Whether the constructor starts by calling the superclass constructor itself.
+    pub calls_super: bool,
+    /// The static fields this class declares, initialized once when its declaration is reached.
+    pub static_fields: Vec<Field>,
+    /// Current values of the static fields this class declares.
+    pub statics: RefCell<HashMap<String, Value>>,
+    /// The static methods this class declares.
+    pub static_methods: HashMap<String, Rc<Function>>,
+    /// The environment the class was declared in, which field initializers are evaluated in.
+    pub closure: Rc<Environment>,
+}
+
+/// A field a class declares, with what its value is initialized to.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub type_: Type,
+    /// Fields without an initializer start out with the default value of their type.
+    pub initializer: Option<Initializer>,
+}
+
+/// The initializer of a field, evaluated in the environment its class was declared in.
+#[derive(Debug, Clone)]
+pub enum Initializer {
+    Expression(Rc<Expression>),
+    /// Bytecode, for classes declared in compiled code.
+    Code(Rc<Chunk>),
+}
+
+impl Class {
+    /// Whether this class is `ancestor` or inherits from it.
+    pub fn is_subclass_of(&self, ancestor: &Class) -> bool {
+        std::ptr::eq(self, ancestor) || self.superclass.as_ref().is_some_and(|superclass| superclass.is_subclass_of(ancestor))
+    }
+
+    /// Reads a static field or static method of this class or one of its superclasses.
+    pub fn get_static(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.statics.borrow().get(name) {
+            return Some(value.clone());
+        }
+        if let Some(method) = self.static_methods.get(name) {
+            return Some(Value::Function(method.clone()));
+        }
+        self.superclass.as_ref()?.get_static(name)
+    }
+
+    /// Sets a static field, returning `false` if neither the class nor a superclass declares it.
+    pub fn set_static(&self, name: &str, value: Value) -> bool {
+        if let Some(slot) = self.statics.borrow_mut().get_mut(name) {
+            *slot = value;
+            return true;
+        }
+        match &self.superclass {
+            Some(superclass) => superclass.set_static(name, value),
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Class({})", self.name)
+    }
+}
+
+/// An instance of a class, with one slot per field in declaration order.
+pub struct Object {
+    pub class: Rc<Class>,
+    pub fields: RefCell<Vec<Value>>,
+}
+
+impl Object {
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let slot = *self.class.field_slots.get(name)?;
+        Some(self.fields.borrow()[slot].clone())
+    }
+
+    /// Sets a field, returning `false` if the class declares no such field.
+    pub fn set(&self, name: &str, value: Value) -> bool {
+        match self.class.field_slots.get(name) {
+            Some(slot) => {
+                self.fields.borrow_mut()[*slot] = value;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for Object {
+    fn drop(&mut self) {
+        release(std::mem::take(self.fields.get_mut()));
+    }
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Object({})", self.class.name)
+    }
+}
+
+/// An instance of a host class, wrapping the Rust value its methods work on.
+pub struct HostObject {
+    pub class: Rc<NativeClass>,
+    pub data: RefCell<Box<dyn Any>>,
+}
+
+impl HostObject {
+    /// Borrows the wrapped Rust value, if it is a `T`.
+    pub fn borrow<T: 'static>(&self) -> Option<Ref<'_, T>> {
+        Ref::filter_map(self.data.borrow(), |data| data.downcast_ref()).ok()
+    }
+}
+
+impl fmt::Debug for HostObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HostObject({})", self.class.name)
+    }
+}
+
+/// An enum declaration evaluated into the namespace its variants are constructed through.
+#[derive(Debug, Clone)]
+pub struct Enum {
+    pub name: String,
+    /// The name and payload size of each variant, indexed by tag.
+    pub variants: Vec<(String, usize)>,
+}
+
+/// A value of an enum: the tag of one of its variants and that variant's payload.
+#[derive(Debug)]
+pub struct Variant {
+    pub enum_: Rc<Enum>,
+    pub tag: usize,
+    pub payload: Vec<Value>,
+}
+
+impl Drop for Variant {
+    fn drop(&mut self) {
+        release(std::mem::take(&mut self.payload));
+    }
+}
+
+impl Variant {
+    pub fn name(&self) -> &str {
+        &self.enum_.variants[self.tag].0
+    }
+}
+
+/// The subset of values that can be used as map keys.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MapKey {
+    Bool(bool),
+    Integer(i64),
+    Char(char),
+    String(Rc<str>),
+}
+
+impl Value {
+    pub fn string(value: &str) -> Value {
+        Value::String(Rc::from(value))
+    }
+
+    pub fn array(elements: Vec<Value>) -> Value {
+        Value::Array(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn map(entries: BTreeMap<MapKey, Value>) -> Value {
+        Value::Map(Rc::new(RefCell::new(entries)))
+    }
+
+    /// An instance of a host class wrapping `data`, which must be of the type the class was registered with.
+    pub fn host(class: &Rc<NativeClass>, data: impl Any) -> Value {
+        Value::HostObject(Rc::new(HostObject {
+            class: class.clone(),
+            data: RefCell::new(Box::new(data)),
+        }))
+    }
+
+    /// The name of the value's type as it is spelled in Wally source.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Integer(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Char(_) => "char",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Map(_) => "map",
+            Value::Function(_) | Value::Native(_) => "function",
+            Value::Class(_) | Value::HostClass(_) => "class",
+            Value::Object(_) | Value::HostObject(_) => "object",
+            Value::Enum(_) => "enum",
+            Value::Variant(_) => "enum value",
+            Value::Null => "null",
+        }
+    }
+
+    pub fn to_map_key(&self) -> Option<MapKey> {
+        match self {
+            Value::Bool(boolean) => Some(MapKey::Bool(*boolean)),
+            Value::Integer(integer) => Some(MapKey::Integer(*integer)),
+            Value::Char(character) => Some(MapKey::Char(*character)),
+            Value::String(string) => Some(MapKey::String(string.clone())),
+            _ => None,
+        }
+    }
+}
+
+impl MapKey {
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Bool(boolean) => Value::Bool(*boolean),
+            MapKey::Integer(integer) => Value::Integer(*integer),
+            MapKey::Char(character) => Value::Char(*character),
+            MapKey::String(string) => Value::String(string.clone()),
+        }
+    }
+}
+
+/// Drops `values`, and what only they refer to, one at a time rather than recursively.
+///
+/// Objects, variants and closures can refer to others of their kind, so a program can build
+/// chains of any length, which dropping recursively would overflow the stack on. Objects,
+/// variants and environments hand what they hold to this when dropped, which takes apart
+/// everything no other value refers to before that is dropped in turn.
+pub(crate) fn release(mut values: Vec<Value>) {
+    while let Some(value) = values.pop() {
+        match value {
+            Value::Array(elements) => values.extend(Rc::into_inner(elements).map(RefCell::into_inner).unwrap_or_default()),
+            Value::Map(entries) => values.extend(Rc::into_inner(entries).map(RefCell::into_inner).unwrap_or_default().into_values()),
+            Value::Object(object) => {
+                if let Some(mut object) = Rc::into_inner(object) {
+                    values.append(object.fields.get_mut());
+                }
+            }
+            Value::Variant(variant) => {
+                if let Some(mut variant) = Rc::into_inner(variant) {
+                    values.append(&mut variant.payload);
+                }
+            }
+            Value::Function(function) => {
+                if let Some(function) = Rc::into_inner(function) {
+                    Environment::release_into(function.closure, &mut values);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+            (Value::HostClass(a), Value::HostClass(b)) => Rc::ptr_eq(a, b),
+            (Value::HostObject(a), Value::HostObject(b)) => Rc::ptr_eq(a, b),
+            (Value::Enum(a), Value::Enum(b)) => Rc::ptr_eq(a, b),
+            (Value::Variant(a), Value::Variant(b)) => Rc::ptr_eq(&a.enum_, &b.enum_) && a.tag == b.tag && a.payload == b.payload,
+            (Value::Null, Value::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(integer) => write!(f, "{}", integer),
+            Value::Float(float) => write!(f, "{:?}", float),
+            Value::Bool(boolean) => write!(f, "{}", boolean),
+            Value::Char(character) => write!(f, "{}", character),
+            Value::String(string) => write!(f, "{}", string),
+            Value::Array(elements) => {
+                write!(f, "[")?;
+                for (i, element) in elements.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key.to_value(), value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Function(function) => write!(f, "<fn {}>", function.declaration.name),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
+            Value::Class(class) => write!(f, "<class {}>", class.name),
+            Value::Object(object) => write!(f, "<{} object>", object.class.name),
+            Value::HostClass(class) => write!(f, "<class {}>", class.name),
+            Value::HostObject(object) => write!(f, "<{} object>", object.class.name),
+            Value::Enum(enum_) => write!(f, "<enum {}>", enum_.name),
+            Value::Variant(variant) => {
+                write!(f, "{}.{}", variant.enum_.name, variant.name())?;
+                if !variant.payload.is_empty() {
+                    let payload: Vec<String> = variant.payload.iter().map(|value| value.to_string()).collect();
+                    write!(f, "({})", payload.join(", "))?;
+                }
+                Ok(())
+            }
+            Value::Null => write!(f, "null"),
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use crate::compiler::{compile, Access, Chunk, Instruction, Operator, TryBlock};
+use crate::environment::Environment;
+use crate::interpreter::{apply_unary, convert, elements, match_value, superclass_of, thrown, Flow, Interpreter, RuntimeError};
+use crate::value::{Function, Value};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+/// How an interpreter runs the bodies of functions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// Walks the syntax tree of the body each time it runs.
+    Tree,
+    /// Compiles the body to bytecode the first time the function is called, and runs that on a
+    /// stack machine.
+    #[default]
+    Bytecode,
+}
+
+impl Interpreter {
+    /// Runs the body of a function in `closure` as bytecode, compiling it first if it has not been yet.
+    pub(crate) fn run_compiled(&mut self, function: &Function, closure: Rc<Environment>, mut arguments: Vec<Value>) -> Result<Flow, RuntimeError> {
+        let chunk = function.code.get_or_init(|| compile(&function.declaration));
+        let environment = if chunk.environments {
+            let environment = Environment::new(Some(closure));
+            for value in arguments.drain(..) {
+                environment.define(value);
+            }
+            environment
+        } else {
+            closure
+        };
+        // Without environments the parameters are the first slots of the frame.
+        let mut locals = arguments;
+        locals.resize(chunk.locals as usize, Value::Null);
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let stack = self.spared();
+        let result = self.run(chunk, &mut locals, (0, chunk.code.len() as u32), stack);
+        self.environment = previous;
+        self.recycle(locals);
+        result
+    }
+
+    /// Runs code that keeps no frame of its own, like the top level of a module or a field
+    /// initializer, in the current environment, returning the value it returns if any.
+    pub(crate) fn run_in_place(&mut self, chunk: &Chunk) -> Result<Value, RuntimeError> {
+        let mut locals = vec![Value::Null; chunk.locals as usize];
+        let stack = self.spared();
+        match self.run(chunk, &mut locals, (0, chunk.code.len() as u32), stack)? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Null),
+        }
+    }
+
+    /// Runs the code of `chunk` in `range` until it reaches the end of it or control leaves it,
+    /// restoring the current environment afterwards.
+    fn run(&mut self, chunk: &Chunk, locals: &mut [Value], range: (u32, u32), mut stack: Vec<Value>) -> Result<Flow, RuntimeError> {
+        let environment = self.environment.clone();
+        let result = self.run_range(chunk, locals, range, &mut stack);
+        self.environment = environment;
+        self.recycle(stack);
+        result
+    }
+
+    /// An empty vector, reusing one a finished call left behind if there is one.
+    fn spared(&mut self) -> Vec<Value> {
+        self.spare.pop().unwrap_or_default()
+    }
+
+    /// Empties `values` and keeps it for later calls, unless enough are kept already.
+    fn recycle(&mut self, mut values: Vec<Value>) {
+        if self.spare.len() < SPARE {
+            values.clear();
+            self.spare.push(values);
+        }
+    }
+
+    /// Moves the last `count` values of `stack` into a vector of their own, for the arguments of a call.
+    fn take(&mut self, stack: &mut Vec<Value>, count: usize) -> Vec<Value> {
+        let mut values = self.spared();
+        values.extend(stack.drain(stack.len() - count..));
+        values
+    }
+
+    fn run_range(&mut self, chunk: &Chunk, locals: &mut [Value], (start, end): (u32, u32), stack: &mut Vec<Value>) -> Result<Flow, RuntimeError> {
+        let mut pc = start as usize;
+        while pc < end as usize {
+            let instruction = chunk.code[pc];
+            let (line, column) = chunk.position(pc);
+            self.step(line, column)?;
+            pc += 1;
+            match instruction {
+                Instruction::Constant(index) => {
+                    let value = self.allocated(chunk.constants[index as usize].clone(), line, column)?;
+                    stack.push(value);
+                }
+                Instruction::Pop => {
+                    pop(stack);
+                }
+                // Locals cannot be missing, so they skip the checks other variables go through.
+                Instruction::Load(Access::Local(slot)) => stack.push(locals[slot as usize].clone()),
+                Instruction::Load(access) => stack.push(self.load(access, locals, line, column)?),
+                Instruction::Store(Access::Local(slot)) => locals[slot as usize] = top(stack).clone(),
+                Instruction::Store(access) => {
+                    let value = top(stack).clone();
+                    self.store(access, locals, value, line, column)?;
+                }
+                Instruction::SetLocal(slot) => locals[slot as usize] = pop(stack),
+                Instruction::Define => self.environment.define(pop(stack)),
+                Instruction::PushScope => self.environment = Environment::new(Some(self.environment.clone())),
+                Instruction::PopScope => match self.environment.parent() {
+                    Some(parent) => self.environment = parent,
+                    None => return Err(RuntimeError::new("Scope popped past the global environment".to_string(), line, column)),
+                },
+                Instruction::Unary(operator) => {
+                    let operand = pop(stack);
+                    stack.push(apply_unary(&operator.token(), operand, line, column)?);
+                }
+                Instruction::Binary(operator) => {
+                    // Ints are combined in place, where they are on the stack.
+                    if let [.., Value::Integer(left), Value::Integer(right)] = stack.as_mut_slice() {
+                        match integers(operator, *left, *right) {
+                            Some(Value::Integer(result)) => {
+                                *left = result;
+                                stack.pop();
+                                continue;
+                            }
+                            Some(result) => {
+                                stack.pop();
+                                *top_mut(stack) = result;
+                                continue;
+                            }
+                            None => {}
+                        }
+                    }
+                    let right = pop(stack);
+                    let left = pop(stack);
+                    stack.push(self.apply_binary(&operator.token(), left, right, line, column)?);
+                }
+                Instruction::Jump(target) => pc = target as usize,
+                Instruction::JumpIfFalse(target) => {
+                    if !condition(pop(stack), line, column)? {
+                        pc = target as usize;
+                    }
+                }
+                Instruction::And(target) => {
+                    if !condition(pop(stack), line, column)? {
+                        stack.push(Value::Bool(false));
+                        pc = target as usize;
+                    }
+                }
+                Instruction::Or(target) => {
+                    if condition(pop(stack), line, column)? {
+                        stack.push(Value::Bool(true));
+                        pc = target as usize;
+                    }
+                }
+                Instruction::Condition => {
+                    let value = condition(pop(stack), line, column)?;
+                    stack.push(Value::Bool(value));
+                }
+                Instruction::Call(count) => {
+                    let arguments = self.take(stack, count as usize);
+                    let callee = pop(stack);
+                    stack.push(self.call_value(callee, arguments, line, column)?);
+                }
+                Instruction::CallMethod(name, count) => {
+                    let arguments = self.take(stack, count as usize);
+                    let receiver = pop(stack);
+                    stack.push(self.call_method(receiver, &chunk.names[name as usize], arguments, line, column)?);
+                }
+                Instruction::SuperCall(index) => {
+                    let call = &chunk.supers[index as usize];
+                    let arguments = self.take(stack, call.arguments as usize);
+                    let superclass = self.load(call.superclass, locals, line, column)?;
+                    let object = self.load(call.object, locals, line, column)?;
+                    stack.push(self.call_super_on(superclass, object, call.method.as_deref(), arguments, line, column)?);
+                }
+                Instruction::New(count) => {
+                    let arguments = self.take(stack, count as usize);
+                    let class = pop(stack);
+                    stack.push(self.instantiate_value(class, arguments, line, column)?);
+                }
+                Instruction::Index => {
+                    let key = pop(stack);
+                    let callee = pop(stack);
+                    stack.push(self.evaluate_index(callee, key, line, column)?);
+                }
+                Instruction::SetIndex => {
+                    let key = pop(stack);
+                    let callee = pop(stack);
+                    let value = top(stack).clone();
+                    self.assign_index(callee, key, value, line, column)?;
+                }
+                Instruction::Slice => {
+                    let end = pop(stack);
+                    let start = pop(stack);
+                    let callee = pop(stack);
+                    stack.push(self.evaluate_slice(callee, start, end, line, column)?);
+                }
+                Instruction::GetMember(name) => {
+                    let callee = pop(stack);
+                    stack.push(self.get_member(callee, &chunk.names[name as usize], line, column)?);
+                }
+                Instruction::SetMember(name) => {
+                    let callee = pop(stack);
+                    let value = top(stack).clone();
+                    self.assign_member(callee, &chunk.names[name as usize], value, line, column)?;
+                }
+                Instruction::Array(count) => {
+                    let elements = stack.split_off(stack.len() - count as usize);
+                    stack.push(self.allocated(Value::array(elements), line, column)?);
+                }
+                Instruction::MapKey => {
+                    let key = top(stack);
+                    if key.to_map_key().is_none() {
+                        return Err(RuntimeError::new(format!("{} cannot be used as a map key", key.type_name()), line, column));
+                    }
+                }
+                Instruction::Map(count) => {
+                    let mut entries = stack.split_off(stack.len() - 2 * count as usize).into_iter();
+                    let mut map = BTreeMap::new();
+                    while let (Some(key), Some(value)) = (entries.next(), entries.next()) {
+                        match key.to_map_key() {
+                            Some(key) => map.insert(key, value),
+                            None => return Err(RuntimeError::new(format!("{} cannot be used as a map key", key.type_name()), line, column)),
+                        };
+                    }
+                    stack.push(self.allocated(Value::map(map), line, column)?);
+                }
+                Instruction::Cast(index) => {
+                    let value = pop(stack);
+                    stack.push(convert(value, &chunk.types[index as usize], line, column)?);
+                }
+                Instruction::Closure(index) => {
+                    let prototype = &chunk.functions[index as usize];
+                    stack.push(Value::Function(Rc::new(Function {
+                        declaration: prototype.declaration.clone(),
+                        closure: self.environment.clone(),
+                        name: prototype.declaration.name.clone(),
+                        module: self.module,
+                        code: prototype.code.clone(),
+                    })));
+                }
+                Instruction::Iterate(slot) => {
+                    let elements = elements(pop(stack), line, column)?;
+                    locals[slot as usize] = Value::array(elements);
+                    locals[slot as usize + 1] = Value::Integer(0);
+                }
+                Instruction::Next(slot, exit) => {
+                    let (Value::Array(elements), Value::Integer(position)) = (&locals[slot as usize], &locals[slot as usize + 1]) else {
+                        return Err(RuntimeError::new("Loop slots do not hold an array and a position".to_string(), line, column));
+                    };
+                    let position = *position;
+                    let element = elements.borrow().get(position as usize).cloned();
+                    match element {
+                        Some(element) => {
+                            locals[slot as usize + 1] = Value::Integer(position + 1);
+                            stack.push(element);
+                        }
+                        None => pc = exit as usize,
+                    }
+                }
+                Instruction::Throw => return Err(thrown(pop(stack), line, column)),
+                Instruction::Return => return Ok(Flow::Return(pop(stack))),
+                Instruction::Break => return Ok(Flow::Break),
+                Instruction::Continue => return Ok(Flow::Continue),
+                Instruction::Try(index) => {
+                    let block = &chunk.tries[index as usize];
+                    pc = match self.run_try(chunk, locals, block)? {
+                        Flow::Normal => block.after,
+                        Flow::Break => block.on_break,
+                        Flow::Continue => block.on_continue,
+                        flow @ Flow::Return(_) => return Ok(flow),
+                    } as usize;
+                }
+                Instruction::MatchArm(index) => {
+                    let arm = &chunk.arms[index as usize];
+                    let previous = self.environment.clone();
+                    if chunk.environments {
+                        self.environment = Environment::new(Some(previous.clone()));
+                    }
+                    let mut enums = arm.enums.iter();
+                    let mut bound = Vec::new();
+                    let matched = match_value(
+                        &arm.pattern,
+                        &locals[arm.subject as usize],
+                        &mut |enum_| match enums.next() {
+                            Some(Some(access)) => self.load(*access, locals, line, column),
+                            _ => Err(RuntimeError::new(format!("Unresolved variable `{}`", enum_.name), enum_.line, enum_.column)),
+                        },
+                        &mut bound,
+                    )?;
+                    if !matched {
+                        self.environment = previous;
+                        pc = arm.next as usize;
+                    } else if chunk.environments {
+                        for value in bound {
+                            self.environment.define(value);
+                        }
+                    } else {
+                        for (offset, value) in bound.into_iter().enumerate() {
+                            locals[arm.bindings as usize + offset] = value;
+                        }
+                    }
+                }
+                Instruction::NoMatch(slot) => {
+                    return Err(RuntimeError::new(format!("No match arm matches {}", locals[slot as usize]), line, column));
+                }
+                Instruction::Class(index) => {
+                    let prototype = &chunk.classes[index as usize];
+                    let superclass = match prototype.superclass {
+                        true => Some(superclass_of(&prototype.name, pop(stack), line, column)?),
+                        false => None,
+                    };
+                    stack.push(Value::Class(self.create_class(prototype, superclass)));
+                }
+                Instruction::Enum(index) => stack.push(Value::Enum(Rc::new(chunk.enums[index as usize].clone()))),
+                Instruction::Statics => {
+                    if let Value::Class(class) = pop(stack) {
+                        self.initialize_statics(&class)?;
+                    }
+                }
+                Instruction::Fail(message) => return Err(RuntimeError::new(chunk.names[message as usize].clone(), line, column)),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    /// Runs the blocks of a `try` statement the way the interpreter does.
+    fn run_try(&mut self, chunk: &Chunk, locals: &mut [Value], block: &TryBlock) -> Result<Flow, RuntimeError> {
+        let stack = self.spared();
+        let mut result = self.run(chunk, locals, block.body, stack);
+        if let Err(error) = result {
+            result = self.run_catch(chunk, locals, block, error);
+        }
+        if let Some(finally) = block.finally {
+            let stack = self.spared();
+            let flow = self.run(chunk, locals, finally, stack);
+            // Nothing a `finally` block does can keep the run going past a limit.
+            if result.as_ref().is_err_and(|error| error.limit.is_some()) {
+                return result;
+            }
+            match flow? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        result
+    }
+
+    /// Runs the first `catch` block that catches `error`, or passes the error on if none does.
+    fn run_catch(&mut self, chunk: &Chunk, locals: &mut [Value], block: &TryBlock, error: RuntimeError) -> Result<Flow, RuntimeError> {
+        if error.limit.is_some() {
+            return Err(error);
+        }
+        for catch in &block.catches {
+            let (line, column) = (catch.position.0 as usize, catch.position.1 as usize);
+            let class = match catch.class {
+                Some(access) => self.load(access, locals, line, column)?,
+                None => return Err(RuntimeError::new(format!("Unresolved variable `{}`", catch.name), line, column)),
+            };
+            if let Some(caught) = self.caught(class, &error, line, column)? {
+                let mut stack = self.spared();
+                stack.push(caught);
+                return self.run(chunk, locals, catch.code, stack);
+            }
+        }
+        Err(error)
+    }
+
+    /// Reads a variable. Slots of environments are only known to exist once the code declaring
+    /// them has run, which loaded files do not promise, so a missing one is an error.
+    fn load(&self, access: Access, locals: &[Value], line: usize, column: usize) -> Result<Value, RuntimeError> {
+        let value = match access {
+            Access::Local(slot) => locals[slot as usize].clone(),
+            Access::Environment(depth, slot) => self.environment.try_get(depth as usize, slot as usize).ok_or_else(|| undefined(line, column))?,
+            Access::Global(slot) => self.globals.try_get(0, slot as usize).ok_or_else(|| undefined(line, column))?,
+            Access::Native(index) => Value::Native(self.natives.get(index as usize).clone()),
+            Access::NativeClass(index) => Value::HostClass(self.natives.class(index as usize).clone()),
+            Access::NativeConstant(index) => self.natives.constant(index as usize).value.clone(),
+        };
+        Ok(value)
+    }
+
+    fn store(&self, access: Access, locals: &mut [Value], value: Value, line: usize, column: usize) -> Result<(), RuntimeError> {
+        let stored = match access {
+            Access::Local(slot) => {
+                locals[slot as usize] = value;
+                true
+            }
+            Access::Environment(depth, slot) => self.environment.try_assign(depth as usize, slot as usize, value),
+            Access::Global(slot) => self.globals.try_assign(0, slot as usize, value),
+            // Assigning to a native is compiled to an error instead.
+            Access::Native(_) | Access::NativeClass(_) | Access::NativeConstant(_) => false,
+        };
+        match stored {
+            true => Ok(()),
+            false => Err(undefined(line, column)),
+        }
+    }
+}
+
+/// How many emptied vectors an interpreter keeps for reuse, which deep recursion would
+/// otherwise leave behind in great numbers.
+const SPARE: usize = 256;
+
+fn undefined(line: usize, column: usize) -> RuntimeError {
+    RuntimeError::new("The code refers to a variable that does not exist".to_string(), line, column)
+}
+
+fn pop(stack: &mut Vec<Value>) -> Value {
+    stack.pop().expect("the compiler balances the stack")
+}
+
+fn top(stack: &[Value]) -> &Value {
+    stack.last().expect("the compiler balances the stack")
+}
+
+fn top_mut(stack: &mut [Value]) -> &mut Value {
+    stack.last_mut().expect("the compiler balances the stack")
+}
+
+/// Applies an operator to two ints without going through the interpreter, unless the result
+/// overflows or is an error.
+#[inline]
+fn integers(operator: Operator, a: i64, b: i64) -> Option<Value> {
+    match operator {
+        Operator::Add => a.checked_add(b).map(Value::Integer),
+        Operator::Subtract => a.checked_sub(b).map(Value::Integer),
+        Operator::Multiply => a.checked_mul(b).map(Value::Integer),
+        Operator::Divide => a.checked_div(b).map(Value::Integer),
+        Operator::Remainder => a.checked_rem(b).map(Value::Integer),
+        Operator::Equal => Some(Value::Bool(a == b)),
+        Operator::NotEqual => Some(Value::Bool(a != b)),
+        Operator::Less => Some(Value::Bool(a < b)),
+        Operator::LessEqual => Some(Value::Bool(a <= b)),
+        Operator::Greater => Some(Value::Bool(a > b)),
+        Operator::GreaterEqual => Some(Value::Bool(a >= b)),
+        _ => None,
+    }
+}
+
+fn condition(value: Value, line: usize, column: usize) -> Result<bool, RuntimeError> {
+    match value {
+        Value::Bool(boolean) => Ok(boolean),
+        value => Err(RuntimeError::new(format!("Condition must be a bool, got {}", value.type_name()), line, column)),
+    }
+}
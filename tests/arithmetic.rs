@@ -0,0 +1,108 @@
+mod common;
+
+use wally_lang::{Error, FromValue};
+
+/// Evaluates `body` as the body of a function returning `type_` and returns its result.
+fn run<T: FromValue>(type_: &str, body: &str) -> Result<T, Error> {
+    common::run(&format!("import math;\nfn test(): {} {{\n{}\n}}", type_, body))
+}
+
+fn runtime_error(body: &str) -> String {
+    match run::<i64>("int", body).unwrap_err() {
+        Error::Runtime(error) => error.message,
+        error => panic!("expected a runtime error, got {}", error),
+    }
+}
+
+fn compile_error(body: &str) -> String {
+    common::compile_error(&format!("import math;\nfn test(): int {{\n{}\n}}", body))
+}
+
+#[test]
+fn overflow_is_a_runtime_error() {
+    assert_eq!(runtime_error("var n: int = math.MAX_INT; return n + 1;"), "Integer overflow in `9223372036854775807 + 1`");
+    assert_eq!(runtime_error("var n: int = math.MIN_INT; return n - 1;"), "Integer overflow in `-9223372036854775808 - 1`");
+    assert_eq!(runtime_error("var n: int = math.MAX_INT; return n * 2;"), "Integer overflow in `9223372036854775807 * 2`");
+    assert_eq!(runtime_error("var n: int = math.MIN_INT; return n / -1;"), "Integer overflow in `-9223372036854775808 / -1`");
+    assert_eq!(runtime_error("var n: int = math.MIN_INT; return -n;"), "Integer overflow in `-(-9223372036854775808)`");
+}
+
+#[test]
+fn overflow_can_be_caught() {
+    let body = "var n: int = math.MAX_INT; try { return n + 1; } catch (e: Error) { return -1; }";
+    assert_eq!(run::<i64>("int", body).unwrap(), -1);
+}
+
+#[test]
+fn results_in_range_are_unaffected() {
+    assert_eq!(run::<i64>("int", "var n: int = math.MAX_INT; return n - 1 + 1;").unwrap(), i64::MAX);
+    assert_eq!(run::<i64>("int", "var n: int = math.MIN_INT; return n % -1;").unwrap(), 0);
+    assert_eq!(run::<i64>("int", "return -7 / 2;").unwrap(), -3);
+    assert_eq!(run::<i64>("int", "return -7 % 2;").unwrap(), -1);
+}
+
+#[test]
+fn integer_division_by_zero_is_a_runtime_error() {
+    assert_eq!(runtime_error("var zero: int = 0; return 1 / zero;"), "Division by zero");
+    assert_eq!(runtime_error("var zero: int = 0; return 1 % zero;"), "Division by zero");
+}
+
+#[test]
+fn float_division_follows_ieee_754() {
+    assert_eq!(run::<f64>("float", "var zero: float = 0.0; return -1.0 / zero;").unwrap(), f64::NEG_INFINITY);
+    assert!(run::<f64>("float", "var zero: float = 0.0; return zero / zero;").unwrap().is_nan());
+    assert!(run::<f64>("float", "var zero: float = 0.0; return 1.0 % zero;").unwrap().is_nan());
+    assert_eq!(run::<f64>("float", "return math.INFINITY * 2.0;").unwrap(), f64::INFINITY);
+}
+
+#[test]
+fn wrapping_helpers() {
+    assert_eq!(run::<i64>("int", "return math.wrapping_add(math.MAX_INT, 1);").unwrap(), i64::MIN);
+    assert_eq!(run::<i64>("int", "return math.wrapping_sub(math.MIN_INT, 1);").unwrap(), i64::MAX);
+    assert_eq!(run::<i64>("int", "return math.wrapping_mul(math.MAX_INT, 2);").unwrap(), -2);
+    assert_eq!(run::<i64>("int", "return math.wrapping_add(2, 3);").unwrap(), 5);
+}
+
+#[test]
+fn saturating_helpers() {
+    assert_eq!(run::<i64>("int", "return math.saturating_add(math.MAX_INT, 1);").unwrap(), i64::MAX);
+    assert_eq!(run::<i64>("int", "return math.saturating_sub(math.MIN_INT, 1);").unwrap(), i64::MIN);
+    assert_eq!(run::<i64>("int", "return math.saturating_mul(math.MIN_INT, 2);").unwrap(), i64::MIN);
+    assert_eq!(run::<i64>("int", "return math.saturating_mul(-3, 4);").unwrap(), -12);
+}
+
+#[test]
+fn constant_overflow_is_a_compile_error() {
+    let error = compile_error("return 9223372036854775807 + 1;");
+    assert!(error.contains("Integer overflow in `9223372036854775807 + 1`"), "{}", error);
+    let error = compile_error("return (4611686018427387904 * 2) - 1;");
+    assert!(error.contains("Integer overflow in `4611686018427387904 * 2`"), "{}", error);
+    // Only the innermost overflow is reported.
+    assert_eq!(error.matches("Integer overflow").count(), 1, "{}", error);
+    let error = compile_error("return -(-9223372036854775807 - 1);");
+    assert!(error.contains("Integer overflow in `-(-9223372036854775808)`"), "{}", error);
+}
+
+#[test]
+fn constant_division_by_zero_is_a_compile_error() {
+    assert!(compile_error("return 1 / 0;").contains("Division by zero"));
+    assert!(compile_error("return 10 % (2 - 2);").contains("Division by zero"));
+}
+
+#[test]
+fn the_folder_only_reports_what_it_can_prove() {
+    assert_eq!(run::<i64>("int", "return 9223372036854775807 - 1 + 1;").unwrap(), i64::MAX);
+    assert_eq!(run::<i64>("int", "return -9223372036854775807 - 1;").unwrap(), i64::MIN);
+    // Variables are not constant, so this is only found when it runs.
+    assert_eq!(runtime_error("var one: int = 1; return 9223372036854775807 + one;"), "Integer overflow in `9223372036854775807 + 1`");
+}
+
+#[test]
+fn the_minimum_int_can_be_written_as_a_literal() {
+    assert_eq!(run::<i64>("int", "return -9223372036854775808;").unwrap(), i64::MIN);
+    assert_eq!(run::<i64>("int", "return match (math.MIN_INT) { -9223372036854775808 => 1, _ => 0 };").unwrap(), 1);
+    let error = compile_error("return -(-9223372036854775808);");
+    assert!(error.contains("Integer overflow in `-(-9223372036854775808)`"), "{}", error);
+    assert!(compile_error("return 9223372036854775808;").contains("Integer literal out of range: 9223372036854775808"));
+    assert!(compile_error("return 1 - 9223372036854775808;").contains("Integer literal out of range"));
+}
@@ -0,0 +1,215 @@
+use std::thread;
+use wally_lang::bytecode::{FormatError, MAGIC, VERSION};
+use wally_lang::limits::Limits;
+use wally_lang::native::primitive;
+use wally_lang::parser::{self, TypeKind};
+use wally_lang::{Engine, Error, Value};
+
+/// Compiles `program` to bytecode and runs it in another engine, checking that its `test`
+/// function returns what it does when the program is evaluated from source.
+fn round_trip(program: &str) -> String {
+    let mut source = Engine::new();
+    source.eval(program).unwrap();
+    let expected = source.call::<Value>("test", ()).unwrap().to_string();
+
+    let bytes = Engine::new().compile(program).unwrap();
+    let mut compiled = Engine::new();
+    compiled.eval_bytecode(&bytes).unwrap();
+    let result = compiled.call::<Value>("test", ()).unwrap().to_string();
+    assert_eq!(expected, result, "the compiled program disagrees on\n{}", program);
+    result
+}
+
+#[test]
+fn top_level_runs_like_the_source() {
+    let program = "var doubled: int = double(21);
+    var names: array<string> = [];
+    class Dog extends Animal {
+        constructor(name: string) { super(name); }
+        fn speak(): string { return super.speak() + \" woof\"; }
+    }
+    class Animal {
+        static var created: int = 10;
+        var name: string;
+        var tags: map<string, int> = {\"legs\": 4};
+        constructor(name: string) { this.name = name; Animal.created = Animal.created + 1; }
+        fn speak(): string { return this.name; }
+    }
+    fn double(n: int): int { return n * 2; }
+    for (name in [\"a\", \"b\"]) { names.push(name); }
+    if (doubled > 40) { names.push(\"big\"); }
+    fn test(): array<int> {
+        var dog: Dog = new Dog(\"Rex\");
+        return [dog.speak().len(), doubled, names.len(), Animal.created, dog.tags[\"legs\"]];
+    }";
+    assert_eq!(round_trip(program), "[8, 42, 3, 11, 4]");
+}
+
+#[test]
+fn closures_enums_and_matches_survive_compilation() {
+    let program = "enum Shape { Circle(float), Square(float), Point }
+    fn area(shape: Shape): float {
+        return match (shape) {
+            Shape.Circle(r) => 3.0 * r * r,
+            Shape.Square(side) if side > 0.0 => side * side,
+            _ => 0.0,
+        };
+    }
+    fn counter(): fn(): int {
+        var count: int = 0;
+        return fn(): int { count = count + 1; return count; };
+    }
+    fn test(): array<float> {
+        class Local { var value: float = 0.5; }
+        var next: fn(): int = counter();
+        next();
+        var caught: float = 0.0;
+        try { var empty: array<int> = []; empty[1]; } catch (e: Error) { caught = 1.0; }
+        return [area(Shape.Circle(1.0)) + area(Shape.Square(2.0)) + area(Shape.Point), next() as float, new Local().value, caught];
+    }";
+    assert_eq!(round_trip(program), "[7.0, 2.0, 0.5, 1.0]");
+}
+
+#[test]
+fn globals_of_compiled_programs_can_be_read_and_set() {
+    let bytes = Engine::new().compile("var limit: int = 3; fn test(): int { return limit * 2; }").unwrap();
+    let mut engine = Engine::new();
+    engine.eval_bytecode(&bytes).unwrap();
+    assert_eq!(engine.get::<i64>("limit").unwrap(), 3);
+    engine.set("limit", 5).unwrap();
+    assert_eq!(engine.call::<i64>("test", ()).unwrap(), 10);
+    assert_eq!(engine.set("test", 1), Err(Error::NotAssignable("test".to_string())));
+}
+
+#[test]
+fn runtime_errors_keep_their_positions() {
+    let bytes = Engine::new().compile("fn divide(a: int, b: int): int {\n    return a / b;\n}\nfn test(): int { return divide(1, 0); }").unwrap();
+    let mut engine = Engine::new();
+    engine.eval_bytecode(&bytes).unwrap();
+    let error = engine.call::<i64>("test", ()).unwrap_err().to_string();
+    assert!(error.contains("Division by zero at line 2, column 14"), "{}", error);
+    assert!(error.contains("in `divide`"), "{}", error);
+}
+
+#[test]
+fn other_versions_are_rejected() {
+    let mut bytes = Engine::new().compile("fn test(): int { return 1; }").unwrap();
+    assert_eq!(&bytes[..4], b"WLYC");
+    bytes[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    let error = Engine::new().eval_bytecode(&bytes).unwrap_err();
+    assert_eq!(error, Error::Bytecode(FormatError::Version(VERSION + 1)));
+    assert!(error.to_string().contains(&format!("Compiled for bytecode version {}", VERSION + 1)), "{}", error);
+}
+
+#[test]
+fn files_that_are_not_bytecode_are_rejected() {
+    let error = Engine::new().eval_bytecode(b"fn main() {}").unwrap_err();
+    assert_eq!(error, Error::Bytecode(FormatError::NotBytecode));
+    let bytes = Engine::new().compile("fn test(): int { return 1; }").unwrap();
+    let error = Engine::new().eval_bytecode(&bytes[..bytes.len() - 3]).unwrap_err();
+    assert!(matches!(error, Error::Bytecode(FormatError::Corrupt(_))), "{}", error);
+}
+
+#[test]
+fn natives_are_found_by_name() {
+    let mut compiler = Engine::new();
+    compiler.register("triple", vec![primitive(TypeKind::Integer)], primitive(TypeKind::Integer), |arguments| match arguments[0] {
+        Value::Integer(n) => Ok(Value::Integer(n * 3)),
+        _ => Err("expected an int".to_string()),
+    });
+    let bytes = compiler.compile("import math; fn test(): int { return triple(math.abs(-2)); }").unwrap();
+
+    let error = Engine::new().eval_bytecode(&bytes).unwrap_err();
+    assert_eq!(error, Error::Bytecode(FormatError::MissingNative("triple".to_string())));
+
+    let mut engine = Engine::new();
+    engine.register("triple", vec![primitive(TypeKind::Integer)], primitive(TypeKind::Integer), |arguments| match arguments[0] {
+        Value::Integer(n) => Ok(Value::Integer(n * 3)),
+        _ => Err("expected an int".to_string()),
+    });
+    engine.eval_bytecode(&bytes).unwrap();
+    assert_eq!(engine.call::<i64>("test", ()).unwrap(), 6);
+}
+
+#[test]
+fn damaged_files_fail_without_panicking() {
+    let program = "enum Shape { Circle(float), Square(float), Point }
+    class Animal {
+        static var created: int = 0;
+        var name: string;
+        var tags: map<string, int> = {\"legs\": 4};
+        constructor(name: string) { this.name = name; Animal.created = Animal.created + 1; }
+        fn speak(): string { return this.name; }
+    }
+    class Dog extends Animal {
+        constructor(name: string) { super(name); }
+        fn speak(): string { return super.speak() + \" woof\"; }
+    }
+    fn area(shape: Shape): float {
+        return match (shape) {
+            Shape.Circle(r) => 3.0 * r * r,
+            Shape.Square(side) if side > 0.0 => side * side,
+            _ => 0.0,
+        };
+    }
+    fn test(): array<float> {
+        var total: int = 0;
+        var add: fn(int): int = fn(n: int): int { return total + n; };
+        for (n in [1, 2, 3, 4][1:3]) {
+            try {
+                if (n == 3 && total > 0 || n < 0) { break; }
+                total = add(n);
+                throw new Error(\"odd\");
+            } catch (e: Error) {
+                total = total * 2;
+            } finally {
+                total = total + 1;
+            }
+        }
+        var dog: Dog = new Dog(\"Rex\");
+        return [area(Shape.Circle(1.0)), total as float, dog.speak().len() as float, dog.tags[\"legs\"] as float];
+    }";
+    assert_eq!(round_trip(program), "[3.0, 6.0, 8.0, 4.0]");
+    let bytes = Engine::new().compile(program).unwrap();
+    for length in 0..bytes.len() {
+        assert!(Engine::new().eval_bytecode(&bytes[..length]).is_err(), "a file cut to {} bytes loaded", length);
+    }
+    // Whatever a damaged file does, it must load or run into an error rather than a panic.
+    for position in MAGIC.len()..bytes.len() {
+        for flip in [0x01, 0x02, 0x10, 0x80, 0xff] {
+            let mut damaged = bytes.clone();
+            damaged[position] ^= flip;
+            let mut engine = Engine::new();
+            engine.set_limits(Limits::new().with_steps(10_000).with_call_depth(32).with_bytes(1 << 20));
+            if engine.eval_bytecode(&damaged).is_ok() {
+                let _ = engine.call::<Value>("test", ());
+            }
+        }
+    }
+}
+
+/// The deepest nesting the parser accepts of the programs `program` makes for each depth, and that program.
+fn deepest(program: impl Fn(usize) -> String) -> (usize, String) {
+    (1..=parser::MAX_NESTING).rev().map(|depth| (depth, program(depth))).find(|(_, program)| Engine::new().compile(program).is_ok()).unwrap()
+}
+
+#[test]
+fn programs_nested_as_deeply_as_the_parser_allows_survive_compilation() {
+    let run = || {
+        let (depth, program) = deepest(|depth| {
+            let body = (0..depth).fold("return 1;".to_string(), |inner, _| format!("try {{ {} }} catch (e: Error) {{ return 0; }}", inner));
+            format!("fn test(): int {{ {} }}", body)
+        });
+        assert!(depth > parser::MAX_NESTING / 2, "only {} try blocks parse", depth);
+        assert_eq!(round_trip(&program), "1");
+
+        let (depth, program) = deepest(|depth| {
+            let type_ = (0..depth).fold("int".to_string(), |inner, _| format!("array<{}>", inner));
+            format!("var deep: {} = [];\nfn test(): int {{ return deep.len(); }}", type_)
+        });
+        assert!(depth > parser::MAX_NESTING / 2, "only {} array types parse", depth);
+        assert_eq!(round_trip(&program), "0");
+    };
+    // Unoptimized builds parse deep nesting on more stack than test threads have.
+    thread::Builder::new().stack_size(1 << 28).spawn(run).unwrap().join().unwrap();
+}
@@ -0,0 +1,37 @@
+mod common;
+
+use common::{error, exit_code};
+
+#[test]
+fn well_typed_programs_run() {
+    let program = "class Pair { var left: int = 1; var right: float = 2.5; }
+    fn total(pair: Pair, scale: int): float { return pair.right * 2.0 + 1.0; }
+    fn main(args: array<string>): int { var pair: Pair = new Pair(); if (total(pair, 2) != 6.0) { return 1; } return pair.left; }";
+    assert_eq!(exit_code(program), 1);
+}
+
+#[test]
+fn mismatched_types_are_rendered_with_their_source() {
+    let rendered = error("fn main(args: array<string>): int {\n    var x: int = \"one\";\n    return x;\n}");
+    assert!(rendered.contains("error: Expected `int` for `x`, found `string`"), "{}", rendered);
+    assert!(rendered.contains("main.wly:2:18"), "{}", rendered);
+    assert!(rendered.contains("2 |     var x: int = \"one\";"), "{}", rendered);
+    assert!(error("fn main(args: array<string>): int { return 1 + 1.5; }").contains("Cannot apply `+` to `int` and `float`"));
+}
+
+#[test]
+fn calls_are_checked_against_signatures() {
+    let program = "fn add(a: int, b: int): int { return a + b; }\n";
+    let arity = error(&format!("{}fn main(args: array<string>): int {{ return add(1); }}", program));
+    assert!(arity.contains("expects 2 argument(s) but was given 1"), "{}", arity);
+    let typed = error(&format!("{}fn main(args: array<string>): int {{ return add(1, true); }}", program));
+    assert!(typed.contains("Expected `int` for argument 2 of"), "{}", typed);
+    assert!(error("fn main(args: array<string>): int { var n: int = 1; return n(); }").contains("is not callable"));
+}
+
+#[test]
+fn functions_and_main_are_checked() {
+    assert!(error("fn pick(flag: bool): int { if (flag) { return 1; } }\nfn main(args: array<string>): int { return pick(true); }").contains("`pick` does not return a value on every path"));
+    assert!(error("fn main(args: array<string>): string { return \"\"; }").contains("`main` must return `int` or `void`"));
+    assert!(error("fn main(args: array<string>): int { var w: Widget = null; return 0; }").contains("Unknown type `Widget`"));
+}
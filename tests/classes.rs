@@ -0,0 +1,74 @@
+mod common;
+
+use common::{error, exit_code};
+
+const ACCOUNT: &str = "class Account {
+    var owner: string;
+    var balance: int = 0;
+    var deposits: int = 0;
+    constructor(owner: string, opening: int) {
+        this.owner = owner;
+        this.deposit(opening);
+    }
+    fn deposit(amount: int): int {
+        this.balance = this.balance + amount;
+        this.deposits = this.deposits + 1;
+        return this.balance;
+    }
+    fn describe(): string {
+        return this.owner + \"'s account\";
+    }
+}
+";
+
+#[test]
+fn constructors_initialize_fields_and_call_methods() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{
+            var account: Account = new Account(\"ada\", 5);
+            if (account.describe() != \"ada's account\") {{ return 1; }}
+            return account.deposit(10);
+        }}",
+        ACCOUNT
+    );
+    assert_eq!(exit_code(&program), 15);
+}
+
+#[test]
+fn fields_are_read_and_written_through_members() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{
+            var account: Account = new Account(\"bob\", 1);
+            account.balance = account.balance * 100;
+            return account.balance + account.deposits;
+        }}",
+        ACCOUNT
+    );
+    assert_eq!(exit_code(&program), 101);
+}
+
+#[test]
+fn objects_are_shared_by_reference() {
+    let program = format!(
+        "{}fn drain(account: Account): void {{ account.balance = 0; }}
+        fn main(args: array<string>): int {{ var first: Account = new Account(\"c\", 7); var second: Account = first; drain(second); return first.balance; }}",
+        ACCOUNT
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn classes_without_a_constructor_use_field_initializers() {
+    let program = "class Point { var x: int = 3; var y: int = 4; fn length_squared(): int { return this.x * this.x + this.y * this.y; } }
+    fn main(args: array<string>): int { return Point().length_squared(); }";
+    assert_eq!(exit_code(program), 25);
+}
+
+#[test]
+fn unknown_members_and_stray_this_are_errors() {
+    let missing = error(&format!("{}fn main(args: array<string>): int {{ return new Account(\"d\", 1).limit; }}", ACCOUNT));
+    assert!(missing.contains("`Account` has no member `limit`"), "{}", missing);
+    let assigned = error(&format!("{}fn main(args: array<string>): int {{ var account: Account = new Account(\"d\", 1); account.limit = 2; return 0; }}", ACCOUNT));
+    assert!(assigned.contains("`Account` has no member `limit`"), "{}", assigned);
+    assert!(error("fn main(args: array<string>): int { return this.x; }").contains("`this` used outside of a method or constructor"));
+}
@@ -0,0 +1,27 @@
+use std::process::Command;
+
+#[test]
+fn wallyc_output_runs_with_wally() {
+    let directory = std::env::temp_dir().join(format!("wally-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("greet.wly"), "export fn greet(name: string): string { return \"Hello \" + name; }").unwrap();
+    std::fs::write(
+        directory.join("main.wly"),
+        "import greet;\nfn main(args: array<string>): int {\n    println(greet.greet(args[0]));\n    return 3;\n}",
+    )
+    .unwrap();
+    let output = directory.join("main.wlyc");
+
+    let compiled = Command::new(env!("CARGO_BIN_EXE_wallyc")).arg(directory.join("main.wly")).arg("-o").arg(&output).output().unwrap();
+    assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+    // The module holds the imported file, so the source is not needed any more.
+    std::fs::remove_file(directory.join("greet.wly")).unwrap();
+    let run = Command::new(env!("CARGO_BIN_EXE_wally")).arg(&output).arg("World").output().unwrap();
+
+    let usage = Command::new(env!("CARGO_BIN_EXE_wallyc")).arg(directory.join("main.wly")).output().unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(String::from_utf8_lossy(&run.stdout), "Hello World\n");
+    assert_eq!(run.status.code(), Some(3));
+    assert_eq!(usage.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&usage.stderr).contains("usage: wallyc"));
+}
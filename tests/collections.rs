@@ -0,0 +1,158 @@
+mod common;
+
+use std::collections::HashMap;
+use wally_lang::native::primitive;
+use wally_lang::parser::{Type, TypeKind};
+use wally_lang::{ConversionError, Engine, Error, FromValue, Value};
+
+/// Evaluates `body` as the body of a function returning `type_` and returns its result.
+fn run<T: FromValue>(type_: &str, body: &str) -> Result<T, Error> {
+    common::run(&format!("fn test(): {} {{\n{}\n}}", type_, body))
+}
+
+#[test]
+fn push_pop_and_len() {
+    let body = "var xs: array<int> = [1, 2]; xs.push(3); var last: int = xs.pop(); xs.push(last * 10); return xs;";
+    assert_eq!(run::<Vec<i64>>("array<int>", body).unwrap(), vec![1, 2, 30]);
+    assert_eq!(run::<i64>("int", "var xs: array<int> = [1, 2, 3]; return xs.len();").unwrap(), 3);
+}
+
+#[test]
+fn pop_from_empty_array_is_an_error() {
+    let error = run::<i64>("int", "var xs: array<int> = []; return xs.pop();").unwrap_err();
+    assert!(error.to_string().contains("Cannot pop from an empty array"), "{}", error);
+}
+
+#[test]
+fn insert_and_remove_by_index() {
+    let body = "var xs: array<string> = [\"a\", \"c\"]; xs.insert(1, \"b\"); xs.insert(3, \"d\"); xs.remove(0); return xs;";
+    assert_eq!(run::<Vec<String>>("array<string>", body).unwrap(), vec!["b", "c", "d"]);
+    let error = run::<i64>("int", "var xs: array<int> = [1]; xs.insert(3, 2); return 0;").unwrap_err();
+    assert!(error.to_string().contains("Index 3 out of bounds for array of length 1"), "{}", error);
+    assert!(run::<i64>("int", "var xs: array<int> = [1]; return xs.remove(1);").is_err());
+    assert!(run::<i64>("int", "var xs: array<int> = [1]; return xs.remove(-1);").is_err());
+}
+
+#[test]
+fn indexing_out_of_bounds_is_an_error() {
+    let error = run::<i64>("int", "var xs: array<int> = [1, 2]; return xs[2];").unwrap_err();
+    assert!(matches!(error, Error::Runtime(_)), "{}", error);
+    assert!(run::<i64>("int", "var xs: array<int> = [1, 2]; xs[-1] = 0; return 0;").is_err());
+}
+
+#[test]
+fn contains_sort_and_reverse() {
+    assert!(run::<bool>("bool", "var xs: array<int> = [1, 2]; return xs.contains(2);").unwrap());
+    assert!(!run::<bool>("bool", "var xs: array<int> = [1, 2]; return xs.contains(3);").unwrap());
+    assert_eq!(run::<Vec<i64>>("array<int>", "var xs: array<int> = [3, -1, 2]; xs.sort(); return xs;").unwrap(), vec![-1, 2, 3]);
+    let body = "var xs: array<string> = [\"pear\", \"apple\", \"fig\"]; xs.sort(); xs.reverse(); return xs;";
+    assert_eq!(run::<Vec<String>>("array<string>", body).unwrap(), vec!["pear", "fig", "apple"]);
+}
+
+#[test]
+fn only_ordered_elements_can_be_sorted() {
+    let error = run::<i64>("int", "var xs: array<bool> = [true, false]; xs.sort(); return 0;").unwrap_err();
+    assert!(error.to_string().contains("Cannot sort `array<bool>`"), "{}", error);
+}
+
+#[test]
+fn map_filter_and_reduce() {
+    let body = "var xs: array<int> = [1, 2, 3, 4]; return xs.filter(fn(x: int): bool => x % 2 == 0).map(fn(x: int): int => x * 10);";
+    assert_eq!(run::<Vec<i64>>("array<int>", body).unwrap(), vec![20, 40]);
+    let body = "var xs: array<int> = [1, 2, 3, 4]; return xs.reduce(0, fn(sum: int, x: int): int => sum + x * x);";
+    assert_eq!(run::<i64>("int", body).unwrap(), 30);
+    let body = "var xs: array<int> = [1, 2, 3]; return xs.map(fn(x: int): float => x as float / 2.0);";
+    assert_eq!(run::<Vec<f64>>("array<float>", body).unwrap(), vec![0.5, 1.0, 1.5]);
+}
+
+#[test]
+fn callbacks_are_type_checked() {
+    let error = run::<Vec<i64>>("array<int>", "var xs: array<int> = [1]; return xs.filter(fn(x: string): bool => true);").unwrap_err();
+    assert!(matches!(error, Error::Compile(_)), "{}", error);
+    let error = run::<Vec<i64>>("array<int>", "var xs: array<int> = [1]; return xs.map(fn(x: int): string => \"a\");").unwrap_err();
+    assert!(matches!(error, Error::Compile(_)), "{}", error);
+}
+
+#[test]
+fn map_methods() {
+    let body = r#"
+        var ages: map<string, int> = {"ann": 31};
+        ages.set("bob", 25);
+        ages.set("ann", 32);
+        var removed: int = ages.remove("bob");
+        return ages.get("ann", 0) + ages.get("cy", -1) + removed + ages.len();
+    "#;
+    assert_eq!(run::<i64>("int", body).unwrap(), 32 - 1 + 25 + 1);
+    assert!(run::<bool>("bool", r#"var m: map<string, int> = {"a": 1}; return m.contains_key("a");"#).unwrap());
+    let error = run::<i64>("int", r#"var m: map<string, int> = {}; return m.remove("a");"#).unwrap_err();
+    assert!(error.to_string().contains("Key a not found in map"), "{}", error);
+}
+
+#[test]
+fn keys_and_values_are_in_key_order() {
+    let map = r#"var m: map<string, int> = {"b": 2, "c": 3, "a": 1};"#;
+    assert_eq!(run::<Vec<String>>("array<string>", &format!("{} return m.keys();", map)).unwrap(), vec!["a", "b", "c"]);
+    assert_eq!(run::<Vec<i64>>("array<int>", &format!("{} return m.values();", map)).unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn for_loops_over_arrays_maps_and_strings() {
+    let body = "var sum: int = 0; for (x in [1, 2, 3]) { if (x == 2) { continue; } sum = sum + x; } return sum;";
+    assert_eq!(run::<i64>("int", body).unwrap(), 4);
+    let body = r#"var m: map<string, int> = {"b": 2, "a": 1}; var total: int = 0; for (key in m) total = total * 10 + m[key]; return total;"#;
+    assert_eq!(run::<i64>("int", body).unwrap(), 12);
+    let body = r#"var count: int = 0; for (c in "héllo") { if (c == 'l') { break; } count = count + 1; } return count;"#;
+    assert_eq!(run::<i64>("int", body).unwrap(), 2);
+}
+
+#[test]
+fn for_loops_iterate_over_a_snapshot() {
+    let body = "var xs: array<int> = [1, 2]; for (x in xs) { xs.push(x); } return xs;";
+    assert_eq!(run::<Vec<i64>>("array<int>", body).unwrap(), vec![1, 2, 1, 2]);
+}
+
+#[test]
+fn for_loop_variable_is_typed_and_scoped() {
+    let error = run::<i64>("int", r#"for (x in [1]) { var s: string = x; } return 0;"#).unwrap_err();
+    assert!(matches!(error, Error::Compile(_)), "{}", error);
+    let error = run::<i64>("int", "for (x in [1]) {} return x;").unwrap_err();
+    assert!(matches!(error, Error::Compile(_)), "{}", error);
+    let error = run::<i64>("int", "for (x in 3) {} return 0;").unwrap_err();
+    assert!(error.to_string().contains("Cannot iterate over `int`"), "{}", error);
+}
+
+#[test]
+fn unknown_methods_are_compile_errors() {
+    let error = run::<i64>("int", "var xs: array<int> = []; return xs.size();").unwrap_err();
+    assert!(error.to_string().contains("`array<int>` has no method `size`"), "{}", error);
+    let error = run::<i64>("int", "var xs: array<int> = []; var f: fn(): int = xs.len; return f();").unwrap_err();
+    assert!(error.to_string().contains("`array<int>.len` can only be called"), "{}", error);
+}
+
+#[test]
+fn host_values_are_checked_element_by_element() {
+    let mut engine = Engine::new();
+    engine.eval("var xs: array<int> = []; var counts: map<string, int> = {}; fn total(ys: array<array<int>>): int { return ys.len(); }").unwrap();
+    engine.set("xs", vec![1, 2]).unwrap();
+    let error = engine.set("xs", vec![Value::Integer(1), Value::string("two")]).unwrap_err();
+    assert_eq!(error, Error::Conversion(ConversionError { expected: "int", found: "string" }));
+    let counts: HashMap<String, Value> = HashMap::from([("a".to_string(), Value::Bool(true))]);
+    let error = engine.set("counts", counts).unwrap_err();
+    assert_eq!(error, Error::Conversion(ConversionError { expected: "int", found: "bool" }));
+    assert_eq!(engine.call::<i64>("total", (vec![vec![1], vec![2]],)).unwrap(), 2);
+    assert!(engine.call::<i64>("total", (vec![vec![1.5]],)).is_err());
+}
+
+#[test]
+fn ill_typed_values_from_natives_are_errors() {
+    let mut engine = Engine::new();
+    let ints = Type::new(TypeKind::Array(Box::new(primitive(TypeKind::Integer))), 0, 0);
+    engine.register("mixed", Vec::new(), ints, |_| Ok(Value::array(vec![Value::Integer(1), Value::string("a")])));
+    engine.register("index", Vec::new(), primitive(TypeKind::Integer), |_| Ok(Value::string("0")));
+    engine.eval("fn sorted(): array<int> { var xs: array<int> = mixed(); xs.sort(); return xs; }
+    fn inserted(): array<int> { var xs: array<int> = []; xs.insert(index(), 1); return xs; }").unwrap();
+    let error = engine.call::<Vec<Value>>("sorted", ()).unwrap_err();
+    assert!(error.to_string().contains("Cannot sort an array holding both int and string"), "{}", error);
+    let error = engine.call::<Vec<i64>>("inserted", ()).unwrap_err();
+    assert!(error.to_string().contains("Expected an int, found string"), "{}", error);
+}
@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use wally_lang::{Engine, Error, FromValue};
+
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes `files` to a directory of their own and runs its `main.wly` with `wally`.
+pub fn wally_files(files: &[(&str, &str)], args: &[&str]) -> Output {
+    spawn(files, args, b"")
+}
+
+/// Runs `program` with `wally`, feeding it `input` on stdin.
+pub fn wally_with_input(program: &str, input: &[u8]) -> Output {
+    spawn(&[("main.wly", program)], &[], input)
+}
+
+fn spawn(files: &[(&str, &str)], args: &[&str], input: &[u8]) -> Output {
+    let run = RUNS.fetch_add(1, Ordering::Relaxed);
+    let directory: PathBuf = std::env::temp_dir().join(format!("wally-test-{}-{}", std::process::id(), run));
+    for (path, source) in files {
+        let path = directory.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source).unwrap();
+    }
+    let mut child = Command::new(env!("CARGO_BIN_EXE_wally"))
+        .arg(directory.join("main.wly"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_dir_all(&directory).unwrap();
+    output
+}
+
+/// Runs `program` with `wally`.
+pub fn wally(program: &str, args: &[&str]) -> Output {
+    wally_files(&[("main.wly", program)], args)
+}
+
+/// Returns the exit code `main` returned in a run that reported nothing.
+pub fn status(output: Output) -> i32 {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.is_empty(), "expected the program to run, got\n{}", stderr);
+    output.status.code().unwrap()
+}
+
+/// Returns what a run that failed printed to stderr.
+pub fn failure(output: Output) -> String {
+    assert_eq!(output.status.code(), Some(1), "expected the program to fail");
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// Runs `program` and returns the exit code its `main` returned.
+pub fn exit_code(program: &str) -> i32 {
+    status(wally(program, &[]))
+}
+
+/// Runs `program`, which is expected to fail, and returns what it printed to stderr.
+pub fn error(program: &str) -> String {
+    failure(wally(program, &[]))
+}
+
+/// Evaluates `program` in `engine` and calls its `test` function.
+pub fn call_test<T: FromValue>(mut engine: Engine, program: &str) -> Result<T, Error> {
+    engine.eval(program)?;
+    engine.call("test", ())
+}
+
+/// Evaluates `program` and calls its `test` function.
+pub fn run<T: FromValue>(program: &str) -> Result<T, Error> {
+    call_test(Engine::new(), program)
+}
+
+/// Evaluates `program` in `engine`, which is expected to reject it, and returns the rendered errors.
+pub fn compile_error_in(mut engine: Engine, program: &str) -> String {
+    match engine.eval(program) {
+        Err(Error::Compile(rendered)) => rendered,
+        result => panic!("expected a compile error, got {:?}", result),
+    }
+}
+
+/// Evaluates `program`, which is expected to be rejected, and returns the rendered errors.
+pub fn compile_error(program: &str) -> String {
+    compile_error_in(Engine::new(), program)
+}
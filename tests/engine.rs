@@ -0,0 +1,151 @@
+mod common;
+
+use common::compile_error;
+use std::collections::HashMap;
+use std::thread;
+use wally_lang::{ConversionError, Engine, Error, Value};
+
+#[test]
+fn syntax_errors_are_compile_errors() {
+    let rendered = compile_error("fn main(): void {\n    var x: = 1;\n}");
+    assert!(rendered.starts_with("error: Expected type, found Equal\n --> <eval>:2:12\n"), "{}", rendered);
+    assert!(compile_error("fn main(): void { println(1;").contains("Expected RightParen"));
+    assert!(compile_error("class A { static constructor() {} }").contains("Constructors cannot be static"));
+    assert!(compile_error("fn f(): void { 1 = 2; }").contains("Invalid assignment target"));
+    assert!(compile_error("fn f(): void { try {} }").contains("Expected `catch` or `finally`"));
+    assert!(compile_error("fn f(): void {").contains("Unexpected end of file"));
+}
+
+#[test]
+fn lexical_errors_are_compile_errors() {
+    assert!(compile_error("var s: string = \"a\\q\";").contains("Invalid escape sequence"));
+    assert!(compile_error("var c: char = 'ab';").contains("Invalid character literal"));
+    assert!(compile_error("var n: int = 99999999999999999999;").contains("Integer literal out of range"));
+}
+
+#[test]
+fn deeply_nested_programs_are_compile_errors() {
+    let nested = |depth: usize, open: &str, inner: &str, close: &str| format!("{}{}{}", open.repeat(depth), inner, close.repeat(depth));
+    let programs = [
+        format!("fn test(): int {{ return {}; }}", nested(100, "(", "1", ")")),
+        format!("fn test(): int {{ return {}; }}", nested(100, "-", "1", "")),
+        format!("fn test(): int {{ return {}; }}", nested(100, "", "1", " + 1")),
+        format!("fn test(): int {{ {} return 1; }}", nested(60, "if (true) { ", "", "}")),
+        format!("fn test(): int {{ var xs: {} = []; return 1; }}", nested(100, "array<", "int", ">")),
+    ];
+    // Unoptimized builds use tens of kilobytes of stack for each level, more than test threads have.
+    let tester = thread::Builder::new().stack_size(64 << 20).spawn(move || {
+        for program in &programs {
+            let mut engine = Engine::new();
+            engine.eval(program).unwrap();
+            engine.call::<i64>("test", ()).unwrap();
+        }
+        let rendered = compile_error(&format!("fn test(): int {{ return {}; }}", nested(10_000, "(", "1", ")")));
+        assert!(rendered.contains("Nested too deeply, more than 128 levels"), "{}", rendered);
+        assert!(compile_error(&format!("fn test(): int {{ return {}; }}", nested(10_000, "", "1", " + 1"))).contains("Nested too deeply"));
+        assert!(compile_error(&format!("fn test(): void {{ {} }}", nested(10_000, "if (true) { ", "", "}"))).contains("Nested too deeply"));
+    });
+    tester.unwrap().join().unwrap();
+}
+
+#[test]
+fn syntax_errors_in_imported_files_point_at_them() {
+    let directory = std::env::temp_dir().join(format!("wally-syntax-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("broken.wly"), "fn f(): int { return 1 }").unwrap();
+    std::fs::write(directory.join("main.wly"), "import broken;\nfn main(): void {}").unwrap();
+    let error = Engine::new().eval_file(directory.join("main.wly")).unwrap_err().to_string();
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert!(error.contains("Expected Semicolon, got RightBrace"), "{}", error);
+    assert!(error.contains("broken.wly:1:24"), "{}", error);
+}
+
+const PROGRAM: &str = "var limit: int = 10;
+const var NAME: string = \"engine\";
+class Point { var x: int; constructor(x: int) { this.x = x; } }
+fn add(a: int, b: int): int { return a + b; }
+fn positions(words: array<string>): map<string, int> { var result: map<string, int> = {}; result[words[0]] = 0; result[words[1]] = 1; return result; }
+fn nothing(): void {}
+fn over(): bool { return limit > 20; }
+fn fail(n: int): int {
+    return n / 0;
+}";
+
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.eval(PROGRAM).unwrap();
+    engine
+}
+
+#[test]
+fn functions_are_called_with_rust_values() {
+    let mut engine = engine();
+    assert_eq!(engine.call::<i64>("add", (2, 40)).unwrap(), 42);
+    let positions: HashMap<String, i64> = engine.call("positions", (vec!["a", "abc"],)).unwrap();
+    assert_eq!(positions, HashMap::from([("a".to_string(), 0), ("abc".to_string(), 1)]));
+    assert_eq!(engine.call::<Option<i64>>("add", (1, 1)).unwrap(), Some(2));
+    assert_eq!(engine.call::<Option<String>>("nothing", ()).unwrap(), None);
+    let point = engine.call::<Value>("Point", (7,)).unwrap();
+    assert_eq!(point.type_name(), "object");
+}
+
+#[test]
+fn globals_are_read_and_written() {
+    let mut engine = engine();
+    assert_eq!(engine.get::<i64>("limit").unwrap(), 10);
+    assert_eq!(engine.get::<String>("NAME").unwrap(), "engine");
+    assert!(!engine.call::<bool>("over", ()).unwrap());
+    engine.set("limit", 30).unwrap();
+    assert!(engine.call::<bool>("over", ()).unwrap());
+    assert_eq!(engine.set("NAME", "other"), Err(Error::NotAssignable("NAME".to_string())));
+    assert_eq!(engine.set("missing", 1), Err(Error::Undefined("missing".to_string())));
+    assert_eq!(engine.get::<i64>("missing"), Err(Error::Undefined("missing".to_string())));
+    assert_eq!(engine.call::<i64>("missing", ()), Err(Error::Undefined("missing".to_string())));
+}
+
+#[test]
+fn values_of_the_wrong_type_are_conversion_errors() {
+    let mut engine = engine();
+    let expected = |expected, found| Error::Conversion(ConversionError { expected, found });
+    assert_eq!(engine.set("limit", "ten").unwrap_err(), expected("int", "string"));
+    assert_eq!(engine.call::<i64>("add", (1, 2.5)).unwrap_err(), expected("int", "float"));
+    assert_eq!(engine.call::<String>("add", (1, 2)).unwrap_err(), expected("string", "int"));
+    assert_eq!(engine.get::<bool>("limit").unwrap_err(), expected("bool", "int"));
+}
+
+#[test]
+fn compile_and_runtime_errors_are_told_apart() {
+    let error = Engine::new().eval("fn main(): int {\n    return \"no\";\n}").unwrap_err();
+    assert!(matches!(&error, Error::Compile(rendered) if rendered.contains(" --> <eval>:2:12")), "{}", error);
+    let error = engine().call::<i64>("fail", (1,)).unwrap_err();
+    assert!(matches!(error, Error::Runtime(_)));
+    assert!(error.to_string().contains("Division by zero at line 9, column 14"), "{}", error);
+}
+
+#[test]
+fn files_import_relative_to_their_directory_or_the_root() {
+    let directory = std::env::temp_dir().join(format!("wally-engine-{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("lib")).unwrap();
+    std::fs::write(directory.join("lib/util.wly"), "export fn twice(n: int): int { return n * 2; }").unwrap();
+    std::fs::write(directory.join("main.wly"), "import lib.util;\nfn test(): int { var unused: int = 1; return util.twice(21); }").unwrap();
+    let mut engine = Engine::new();
+    engine.eval_file(directory.join("main.wly")).unwrap();
+    let from_file = engine.call::<i64>("test", ());
+
+    let mut rooted = Engine::new();
+    rooted.set_root(&directory);
+    let from_source = rooted.eval("import lib.util;\nfn test(): int { return util.twice(5); }").and_then(|_| rooted.call::<i64>("test", ()));
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(from_file.unwrap(), 42);
+    assert_eq!(from_source.unwrap(), 10);
+}
+
+#[test]
+fn warnings_are_kept_for_the_last_program() {
+    let mut engine = Engine::new();
+    engine.eval("fn test(): int { return 1; println(\"never\"); }").unwrap();
+    assert_eq!(engine.warnings().len(), 1);
+    assert!(engine.warnings()[0].contains("Unreachable statement"), "{}", engine.warnings()[0]);
+    engine.eval("fn test(): int { return 1; }").unwrap();
+    assert!(engine.warnings().is_empty());
+}
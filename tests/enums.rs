@@ -0,0 +1,48 @@
+mod common;
+
+use common::{error, exit_code};
+
+const SHAPE: &str = "enum Shape { Circle(float), Rect(float, float), Empty }\n";
+
+#[test]
+fn variants_compare_by_their_payloads() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{
+            if (Shape.Circle(1.0) != Shape.Circle(1.0)) {{ return 1; }}
+            if (Shape.Circle(1.0) == Shape.Circle(2.0)) {{ return 2; }}
+            if (Shape.Rect(1.0, 2.0) == Shape.Rect(2.0, 1.0)) {{ return 3; }}
+            if (Shape.Empty != Shape.Empty || Shape.Empty == Shape.Circle(0.0)) {{ return 4; }}
+            return 0;
+        }}",
+        SHAPE
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn enums_are_types_of_variables_fields_and_arrays() {
+    let program = format!(
+        "{}class Canvas {{ var shapes: array<Shape> = [Shape.Empty, Shape.Empty]; var last: Shape = Shape.Empty; }}
+        fn main(args: array<string>): int {{
+            var canvas: Canvas = new Canvas();
+            canvas.last = Shape.Rect(1.0, 2.0);
+            canvas.shapes[1] = canvas.last;
+            if (canvas.shapes[0] != Shape.Empty || canvas.shapes[1] != Shape.Rect(1.0, 2.0)) {{ return 1; }}
+            return 0;
+        }}",
+        SHAPE
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn variants_and_their_payloads_are_checked() {
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var s: Shape = Shape.Rect(1.0); return 0; }}", SHAPE));
+    assert!(message.contains("`Shape.Rect` expects 2 argument(s) but was given 1"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var s: Shape = Shape.Circle(\"big\"); return 0; }}", SHAPE));
+    assert!(message.contains("Expected `float`"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var s: Shape = Shape.Square(1.0); return 0; }}", SHAPE));
+    assert!(message.contains("`Shape` has no variant `Square`"), "{}", message);
+    let message = error("enum Color { Red, Red }");
+    assert!(message.contains("Variant `Red` is already declared in enum `Color`"), "{}", message);
+}
@@ -0,0 +1,133 @@
+use std::thread;
+use wally_lang::{Engine, Error};
+
+const PROGRAM: &str = "class Counter {
+    var count: int;
+    constructor(start: int) {
+        this.count = 10 / start;
+    }
+    fn step(by: int): int {
+        return this.count / by;
+    }
+}
+
+fn divide(a: int, b: int): int {
+    return a / b;
+}
+
+fn test(): int {
+    return divide(1, 0);
+}
+
+fn stepped(): int {
+    var counter: Counter = new Counter(1);
+    return counter.step(0);
+}
+
+fn constructed(): int {
+    var counter: Counter = new Counter(0);
+    return counter.count;
+}
+
+fn indexed(): int {
+    var xs: array<int> = [1, 2];
+    return xs[5];
+}";
+
+fn runtime_error(function: &str) -> (Engine, Error) {
+    let mut engine = Engine::new();
+    engine.eval(PROGRAM).unwrap();
+    let error = engine.call::<i64>(function, ()).unwrap_err();
+    (engine, error)
+}
+
+#[test]
+fn errors_carry_the_wally_call_stack() {
+    let (_, error) = runtime_error("test");
+    let error = match error {
+        Error::Runtime(error) => error,
+        error => panic!("expected a runtime error, got {}", error),
+    };
+    assert_eq!(error.message, "Division by zero");
+    assert_eq!((error.line, error.column), (12, 14));
+    let frames: Vec<(&str, usize, usize)> = error.trace.iter().map(|frame| (frame.function.as_str(), frame.line, frame.column)).collect();
+    // The outermost call was made by the host, which has no position in the source.
+    assert_eq!(frames, vec![("divide", 16, 12), ("test", 0, 0)]);
+}
+
+#[test]
+fn methods_and_constructors_are_named_after_their_class() {
+    let (_, error) = runtime_error("stepped");
+    let Error::Runtime(error) = error else { panic!("expected a runtime error") };
+    assert_eq!(error.trace[0].function, "Counter.step");
+    let (_, error) = runtime_error("constructed");
+    let Error::Runtime(error) = error else { panic!("expected a runtime error") };
+    assert_eq!(error.trace[0].function, "Counter.constructor");
+}
+
+#[test]
+fn errors_render_with_the_source_of_each_frame() {
+    let (engine, error) = runtime_error("test");
+    let rendered = engine.render(&error);
+    assert!(rendered.starts_with("error: Division by zero\n  --> <eval>:12:14\n"), "{}", rendered);
+    assert!(rendered.contains("return a / b;"), "{}", rendered);
+    assert!(rendered.contains("= note: in `divide`, called here\n  --> <eval>:16:12"), "{}", rendered);
+    assert!(rendered.contains("return divide(1, 0);"), "{}", rendered);
+    assert!(!rendered.contains("in `test`"), "{}", rendered);
+}
+
+#[test]
+fn out_of_bounds_access_is_an_error_not_a_panic() {
+    let (engine, error) = runtime_error("indexed");
+    assert!(matches!(error, Error::Runtime(_)), "{}", error);
+    assert!(engine.render(&error).contains("Index 5 out of bounds for array of length 2"));
+}
+
+#[test]
+fn errors_in_imported_modules_render_in_their_file() {
+    let root = std::env::temp_dir().join(format!("wally-errors-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("util.wly"), "export fn check(n: int): int {\n    return 100 / n;\n}\n").unwrap();
+    let mut engine = Engine::new();
+    engine.set_root(&root);
+    engine.eval("import util;\nfn test(): int {\n    return util.check(0);\n}").unwrap();
+    let error = engine.call::<i64>("test", ()).unwrap_err();
+    let rendered = engine.render(&error);
+    assert!(rendered.contains("util.wly:2:16"), "{}", rendered);
+    assert!(rendered.contains("in `check`, called here\n --> <eval>:3:16"), "{}", rendered);
+}
+
+#[test]
+fn errors_in_the_top_level_are_returned() {
+    let mut engine = Engine::new();
+    let error = engine.eval("var xs: array<int> = [];\nvar first: int = xs[0];").unwrap_err();
+    assert!(matches!(error, Error::Runtime(_)), "{}", error);
+    assert!(engine.render(&error).contains("--> <eval>:2:20"), "{}", engine.render(&error));
+}
+
+#[test]
+fn deep_recursion_is_traced_in_a_few_lines() {
+    // The default call depth limit is deeper than a test thread's stack in unoptimized builds.
+    let run = || {
+        let mut engine = Engine::new();
+        engine.eval("fn down(n: int): int {\n    return down(n + 1);\n}\nfn ping(n: int): int {\n    return pong(n);\n}\nfn pong(n: int): int {\n    return ping(n);\n}").unwrap();
+
+        let error = engine.call::<i64>("down", (0i64,)).unwrap_err();
+        let shown = error.to_string();
+        assert_eq!(shown.lines().count(), 3, "{}", shown);
+        assert!(shown.ends_with("in `down` at line 2, column 12\n    ... repeated 999 more times"), "{}", shown);
+        let rendered = engine.render(&error);
+        assert!(rendered.contains("in `down`, called here 999 times in a row"), "{}", rendered);
+        assert_eq!(rendered.lines().count(), 10, "{}", rendered);
+
+        // Calls alternating between two functions never repeat the one before, so the middle is left out.
+        let error = engine.call::<i64>("ping", (0i64,)).unwrap_err();
+        let shown = error.to_string();
+        assert_eq!(shown.lines().count(), 22, "{}", shown);
+        assert!(shown.contains("... 980 calls not shown"), "{}", shown);
+        let rendered = engine.render(&error);
+        assert!(rendered.contains(", from 979 calls not shown"), "{}", rendered);
+        assert_eq!(rendered.lines().count(), 5 + 20 * 5, "{}", rendered);
+    };
+    thread::Builder::new().stack_size(1 << 28).spawn(run).unwrap().join().unwrap();
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use common::{error, exit_code, wally};
+
+#[test]
+fn expressions_evaluate_to_values() {
+    let program = "fn main(args: array<string>): int {
+        if (2 + 3 * 4 - 10 / 3 % 2 != 13) { return 1; }
+        if (1.5 * 2.0 - 0.5 != 2.5) { return 2; }
+        if (!(!(1 < 2) || 3 >= 3 && 'a' != 'b')) { return 3; }
+        if (\"wal\" + \"ly\" != \"wally\") { return 4; }
+        var xs: array<int> = [1, 2, 3];
+        xs[1] = 20;
+        if (xs[0] + xs[1] + xs[2] != 24) { return 5; }
+        var ages: map<string, int> = {\"ada\": 36};
+        ages[\"alan\"] = 41;
+        if (ages[\"ada\"] + ages[\"alan\"] != 77) { return 6; }
+        return 0;
+    }";
+    assert_eq!(exit_code(program), 0);
+}
+
+#[test]
+fn statements_run_in_order() {
+    let program = "fn main(args: array<string>): int {
+        var total: int = 0;
+        var i: int = 0;
+        while (i < 10) {
+            i = i + 1;
+            if (i % 2 == 0) { continue; } else if (i > 7) { break; }
+            total = total + i;
+        }
+        return total;
+    }";
+    assert_eq!(exit_code(program), 1 + 3 + 5 + 7);
+}
+
+#[test]
+fn functions_call_each_other_recursively() {
+    let program = "fn fib(n: int): int { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+    const var offset: int = fib(4);
+    fn main(args: array<string>): int { return fib(10) + offset; }";
+    assert_eq!(exit_code(program), 55 + 3);
+}
+
+#[test]
+fn runtime_errors_stop_the_program() {
+    let program = "fn main(args: array<string>): int { var xs: array<int> = [1, 2]; return xs[5]; }";
+    assert!(error(program).contains("Index 5 out of bounds for array of length 2"));
+    assert!(error("fn helper(): int { return 1; }").contains("No `main` function found"));
+}
+
+#[test]
+fn main_receives_the_arguments() {
+    let program = "fn main(args: array<string>): int {
+        if (args[0] != \"hello\" || args[1] != \"world\") { return 1; }
+        return 42;
+    }";
+    assert_eq!(wally(program, &["hello", "world"]).status.code(), Some(42));
+}
+
+#[test]
+fn exit_statuses_out_of_range_are_errors() {
+    let message = error("fn main(args: array<string>): int { return 4294967296; }");
+    assert!(message.contains("Exit status 4294967296 returned by `main` is out of range"), "{}", message);
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use common::{error, exit_code, run, wally};
+
+const ERRORS: &str = "class NotFound extends Error {
+    var key: string;
+    constructor(key: string) { super(\"Missing \" + key); this.key = key; }
+}
+class Timeout extends Error {
+    constructor() { super(\"Too slow\"); }
+}
+fn lookup(key: string): string {
+    if (key == \"slow\") { throw new Timeout(); }
+    throw new NotFound(key);
+}
+";
+
+#[test]
+fn the_first_matching_catch_runs() {
+    let program = format!(
+        "{}fn attempt(key: string): string {{
+            try {{ return lookup(key); }}
+            catch (e: NotFound) {{ return \"not found: \" + e.key; }}
+            catch (e: Error) {{ return \"error: \" + e.message; }}
+        }}
+        fn main(args: array<string>): int {{ if (attempt(\"a\") + \", \" + attempt(\"slow\") != \"not found: a, error: Too slow\") {{ return 1; }} return 0; }}",
+        ERRORS
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn finally_runs_however_the_block_is_left() {
+    let program = "var log: string = \"\";
+    fn returns(): int { try { return 1; } finally { log = log + \"r\"; } }
+    fn throws(): void { try { throw new Error(\"x\"); } finally { log = log + \"t\"; } }
+    fn main(args: array<string>): int {
+        returns();
+        try { throws(); } catch (e: Error) { log = log + \"c\"; }
+        var i: int = 0;
+        while (true) { try { i = i + 1; if (i == 2) { break; } continue; } finally { log = log + (match (i) { 1 => \"1\", _ => \"2\" }); } }
+        if (log != \"rtc12\") { return 1; }
+        return 0;
+    }";
+    assert_eq!(exit_code(program), 0);
+}
+
+#[test]
+fn errors_pass_through_catches_that_do_not_match() {
+    let program = format!(
+        "{}fn inner(): string {{ try {{ return lookup(\"slow\"); }} catch (e: NotFound) {{ return \"wrong\"; }} }}
+        fn rethrow(): string {{ try {{ return inner(); }} catch (e: Timeout) {{ try {{ throw e; }} catch (again: Error) {{ return \"rethrown \" + again.message; }} }} }}
+        fn main(args: array<string>): int {{ if (rethrow() != \"rethrown Too slow\") {{ return 1; }} return 0; }}",
+        ERRORS
+    );
+    assert_eq!(exit_code(&program), 0);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ lookup(\"k\"); return 0; }}", ERRORS));
+    assert!(message.contains("Uncaught `NotFound`: Missing k"), "{}", message);
+}
+
+#[test]
+fn only_errors_are_thrown_and_caught() {
+    assert!(error("fn main(args: array<string>): int { throw \"oops\"; }").contains("Only instances of `Error` can be thrown, found `string`"));
+    let message = error("class Plain {} fn main(args: array<string>): int { try { return 0; } catch (e: Plain) { return 1; } }");
+    assert!(message.contains("`Plain` does not extend `Error` and cannot be caught"), "{}", message);
+}
+
+#[test]
+fn uncaught_errors_end_main_with_a_stack_trace() {
+    let output = wally("fn fail(): void {\n    throw new Error(\"boom\");\n}\nfn main(): int {\n    fail();\n    return 0;\n}", &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr.contains("Uncaught `Error`: boom"), "{}", stderr);
+    assert!(stderr.contains("main.wly:2:5"), "{}", stderr);
+    assert!(stderr.contains("in `fail`, called here"), "{}", stderr);
+    assert!(stderr.contains("main.wly:5:5"), "{}", stderr);
+}
+
+#[test]
+fn only_the_prelude_error_catches_interpreter_errors() {
+    let program = "fn test(): string {
+        class Error { var code: int = 7; }
+        try { var xs: array<int> = []; xs[1]; } catch (e: Error) { return \"caught\"; }
+        return \"not caught\";
+    }";
+    let error = run::<String>(program).unwrap_err();
+    assert!(error.to_string().contains("Index 1 out of bounds for array of length 0"), "{}", error);
+}
@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+use wally_lang::native::Capability;
+use wally_lang::{Engine, Error};
+
+/// A new empty directory for one test to work in.
+fn scratch(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("wally-fs-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Evaluates `body` as the body of a function returning `type_`, with `dir` bound to
+/// `directory`, in an engine granted `capabilities`.
+fn run<T: wally_lang::FromValue>(capabilities: &[Capability], directory: &Path, type_: &str, body: &str) -> Result<T, Error> {
+    let mut engine = Engine::new();
+    for capability in capabilities {
+        engine.allow(*capability);
+    }
+    engine.eval(&format!("import fs;\nvar dir: string = \"\";\nfn test(): {} {{\n{}\n}}", type_, body))?;
+    engine.set("dir", directory.display().to_string())?;
+    engine.call("test", ())
+}
+
+const ALL: [Capability; 2] = [Capability::ReadFiles, Capability::WriteFiles];
+
+#[test]
+fn write_append_and_read() {
+    let directory = scratch("write");
+    let body = r#"var path: string = dir + "/notes.txt"; fs.write(path, "héllo"); fs.append(path, " wörld"); return fs.read(path);"#;
+    assert_eq!(run::<String>(&ALL, &directory, "string", body).unwrap(), "héllo wörld");
+    assert_eq!(std::fs::read_to_string(directory.join("notes.txt")).unwrap(), "héllo wörld");
+}
+
+#[test]
+fn append_creates_missing_files() {
+    let directory = scratch("append");
+    run::<()>(&ALL, &directory, "void", r#"fs.append(dir + "/log.txt", "one");"#).unwrap();
+    assert_eq!(std::fs::read_to_string(directory.join("log.txt")).unwrap(), "one");
+}
+
+#[test]
+fn list_exists_and_remove() {
+    let directory = scratch("list");
+    std::fs::write(directory.join("b.txt"), "").unwrap();
+    std::fs::write(directory.join("a.txt"), "").unwrap();
+    std::fs::create_dir(directory.join("c")).unwrap();
+    assert_eq!(run::<Vec<String>>(&ALL, &directory, "array<string>", "return fs.list(dir);").unwrap(), vec!["a.txt", "b.txt", "c"]);
+    let body = r#"fs.remove(dir + "/a.txt"); fs.remove(dir + "/c"); return fs.exists(dir + "/a.txt") || fs.exists(dir + "/c") || !fs.exists(dir + "/b.txt");"#;
+    assert!(!run::<bool>(&ALL, &directory, "bool", body).unwrap());
+}
+
+#[test]
+fn failures_are_runtime_errors_naming_the_path() {
+    let directory = scratch("failures");
+    let error = run::<String>(&ALL, &directory, "string", r#"return fs.read(dir + "/missing.txt");"#).unwrap_err();
+    assert!(matches!(error, Error::Runtime(_)), "{}", error);
+    assert!(error.to_string().contains("Could not read `"), "{}", error);
+    assert!(error.to_string().contains("missing.txt`"), "{}", error);
+    let body = r#"try { fs.remove(dir + "/missing.txt"); return false; } catch (e: Error) { return true; }"#;
+    assert!(run::<bool>(&ALL, &directory, "bool", body).unwrap());
+}
+
+#[test]
+fn disk_access_is_denied_by_default() {
+    let directory = scratch("denied");
+    let error = run::<()>(&[], &directory, "void", r#"fs.write(dir + "/x.txt", "x");"#).unwrap_err();
+    assert!(error.to_string().contains("`fs.write` needs the WriteFiles capability, which is not granted"), "{}", error);
+    assert!(!directory.join("x.txt").exists());
+    assert!(run::<bool>(&[], &directory, "bool", "return fs.exists(dir);").is_err());
+}
+
+#[test]
+fn reading_and_writing_are_granted_separately() {
+    let directory = scratch("separately");
+    std::fs::write(directory.join("config.txt"), "debug").unwrap();
+    let read = [Capability::ReadFiles];
+    assert_eq!(run::<String>(&read, &directory, "string", r#"return fs.read(dir + "/config.txt");"#).unwrap(), "debug");
+    assert!(run::<()>(&read, &directory, "void", r#"fs.write(dir + "/config.txt", "");"#).is_err());
+    assert_eq!(std::fs::read_to_string(directory.join("config.txt")).unwrap(), "debug");
+}
+
+#[test]
+fn capabilities_can_be_revoked() {
+    let directory = scratch("revoked");
+    let mut engine = Engine::new();
+    engine.allow(Capability::ReadFiles);
+    engine.eval("import fs;\nfn exists(path: string): bool { return fs.exists(path); }").unwrap();
+    let path = directory.display().to_string();
+    assert!(engine.call::<bool>("exists", (path.as_str(),)).unwrap());
+    engine.deny(Capability::ReadFiles);
+    assert!(engine.call::<bool>("exists", (path.as_str(),)).is_err());
+}
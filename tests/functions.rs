@@ -0,0 +1,68 @@
+mod common;
+
+use common::{error, exit_code};
+
+#[test]
+fn functions_are_passed_and_returned() {
+    let program = "fn apply(f: fn(int, int): int, a: int, b: int): int { return f(a, b); }
+    fn add(a: int, b: int): int { return a + b; }
+    fn adder(n: int): fn(int): int { return fn(x: int): int => x + n; }
+    fn main(args: array<string>): int { return (apply(add, 2, 3) * 10 + apply(fn(a: int, b: int): int => a * b, 2, 3)) * 2 + adder(10)(5); }";
+    assert_eq!(exit_code(program), 127);
+}
+
+#[test]
+fn functions_are_stored_in_variables_arrays_and_fields() {
+    let program = "class Button { var on_click: fn(): string = fn(): string => \"default\"; }
+    fn main(args: array<string>): int {
+        var greet: fn(string): string = fn(name: string): string { var greeting: string = \"hi \"; return greeting + name; };
+        var all: array<fn(string): string> = [greet, fn(name: string): string => name + \"!\"];
+        var button: Button = new Button();
+        var before: string = button.on_click();
+        button.on_click = fn(): string => \"clicked\";
+        if (all[0](\"bo\") != \"hi bo\" || all[1](\"yo\") != \"yo!\") { return 1; }
+        if (before != \"default\" || button.on_click() != \"clicked\") { return 2; }
+        return 0;
+    }";
+    assert_eq!(exit_code(program), 0);
+}
+
+#[test]
+fn lambdas_capture_variables_by_reference() {
+    let program = "fn counter(start: int): fn(): int {
+        var count: int = start;
+        return fn(): int { count = count + 1; return count; };
+    }
+    fn main(args: array<string>): int {
+        var total: int = 0;
+        var add: fn(int): void = fn(n: int): void { total = total + n; };
+        add(3);
+        add(4);
+        var a: fn(): int = counter(0);
+        var b: fn(): int = counter(100);
+        a();
+        b();
+        return total * 10 + a() + b() - 100;
+    }";
+    assert_eq!(exit_code(program), 74);
+}
+
+#[test]
+fn function_types_are_checked() {
+    let message = error("fn main(args: array<string>): int { var f: fn(int): int = fn(s: string): int => 1; return 0; }");
+    assert!(message.contains("Expected `fn(int): int`"), "{}", message);
+    let message = error("fn main(args: array<string>): int { var f: fn(int): int = fn(x: int): int => x; return f(\"a\"); }");
+    assert!(message.contains("Expected `int` for argument 1"), "{}", message);
+    let message = error("fn main(args: array<string>): int { var n: int = 1; return n(); }");
+    assert!(message.contains("of type `int` is not callable"), "{}", message);
+    let message = error("fn main(args: array<string>): int { var f: fn(int): int = fn(x: int): int => x; return f(); }");
+    assert!(message.contains("expects 1 argument(s) but was given 0"), "{}", message);
+}
+
+#[test]
+fn deep_recursion_is_an_error_rather_than_a_crash() {
+    let program = |depth: usize| format!("fn down(n: int): int {{ if (n == 0) {{ return 0; }} return down(n - 1) + 1; }}
+    fn main(args: array<string>): int {{ return down({}) - {}; }}", depth, depth);
+    assert_eq!(exit_code(&program(9_000)), 0);
+    assert!(error(&program(1_000_000)).contains("Call depth limit of 10000 exceeded"));
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{error, exit_code};
+
+const STACK: &str = "class Node<T> {
+    var value: T;
+    var next: Node<T>;
+    constructor(value: T, next: Node<T>) { this.value = value; this.next = next; }
+}
+class Stack<T> {
+    var top: Node<T> = null;
+    var size: int = 0;
+    fn push(item: T): void { this.top = new Node<T>(item, this.top); this.size = this.size + 1; }
+    fn pop(): T { var item: T = this.top.value; this.top = this.top.next; this.size = this.size - 1; return item; }
+}
+class Pair<A, B> {
+    var first: A;
+    var second: B;
+    constructor(first: A, second: B) { this.first = first; this.second = second; }
+    fn swap(): Pair<B, A> { return new Pair<B, A>(this.second, this.first); }
+}
+fn first<T>(xs: array<T>): T { return xs[0]; }
+";
+
+#[test]
+fn generic_classes_hold_any_type() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{
+            var words: Stack<string> = new Stack<string>();
+            words.push(\"a\");
+            words.push(\"b\");
+            var numbers: Stack<int> = new Stack<int>();
+            numbers.push(40);
+            if (words.pop() != \"b\" || words.pop() != \"a\") {{ return 1; }}
+            return numbers.pop() + words.size + numbers.size;
+        }}",
+        STACK
+    );
+    assert_eq!(exit_code(&program), 40);
+}
+
+#[test]
+fn type_arguments_are_substituted_in_signatures() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{ var pair: Pair<int, string> = new Pair<int, string>(1, \"one\"); if (pair.swap().first != \"one\") {{ return 1; }} return pair.swap().second; }}",
+        STACK
+    );
+    assert_eq!(exit_code(&program), 1);
+}
+
+#[test]
+fn generic_functions_infer_their_type_arguments() {
+    let program = format!("{}fn main(args: array<string>): int {{ if (first([1.5, 2.5]) + first([first([3.5])]) != 5.0) {{ return 1; }} return 0; }}", STACK);
+    assert_eq!(exit_code(&program), 0);
+    let program = format!("{}fn main(args: array<string>): int {{ var empty: array<int> = []; return first(empty); }}", STACK);
+    assert!(error(&program).contains("out of bounds"));
+}
+
+#[test]
+fn mismatched_type_arguments_are_errors() {
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var s: Stack<int> = new Stack<int>(); s.push(\"x\"); return s.size; }}", STACK));
+    assert!(message.contains("Expected `int` for argument 1 of"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var s: Stack<int> = new Stack<string>(); return 0; }}", STACK));
+    assert!(message.contains("Stack<string>"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var s: Stack<int, int> = new Stack<int>(); return 0; }}", STACK));
+    assert!(message.contains("`Stack` expects 1 type argument(s) but was given 2"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var x: int = first([\"a\"]); return x; }}", STACK));
+    assert!(message.contains("Expected `int`"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ var x: int = first([]); return x; }}", STACK));
+    assert!(message.contains("Cannot infer type argument `T`"), "{}", message);
+}
+
+#[test]
+fn type_arguments_can_be_given_at_the_call() {
+    let program = format!(
+        "{}fn size<T>(xs: array<T>): int {{ return xs.len(); }}
+fn main(args: array<string>): int {{ var a: int = 1; var b: int = 2; if (first<float>([1.5]) != 1.5 || a < b != (b > a)) {{ return 1; }} return size<string>([]); }}",
+        STACK
+    );
+    assert_eq!(exit_code(&program), 0);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ return first<string>([1]); }}", STACK));
+    assert!(message.contains("Expected `string` for array element"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ return first<int, int>([1]); }}", STACK));
+    assert!(message.contains("`first` expects 1 type argument(s) but was given 2"), "{}", message);
+    let message = error(&format!("{}fn one(): int {{ return 1; }}\nfn main(args: array<string>): int {{ return one<int>(); }}", STACK));
+    assert!(message.contains("`one` is not generic, so it takes no type arguments"), "{}", message);
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use common::{call_test, compile_error_in};
+use wally_lang::native::{primitive, NativeClass};
+use wally_lang::parser::TypeKind;
+use wally_lang::vm::Backend;
+use wally_lang::{Engine, Error, Value};
+
+struct Counter {
+    count: i64,
+    step: i64,
+}
+
+fn engine_with_counter() -> Engine {
+    let int = || primitive(TypeKind::Integer);
+    let class = NativeClass::new("Counter")
+        .with_constructor(vec![int()], |arguments| match arguments[0] {
+            Value::Integer(step) if step > 0 => Ok(Counter { count: 0, step }),
+            _ => Err("The step of a `Counter` must be positive".to_string()),
+        })
+        .with_method("increment", vec![], int(), |counter: &mut Counter, _| {
+            counter.count += counter.step;
+            Ok(Value::Integer(counter.count))
+        })
+        .with_method("add", vec![int()], primitive(TypeKind::Void), |counter: &mut Counter, arguments| {
+            if let Value::Integer(n) = arguments[0] {
+                counter.count += n;
+            }
+            Ok(Value::Null)
+        })
+        .with_property("count", int(), |counter: &Counter| Value::Integer(counter.count))
+        .with_mutable_property("step", int(), |counter: &Counter| Value::Integer(counter.step), |counter: &mut Counter, value| match value {
+            Value::Integer(step) if step > 0 => {
+                counter.step = step;
+                Ok(())
+            }
+            _ => Err("The step of a `Counter` must be positive".to_string()),
+        });
+    let mut engine = Engine::new();
+    engine.register_class(class);
+    engine
+}
+
+fn run(program: &str) -> Result<Value, Error> {
+    call_test(engine_with_counter(), program)
+}
+
+fn compile_error(program: &str) -> String {
+    compile_error_in(engine_with_counter(), program)
+}
+
+#[test]
+fn host_classes_are_instantiated_and_called_like_any_other() {
+    let program = "fn test(): array<int> {
+        var counter: Counter = new Counter(2);
+        counter.increment();
+        counter.add(5);
+        var after: int = counter.increment();
+        counter.step = 10;
+        return [after, counter.count, counter.increment(), counter.step];
+    }";
+    assert_eq!(run(program).unwrap().to_string(), "[9, 9, 19, 10]");
+}
+
+#[test]
+fn constructor_calls_are_checked() {
+    let error = compile_error("fn test(): int { return new Counter().count; }");
+    assert!(error.contains("expects 1 argument(s) but was given 0"), "{}", error);
+    let error = compile_error("fn test(): int { return new Counter(\"2\").count; }");
+    assert!(error.contains("Expected `int` for argument 1"), "{}", error);
+    let error = compile_error("fn test(): int { return new Counter(1).missing(); }");
+    assert!(error.contains("`Counter` has no member `missing`"), "{}", error);
+}
+
+#[test]
+fn only_properties_with_a_setter_are_assignable() {
+    let error = compile_error("fn test(): int { var counter: Counter = new Counter(1); counter.count = 3; return 0; }");
+    assert!(error.contains("Property `Counter.count` is read-only"), "{}", error);
+    let error = compile_error("fn test(): int { var counter: Counter = new Counter(1); counter.step = \"3\"; return 0; }");
+    assert!(error.contains("Expected `int`"), "{}", error);
+}
+
+#[test]
+fn host_classes_cannot_be_extended() {
+    let error = compile_error("class Fast extends Counter {} fn test(): int { return 0; }");
+    assert!(error.contains("`Counter` is implemented by the host and cannot be extended"), "{}", error);
+}
+
+#[test]
+fn host_failures_are_runtime_errors() {
+    let error = run("fn test(): int {\n    return new Counter(0).count;\n}").unwrap_err().to_string();
+    assert!(error.contains("The step of a `Counter` must be positive at line 2"), "{}", error);
+    let error = run("fn test(): int {\n    var counter: Counter = new Counter(1);\n    counter.step = -1;\n    return 0;\n}").unwrap_err().to_string();
+    assert!(error.contains("The step of a `Counter` must be positive at line 3"), "{}", error);
+}
+
+#[test]
+fn instances_are_passed_in_by_the_host() {
+    let mut engine = engine_with_counter();
+    engine.eval("fn bump(counter: Counter): int { counter.increment(); return counter.increment(); }").unwrap();
+    let counter = engine.instance("Counter", Counter { count: 1, step: 3 }).unwrap();
+    assert_eq!(engine.call::<i64>("bump", (counter.clone(),)).unwrap(), 7);
+    assert_eq!(engine.call::<i64>("bump", (counter,)).unwrap(), 13);
+    assert!(matches!(engine.instance("Missing", 0), Err(Error::Undefined(name)) if name == "Missing"));
+}
+
+#[test]
+fn instances_wrapping_another_type_fail_when_used() {
+    let mut engine = engine_with_counter();
+    engine.eval("fn bump(counter: Counter): int { return counter.increment(); }").unwrap();
+    let counter = engine.instance("Counter", "not a counter").unwrap();
+    let error = engine.call::<i64>("bump", (counter,)).unwrap_err().to_string();
+    assert!(error.contains("`Counter.increment` was called on an instance of another type"), "{}", error);
+    engine.eval("fn count(counter: Counter): int { return counter.count; } fn slow(counter: Counter): void { counter.step = 2; }").unwrap();
+    let counter = engine.instance("Counter", 7_i64).unwrap();
+    let error = engine.call::<i64>("count", (counter.clone(),)).unwrap_err().to_string();
+    assert!(error.contains("`Counter.count` was read on an instance of another type"), "{}", error);
+    let error = engine.call::<Value>("slow", (counter,)).unwrap_err().to_string();
+    assert!(error.contains("`Counter.step` was assigned on an instance of another type"), "{}", error);
+}
+
+#[test]
+fn bound_methods_fail_where_they_are_called() {
+    for backend in [Backend::Tree, Backend::Bytecode] {
+        let mut engine = engine_with_counter();
+        engine.set_backend(backend);
+        engine.eval("fn bump(counter: Counter): int {\n    var increment: fn(): int = counter.increment;\n    return increment();\n}").unwrap();
+        let counter = engine.instance("Counter", "not a counter").unwrap();
+        let error = engine.call::<i64>("bump", (counter,)).unwrap_err().to_string();
+        assert!(error.contains("`Counter.increment` was called on an instance of another type at line 3, column 12"), "{}", error);
+    }
+}
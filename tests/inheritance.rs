@@ -0,0 +1,65 @@
+mod common;
+
+use common::{error, exit_code};
+
+const ANIMALS: &str = "class Animal {
+    var name: string;
+    constructor(name: string) { this.name = name; }
+    fn sound(): string { return \"...\"; }
+    fn speak(): string { return this.name + \" says \" + this.sound(); }
+}
+class Dog extends Animal {
+    constructor(name: string) { super(name + \" the dog\"); }
+    fn sound(): string { return \"woof\"; }
+}
+class Puppy extends Dog {
+    constructor() { super(\"Rex\"); }
+    fn sound(): string { return super.sound() + \"!\"; }
+}
+";
+
+#[test]
+fn calls_dispatch_to_the_override() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{ var animal: Animal = new Dog(\"Fido\"); if (animal.speak() != \"Fido the dog says woof\") {{ return 1; }} return 0; }}",
+        ANIMALS
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn super_chains_constructors_and_methods() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{ if (new Puppy().speak() != \"Rex the dog says woof!\") {{ return 1; }} return 0; }}",
+        ANIMALS
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn subclasses_inherit_fields_and_methods() {
+    let program = "class Base { var count: int = 1; fn twice(): int { return this.count * 2; } }
+    class Derived extends Base { fn thrice(): int { return this.count * 3; } }
+    fn main(args: array<string>): int { var d: Derived = new Derived(); d.count = 5; return d.twice() + d.thrice(); }";
+    assert_eq!(exit_code(program), 25);
+}
+
+#[test]
+fn overrides_must_keep_the_signature() {
+    let message = error(&format!("{}class Cat extends Animal {{ constructor() {{ super(\"Tom\"); }} fn sound(): int {{ return 1; }} }}", ANIMALS));
+    assert!(message.contains("Method `sound` overrides `Animal.sound` with an incompatible signature: expected `fn(): string`, found `fn(): int`"), "{}", message);
+    assert!(message.contains("overridden method declared here"), "{}", message);
+    let message = error(&format!("{}class Cat extends Animal {{ constructor() {{ super(\"Tom\"); }} fn speak(loud: bool): string {{ return \"\"; }} }}", ANIMALS));
+    assert!(message.contains("Method `speak` overrides `Animal.speak` with an incompatible signature"), "{}", message);
+}
+
+#[test]
+fn class_hierarchies_are_checked() {
+    assert!(error("class A extends B {} class B extends A {}").contains("inherits from itself"));
+    assert!(error("class A extends A {}").contains("Class `A` cannot extend itself"));
+    assert!(error("fn f(): void { super.f(); }").contains("`super` used outside of a class that extends another"));
+    let message = error(&format!("{}class Cat extends Animal {{ constructor() {{ var x: int = 1; super(\"Tom\"); }} }}", ANIMALS));
+    assert!(message.contains("`super(...)` must be the first statement of a constructor"), "{}", message);
+    let message = error(&format!("{}class Cat extends Animal {{ var name: string = \"\"; }}", ANIMALS));
+    assert!(message.contains("Field `name` is already declared by superclass `Animal`"), "{}", message);
+}
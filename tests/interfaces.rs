@@ -0,0 +1,67 @@
+mod common;
+
+use common::{error, exit_code};
+
+const SHAPES: &str = "interface Shape { fn area(): float; }
+interface Named { fn name(): string; }
+class Square implements Shape, Named {
+    var side: float;
+    constructor(side: float) { this.side = side; }
+    fn area(): float { return this.side * this.side; }
+    fn name(): string { return \"square\"; }
+}
+class Circle implements Shape {
+    var radius: float;
+    constructor(radius: float) { this.radius = radius; }
+    fn area(): float { return 3.0 * this.radius * this.radius; }
+}
+";
+
+#[test]
+fn calls_through_an_interface_dispatch_to_the_class() {
+    let program = format!(
+        "{}fn total(shapes: array<Shape>): float {{ return shapes[0].area() + shapes[1].area(); }}
+        fn main(args: array<string>): int {{ if (total([new Square(2.0), new Circle(1.0)]) != 7.0) {{ return 1; }} return 0; }}",
+        SHAPES
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn classes_implement_several_interfaces() {
+    let program = format!(
+        "{}fn main(args: array<string>): int {{
+            var named: Named = new Square(3.0);
+            var shape: Shape = new Square(3.0);
+            if (named.name() != \"square\") {{ return 1; }}
+            if (shape.area() != 9.0) {{ return 2; }}
+            return 0;
+        }}",
+        SHAPES
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn inherited_methods_implement_interfaces() {
+    let program = format!(
+        "{}class Base {{ fn area(): float {{ return 2.5; }} }}
+        class Derived extends Base implements Shape {{}}
+        fn main(args: array<string>): int {{ var shape: Shape = new Derived(); if (shape.area() != 2.5) {{ return 1; }} return 0; }}",
+        SHAPES
+    );
+    assert_eq!(exit_code(&program), 0);
+}
+
+#[test]
+fn conformance_is_checked() {
+    let missing = error(&format!("{}class Empty implements Shape {{}}", SHAPES));
+    assert!(missing.contains("Class `Empty` does not implement `Shape.area`"), "{}", missing);
+    let mismatched = error(&format!("{}class Wrong implements Shape {{ fn area(): int {{ return 1; }} }}", SHAPES));
+    assert!(mismatched.contains("Method `Wrong.area` does not match `Shape.area`: expected `fn(): float`, found `fn(): int`"), "{}", mismatched);
+    let hidden = error(&format!("{}class Hidden implements Shape {{ private fn area(): float {{ return 1.0; }} }}", SHAPES));
+    assert!(hidden.contains("Method `Hidden.area` implements `Shape.area` and must be public"), "{}", hidden);
+    assert!(error("class A implements Missing {}").contains("Unknown interface `Missing`"));
+    let assigned = error(&format!("{}fn main(args: array<string>): int {{ var named: Named = new Circle(1.0); return 0; }}", SHAPES));
+    assert!(assigned.contains("Expected `Named`"), "{}", assigned);
+}
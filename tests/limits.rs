@@ -0,0 +1,178 @@
+use std::thread;
+use std::time::Duration;
+use wally_lang::limits::{Limit, Limits};
+use wally_lang::vm::Backend;
+use wally_lang::{Engine, Error};
+
+const PROGRAM: &str = "import strings;
+fn spin(): int { var i: int = 0; while (true) { i = i + 1; } return i; }
+fn count(n: int): int { var i: int = 0; while (i < n) { i = i + 1; } return i; }
+fn recurse(n: int): int { if (n == 0) { return 0; } return recurse(n - 1) + 1; }
+fn arrays(n: int): int { var i: int = 0; while (i < n) { var xs: array<int> = [i]; i = i + 1; } return i; }
+fn grow(n: int): int { var s: string = \"x\"; var i: int = 0; while (i < n) { s = s + s; i = i + 1; } return s.len(); }
+fn square(): int { var s: string = \"xx\"; while (true) { s = s.replace(\"x\", s); } return s.len(); }
+fn joined(): int { var parts: array<string> = []; var s: string = \"x\"; while (true) { parts.push(s); s = strings.join(parts, s); } return s.len(); }
+fn push(n: int): int { var xs: array<int> = []; var i: int = 0; while (i < n) { xs.push(i); i = i + 1; } return xs.len(); }
+fn caught(): int { try { return spin(); } catch (e: Error) { return -1; } }
+fn returning_finally(): int { try { return recurse(1000000); } finally { return -1; } }
+fn breaking_finally(): int { while (true) { try { recurse(1000000); } finally { break; } } return -1; }
+fn continuing_finally(): int { var i: int = 0; while (i < 3) { i = i + 1; try { recurse(1000000); } finally { continue; } } return -1; }";
+
+fn engine(limits: Limits) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_limits(limits);
+    engine.eval(PROGRAM).unwrap();
+    engine
+}
+
+fn limit_of(result: Result<i64, Error>) -> Limit {
+    match result {
+        Err(error) => error.limit().unwrap_or_else(|| panic!("expected a limit error, got {}", error)),
+        Ok(value) => panic!("expected a limit error, got {}", value),
+    }
+}
+
+#[test]
+fn step_limit() {
+    let mut engine = engine(Limits::new().with_steps(10_000));
+    let error = engine.call::<i64>("spin", ()).unwrap_err();
+    assert_eq!(error.limit(), Some(Limit::Steps));
+    assert!(error.to_string().contains("Step limit of 10000 exceeded"), "{}", error);
+    assert_eq!(engine.call::<i64>("count", (100,)).unwrap(), 100);
+}
+
+#[test]
+fn each_call_gets_its_own_budget() {
+    let mut engine = engine(Limits::new().with_steps(2_000));
+    for _ in 0..5 {
+        assert_eq!(engine.call::<i64>("count", (100,)).unwrap(), 100);
+    }
+}
+
+#[test]
+fn the_top_level_is_limited_too() {
+    let mut engine = Engine::new();
+    engine.set_limits(Limits::new().with_steps(1_000));
+    let error = engine.eval("var i: int = 0;\nwhile (true) { i = i + 1; }").unwrap_err();
+    assert_eq!(error.limit(), Some(Limit::Steps));
+    assert!(engine.render(&error).contains("error: Step limit of 1000 exceeded"), "{}", engine.render(&error));
+}
+
+#[test]
+fn call_depth_limit() {
+    // Test threads have small stacks, and unoptimized builds use a lot of it for each call.
+    let mut engine = engine(Limits::new().with_call_depth(32));
+    assert_eq!(engine.call::<i64>("recurse", (30,)).unwrap(), 30);
+    let error = engine.call::<i64>("recurse", (1_000_000,)).unwrap_err();
+    assert_eq!(error.limit(), Some(Limit::CallDepth));
+    assert!(error.to_string().contains("Call depth limit of 32 exceeded"), "{}", error);
+    // The depth is back to zero after the error unwinds.
+    assert_eq!(engine.call::<i64>("recurse", (30,)).unwrap(), 30);
+}
+
+#[test]
+fn value_limit() {
+    let mut engine = engine(Limits::new().with_values(100));
+    assert_eq!(engine.call::<i64>("arrays", (50,)).unwrap(), 50);
+    assert_eq!(limit_of(engine.call("arrays", (200,))), Limit::Values);
+}
+
+#[test]
+fn byte_limit() {
+    let mut engine = engine(Limits::new().with_bytes(1 << 20));
+    assert_eq!(engine.call::<i64>("grow", (10,)).unwrap(), 1024);
+    let error = engine.call::<i64>("grow", (40,)).unwrap_err();
+    assert_eq!(error.limit(), Some(Limit::Bytes));
+    assert!(error.to_string().contains("Allocation limit of 1048576 bytes exceeded"), "{}", error);
+}
+
+#[test]
+fn natives_are_stopped_before_building_results_over_the_byte_limit() {
+    // Each step squares the length, so the step past the limit would ask for terabytes if it ran.
+    let mut engine = engine(Limits::new().with_bytes(1 << 20));
+    assert_eq!(limit_of(engine.call("square", ())), Limit::Bytes);
+    assert_eq!(limit_of(engine.call("joined", ())), Limit::Bytes);
+}
+
+#[test]
+fn growing_an_array_counts_towards_the_byte_limit() {
+    let mut engine = engine(Limits::new().with_bytes(64 * 1024));
+    assert_eq!(engine.call::<i64>("push", (100,)).unwrap(), 100);
+    assert_eq!(limit_of(engine.call("push", (100_000,))), Limit::Bytes);
+}
+
+#[test]
+fn limits_cannot_be_caught_by_the_program() {
+    let mut engine = engine(Limits::new().with_steps(10_000));
+    assert_eq!(limit_of(engine.call("caught", ())), Limit::Steps);
+}
+
+#[test]
+fn finally_blocks_cannot_swallow_a_limit() {
+    for backend in [Backend::Tree, Backend::Bytecode] {
+        let mut engine = Engine::new();
+        engine.set_backend(backend);
+        engine.set_limits(Limits::new().with_call_depth(32));
+        engine.eval(PROGRAM).unwrap();
+        for function in ["returning_finally", "breaking_finally", "continuing_finally"] {
+            assert_eq!(limit_of(engine.call(function, ())), Limit::CallDepth, "{} with {:?}", function, backend);
+        }
+    }
+}
+
+#[test]
+fn cancellation_from_another_thread() {
+    let mut engine = engine(Limits::new());
+    let handle = engine.cancel_handle();
+    let canceller = thread::spawn({
+        let handle = handle.clone();
+        move || {
+            thread::sleep(Duration::from_millis(50));
+            handle.cancel();
+        }
+    });
+    let error = engine.call::<i64>("spin", ()).unwrap_err();
+    canceller.join().unwrap();
+    assert_eq!(error.limit(), Some(Limit::Cancelled));
+    assert!(error.to_string().contains("Execution was cancelled"), "{}", error);
+    assert_eq!(limit_of(engine.call("count", (1,))), Limit::Cancelled);
+    handle.reset();
+    assert_eq!(engine.call::<i64>("count", (1,)).unwrap(), 1);
+}
+
+#[test]
+fn other_errors_have_no_limit() {
+    let mut engine = engine(Limits::new().with_steps(10_000));
+    let error = engine.eval("fn f(): int { var zero: int = 0; return 1 / zero; }").and_then(|_| engine.call::<i64>("f", ())).unwrap_err();
+    assert_eq!(error.limit(), None);
+}
+
+#[test]
+fn field_initializers_that_instantiate_their_class_hit_the_call_depth_limit() {
+    // The default limit is deeper than a test thread's stack in unoptimized builds.
+    let run = || {
+        for backend in [Backend::Tree, Backend::Bytecode] {
+            let mut engine = Engine::new();
+            engine.set_backend(backend);
+            engine.eval("class A { var next: A = new A(); }\nfn make(): A { return new A(); }").unwrap();
+            let error = engine.call::<wally_lang::Value>("make", ()).unwrap_err();
+            assert_eq!(error.limit(), Some(Limit::CallDepth), "{} with {:?}", error, backend);
+        }
+    };
+    thread::Builder::new().stack_size(1 << 30).spawn(run).unwrap().join().unwrap();
+}
+
+#[test]
+fn long_chains_of_objects_and_variants_are_dropped_without_overflowing_the_stack() {
+    let program = "class Node { var next: Node = null; }
+enum List { Cons(int, List), Nil }
+fn objects(n: int): int { var head: Node = null; var i: int = 0; while (i < n) { var node: Node = new Node(); node.next = head; head = node; i = i + 1; } return i; }
+fn variants(n: int): int { var list: List = List.Nil; var i: int = 0; while (i < n) { list = List.Cons(i, list); i = i + 1; } return i; }";
+    for backend in [Backend::Tree, Backend::Bytecode] {
+        let mut engine = Engine::new();
+        engine.set_backend(backend);
+        engine.eval(program).unwrap();
+        assert_eq!(engine.call::<i64>("objects", (300_000i64,)).unwrap(), 300_000);
+        assert_eq!(engine.call::<i64>("variants", (300_000i64,)).unwrap(), 300_000);
+    }
+}
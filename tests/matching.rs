@@ -0,0 +1,80 @@
+mod common;
+
+use common::{error, exit_code, wally};
+
+#[test]
+fn literals_ranges_and_wildcards() {
+    let program = "fn classify(n: int): string {
+        return match (n) {
+            0 => \"zero\",
+            1..=9 => \"digit\",
+            10..100 => \"small\",
+            _ => \"large\",
+        };
+    }
+    fn letter(c: char): int { return match (c) { 'a'..='z' => 1, 'A'..='Z' => 2, _ => 0 }; }
+    fn main(args: array<string>): int {
+        if (classify(0) != \"zero\" || classify(9) != \"digit\") { return 1; }
+        if (classify(10) != \"small\" || classify(99) != \"small\" || classify(100) != \"large\") { return 2; }
+        return match (letter('q') + letter('Q')) { 3 => 0, _ => 3 };
+    }";
+    assert_eq!(exit_code(program), 0);
+}
+
+#[test]
+fn variants_destructure_into_bindings_with_guards() {
+    let program = "enum Tree { Leaf(int), Node(Tree, Tree) }
+    fn sum(tree: Tree): int {
+        return match (tree) {
+            Tree.Leaf(n) if n < 0 => 0,
+            Tree.Leaf(n) => n,
+            Tree.Node(Tree.Leaf(a), Tree.Leaf(b)) => a + b + 100,
+            Tree.Node(left, right) => sum(left) + sum(right),
+        };
+    }
+    fn main(args: array<string>): int { return sum(Tree.Node(Tree.Node(Tree.Leaf(1), Tree.Leaf(2)), Tree.Node(Tree.Leaf(-5), Tree.Node(Tree.Leaf(3), Tree.Leaf(4))))); }";
+    assert_eq!(exit_code(program), 210);
+}
+
+#[test]
+fn a_binding_matches_anything() {
+    let program = "fn main(args: array<string>): int {
+        var s: string = \"wally\";
+        var shouted: string = match (s) { \"other\" => \"no\", name => name + \"!\" };
+        if (shouted != \"wally!\") { return 1; }
+        return 0;
+    }";
+    assert_eq!(exit_code(program), 0);
+}
+
+#[test]
+fn matches_must_be_exhaustive() {
+    let program = "enum Light { Red, Yellow, Green }
+    fn main(args: array<string>): int { var light: Light = Light.Green; return match (light) { Light.Red => 1, Light.Yellow => 2 }; }";
+    assert!(error(program).contains("Non-exhaustive match: `Light.Green` is not covered"));
+    let message = error("fn main(args: array<string>): int { var n: int = 1; return match (n) { 0 => 1, 1..=5 => 2 }; }");
+    assert!(message.contains("Non-exhaustive match"), "{}", message);
+    let message = error("enum Option { Some(int), None } fn main(args: array<string>): int { return match (Option.Some(1)) { Option.Some(1) => 1, Option.None => 0 }; }");
+    assert!(message.contains("Non-exhaustive match"), "{}", message);
+    let message = error("fn main(args: array<string>): int { return match (true) { true => 1 }; }");
+    assert!(message.contains("Non-exhaustive match: `false` is not covered"), "{}", message);
+}
+
+#[test]
+fn arms_after_a_catch_all_are_unreachable() {
+    let output = wally("fn main(args: array<string>): int { var n: int = 3; return match (n) { _ => 1, 2 => 2 }; }", &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("warning: Unreachable match arm"), "{}", stderr);
+    assert_eq!(output.status.code(), Some(1));
+    let output = wally("enum E { A, B } fn main(args: array<string>): int { return match (E.A) { E.A => 1, E.B => 2, E.A => 3 }; }", &[]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("warning: Unreachable match arm"));
+}
+
+#[test]
+fn patterns_are_checked_against_the_subject() {
+    let message = error("fn main(args: array<string>): int { var n: int = 1; return match (n) { \"one\" => 1, _ => 0 }; }");
+    assert!(message.contains("Expected a pattern of type `int`, found `string`"), "{}", message);
+    let message = error("enum Pair { Two(int, int) } fn main(args: array<string>): int { return match (Pair.Two(1, 2)) { Pair.Two(a) => a }; }");
+    assert!(message.contains("Variant `Pair.Two` has 2 value(s) but the pattern has 1"), "{}", message);
+    assert!(error("fn main(args: array<string>): int { return match (1) { 5..1 => 1, _ => 0 }; }").contains("Range pattern matches nothing"));
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use wally_lang::{Error, FromValue};
+
+/// Evaluates `body` as the body of a function returning `type_` and returns its result.
+fn run<T: FromValue>(type_: &str, body: &str) -> Result<T, Error> {
+    common::run(&format!("import math;\nfn test(): {} {{\n{}\n}}", type_, body))
+}
+
+#[test]
+fn float_functions() {
+    assert_eq!(run::<f64>("float", "return math.sqrt(16.0);").unwrap(), 4.0);
+    assert_eq!(run::<f64>("float", "return math.pow(2.0, 10.0);").unwrap(), 1024.0);
+    assert_eq!(run::<f64>("float", "return math.fabs(-1.5);").unwrap(), 1.5);
+    assert_eq!(run::<f64>("float", "return math.fmax(1.5, math.fmin(3.0, 2.0));").unwrap(), 2.0);
+    assert!((run::<f64>("float", "return math.sin(math.PI / 2.0);").unwrap() - 1.0).abs() < 1e-12);
+    assert!((run::<f64>("float", "return math.atan2(1.0, 1.0) * 4.0;").unwrap() - std::f64::consts::PI).abs() < 1e-12);
+    assert!((run::<f64>("float", "return math.ln(math.E);").unwrap() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn float_functions_follow_ieee_754() {
+    assert!(run::<bool>("bool", "return math.is_nan(math.sqrt(-1.0));").unwrap());
+    assert_eq!(run::<f64>("float", "return math.ln(0.0);").unwrap(), f64::NEG_INFINITY);
+    assert_eq!(run::<f64>("float", "return 1.0 / 0.0;").unwrap(), f64::INFINITY);
+}
+
+#[test]
+fn rounding() {
+    assert_eq!(run::<f64>("float", "return math.floor(-2.5);").unwrap(), -3.0);
+    assert_eq!(run::<f64>("float", "return math.ceil(-2.5);").unwrap(), -2.0);
+    assert_eq!(run::<f64>("float", "return math.round(2.5);").unwrap(), 3.0);
+    assert_eq!(run::<f64>("float", "return math.round(-2.5);").unwrap(), -3.0);
+    assert_eq!(run::<f64>("float", "return math.trunc(-2.7);").unwrap(), -2.0);
+}
+
+#[test]
+fn integer_functions() {
+    assert_eq!(run::<i64>("int", "return math.abs(-7);").unwrap(), 7);
+    assert_eq!(run::<i64>("int", "return math.min(3, -4) + math.max(3, -4);").unwrap(), -1);
+    let error = run::<i64>("int", "return math.abs(math.MIN_INT);").unwrap_err();
+    assert!(error.to_string().contains("out of the range of int"), "{}", error);
+}
+
+#[test]
+fn constants_are_read_only() {
+    assert_eq!(run::<i64>("int", "return math.MAX_INT;").unwrap(), i64::MAX);
+    assert_eq!(run::<f64>("float", "return math.TAU / math.PI;").unwrap(), 2.0);
+    assert!(matches!(run::<f64>("float", "math.PI = 3.0; return math.PI;").unwrap_err(), Error::Compile(_)));
+}
+
+#[test]
+fn int_to_float_conversion() {
+    assert_eq!(run::<f64>("float", "var n: int = 7; return n as float / 2.0;").unwrap(), 3.5);
+    assert_eq!(run::<f64>("float", "return -3 as float;").unwrap(), -3.0);
+}
+
+#[test]
+fn float_to_int_conversion_truncates() {
+    assert_eq!(run::<i64>("int", "return 2.9 as int;").unwrap(), 2);
+    assert_eq!(run::<i64>("int", "return -2.9 as int;").unwrap(), -2);
+    assert_eq!(run::<i64>("int", "return math.round(2.5) as int + 1;").unwrap(), 4);
+}
+
+#[test]
+fn float_to_int_conversion_out_of_range_is_an_error() {
+    let error = run::<i64>("int", "return math.INFINITY as int;").unwrap_err();
+    assert!(error.to_string().contains("inf cannot be converted to int"), "{}", error);
+    assert!(run::<i64>("int", "return math.NAN as int;").is_err());
+    assert!(run::<i64>("int", "return (math.MAX_INT as float) as int;").is_err());
+    assert_eq!(run::<i64>("int", "return (math.MIN_INT as float) as int;").unwrap(), i64::MIN);
+}
+
+#[test]
+fn mixed_arithmetic_needs_a_conversion() {
+    let error = run::<f64>("float", "var n: int = 1; return n + 1.5;").unwrap_err();
+    assert!(error.to_string().contains("Cannot apply `+` to `int` and `float`"), "{}", error);
+    assert!(error.to_string().contains("convert this operand with `as float`"), "{}", error);
+    assert!(run::<bool>("bool", "return 1 < 1.5;").is_err());
+}
+
+#[test]
+fn only_numbers_can_be_converted() {
+    let error = run::<i64>("int", r#"return "1" as int;"#).unwrap_err();
+    assert!(error.to_string().contains("Cannot convert `string` to `int`"), "{}", error);
+    assert!(run::<bool>("bool", "return 1 as bool;").is_err());
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::{failure, status, wally_files};
+
+#[test]
+fn imports_by_module_path_and_by_file() {
+    let files = [
+        ("main.wly", "import geometry.shapes;\nimport \"lib/util.wly\";\nfn main(args: array<string>): int { return shapes.area(new shapes.Rect(2, 3)) + util.double(1); }"),
+        ("geometry/shapes.wly", "export class Rect { var w: int; var h: int; constructor(w: int, h: int) { this.w = w; this.h = h; } }\nexport fn area(r: Rect): int { return r.w * r.h; }"),
+        ("lib/util.wly", "export fn double(n: int): int { return n * 2; }"),
+    ];
+    assert_eq!(status(wally_files(&files, &[])), 8);
+}
+
+#[test]
+fn each_module_has_its_own_namespace_and_state() {
+    let files = [
+        ("main.wly", "import a;\nimport b;\nvar bumps: int = 100;\nfn main(args: array<string>): int { a.bump(); a.bump(); return bumps + a.count() * 10 + b.count(); }"),
+        ("a.wly", "var bumps: int = 0;\nexport fn bump(): void { bumps = bumps + 1; }\nexport fn count(): int { return bumps; }"),
+        ("b.wly", "import a;\nexport fn count(): int { return 7; }"),
+    ];
+    assert_eq!(status(wally_files(&files, &[])), 127);
+}
+
+#[test]
+fn only_exported_items_are_visible() {
+    let files = [
+        ("main.wly", "import util;\nfn main(args: array<string>): int { return util.hidden(); }"),
+        ("util.wly", "fn hidden(): int { return 1; }"),
+    ];
+    let message = failure(wally_files(&files, &[]));
+    assert!(message.contains("`hidden` is private to module `util`"), "{}", message);
+    let files = [
+        ("main.wly", "import util;\nfn main(args: array<string>): int { return util.missing(); }"),
+        ("util.wly", "export fn present(): int { return 1; }"),
+    ];
+    let message = failure(wally_files(&files, &[]));
+    assert!(message.contains("Module `util` has no `missing`"), "{}", message);
+}
+
+#[test]
+fn import_cycles_are_detected() {
+    let files = [
+        ("main.wly", "import a;\nfn main(args: array<string>): int { return 0; }"),
+        ("a.wly", "import b;\nexport fn f(): int { return 1; }"),
+        ("b.wly", "import a;\nexport fn g(): int { return 2; }"),
+    ];
+    let message = failure(wally_files(&files, &[]));
+    assert!(message.contains("Import cycle: a -> b -> a"), "{}", message);
+    assert!(message.contains("b.wly:1:1"), "{}", message);
+}
+
+#[test]
+fn missing_modules_are_errors() {
+    let message = failure(wally_files(&[("main.wly", "import nowhere.to_be.found;\nfn main(args: array<string>): int { return 0; }")], &[]));
+    assert!(message.contains("Cannot find module `nowhere.to_be.found`"), "{}", message);
+    let message = failure(wally_files(&[("main.wly", "fn main(args: array<string>): int { import util; return 0; }"), ("util.wly", "")], &[]));
+    assert!(message.contains("`import` is only allowed at the top level of a module"), "{}", message);
+}
+
+#[test]
+fn imports_cannot_leave_the_root() {
+    let import = |path: &str| failure(wally_files(&[("main.wly", &format!("import \"{}\";\nfn main(args: array<string>): int {{ return 0; }}", path))], &[]));
+    // Files outside the root are refused the same way whether they exist or not.
+    for path in ["../main.wly", "/etc/passwd", "lib/../../nowhere.wly", "/no/such/file.wly"] {
+        let message = import(path);
+        assert!(message.contains(&format!("Cannot import `{}`, which is outside the root", path)), "{}", message);
+    }
+    let program = "import \"lib/../util.wly\";\nfn main(args: array<string>): int { return util.one(); }";
+    assert_eq!(status(wally_files(&[("main.wly", program), ("util.wly", "export fn one(): int { return 1; }")], &[])), 1);
+}
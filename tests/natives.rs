@@ -0,0 +1,97 @@
+mod common;
+
+use common::{error, exit_code, wally_with_input};
+use wally_lang::native::{primitive, Natives};
+use wally_lang::parser::TypeKind;
+use wally_lang::{Engine, Value};
+
+#[test]
+fn printing_and_reading_go_through_the_standard_streams() {
+    let program = "fn main(): int {
+        print(\"name? \");
+        var name: string = read_line();
+        println(\"hello \" + name);
+        eprintln(\"done\");
+        if (read_line() != \"\") { return 1; }
+        return 0;
+    }";
+    let output = wally_with_input(program, b"wally\r\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "name? hello wally\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "done\n");
+    // Reading past the end of input gives an empty line.
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn natives_are_values_like_any_other_function() {
+    let program = "fn main(): int { var say: fn(string): void = println; say(\"hi\"); return 0; }";
+    assert_eq!(exit_code(program), 0);
+}
+
+#[test]
+fn the_checker_uses_the_signatures_of_natives() {
+    let message = error("fn main(): int { println(); return 0; }");
+    assert!(message.contains("expects 1 argument(s) but was given 0"), "{}", message);
+    let message = error("fn main(): int { println(1); return 0; }");
+    assert!(message.contains("Expected `string` for argument 1 of"), "{}", message);
+    let message = error("fn main(): int { var line: int = read_line(); return line; }");
+    assert!(message.contains("Expected `int`"), "{}", message);
+}
+
+#[test]
+fn globals_of_the_program_shadow_natives() {
+    assert_eq!(exit_code("fn println(n: int): int { return -n; } fn main(): int { return println(-4); }"), 4);
+}
+
+fn engine_with_clamp() -> Engine {
+    let mut engine = Engine::new();
+    let int = || primitive(TypeKind::Integer);
+    engine.register("clamp", vec![int(), int(), int()], int(), |arguments| match (&arguments[0], &arguments[1], &arguments[2]) {
+        (Value::Integer(n), Value::Integer(low), Value::Integer(high)) if low <= high => Ok(Value::Integer(*n.max(low).min(high))),
+        _ => Err("The bounds of `clamp` are reversed".to_string()),
+    });
+    engine
+}
+
+#[test]
+fn the_registry_holds_the_builtins_with_their_signatures() {
+    let natives = Natives::new();
+    for name in ["print", "println", "eprintln", "read_line"] {
+        assert!(natives.index_of(name).is_some(), "`{}` is not registered", name);
+    }
+    let println = natives.get(natives.index_of("println").unwrap());
+    assert_eq!(println.parameters.len(), 1);
+    assert_eq!(println.parameters[0].kind, TypeKind::String);
+    assert_eq!(println.return_type.kind, TypeKind::Void);
+    let read_line = natives.get(natives.index_of("read_line").unwrap());
+    assert!(read_line.parameters.is_empty());
+    assert_eq!(read_line.return_type.kind, TypeKind::String);
+}
+
+#[test]
+fn builtins_reject_arguments_of_the_wrong_kind() {
+    let natives = Natives::new();
+    let call = |name: &str, arguments: Vec<Value>| (natives.get(natives.index_of(name).unwrap()).function)(arguments);
+    assert_eq!(call("strings.len", vec![Value::Integer(3)]).unwrap_err(), "Expected a string argument, found int");
+    assert_eq!(call("strings.substring", vec![Value::string("abc"), Value::Float(0.0), Value::Integer(1)]).unwrap_err(), "Expected an int argument, found float");
+    assert_eq!(call("strings.join", vec![Value::array(vec![Value::Integer(1)]), Value::string(",")]).unwrap_err(), "Expected a string argument, found int");
+    assert_eq!(call("math.sqrt", vec![Value::Integer(4)]).unwrap_err(), "Expected a float argument, found int");
+    assert_eq!(call("math.max", vec![Value::Integer(4), Value::Null]).unwrap_err(), "Expected an int argument, found null");
+}
+
+#[test]
+fn registered_functions_are_called_like_any_other() {
+    let mut engine = engine_with_clamp();
+    engine.eval("fn test(): int { var f: fn(int, int, int): int = clamp; return clamp(15, 0, 10) * 100 + f(-3, 0, 10); }").unwrap();
+    assert_eq!(engine.call::<i64>("test", ()).unwrap(), 1000);
+    let error = engine_with_clamp().eval("fn test(): int { return clamp(1, 2, \"3\"); }").unwrap_err().to_string();
+    assert!(error.contains("Expected `int` for argument 3 of"), "{}", error);
+}
+
+#[test]
+fn native_failures_are_runtime_errors() {
+    let mut engine = engine_with_clamp();
+    engine.eval("fn test(): int {\n    return clamp(1, 10, 0);\n}").unwrap();
+    let error = engine.call::<i64>("test", ()).unwrap_err().to_string();
+    assert!(error.contains("The bounds of `clamp` are reversed at line 2, column 12"), "{}", error);
+}
@@ -0,0 +1,44 @@
+use wally_lang::native::Capability;
+use wally_lang::{Engine, Error};
+
+fn engine(source: &str) -> Engine {
+    let mut engine = Engine::new();
+    engine.eval(&format!("import process;\n{}", source)).unwrap();
+    engine
+}
+
+#[test]
+fn args_are_set_by_the_host() {
+    let source = "fn test(): array<string> { return process.args(); }";
+    assert!(engine(source).call::<Vec<String>>("test", ()).unwrap().is_empty());
+    let mut engine = Engine::new();
+    engine.set_arguments(vec!["--verbose".to_string(), "ünï".to_string()]);
+    engine.eval(&format!("import process;\n{}", source)).unwrap();
+    assert_eq!(engine.call::<Vec<String>>("test", ()).unwrap(), vec!["--verbose", "ünï"]);
+}
+
+#[test]
+fn env_needs_the_environment_capability() {
+    std::env::set_var("WALLY_PROCESS_TEST", "set");
+    let mut engine = engine("fn test(name: string): string { return process.env(name, \"unset\"); }");
+    let error = engine.call::<String>("test", ("WALLY_PROCESS_TEST",)).unwrap_err();
+    assert!(matches!(error, Error::Runtime(_)), "{}", error);
+    assert!(error.to_string().contains("`process.env` needs the Environment capability"), "{}", error);
+    engine.allow(Capability::Environment);
+    assert_eq!(engine.call::<String>("test", ("WALLY_PROCESS_TEST",)).unwrap(), "set");
+    assert_eq!(engine.call::<String>("test", ("WALLY_PROCESS_TEST_MISSING",)).unwrap(), "unset");
+}
+
+#[test]
+fn exit_is_denied_by_default() {
+    let mut engine = engine("fn test(): int { process.exit(3); return 0; }");
+    let error = engine.call::<i64>("test", ()).unwrap_err();
+    assert!(error.to_string().contains("`process.exit` needs the Exit capability"), "{}", error);
+}
+
+#[test]
+fn functions_are_type_checked() {
+    let mut engine = Engine::new();
+    let error = engine.eval("import process;\nfn test(): void { process.exit(\"1\"); }").unwrap_err();
+    assert!(matches!(error, Error::Compile(_)), "{}", error);
+}
@@ -0,0 +1,47 @@
+mod common;
+
+use common::{error, exit_code};
+
+#[test]
+fn blocks_shadow_and_restore_variables() {
+    let program = "fn main(args: array<string>): int {
+        var x: int = 1;
+        var seen: int = 0;
+        {
+            var x: int = 10;
+            seen = x;
+            x = x + 1;
+        }
+        return x * 100 + seen;
+    }";
+    assert_eq!(exit_code(program), 110);
+}
+
+#[test]
+fn functions_see_globals_declared_anywhere() {
+    let program = "fn main(args: array<string>): int { bump(); bump(); return count; }
+    fn bump(): void { count = count + step; }
+    var count: int = 0;
+    var step: int = 5;";
+    assert_eq!(exit_code(program), 10);
+}
+
+#[test]
+fn closures_capture_their_own_variables() {
+    let program = "fn counter(start: int): int {
+        var count: int = start;
+        fn next(): int { count = count + 1; return count; }
+        next();
+        return next();
+    }
+    fn main(args: array<string>): int { return counter(0) * 100 + counter(40); }";
+    assert_eq!(exit_code(program), 242);
+}
+
+#[test]
+fn names_must_be_declared_in_scope() {
+    assert!(error("fn main(args: array<string>): int { { var inner: int = 1; } return inner; }").contains("Undefined variable `inner`"));
+    assert!(error("fn main(args: array<string>): int { var x: int = 1; var x: int = 2; return x; }").contains("`x` is already declared in this scope"));
+    assert!(error("fn main(args: array<string>): int { var y: int = y; return y; }").contains("Undefined variable `y`"));
+    assert!(error("var a: int = b;\nvar b: int = 1;\nfn main(args: array<string>): int { return a; }").contains("`b` is used before its declaration"));
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::{error, exit_code};
+
+#[test]
+fn static_fields_are_shared_by_the_class() {
+    let program = "class Ticket {
+        static var issued: int = 0;
+        var number: int;
+        constructor() { Ticket.issued = Ticket.issued + 1; this.number = Ticket.issued; }
+        static fn reset(): void { Ticket.issued = 0; }
+    }
+    fn main(args: array<string>): int {
+        new Ticket();
+        var second: Ticket = new Ticket();
+        var before: int = Ticket.issued;
+        Ticket.reset();
+        return second.number * 64 + before * 16 + Ticket.issued * 4 + new Ticket().number;
+    }";
+    assert_eq!(exit_code(program), 2 * 64 + 2 * 16 + 1);
+}
+
+#[test]
+fn static_fields_initialize_once_in_declaration_order() {
+    let program = "var log: string = \"\";
+    fn note(entry: string, value: int): int { log = log + entry + \";\"; return value; }
+    class Config {
+        static var base: int = note(\"base\", 10);
+        static var doubled: int = note(\"doubled\", Config.base * 2);
+        static fn total(): int { return Config.base + Config.doubled; }
+    }
+    fn main(args: array<string>): int {
+        Config.total();
+        var total: int = Config.total();
+        if (log != \"base;doubled;\") { return 1; }
+        return total;
+    }";
+    assert_eq!(exit_code(program), 30);
+}
+
+#[test]
+fn static_methods_are_called_on_the_class() {
+    let program = "class Math2 { static fn square(n: int): int { return n * n; } static fn sum_squares(a: int, b: int): int { return Math2.square(a) + Math2.square(b); } }
+    class Vault { var secret: int = 42; protected constructor() {} static fn open(): Vault { return new Vault(); } }
+    fn main(args: array<string>): int { return Math2.sum_squares(3, 4) + Vault.open().secret; }";
+    assert_eq!(exit_code(program), 67);
+}
+
+#[test]
+fn static_and_instance_members_are_kept_apart() {
+    let message = error("class A { var x: int = 1; static fn f(): int { return this.x; } }");
+    assert!(message.contains("`this` cannot be used in a static method"), "{}", message);
+    let message = error("class A { static var count: int = 0; } fn main(args: array<string>): int { return new A().count; }");
+    assert!(message.contains("`count` is static and must be accessed as `A.count`"), "{}", message);
+    let message = error("class A { var x: int = 1; } fn main(args: array<string>): int { return A.x; }");
+    assert!(message.contains("`A.x` is not static and can only be accessed on an instance"), "{}", message);
+    let message = error("class A {} fn main(args: array<string>): int { return A.missing; }");
+    assert!(message.contains("`A` has no static member `missing`"), "{}", message);
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use wally_lang::{Error, FromValue};
+
+/// Evaluates `body` as the body of a function returning `type_` and returns its result.
+fn run<T: FromValue>(type_: &str, body: &str) -> Result<T, Error> {
+    common::run(&format!("import strings;\nfn test(): {} {{\n{}\n}}", type_, body))
+}
+
+#[test]
+fn len_counts_characters() {
+    assert_eq!(run::<i64>("int", r#"return "héllo".len();"#).unwrap(), 5);
+    assert_eq!(run::<i64>("int", r#"return strings.len("日本語");"#).unwrap(), 3);
+    assert_eq!(run::<i64>("int", r#"return "".len();"#).unwrap(), 0);
+}
+
+#[test]
+fn chars_splits_into_characters() {
+    assert_eq!(run::<Vec<char>>("array<char>", r#"return "añb🦀".chars();"#).unwrap(), vec!['a', 'ñ', 'b', '🦀']);
+}
+
+#[test]
+fn substring_uses_character_positions() {
+    assert_eq!(run::<String>("string", r#"return "héllo wörld".substring(6, 11);"#).unwrap(), "wörld");
+    assert_eq!(run::<String>("string", r#"return "🦀🦀🦀".substring(1, 2);"#).unwrap(), "🦀");
+    assert_eq!(run::<String>("string", r#"return "abc".substring(3, 3);"#).unwrap(), "");
+}
+
+#[test]
+fn substring_out_of_bounds_is_an_error() {
+    let error = run::<String>("string", r#"return "abc".substring(2, 5);"#).unwrap_err();
+    assert!(error.to_string().contains("Substring 2..5 out of bounds for string of length 3"), "{}", error);
+    assert!(run::<String>("string", r#"return "abc".substring(2, 1);"#).is_err());
+    assert!(run::<String>("string", r#"return "abc".substring(-1, 1);"#).is_err());
+}
+
+#[test]
+fn index_of_counts_characters_before_the_match() {
+    assert_eq!(run::<i64>("int", r#"return "ünïcödé".index_of("cö");"#).unwrap(), 3);
+    assert_eq!(run::<i64>("int", r#"return "abc".index_of("x");"#).unwrap(), -1);
+    assert_eq!(run::<i64>("int", r#"return "abc".index_of("");"#).unwrap(), 0);
+}
+
+#[test]
+fn split_and_join() {
+    assert_eq!(run::<Vec<String>>("array<string>", r#"return "a,b,,ç".split(",");"#).unwrap(), vec!["a", "b", "", "ç"]);
+    assert_eq!(run::<String>("string", r#"return strings.join(["x", "ÿ", "z"], " → ");"#).unwrap(), "x → ÿ → z");
+    assert_eq!(run::<String>("string", r#"var none: array<string> = []; return strings.join(none, ",");"#).unwrap(), "");
+    assert!(run::<Vec<String>>("array<string>", r#"return "abc".split("");"#).is_err());
+}
+
+#[test]
+fn trim_replace_and_starts_with() {
+    assert_eq!(run::<String>("string", "return \"\\t  ünï \\n\".trim();").unwrap(), "ünï");
+    assert_eq!(run::<String>("string", r#"return "a-b-c".replace("-", "–");"#).unwrap(), "a–b–c");
+    assert!(run::<bool>("bool", r#"return "éclair".starts_with("éc");"#).unwrap());
+    assert!(!run::<bool>("bool", r#"return "éclair".starts_with("e");"#).unwrap());
+}
+
+#[test]
+fn case_conversion_follows_unicode() {
+    assert_eq!(run::<String>("string", r#"return "straße".to_upper();"#).unwrap(), "STRASSE");
+    assert_eq!(run::<String>("string", r#"return "ÀÉÎ".to_lower();"#).unwrap(), "àéî");
+}
+
+#[test]
+fn parse_numbers() {
+    assert_eq!(run::<i64>("int", r#"return " -42 ".parse_int();"#).unwrap(), -42);
+    assert_eq!(run::<f64>("float", r#"return strings.parse_float("2.5");"#).unwrap(), 2.5);
+}
+
+#[test]
+fn parse_failures_can_be_caught() {
+    let body = r#"
+        try {
+            return "4x".parse_int();
+        } catch (e: Error) {
+            return e.message.len();
+        }
+    "#;
+    assert_eq!(run::<i64>("int", body).unwrap(), "`4x` is not a valid int".chars().count() as i64);
+    let error = run::<f64>("float", r#"return "one".parse_float();"#).unwrap_err();
+    assert!(error.to_string().contains("`one` is not a valid float"), "{}", error);
+}
+
+#[test]
+fn methods_are_type_checked() {
+    let error = run::<i64>("int", r#"return "abc".substring("1", 2);"#).unwrap_err();
+    assert!(error.to_string().contains("Expected `int` for argument 1 of `substring`, found `string`"), "{}", error);
+    let error = run::<i64>("int", r#"return "abc".size();"#).unwrap_err();
+    assert!(error.to_string().contains("`string` has no method `size`"), "{}", error);
+}
+
+#[test]
+fn module_functions_need_an_import() {
+    let error = common::compile_error(r#"fn test(): int { return strings.len("abc"); }"#);
+    assert!(error.contains("Undefined variable `strings`"), "{}", error);
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{error, exit_code};
+
+const VAULT: &str = "class Vault {
+    private var secret: int = 42;
+    protected var hint: int = 4;
+    public var label: int = 1;
+    private fn reveal(): int { return this.secret; }
+    fn peek(): int { return this.reveal(); }
+}
+class Heir extends Vault {
+    fn guess(): int { return this.hint * 10; }
+}
+";
+
+#[test]
+fn members_are_accessible_where_their_visibility_allows() {
+    let program = format!("{}fn main(args: array<string>): int {{ var vault: Vault = new Vault(); return vault.peek() + vault.label + new Heir().label; }}", VAULT);
+    assert_eq!(exit_code(&program), 44);
+}
+
+#[test]
+fn private_members_are_hidden_outside_their_class() {
+    let message = error(&format!("{}fn main(args: array<string>): int {{ return new Vault().secret; }}", VAULT));
+    assert!(message.contains("Field `secret` of `Vault` is private"), "{}", message);
+    assert!(message.contains("declared private here"), "{}", message);
+    assert!(message.contains("main.wly:2:13"), "{}", message);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ return new Vault().reveal(); }}", VAULT));
+    assert!(message.contains("Method `reveal` of `Vault` is private"), "{}", message);
+    let message = error(&format!("{}class Thief extends Vault {{ fn steal(): int {{ return this.secret; }} }}", VAULT));
+    assert!(message.contains("Field `secret` of `Vault` is private"), "{}", message);
+}
+
+#[test]
+fn protected_members_are_visible_to_subclasses_only() {
+    let program = format!("{}fn main(args: array<string>): int {{ return new Heir().guess(); }}", VAULT);
+    assert_eq!(exit_code(&program), 40);
+    let message = error(&format!("{}fn main(args: array<string>): int {{ return new Vault().hint; }}", VAULT));
+    assert!(message.contains("Field `hint` of `Vault` is protected"), "{}", message);
+    assert!(message.contains("declared protected here"), "{}", message);
+    let program = "class Base { protected constructor() {} }
+    class Derived extends Base { constructor() { super(); } }
+    fn main(args: array<string>): int { var derived: Derived = new Derived(); return 3; }";
+    assert_eq!(exit_code(program), 3);
+    let message = error("class Base { protected constructor() {} }\nfn main(args: array<string>): int { var base: Base = new Base(); return 0; }");
+    assert!(message.contains("Constructor of `Base` is protected"), "{}", message);
+}
+
+#[test]
+fn overrides_cannot_narrow_visibility() {
+    let message = error(&format!("{}class Narrow extends Vault {{ private fn peek(): int {{ return 0; }} }}", VAULT));
+    assert!(message.contains("Method `peek` overrides public `Vault.peek` and cannot be made private"), "{}", message);
+}
+
+#[test]
+fn only_top_level_declarations_are_exported() {
+    let message = error("fn main(args: array<string>): int { export var x: int = 1; return x; }");
+    assert!(message.contains("Only top-level declarations can be exported or made `public`"), "{}", message);
+    let message = error("fn main(args: array<string>): int { public fn f(): int { return 1; } return f(); }");
+    assert!(message.contains("Only top-level declarations can be exported or made `public`"), "{}", message);
+}
@@ -0,0 +1,203 @@
+use wally_lang::limits::Limits;
+use wally_lang::vm::Backend;
+use wally_lang::{Engine, Value};
+
+/// Evaluates `program` and calls its `test` function with `backend`, rendering the result or
+/// the error with its location and stack trace.
+fn run_with(backend: Backend, program: &str) -> String {
+    let mut engine = Engine::new();
+    engine.set_backend(backend);
+    engine.eval(program).unwrap();
+    match engine.call::<Value>("test", ()) {
+        Ok(value) => value.to_string(),
+        Err(error) => error.to_string(),
+    }
+}
+
+/// Runs `program` on both backends, checking that they agree, and returns what it produced.
+fn run(program: &str) -> String {
+    let tree = run_with(Backend::Tree, program);
+    let bytecode = run_with(Backend::Bytecode, program);
+    assert_eq!(tree, bytecode, "the backends disagree on\n{}", program);
+    bytecode
+}
+
+#[test]
+fn loops_and_locals() {
+    let program = "fn test(): int {
+        var total: int = 0;
+        var i: int = 0;
+        while (i < 10) {
+            var square: int = i * i;
+            i = i + 1;
+            if (square % 2 == 0) { continue; }
+            if (i > 8) { break; }
+            total = total + square;
+        }
+        for (x in [1, 2, 3]) { var y: int = x * 100; total = total + y; }
+        for (c in \"ab\") total = total + 1;
+        return total;
+    }";
+    assert_eq!(run(program), "686");
+}
+
+#[test]
+fn closures_capture_their_scopes() {
+    let program = "fn test(): array<int> {
+        var counters: array<fn(): int> = [];
+        for (start in [10, 20]) {
+            var count: int = start;
+            counters.push(fn(): int { count = count + 1; return count; });
+        }
+        fn twice(f: fn(): int): int { f(); return f(); }
+        var results: array<int> = [];
+        for (counter in counters) { results.push(twice(counter)); }
+        results.push(counters[0]());
+        return results;
+    }";
+    assert_eq!(run(program), "[12, 22, 13]");
+}
+
+#[test]
+fn short_circuits_and_conditions() {
+    let program = "fn boom(): bool { throw new Error(\"evaluated\"); }
+    fn test(): array<bool> {
+        return [false && boom(), true || boom(), true && false, false || true, !(1 < 2)];
+    }";
+    assert_eq!(run(program), "[false, true, false, true, false]");
+}
+
+#[test]
+fn classes_methods_and_super() {
+    let program = "class Animal {
+        var name: string;
+        static var created: int = 0;
+        constructor(name: string) { this.name = name; Animal.created = Animal.created + 1; }
+        fn speak(): string { return this.name + \" makes a sound\"; }
+    }
+    class Dog extends Animal {
+        var tricks: map<string, int> = {};
+        constructor(name: string) { super(name); this.tricks[\"sit\"] = 1; }
+        fn speak(): string { return super.speak() + \" and barks\"; }
+    }
+    fn test(): array<string> {
+        var dog: Dog = new Dog(\"Rex\");
+        var speak: fn(): string = dog.speak;
+        dog.name = \"Max\";
+        if (dog.tricks[\"sit\"] + Animal.created != 2) {
+            return [];
+        }
+        return [speak(), dog.name];
+    }";
+    assert_eq!(run(program), "[Max makes a sound and barks, Max]");
+}
+
+#[test]
+fn classes_and_enums_declared_in_functions() {
+    let program = "fn test(): int {
+        var base: int = 5;
+        class Counter {
+            var count: int = base;
+            fn next(): int { this.count = this.count + 1; return this.count; }
+        }
+        enum Shape { Dot, Line(int) }
+        var counter: Counter = new Counter();
+        counter.next();
+        var shape: Shape = Shape.Line(counter.next());
+        return match (shape) {
+            Shape.Dot => 0,
+            Shape.Line(n) if n > 100 => -1,
+            Shape.Line(n) => n + base,
+        };
+    }";
+    assert_eq!(run(program), "12");
+}
+
+#[test]
+fn match_arms_bind_and_guard() {
+    let program = "enum Tree { Leaf(int), Node(Tree, Tree) }
+    fn sum(tree: Tree): int {
+        return match (tree) {
+            Tree.Leaf(value) if value < 0 => 0,
+            Tree.Leaf(value) => value,
+            Tree.Node(left, right) => sum(left) + sum(right),
+        };
+    }
+    fn describe(n: int): string {
+        return match (n) { 0 => \"zero\", 1..=9 => \"digit\", _ => \"many\" };
+    }
+    fn test(): string {
+        var tree: Tree = Tree.Node(Tree.Leaf(4), Tree.Node(Tree.Leaf(-3), Tree.Leaf(5)));
+        return describe(sum(tree)) + \" \" + describe(0) + \" \" + describe(42);
+    }";
+    assert_eq!(run(program), "digit zero many");
+}
+
+#[test]
+fn try_catch_finally() {
+    let program = "class Oops extends Error {}
+    fn attempt(n: int): int {
+        try {
+            if (n == 0) { throw new Oops(\"zero\"); }
+            if (n == 1) { return 10; }
+            var empty: array<int> = [];
+            return empty[n];
+        } catch (e: Oops) {
+            return 20;
+        } catch (e: Error) {
+            return 30;
+        } finally {
+            if (n == 3) { return 40; }
+        }
+    }
+    fn loop(): int {
+        var steps: int = 0;
+        for (i in [1, 2, 3, 4, 5]) {
+            try {
+                if (i == 2) { continue; }
+                if (i == 4) { break; }
+                steps = steps + i;
+            } finally {
+                steps = steps + 100;
+            }
+        }
+        return steps;
+    }
+    fn test(): array<int> {
+        return [attempt(0), attempt(1), attempt(2), attempt(3), loop()];
+    }";
+    assert_eq!(run(program), "[20, 10, 30, 40, 404]");
+}
+
+#[test]
+fn runtime_errors_are_reported_alike() {
+    let program = "fn divide(a: int, b: int): int { return a / b; }
+    fn test(): int {
+        var values: map<string, int> = {\"a\": 1};
+        return divide(values[\"a\"], 0);
+    }";
+    let error = run(program);
+    assert!(error.contains("Division by zero at line 1"), "{}", error);
+    assert!(error.contains("in `divide`"), "{}", error);
+    let error = run("fn test(): int { throw new Error(\"custom\"); }");
+    assert!(error.contains("Uncaught `Error`: custom at line 1, column 18"), "{}", error);
+}
+
+#[test]
+fn recursion() {
+    let program = "fn fib(n: int): int { if (n < 2) { return n; } return fib(n - 1) + fib(n - 2); }
+    fn test(): int { return fib(15); }";
+    assert_eq!(run(program), "610");
+}
+
+#[test]
+fn limits_apply_to_bytecode() {
+    let mut engine = Engine::new();
+    engine.set_backend(Backend::Bytecode);
+    engine.set_limits(Limits::new().with_steps(1_000).with_values(10));
+    engine.eval("fn spin(): int { while (true) {} return 0; } fn strings(): int { var s: string = \"\"; for (i in [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]) { s = s + \"x\"; } return s.len(); }").unwrap();
+    let error = engine.call::<i64>("spin", ()).unwrap_err();
+    assert!(error.to_string().contains("Step limit of 1000 exceeded"), "{}", error);
+    let error = engine.call::<i64>("strings", ()).unwrap_err();
+    assert!(error.to_string().contains("Allocation limit of 10 values exceeded"), "{}", error);
+}